@@ -0,0 +1,118 @@
+"""
+ASGI Adapter
+
+Serves the functions of an ``Oparaca`` from an existing ASGI app (FastAPI,
+Starlette, plain uvicorn) instead of a second listener. Requests are
+dispatched through the Rust layer, so they pass the same server-side policy
+as the gRPC and Zenoh servers.
+"""
+
+import asyncio
+import logging
+from typing import TYPE_CHECKING, Optional
+from urllib.parse import parse_qsl
+
+from .handler import AsyncInvocationHandler
+
+if TYPE_CHECKING:
+    import oprc_py
+    from oaas_sdk2_py.engine import Oparaca
+
+
+logger = logging.getLogger(__name__)
+
+# HTTP request headers with this prefix are passed on as request options.
+OPTION_HEADER_PREFIX = "oprc-"
+
+
+class OaasAsgiApp:
+    """
+    ASGI app invoking the functions of ``oprc`` on ``POST`` requests.
+
+    Paths follow the Zenoh key expressions of the functions, relative to where
+    the app is mounted: ``/<cls_id>/<partition_id>/invokes/<fn_id>`` and
+    ``/<cls_id>/<partition_id>/objects/<object_id>/invokes/<fn_id>``. The
+    body is the payload; query parameters and ``oprc-*`` headers become
+    request options. The response body is the response payload, the HTTP
+    status follows its error code, and its headers are sent as HTTP headers,
+    with ``oprc-status`` holding the response status.
+
+    Example::
+
+        app = FastAPI()
+        app.mount("/oaas", oaas.asgi_app())
+    """
+
+    def __init__(self, oprc: 'Oparaca'):
+        if oprc.engine is None:
+            raise RuntimeError("The ASGI adapter needs an engine; it is not available in mock mode")
+        self.oprc = oprc
+        self._dispatcher: Optional['oprc_py.HttpDispatcher'] = None
+        self._loop: Optional[asyncio.AbstractEventLoop] = None
+
+    def _dispatcher_for(self, loop: asyncio.AbstractEventLoop) -> 'oprc_py.HttpDispatcher':
+        # Handlers run on the loop of the server, only known once it serves.
+        if self._dispatcher is None or self._loop is not loop:
+            self._dispatcher = self.oprc.engine.http_dispatcher(loop, AsyncInvocationHandler(self.oprc))
+            self._loop = loop
+        return self._dispatcher
+
+    async def __call__(self, scope, receive, send):
+        if scope["type"] == "lifespan":
+            await self._lifespan(receive, send)
+            return
+        if scope["type"] != "http":
+            return
+        if scope["method"] != "POST":
+            await self._respond(send, 405, [(b"allow", b"POST")], b"method not allowed")
+            return
+        body = bytearray()
+        while True:
+            message = await receive()
+            if message["type"] == "http.disconnect":
+                return
+            body.extend(message.get("body", b""))
+            if not message.get("more_body", False):
+                break
+        path = scope["path"]
+        root_path = scope.get("root_path", "")
+        if root_path and path.startswith(root_path):
+            path = path[len(root_path):]
+        options = dict(parse_qsl(scope.get("query_string", b"").decode("latin-1")))
+        for name, value in scope.get("headers", []):
+            name = name.decode("latin-1").lower()
+            if name.startswith(OPTION_HEADER_PREFIX):
+                options.setdefault(name, value.decode("latin-1"))
+        dispatcher = self._dispatcher_for(asyncio.get_running_loop())
+        try:
+            status, headers, payload = await dispatcher.dispatch(path, bytes(body), options)
+        except Exception as e:
+            logger.error("Failed to dispatch %s", path, exc_info=True)
+            await self._respond(send, 500, [], str(e).encode())
+            return
+        await self._respond(
+            send,
+            status,
+            [(k.encode("latin-1", "replace"), v.encode("latin-1", "replace")) for k, v in headers.items()],
+            payload,
+        )
+
+    @staticmethod
+    async def _respond(send, status: int, headers: list, body: bytes) -> None:
+        await send({
+            "type": "http.response.start",
+            "status": status,
+            "headers": [(b"content-type", b"application/octet-stream"), *headers],
+        })
+        await send({"type": "http.response.body", "body": body})
+
+    @staticmethod
+    async def _lifespan(receive, send) -> None:
+        # Mounted apps do not get lifespan events; a standalone app has nothing to set up.
+        while True:
+            message = await receive()
+            if message["type"] == "lifespan.startup":
+                await send({"type": "lifespan.startup.complete"})
+            elif message["type"] == "lifespan.shutdown":
+                await send({"type": "lifespan.shutdown.complete"})
+                return
@@ -28,6 +28,10 @@ class OaasConfig(BaseSettings):
     # Performance settings
     auto_commit: bool = Field(default=True, description="Enable automatic transaction commits")
     batch_size: int = Field(default=100, description="(DEPRECATED) Batch size for bulk operations")
+
+    # Warm-up
+    warmup_rounds: int = Field(default=1, description="Times each warm-up payload is invoked on startup")
+    warmup_object_id: Optional[int] = Field(default=None, description="Object the gRPC server warms methods up on; methods are not warmed up there if unset")
     
     def get_zenoh_peers(self) -> Optional[list[str]]:
         """Get Zenoh peers as a list."""
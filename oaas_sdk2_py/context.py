@@ -0,0 +1,241 @@
+"""Context of the invocation a handler is serving.
+
+Inside a handler, ``context.invoke`` and ``context.invoke_obj`` call other
+functions through the server's own ``RpcManager``. The trace context, chain
+id, deadline and auth token of the request being served are carried over, so
+the nested call joins the same trace, shares the caller's deadline budget and
+passes the same auth checks. ``context.remaining_ms()`` tells how much of that
+budget is left::
+
+    from oaas_sdk2_py import context
+
+    resp = await context.invoke_async("summarize", payload)
+"""
+from __future__ import annotations
+
+import contextvars
+import time
+from typing import TYPE_CHECKING, Optional
+
+from oprc_py.oprc_py import (
+    InvocationRequest,
+    InvocationResponse,
+    ObjectInvocationRequest,
+    ObjectMetadata,
+)
+
+if TYPE_CHECKING:
+    from oaas_sdk2_py.engine import Oparaca
+
+# Request options forwarded to invocations made from a handler.
+PROPAGATED_OPTIONS = (
+    "traceparent",
+    "tracestate",
+    "oprc-chain-id",
+    "oprc-deadline",
+    "oprc-auth-token",
+)
+
+# Request option set on the invocations a server warms its functions up with.
+WARMUP_OPTION = "oprc-warmup"
+
+_current: contextvars.ContextVar[Optional["InvocationContext"]] = contextvars.ContextVar(
+    "oprc_invocation_context", default=None
+)
+
+
+class InvocationContext:
+    """The request a handler is serving and the engine it runs in."""
+
+    def __init__(
+        self,
+        oprc: "Oparaca",
+        request: InvocationRequest | ObjectInvocationRequest,
+    ):
+        self.oprc = oprc
+        self.request = request
+
+    @property
+    def warmup(self) -> bool:
+        """Whether this is a warm-up invocation, run before the server takes invocations."""
+        return WARMUP_OPTION in self.request.options
+
+    @property
+    def deadline_ms(self) -> Optional[int]:
+        """The caller's deadline in Unix epoch milliseconds, if it set one."""
+        return self.request.deadline_ms
+
+    def remaining_ms(self) -> Optional[int]:
+        """Milliseconds left until the caller's deadline, 0 once it passed, or None."""
+        deadline = self.deadline_ms
+        if deadline is None:
+            return None
+        return max(0, deadline - int(time.time() * 1000))
+
+    def _timeout(self, timeout_ms: Optional[int]) -> Optional[int]:
+        """Caps ``timeout_ms`` to the remaining budget.
+
+        Raises ``TimeoutError`` without sending once the deadline has passed.
+        """
+        remaining = self.remaining_ms()
+        if remaining is None:
+            return timeout_ms
+        if remaining == 0:
+            raise TimeoutError(
+                f"deadline of {self.request.cls_id}/{self.request.fn_id} has passed"
+            )
+        return remaining if timeout_ms is None else min(timeout_ms, remaining)
+
+    def propagated_options(self) -> dict[str, str]:
+        """Returns the options of the served request that nested invocations carry."""
+        options = self.request.options
+        return {k: options[k] for k in PROPAGATED_OPTIONS if k in options}
+
+    def _fn_request(
+        self,
+        fn_id: str,
+        payload: bytes,
+        cls_id: Optional[str],
+        partition_id: Optional[int],
+        options: Optional[dict[str, str]],
+        timeout_ms: Optional[int],
+    ) -> InvocationRequest:
+        return InvocationRequest(
+            cls_id=cls_id if cls_id is not None else self.request.cls_id,
+            fn_id=fn_id,
+            partition_id=(
+                partition_id if partition_id is not None else self.request.partition_id
+            ),
+            options={**self.propagated_options(), **(options or {})},
+            payload=payload,
+            timeout_ms=self._timeout(timeout_ms),
+        )
+
+    def _obj_request(
+        self,
+        meta: ObjectMetadata,
+        fn_id: str,
+        payload: bytes,
+        options: Optional[dict[str, str]],
+        timeout_ms: Optional[int],
+    ) -> ObjectInvocationRequest:
+        return ObjectInvocationRequest(
+            cls_id=meta.cls_id,
+            fn_id=fn_id,
+            object_id=meta.object_id,
+            partition_id=meta.partition_id,
+            options={**self.propagated_options(), **(options or {})},
+            payload=payload,
+            timeout_ms=self._timeout(timeout_ms),
+        )
+
+    def invoke(
+        self,
+        fn_id: str,
+        payload: bytes = b"",
+        cls_id: Optional[str] = None,
+        partition_id: Optional[int] = None,
+        options: Optional[dict[str, str]] = None,
+        timeout_ms: Optional[int] = None,
+    ) -> InvocationResponse:
+        """Invokes a stateless function, of the served class unless ``cls_id`` is given.
+
+        Blocks until the response arrives; use ``invoke_async`` in async handlers.
+        """
+        req = self._fn_request(fn_id, payload, cls_id, partition_id, options, timeout_ms)
+        return self.oprc.rpc_manager.invoke_fn(req)
+
+    async def invoke_async(
+        self,
+        fn_id: str,
+        payload: bytes = b"",
+        cls_id: Optional[str] = None,
+        partition_id: Optional[int] = None,
+        options: Optional[dict[str, str]] = None,
+        timeout_ms: Optional[int] = None,
+    ) -> InvocationResponse:
+        """Invokes a stateless function, of the served class unless ``cls_id`` is given."""
+        req = self._fn_request(fn_id, payload, cls_id, partition_id, options, timeout_ms)
+        return await self.oprc.rpc_manager.invoke_fn_async(req)
+
+    def invoke_obj(
+        self,
+        meta: ObjectMetadata,
+        fn_id: str,
+        payload: bytes = b"",
+        options: Optional[dict[str, str]] = None,
+        timeout_ms: Optional[int] = None,
+    ) -> InvocationResponse:
+        """Invokes a method of the object identified by ``meta``.
+
+        Blocks until the response arrives; use ``invoke_obj_async`` in async handlers.
+        """
+        req = self._obj_request(meta, fn_id, payload, options, timeout_ms)
+        return self.oprc.rpc_manager.invoke_obj(req)
+
+    async def invoke_obj_async(
+        self,
+        meta: ObjectMetadata,
+        fn_id: str,
+        payload: bytes = b"",
+        options: Optional[dict[str, str]] = None,
+        timeout_ms: Optional[int] = None,
+    ) -> InvocationResponse:
+        """Invokes a method of the object identified by ``meta``."""
+        req = self._obj_request(meta, fn_id, payload, options, timeout_ms)
+        return await self.oprc.rpc_manager.invoke_obj_async(req)
+
+
+def current() -> Optional[InvocationContext]:
+    """Returns the context of the invocation being served, or None outside a handler."""
+    return _current.get()
+
+
+def _require() -> InvocationContext:
+    ctx = _current.get()
+    if ctx is None:
+        raise RuntimeError("no invocation is being served; call this from inside a handler")
+    return ctx
+
+
+def enter(oprc: "Oparaca", request) -> contextvars.Token:
+    """Makes ``request`` the served invocation until ``leave`` is called with the token."""
+    return _current.set(InvocationContext(oprc, request))
+
+
+def leave(token: contextvars.Token) -> None:
+    _current.reset(token)
+
+
+def remaining_ms() -> Optional[int]:
+    """``InvocationContext.remaining_ms`` of the served invocation."""
+    return _require().remaining_ms()
+
+
+def is_warmup() -> bool:
+    """``InvocationContext.warmup`` of the served invocation."""
+    return _require().warmup
+
+
+def invoke(fn_id: str, payload: bytes = b"", **kwargs) -> InvocationResponse:
+    """``InvocationContext.invoke`` on the context of the served invocation."""
+    return _require().invoke(fn_id, payload, **kwargs)
+
+
+async def invoke_async(fn_id: str, payload: bytes = b"", **kwargs) -> InvocationResponse:
+    """``InvocationContext.invoke_async`` on the context of the served invocation."""
+    return await _require().invoke_async(fn_id, payload, **kwargs)
+
+
+def invoke_obj(
+    meta: ObjectMetadata, fn_id: str, payload: bytes = b"", **kwargs
+) -> InvocationResponse:
+    """``InvocationContext.invoke_obj`` on the context of the served invocation."""
+    return _require().invoke_obj(meta, fn_id, payload, **kwargs)
+
+
+async def invoke_obj_async(
+    meta: ObjectMetadata, fn_id: str, payload: bytes = b"", **kwargs
+) -> InvocationResponse:
+    """``InvocationContext.invoke_obj_async`` on the context of the served invocation."""
+    return await _require().invoke_obj_async(meta, fn_id, payload, **kwargs)
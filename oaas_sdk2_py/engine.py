@@ -1,3 +1,5 @@
+import asyncio
+import json
 import logging
 from typing import Optional
 import oprc_py
@@ -15,6 +17,17 @@ from . import telemetry as _telemetry_mod
 logger = logging.getLogger(__name__)
 
 
+def _warmup_payload(sample) -> bytes:
+    """Encode a warm-up sample the way callers encode payloads."""
+    if isinstance(sample, (bytes, bytearray)):
+        return bytes(sample)
+    if isinstance(sample, str):
+        return sample.encode()
+    if hasattr(sample, "model_dump_json"):
+        return sample.model_dump_json().encode()
+    return json.dumps(sample).encode()
+
+
 class Oparaca:
     data_manager: oprc_py.DataManager
     rpc: oprc_py.RpcManager
@@ -86,6 +99,16 @@ class Oparaca:
                 raise RuntimeError("Engine is not available in mock mode")
             self._data_manager = self.engine.data_manager
         return self._data_manager
+
+    def run_blocking(self, fn, *args, **kwargs):
+        """Run a CPU-heavy or blocking callable without stalling the event loop.
+
+        Returns an awaitable. Uses the engine's dedicated blocking pool
+        (``OaasEngine.blocking_pool``); in mock mode falls back to ``asyncio.to_thread``.
+        """
+        if self.engine is None:
+            return asyncio.to_thread(fn, *args, **kwargs)
+        return self.engine.blocking_pool.run_blocking(fn, *args, **kwargs)
             
         
     def new_cls(self, name: Optional[str] = None, pkg: Optional[str] = None) -> ClsMeta:
@@ -114,10 +137,31 @@ class Oparaca:
                 self.meta_repo,
             )
 
+    def _set_warmup(self, cls_meta: ClsMeta, object_id: Optional[int] = None, partition_id: Optional[int] = None):
+        """Register the warm-up payloads of the functions of ``cls_meta`` with the engine.
+
+        Methods are only warmed up given ``object_id``, as warm-up invocations run
+        the handler for real.
+        """
+        for fn_id, fn_meta in cls_meta.func_dict.items():
+            if not fn_meta.warmup or (not fn_meta.stateless and object_id is None):
+                continue
+            payloads = [_warmup_payload(p) for p in fn_meta.warmup]
+            self.engine.set_warmup(
+                f"{cls_meta.pkg}.{cls_meta.name}",
+                fn_id,
+                payloads,
+                invocations=len(payloads) * self.config.warmup_rounds,
+                partition_id=partition_id if partition_id is not None else self.default_partition_id,
+                object_id=None if fn_meta.stateless else object_id,
+            )
+
     def start_grpc_server(self, loop=None, port=8080):
         if self.mock_mode:
             # No-op in mock mode: simulate server started
             return
+        for cls_meta in self.meta_repo.cls_dict.values():
+            self._set_warmup(cls_meta, self.config.warmup_object_id)
         if self.async_mode:
             self.engine.serve_grpc_server_async(port, loop, AsyncInvocationHandler(self))
         else:
@@ -128,6 +172,13 @@ class Oparaca:
         if self.engine:
             self.engine.stop_server()
 
+    def asgi_app(self):
+        """Return an ASGI app serving the functions of this instance over HTTP,
+        to mount into an existing web app. See ``oaas_sdk2_py.asgi``.
+        """
+        from .asgi import OaasAsgiApp
+        return OaasAsgiApp(self)
+
     async def run_agent(
         self,
         loop,
@@ -137,6 +188,8 @@ class Oparaca:
     ):
         if parition_id is None:
             parition_id = self.default_partition_id
+        if not self.mock_mode and self.engine is not None:
+            self._set_warmup(cls_meta, obj_id, parition_id)
         for fn_id, fn_meta in cls_meta.func_dict.items():
             if fn_meta.serve_with_agent:
                 if self.mock_mode or self.engine is None:
@@ -146,7 +199,10 @@ class Oparaca:
                     key = f"oprc/{cls_meta.pkg}.{cls_meta.name}/{parition_id}/invokes/{fn_id}"
                 else:
                     key = f"oprc/{cls_meta.pkg}.{cls_meta.name}/{parition_id}/objects/{obj_id}/invokes/{fn_id}"
-                await self.engine.serve_function(key, loop, AsyncInvocationHandler(self))
+                await self.engine.serve_function(
+                    key, loop, AsyncInvocationHandler(self),
+                    description=fn_meta.description, schema=fn_meta.schema,
+                )
 
     async def stop_agent(
         self, cls_meta: ClsMeta, obj_id: int, partition_id: Optional[int] = None
@@ -164,6 +220,78 @@ class Oparaca:
                     key = f"oprc/{cls_meta.pkg}.{cls_meta.name}/{partition_id}/objects/{obj_id}/invokes/{fn_id}"
                 await self.engine.stop_function(key)
 
+    def follow_partitions(self, loop, cls_meta: ClsMeta, on_change=None):
+        """Serve the stateless agent functions of ``cls_meta`` on the partitions
+        the platform assigns to this process, following rebalances.
+
+        ``on_change(assigned, revoked, epoch)`` (sync or async) is called after the
+        queryables are updated, e.g. to migrate in-memory state.
+        """
+        if self.mock_mode or self.engine is None:
+            return
+        self._set_warmup(cls_meta)
+        fn_metas = {
+            fn_id: fn_meta
+            for fn_id, fn_meta in cls_meta.func_dict.items()
+            if fn_meta.serve_with_agent and fn_meta.stateless
+        }
+        prefix = f"oprc/{cls_meta.pkg}.{cls_meta.name}"
+
+        async def apply(assigned, revoked, epoch):
+            for partition_id in revoked:
+                for fn_id in fn_metas:
+                    try:
+                        await self.engine.stop_function(f"{prefix}/{partition_id}/invokes/{fn_id}")
+                    except Exception as e:
+                        logger.warning("Failed to stop %s on revoked partition %s: %s", fn_id, partition_id, e)
+            for partition_id in assigned:
+                for fn_id, fn_meta in fn_metas.items():
+                    await self.engine.serve_function(
+                        f"{prefix}/{partition_id}/invokes/{fn_id}", loop, AsyncInvocationHandler(self),
+                        description=fn_meta.description, schema=fn_meta.schema,
+                    )
+            logger.info("Partition assignment epoch %s: +%s -%s", epoch, assigned, revoked)
+            if on_change is not None:
+                result = on_change(assigned, revoked, epoch)
+                if asyncio.iscoroutine(result):
+                    await result
+
+        def schedule(assigned, revoked, epoch):
+            loop.create_task(apply(assigned, revoked, epoch))
+
+        self.engine.watch_partitions(f"{cls_meta.pkg}.{cls_meta.name}", schedule, loop)
+
+    async def enter_standby(self):
+        """Keep agent functions started from now on undeclared until ``promote``.
+
+        Lets a new worker get ready next to the one it replaces.
+        """
+        if self.mock_mode or self.engine is None:
+            return
+        await self.engine.enter_standby()
+
+    async def promote(self, announce: bool = True) -> list[str]:
+        """Declare the agent functions started in standby and start serving.
+
+        With ``announce``, workers that called ``watch_takeover`` stop serving
+        the same functions after their grace period.
+        """
+        if self.mock_mode or self.engine is None:
+            return []
+        return await self.engine.promote(announce)
+
+    async def demote(self) -> list[str]:
+        """Stop receiving invocations and go back to standby."""
+        if self.mock_mode or self.engine is None:
+            return []
+        return await self.engine.demote()
+
+    def watch_takeover(self, grace_ms: int = 1000):
+        """Stop serving functions another worker promotes, ``grace_ms`` later."""
+        if self.mock_mode or self.engine is None:
+            return
+        self.engine.watch_takeover(grace_ms)
+
     def create_object(
         self,
         cls_meta: ClsMeta,
@@ -3,6 +3,9 @@ from typing import TYPE_CHECKING
 
 from oprc_py.oprc_py import InvocationRequest, InvocationResponse, InvocationResponseCode, ObjectInvocationRequest
 
+from . import context
+from .model import EXCEPTION_TYPE_HEADER
+
 if TYPE_CHECKING:
     from oaas_sdk2_py.engine import Oparaca
 
@@ -23,6 +26,7 @@ class AsyncInvocationHandler:
             invocation_request.fn_id,
             invocation_request.partition_id,
         )
+        token = context.enter(self.oprc, invocation_request)
         try:
             if self._use_auto_session_manager:
                 # Use auto session manager for session lifecycle
@@ -42,7 +46,10 @@ class AsyncInvocationHandler:
             return InvocationResponse(
                 payload=str(e).encode(),
                 status=int(InvocationResponseCode.AppError),
+                header={EXCEPTION_TYPE_HEADER: type(e).__name__},
             )
+        finally:
+            context.leave(token)
 
     async def invoke_obj(
         self, invocation_request: "ObjectInvocationRequest"
@@ -54,6 +61,7 @@ class AsyncInvocationHandler:
             invocation_request.partition_id,
             invocation_request.object_id,
         )
+        token = context.enter(self.oprc, invocation_request)
         try:
             if self._use_auto_session_manager:
                 # Use auto session manager for session lifecycle
@@ -72,7 +80,10 @@ class AsyncInvocationHandler:
             return InvocationResponse(
                 payload=str(e).encode(),
                 status=int(InvocationResponseCode.AppError),
+                header={EXCEPTION_TYPE_HEADER: type(e).__name__},
             )
+        finally:
+            context.leave(token)
         return resp
 
 
@@ -93,6 +104,7 @@ class SyncInvocationHandler:
             invocation_request.fn_id,
             invocation_request.partition_id,
         )
+        token = context.enter(self.oprc, invocation_request)
         try:
             if self._use_auto_session_manager:
                 # Use auto session manager for session lifecycle
@@ -112,7 +124,10 @@ class SyncInvocationHandler:
             return InvocationResponse(
                 payload=str(e).encode(),
                 status=int(InvocationResponseCode.AppError),
+                header={EXCEPTION_TYPE_HEADER: type(e).__name__},
             )
+        finally:
+            context.leave(token)
 
     def invoke_obj(
         self, invocation_request: "ObjectInvocationRequest"
@@ -124,6 +139,7 @@ class SyncInvocationHandler:
             invocation_request.partition_id,
             invocation_request.object_id,
         )
+        token = context.enter(self.oprc, invocation_request)
         try:
             if self._use_auto_session_manager:
                 # Use auto session manager for session lifecycle
@@ -142,5 +158,8 @@ class SyncInvocationHandler:
             return InvocationResponse(
                 payload=str(e).encode(),
                 status=int(InvocationResponseCode.AppError),
+                header={EXCEPTION_TYPE_HEADER: type(e).__name__},
             )
+        finally:
+            context.leave(token)
         return resp
@@ -1,4 +1,6 @@
+import asyncio
 import builtins
+import concurrent.futures
 import logging
 from oprc_py.oprc_py import (
     InvocationRequest,
@@ -68,11 +70,16 @@ class LocalDataManager:
         else:
             raise KeyError(f"Object with metadata {metadata} not found")
 
+    # Local objects never reach the data plane, so there is no traffic to
+    # report.
+    def stats(self) -> list:
+        return []
+
 
 class LocalRpcManager:
     session: "Session"
 
-    async def invoke_fn_async(self, req: InvocationRequest) -> InvocationResponse:
+    async def invoke_fn_async(self, req: InvocationRequest, priority=None, congestion_control=None, express=None, on_progress=None) -> InvocationResponse:
         resp = await self.session.invoke_local_async(req)
         try:
             await self.session.commit_async()
@@ -81,7 +88,7 @@ class LocalRpcManager:
         return resp
     
     
-    def invoke_fn(self, req: InvocationRequest) -> InvocationResponse:
+    def invoke_fn(self, req: InvocationRequest, priority=None, congestion_control=None, express=None) -> InvocationResponse:
         resp = self.session.invoke_local(req)
         try:
             self.session.commit()
@@ -89,7 +96,7 @@ class LocalRpcManager:
             pass
         return resp
 
-    async def invoke_obj_async(self, req: ObjectInvocationRequest) -> InvocationResponse:
+    async def invoke_obj_async(self, req: ObjectInvocationRequest, priority=None, congestion_control=None, express=None, on_progress=None) -> InvocationResponse:
         resp = await self.session.invoke_local_async(req)
         try:
             await self.session.commit_async()
@@ -98,10 +105,115 @@ class LocalRpcManager:
         return resp
     
     
-    def invoke_obj(self, req: ObjectInvocationRequest) -> InvocationResponse:
+    def invoke_obj(self, req: ObjectInvocationRequest, priority=None, congestion_control=None, express=None) -> InvocationResponse:
         resp = self.session.invoke_local(req)
         try:
             self.session.commit()
         except Exception:
             pass
         return resp
+
+    def invoke_fn_future(self, req: InvocationRequest, priority=None, congestion_control=None, express=None) -> concurrent.futures.Future:
+        return self._resolved(self.invoke_fn, req)
+
+    def invoke_obj_future(self, req: ObjectInvocationRequest, priority=None, congestion_control=None, express=None) -> concurrent.futures.Future:
+        return self._resolved(self.invoke_obj, req)
+
+    @staticmethod
+    def _resolved(invoke, req) -> concurrent.futures.Future:
+        # Local invocations run inline; the future is already resolved.
+        future = concurrent.futures.Future()
+        try:
+            future.set_result(invoke(req))
+        except Exception as e:
+            future.set_exception(e)
+        return future
+
+    async def invoke_fn_batch_async(self, reqs: list[InvocationRequest]) -> list:
+        return await asyncio.gather(
+            *(self.invoke_fn_async(req) for req in reqs), return_exceptions=True
+        )
+
+    async def invoke_obj_batch_async(self, reqs: list[ObjectInvocationRequest]) -> list:
+        return await asyncio.gather(
+            *(self.invoke_obj_async(req) for req in reqs), return_exceptions=True
+        )
+
+    @staticmethod
+    def _scatter(cls_id, fn_id, partition_ids, payload, options) -> list[InvocationRequest]:
+        if len(set(partition_ids)) != len(partition_ids):
+            raise ValueError("a partition is listed more than once")
+        return [
+            InvocationRequest(
+                partition_id=partition_id,
+                cls_id=cls_id,
+                fn_id=fn_id,
+                options=dict(options or {}),
+                payload=payload or b"",
+            )
+            for partition_id in partition_ids
+        ]
+
+    def invoke_scatter(self, cls_id, fn_id, partition_ids, payload=None, options=None, timeout_ms=None) -> dict:
+        results = {}
+        for req in self._scatter(cls_id, fn_id, partition_ids, payload, options):
+            try:
+                results[req.partition_id] = self.invoke_fn(req)
+            except Exception as e:
+                results[req.partition_id] = e
+        return results
+
+    async def invoke_scatter_async(self, cls_id, fn_id, partition_ids, payload=None, options=None, timeout_ms=None) -> dict:
+        reqs = self._scatter(cls_id, fn_id, partition_ids, payload, options)
+        results = await self.invoke_fn_batch_async(reqs)
+        return {req.partition_id: result for req, result in zip(reqs, results)}
+
+    async def invoke_obj_multi(self, cls_id, fn_id, object_ids, payload=None, partition_id=0, options=None, timeout_ms=None, concurrency=None):
+        object_ids = list(object_ids)
+        if len(set(object_ids)) != len(object_ids):
+            raise ValueError("an object is listed more than once")
+        for object_id in object_ids:
+            req = ObjectInvocationRequest(
+                cls_id=cls_id,
+                fn_id=fn_id,
+                object_id=object_id,
+                partition_id=partition_id,
+                options=dict(options or {}),
+                payload=payload or b"",
+            )
+            try:
+                yield object_id, await self.invoke_obj_async(req)
+            except Exception as e:
+                yield object_id, e
+
+    async def invoke_fn_stream(self, req):
+        # Local invocations have no stream to receive chunks on; only the
+        # final response is yielded.
+        if isinstance(req, ObjectInvocationRequest):
+            yield await self.invoke_obj_async(req)
+        else:
+            yield await self.invoke_fn_async(req)
+
+    # Local invocations carry no progress key, so there is nothing to report
+    # to; events are dropped.
+    def report_progress(self, progress, event) -> None:
+        pass
+
+    async def report_progress_async(self, progress, event) -> None:
+        pass
+
+    # Local invocations complete inline, so none is ever left to drain.
+    closed = False
+
+    def close(self, timeout_ms=None) -> int:
+        self.closed = True
+        return 0
+
+    async def close_async(self, timeout_ms=None) -> int:
+        return self.close(timeout_ms)
+
+    async def __aenter__(self):
+        return self
+
+    async def __aexit__(self, exc_type, exc_value, traceback) -> None:
+        await self.close_async()
@@ -20,6 +20,10 @@ if TYPE_CHECKING:
     from oaas_sdk2_py.simplified.objects import OaasObject
     from oaas_sdk2_py.simplified.accessors import AccessorSpec  
 
+# Response header naming the exception class a failed invocation raised,
+# counted per class by the engine's exception metrics.
+EXCEPTION_TYPE_HEADER = "oprc-exception-type"
+
 
 class FuncMeta:
     def __init__(
@@ -31,6 +35,9 @@ class FuncMeta:
         stateless=False,
         serve_with_agent=False,
         is_async=False,
+        description: Optional[str] = None,
+        schema: Optional[dict] = None,
+        warmup: Optional[list] = None,
     ):
         self.func = func
         self.invoke_handler = invoke_handler
@@ -39,6 +46,9 @@ class FuncMeta:
         self.name = name
         self.serve_with_agent = serve_with_agent
         self.is_async = is_async
+        self.description = description
+        self.schema = schema
+        self.warmup = warmup
         self.__name__ = func.__name__
         self.__qualname__ = func.__qualname__
         self.__doc__ = func.__doc__
@@ -148,10 +158,21 @@ def parse_resp(resp, return_type_hint: Optional[type] = None) -> InvocationRespo
         
         return InvocationResponse(
             status=int(InvocationResponseCode.AppError),
-            payload=json.dumps(error_details).encode()
+            payload=json.dumps(error_details).encode(),
+            header={EXCEPTION_TYPE_HEADER: type(e).__name__},
         )
 
 
+def _payload_schema(sig: inspect.Signature, schema) -> Optional[dict]:
+    """JSON schema of a function's payload: ``schema`` itself, the schema of a
+    pydantic model given as ``schema``, or that of the model the function takes."""
+    if schema is None and len(sig.parameters) >= 2:
+        schema = list(sig.parameters.values())[1].annotation
+    if inspect.isclass(schema) and issubclass(schema, BaseModel):
+        return schema.model_json_schema()
+    return schema if isinstance(schema, dict) else None
+
+
 class ClsMeta:
     func_dict: dict[str, FuncMeta]
     state_dict: dict[int, StateMeta]
@@ -187,7 +208,9 @@ class ClsMeta:
             self.update(self)
         return cls
 
-    def func(self, name="", stateless=False, strict=False, serve_with_agent=False):
+    def func(self, name="", stateless=False, strict=False, serve_with_agent=False,
+             description: Optional[str] = None, schema: Optional[dict] = None,
+             warmup: Optional[list] = None):
         """
         Decorator for registering class methods as invokable functions in OaaS platform.
 
@@ -195,6 +218,11 @@ class ClsMeta:
             name: Optional function name override. Defaults to the method's original name.
             stateless: Whether the function doesn't modify object state.
             strict: Whether to use strict validation when deserializing models.
+            description: Documentation listed by ``list_functions()``. Defaults to the docstring.
+            schema: JSON schema of the payload, or a pydantic model to derive it from.
+                Defaults to the schema of a pydantic model parameter.
+            warmup: Sample payloads the server invokes the function with before
+                taking invocations; see ``OaasEngine.set_warmup``.
 
         Returns:
             A FuncMeta instance that wraps the original method and is callable
@@ -212,6 +240,8 @@ class ClsMeta:
             """
             fn_name = name if len(name) != 0 else function.__name__
             sig = inspect.signature(function)
+            fn_description = description if description is not None else inspect.getdoc(function)
+            fn_schema = _payload_schema(sig, schema)
             
             if inspect.iscoroutinefunction(function):
                 
@@ -307,6 +337,9 @@ class ClsMeta:
                     name=fn_name,
                     serve_with_agent=serve_with_agent,
                     is_async=True,
+                    description=fn_description,
+                    schema=fn_schema,
+                    warmup=warmup,
                 )
                 self.func_dict[fn_name] = fn_meta
                 return fn_meta  # Return FuncMeta instance instead of wrapper
@@ -403,6 +436,9 @@ class ClsMeta:
                     stateless=stateless,
                     name=fn_name,
                     serve_with_agent=serve_with_agent,
+                    description=fn_description,
+                    schema=fn_schema,
+                    warmup=warmup,
                 )
                 self.func_dict[fn_name] = fn_meta
                 return fn_meta  # Return FuncMeta instance instead of wrapper
@@ -616,7 +652,8 @@ class ClsMeta:
         
         return InvocationResponse(
             status=int(InvocationResponseCode.AppError),
-            payload=json.dumps(error_details).encode()
+            payload=json.dumps(error_details).encode(),
+            header={EXCEPTION_TYPE_HEADER: type(error).__name__},
         )
 
     def __str__(self):
@@ -649,7 +686,7 @@ class ClsMeta:
             pkg["functions"].append({
                 "key": f"{self.name}.{k}",
                 "function_type": "CUSTOM",
-                "description": getattr(f.func, "__doc__", None) or "",
+                "description": f.description or "",
                 # Do not emit provision_config by default per proposal
                 "config": {},
             })
@@ -8,7 +8,7 @@ simplified interface with comprehensive error handling.
 import asyncio
 import time
 from functools import wraps
-from typing import Optional
+from typing import Any, Optional
 
 from .errors import DecoratorError, get_debug_context, DebugLevel
 from .performance import PerformanceMetrics
@@ -24,9 +24,13 @@ class EnhancedFunctionDecorator:
     
     def __init__(self, name: str = "", serve_with_agent: bool = False,
                  timeout: Optional[float] = None, retry_count: int = 0,
-                 retry_delay: float = 1.0):
+                 retry_delay: float = 1.0, description: Optional[str] = None,
+                 schema: Optional[Any] = None, warmup: Optional[list] = None):
         self.name = name
         self.serve_with_agent = serve_with_agent
+        self.description = description
+        self.schema = schema
+        self.warmup = warmup
         self.timeout = timeout
         self.retry_count = retry_count
         self.retry_delay = retry_delay
@@ -53,7 +57,10 @@ class EnhancedFunctionDecorator:
             'serve_with_agent': self.serve_with_agent,
             'timeout': self.timeout,
             'retry_count': self.retry_count,
-            'retry_delay': self.retry_delay
+            'retry_delay': self.retry_delay,
+            'description': self.description,
+            'schema': self.schema,
+            'warmup': self.warmup
         }
         
         debug_ctx.log(DebugLevel.DEBUG, f"Enhanced function decorator applied to {func_name}")
@@ -382,9 +389,14 @@ class EnhancedMethodDecorator:
     
     def __init__(self, name: str = "", stateless: bool = False, strict: bool = False,
                  serve_with_agent: bool = False, timeout: Optional[float] = None,
-                 retry_count: int = 0, retry_delay: float = 1.0):
+                 retry_count: int = 0, retry_delay: float = 1.0,
+                 description: Optional[str] = None, schema: Optional[Any] = None,
+                 warmup: Optional[list] = None):
         self.name = name
         self.stateless = stateless
+        self.description = description
+        self.schema = schema
+        self.warmup = warmup
         self.strict = strict
         self.serve_with_agent = serve_with_agent
         self.timeout = timeout
@@ -414,7 +426,10 @@ class EnhancedMethodDecorator:
             'serve_with_agent': self.serve_with_agent,
             'timeout': self.timeout,
             'retry_count': self.retry_count,
-            'retry_delay': self.retry_delay
+            'retry_delay': self.retry_delay,
+            'description': self.description,
+            'schema': self.schema,
+            'warmup': self.warmup
         }
         
         debug_ctx.log(DebugLevel.DEBUG, f"Enhanced method decorator applied to {func_name}")
@@ -9,7 +9,7 @@ import asyncio
 import time
 from contextlib import contextmanager
 from datetime import datetime
-from typing import Any, Callable, Dict, Optional, Type, Union, TYPE_CHECKING
+from typing import Any, Callable, Dict, List, Optional, Type, Union, TYPE_CHECKING
 
 from .config import OaasConfig
 from .decorators import EnhancedFunctionDecorator, ConstructorDecorator, EnhancedMethodDecorator
@@ -203,7 +203,10 @@ class OaasService:
                                         name=method_config.get('name', attr_name),
                                         stateless=method_config.get('stateless', False),
                                         strict=method_config.get('strict', False),
-                                        serve_with_agent=method_config.get('serve_with_agent', False)
+                                        serve_with_agent=method_config.get('serve_with_agent', False),
+                                        description=method_config.get('description'),
+                                        schema=method_config.get('schema'),
+                                        warmup=method_config.get('warmup')
                                     )(attr)
                                     
                                     # Replace the method on the class
@@ -234,7 +237,10 @@ class OaasService:
                                     decorated_function = cls_meta.func(
                                         name=function_config.get('name', attr_name),
                                         stateless=True,  # Functions are always stateless
-                                        serve_with_agent=function_config.get('serve_with_agent', False)
+                                        serve_with_agent=function_config.get('serve_with_agent', False),
+                                        description=function_config.get('description'),
+                                        schema=function_config.get('schema'),
+                                        warmup=function_config.get('warmup')
                                     )(attr)
                                     
                                     # Replace the function on the class
@@ -382,7 +388,9 @@ class OaasService:
     @staticmethod
     def method(func_or_name=None, *, name: str = "", stateless: bool = False, strict: bool = False,
                serve_with_agent: bool = False, timeout: Optional[float] = None,
-               retry_count: int = 0, retry_delay: float = 1.0):
+               retry_count: int = 0, retry_delay: float = 1.0,
+               description: Optional[str] = None, schema: Optional[Any] = None,
+               warmup: Optional[list] = None):
         """
         Enhanced decorator to register a method as an OaaS service method with full feature parity.
         
@@ -398,6 +406,10 @@ class OaasService:
             timeout: Optional timeout in seconds for method execution
             retry_count: Number of retry attempts on failure
             retry_delay: Delay between retries in seconds
+            description: Documentation listed by list_functions(); defaults to the docstring
+            schema: JSON schema of the payload, or a pydantic model to derive it from
+            warmup: Sample payloads (bytes, str or JSON-serializable values) the server
+                invokes it with on startup, before taking invocations
             
         Returns:
             Decorated method with enhanced OaaS capabilities
@@ -414,7 +426,10 @@ class OaasService:
                 serve_with_agent=serve_with_agent,
                 timeout=timeout,
                 retry_count=retry_count,
-                retry_delay=retry_delay
+                retry_delay=retry_delay,
+                description=description,
+                schema=schema,
+                warmup=warmup
             )
             
             # Apply the enhanced decorator
@@ -435,7 +450,8 @@ class OaasService:
     @staticmethod
     def function(name: str = "", serve_with_agent: bool = False,
                  timeout: Optional[float] = None, retry_count: int = 0,
-                 retry_delay: float = 1.0):
+                 retry_delay: float = 1.0, description: Optional[str] = None,
+                 schema: Optional[Any] = None, warmup: Optional[list] = None):
         """
         Enhanced decorator for stateless functions that don't require object instances.
         
@@ -448,6 +464,10 @@ class OaasService:
             timeout: Optional timeout in seconds for function execution
             retry_count: Number of retry attempts on failure
             retry_delay: Delay between retries in seconds
+            description: Documentation listed by list_functions(); defaults to the docstring
+            schema: JSON schema of the payload, or a pydantic model to derive it from
+            warmup: Sample payloads (bytes, str or JSON-serializable values) the server
+                invokes it with on startup, before taking invocations
             
         Returns:
             Decorated function with enhanced OaaS capabilities
@@ -462,7 +482,10 @@ class OaasService:
                 serve_with_agent=serve_with_agent,
                 timeout=timeout,
                 retry_count=retry_count,
-                retry_delay=retry_delay
+                retry_delay=retry_delay,
+                description=description,
+                schema=schema,
+                warmup=warmup
             )
             
             # Apply the enhanced decorator
@@ -928,11 +951,98 @@ class OaasService:
         Modes:
           - No args: start server
           - gen [--out FILE] [--stdout] [--format yaml|json]: generate package spec
+          - doctor [--port PORT] [--timeout MS]: check configuration and connectivity
+          - bench CLS FN [--obj ID] [--partition N] [--payload JSON] [--rps N]
+            [--duration S] [--warmup S] [--arrival poisson|uniform] [--export]:
+            open-loop load test against a deployment
         """
         import sys
         import os
         # Ensure application services are imported/registered if package provides __init__ side effects
         # Users should import their module before invoking this entry.
+        if len(sys.argv) > 1 and sys.argv[1] == "doctor":
+            import oprc_py
+            port = None
+            timeout_ms = 3000
+            args = sys.argv[2:]
+            i = 0
+            while i < len(args):
+                a = args[i]
+                if a in ("--port", "-p") and i + 1 < len(args):
+                    port = int(args[i+1])
+                    i += 2
+                    continue
+                if a in ("--timeout", "-t") and i + 1 < len(args):
+                    timeout_ms = int(args[i+1])
+                    i += 2
+                    continue
+                i += 1
+            report = oprc_py.doctor(grpc_port=port, timeout_ms=timeout_ms)
+            print(report)
+            sys.exit(0 if report.ok else 1)
+        if len(sys.argv) > 3 and sys.argv[1] == "bench":
+            import oprc_py
+            cls_id, fn_id = sys.argv[2], sys.argv[3]
+            obj_id = None
+            partition_id = 0
+            payload = b""
+            rps = 100.0
+            duration_s = 10.0
+            warmup_s = 2.0
+            arrival = "poisson"
+            export = False
+            args = sys.argv[4:]
+            i = 0
+            while i < len(args):
+                a = args[i]
+                if a == "--obj" and i + 1 < len(args):
+                    obj_id = int(args[i+1])
+                    i += 2
+                    continue
+                if a == "--partition" and i + 1 < len(args):
+                    partition_id = int(args[i+1])
+                    i += 2
+                    continue
+                if a in ("--payload", "-p") and i + 1 < len(args):
+                    payload = args[i+1].encode()
+                    i += 2
+                    continue
+                if a == "--rps" and i + 1 < len(args):
+                    rps = float(args[i+1])
+                    i += 2
+                    continue
+                if a in ("--duration", "-d") and i + 1 < len(args):
+                    duration_s = float(args[i+1])
+                    i += 2
+                    continue
+                if a == "--warmup" and i + 1 < len(args):
+                    warmup_s = float(args[i+1])
+                    i += 2
+                    continue
+                if a == "--arrival" and i + 1 < len(args):
+                    arrival = args[i+1]
+                    i += 2
+                    continue
+                if a == "--export":
+                    export = True
+                    i += 1
+                    continue
+                i += 1
+            if export:
+                from oaas_sdk2_py import telemetry
+                telemetry.enable(service_name="oaas-bench")
+            if obj_id is None:
+                req = oprc_py.InvocationRequest(cls_id, fn_id, partition_id=partition_id, payload=payload)
+            else:
+                req = oprc_py.ObjectInvocationRequest(cls_id, fn_id, obj_id, partition_id=partition_id, payload=payload)
+            rpc = OaasService._get_global_oaas().rpc_manager
+            report = rpc.load_test(
+                req, rps, duration_s, warmup_s=warmup_s, arrival=arrival, export=export
+            )
+            print(report)
+            if export:
+                telemetry.shutdown()
+            sys.exit(0 if report.errors == 0 else 1)
         if len(sys.argv) > 1 and sys.argv[1] == "gen":
             # Parse minimal flags
             out_path = None
@@ -1021,6 +1131,23 @@ class OaasService:
         """Check if gRPC server is currently running."""
         return OaasService._server_running
 
+    @staticmethod
+    def asgi_app():
+        """
+        Create an ASGI app serving all registered services over HTTP.
+
+        Mount it into an existing FastAPI/Starlette app to expose the services
+        without a second listener, e.g. ``app.mount("/oaas", oaas.asgi_app())``.
+        Invocations are dispatched through the engine like gRPC ones.
+
+        Raises:
+            ServerError: If the app cannot be created, e.g. in mock mode
+        """
+        try:
+            return OaasService._get_global_oaas().asgi_app()
+        except Exception as e:
+            raise ServerError(f"Failed to create ASGI app: {e}") from e
+
     @staticmethod
     def get_server_info() -> Dict[str, Any]:
         """Get comprehensive server status and configuration."""
@@ -1195,6 +1322,88 @@ class OaasService:
             except Exception as e:
                 debug_ctx = get_debug_context()
                 debug_ctx.log(DebugLevel.ERROR, f"Error stopping agent {agent_id}: {e}")
+
+    @staticmethod
+    def follow_partitions(service_class: Type['OaasObject'], on_change: Any = None,
+                          loop: Any = None) -> None:
+        """
+        Serve the stateless agent methods of a service on the partitions the
+        platform assigns to this process, following rebalances.
+
+        Args:
+            service_class: Service class decorated with @oaas.service
+            on_change: Optional callback (sync or async) called with
+                (assigned, revoked, epoch) after queryables are updated
+            loop: Event loop (auto-detected if None)
+
+        Raises:
+            AgentError: If the service is invalid or the subscription fails
+        """
+        if not hasattr(service_class, '_oaas_cls_meta'):
+            raise AgentError(f"Service class {service_class.__name__} not registered with @oaas.service")
+        try:
+            OaasService._get_global_oaas().follow_partitions(
+                loop or asyncio.get_event_loop(),
+                service_class._oaas_cls_meta,
+                on_change,
+            )
+        except Exception as e:
+            raise AgentError(f"Failed to follow partitions of {service_class.__name__}: {e}") from e
+
+    @staticmethod
+    async def enter_standby() -> None:
+        """
+        Keep agent methods started from now on undeclared until ``promote``,
+        so a new worker can get ready next to the one it replaces.
+
+        Raises:
+            AgentError: If functions are already being served
+        """
+        try:
+            await OaasService._get_global_oaas().enter_standby()
+        except Exception as e:
+            raise AgentError(f"Failed to enter standby: {e}") from e
+
+    @staticmethod
+    async def promote(announce: bool = True) -> List[str]:
+        """
+        Start serving the agent methods started in standby.
+
+        Args:
+            announce: Tell workers watching for takeovers to stop serving them
+
+        Returns:
+            The key expressions now served
+        """
+        try:
+            return await OaasService._get_global_oaas().promote(announce)
+        except Exception as e:
+            raise AgentError(f"Failed to promote: {e}") from e
+
+    @staticmethod
+    async def demote() -> List[str]:
+        """
+        Stop receiving invocations and go back to standby. Running
+        invocations complete; ``promote`` serves the methods again.
+
+        Returns:
+            The key expressions no longer served
+        """
+        return await OaasService._get_global_oaas().demote()
+
+    @staticmethod
+    def watch_takeover(grace_ms: int = 1000) -> None:
+        """
+        Stop serving agent methods another worker promotes, ``grace_ms``
+        after it announced the takeover.
+
+        Raises:
+            AgentError: If the subscription fails
+        """
+        try:
+            OaasService._get_global_oaas().watch_takeover(grace_ms)
+        except Exception as e:
+            raise AgentError(f"Failed to watch takeovers: {e}") from e
     
 
 # Enhanced backward compatibility functions
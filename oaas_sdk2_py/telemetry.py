@@ -4,14 +4,18 @@ import threading
 from typing import Optional
 
 try:
-    from oprc_py import init_telemetry_py, forward_log_py, shutdown_telemetry_py  # type: ignore
+    from oprc_py import init_telemetry_py, reinit_telemetry_py, forward_log_py, shutdown_telemetry_py, telemetry_stats_py  # type: ignore
 except Exception:  # pragma: no cover - module might not be present in some build contexts
     def init_telemetry_py(service_name: Optional[str], service_version: Optional[str]):  # type: ignore
         return None
+    def reinit_telemetry_py(**config):  # type: ignore
+        return None
     def forward_log_py(level: int, message: str, module: Optional[str], line: Optional[int], thread: Optional[str]):  # type: ignore
         return None
     def shutdown_telemetry_py():  # type: ignore
         return None
+    def telemetry_stats_py():  # type: ignore
+        return None
 
 _enabled = False
 _needs_retry = False
@@ -56,10 +60,34 @@ def retry_if_needed(service_name: str | None = None, service_version: str | None
         # Still failing; leave flag set for potential later retry
         pass
 
+def reinit(config: dict | None = None, **overrides) -> None:
+    """Rebuild the exporter with new settings and swap it in at runtime.
+
+    Accepted keys: ``endpoint``, ``protocol``, ``sampler``, ``sampler_arg``,
+    ``service_name`` and ``service_version``. Missing keys keep their current
+    value. Enables telemetry if it is not enabled yet.
+    """
+    global _enabled
+    reinit_telemetry_py(**{**(config or {}), **overrides})
+    if not _enabled:
+        root = logging.getLogger()
+        if not any(isinstance(h, _ForwardHandler) for h in root.handlers):
+            root.addHandler(_ForwardHandler())
+        _enabled = True
+
+def stats():
+    """Return the span export counters as a ``TelemetryStats``.
+
+    Reports ``enabled``, ``exported_spans``, ``dropped_spans``,
+    ``failed_exports`` and whether export is paused (``export_paused``)
+    because the collector kept failing.
+    """
+    return telemetry_stats_py()
+
 def shutdown():  # pragma: no cover - one-liner
     try:
         shutdown_telemetry_py()
     except Exception:
         pass
 
-__all__ = ["enable", "retry_if_needed", "shutdown"]
+__all__ = ["enable", "reinit", "retry_if_needed", "shutdown", "stats"]
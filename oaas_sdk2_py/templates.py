@@ -0,0 +1,162 @@
+"""
+Invocation Templates
+
+Named invocation templates (class, function, options, codec, timeout) loaded
+from YAML, TOML or JSON files, so operational scripts can invoke functions by
+template name with just a payload instead of hardcoding class/function ids.
+
+Example file (YAML)::
+
+    templates:
+      resize:
+        cls: example.Image
+        fn: resize
+        object_id: 42
+        options: {priority: high}
+        codec: json
+        timeout_ms: 2000
+"""
+
+import json
+import tomllib
+from pathlib import Path
+from typing import Any, Dict, List, Literal, Optional, Union
+
+import yaml
+from pydantic import BaseModel, Field
+
+import oprc_py
+
+Request = Union[oprc_py.InvocationRequest, oprc_py.ObjectInvocationRequest]
+
+
+class InvocationTemplate(BaseModel):
+    """A named, reusable description of an invocation."""
+
+    name: str
+    cls: str = Field(description="Target class ID")
+    fn: str = Field(description="Target function ID")
+    partition: int = Field(default=0, description="Target partition ID")
+    object_id: Optional[int] = Field(default=None, description="Target object; stateless invocation if unset")
+    options: Dict[str, str] = Field(default_factory=dict, description="Request options")
+    codec: Literal["json", "text", "raw"] = Field(default="json", description="How payloads are encoded")
+    timeout_ms: Optional[int] = Field(default=None, description="Per-request timeout in milliseconds")
+
+    def encode(self, payload: Any) -> bytes:
+        """Encode ``payload`` with the template codec. ``bytes`` are passed through."""
+        if payload is None:
+            return b""
+        if isinstance(payload, (bytes, bytearray)):
+            return bytes(payload)
+        if self.codec == "json":
+            if isinstance(payload, BaseModel):
+                return payload.model_dump_json().encode()
+            return json.dumps(payload).encode()
+        if self.codec == "text":
+            return str(payload).encode()
+        raise TypeError(f"template '{self.name}' uses the raw codec and requires a bytes payload")
+
+    def build(
+        self,
+        payload: Any = None,
+        *,
+        object_id: Optional[int] = None,
+        partition: Optional[int] = None,
+        options: Optional[Dict[str, str]] = None,
+        timeout_ms: Optional[int] = None,
+    ) -> Request:
+        """Create a request from this template, with optional per-call overrides.
+
+        ``options`` are merged over the template options.
+        """
+        merged = {**self.options, **(options or {})}
+        partition_id = partition if partition is not None else self.partition
+        object_id = object_id if object_id is not None else self.object_id
+        timeout_ms = timeout_ms if timeout_ms is not None else self.timeout_ms
+        body = self.encode(payload)
+        if object_id is None:
+            return oprc_py.InvocationRequest(
+                self.cls, self.fn, partition_id, merged, body, timeout_ms
+            )
+        return oprc_py.ObjectInvocationRequest(
+            self.cls, self.fn, object_id, partition_id, merged, body, timeout_ms
+        )
+
+
+class TemplateRegistry:
+    """A set of invocation templates, looked up by name."""
+
+    def __init__(self, templates: Optional[List[InvocationTemplate]] = None):
+        self._templates: Dict[str, InvocationTemplate] = {}
+        for template in templates or []:
+            self.add(template)
+
+    @classmethod
+    def load(cls, *paths: Union[str, Path]) -> "TemplateRegistry":
+        """Create a registry from one or more template files.
+
+        Later files override templates of the same name from earlier ones.
+        """
+        registry = cls()
+        for path in paths:
+            registry.load_file(path)
+        return registry
+
+    def load_file(self, path: Union[str, Path]) -> None:
+        """Add the templates declared under ``templates`` in a YAML, TOML or JSON file."""
+        path = Path(path)
+        suffix = path.suffix.lower()
+        text = path.read_text()
+        if suffix in (".yaml", ".yml"):
+            data = yaml.safe_load(text) or {}
+        elif suffix == ".toml":
+            data = tomllib.loads(text)
+        elif suffix == ".json":
+            data = json.loads(text)
+        else:
+            raise ValueError(f"Unsupported template file type '{suffix}': {path}")
+        templates = data.get("templates", {}) if isinstance(data, dict) else None
+        if not isinstance(templates, dict):
+            raise ValueError(f"{path}: 'templates' must be a mapping of name to template")
+        for name, spec in templates.items():
+            if not isinstance(spec, dict):
+                raise ValueError(f"{path}: template '{name}' must be a mapping")
+            self.add(InvocationTemplate(name=name, **spec))
+
+    def add(self, template: InvocationTemplate) -> None:
+        """Register ``template``, replacing any template with the same name."""
+        self._templates[template.name] = template
+
+    def get(self, name: str) -> InvocationTemplate:
+        """Return the template called ``name``."""
+        try:
+            return self._templates[name]
+        except KeyError:
+            raise KeyError(
+                f"Unknown invocation template '{name}'; available: {sorted(self._templates)}"
+            ) from None
+
+    def names(self) -> List[str]:
+        """Return the registered template names, sorted."""
+        return sorted(self._templates)
+
+    def __contains__(self, name: str) -> bool:
+        return name in self._templates
+
+    def instantiate(self, name: str, payload: Any = None, **overrides) -> Request:
+        """Create a request from the template called ``name``; see ``InvocationTemplate.build``."""
+        return self.get(name).build(payload, **overrides)
+
+    def invoke(self, rpc: oprc_py.RpcManager, name: str, payload: Any = None, **overrides) -> oprc_py.InvocationResponse:
+        """Invoke the template called ``name`` through ``rpc``."""
+        req = self.instantiate(name, payload, **overrides)
+        if isinstance(req, oprc_py.ObjectInvocationRequest):
+            return rpc.invoke_obj(req)
+        return rpc.invoke_fn(req)
+
+    async def invoke_async(self, rpc: oprc_py.RpcManager, name: str, payload: Any = None, **overrides) -> oprc_py.InvocationResponse:
+        """Invoke the template called ``name`` through ``rpc`` without blocking the event loop."""
+        req = self.instantiate(name, payload, **overrides)
+        if isinstance(req, oprc_py.ObjectInvocationRequest):
+            return await rpc.invoke_obj_async(req)
+        return await rpc.invoke_fn_async(req)
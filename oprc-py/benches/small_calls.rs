@@ -0,0 +1,57 @@
+//! Per-call overhead of small, control-plane-style invocations: empty
+//! options and payloads. Run with `cargo bench --bench small_calls`.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use oprc_py::bench;
+
+fn empty_request() -> oprc_pb::InvocationRequest {
+    oprc_pb::InvocationRequest {
+        cls_id: "example.Counter".into(),
+        fn_id: "get".into(),
+        ..Default::default()
+    }
+}
+
+fn capabilities_header(c: &mut Criterion) {
+    let mut group = c.benchmark_group("capabilities_header");
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            let mut headers = HashMap::new();
+            bench::stamp_capabilities(&mut headers);
+            black_box(headers)
+        })
+    });
+    group.bench_function("formatted", |b| {
+        b.iter(|| {
+            let mut headers = HashMap::new();
+            bench::stamp_capabilities_uncached(&mut headers);
+            black_box(headers)
+        })
+    });
+    group.finish();
+}
+
+fn conversions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("conversions");
+    group.bench_function("empty_request", |b| {
+        b.iter_batched(
+            empty_request,
+            |req| black_box(bench::request_round_trip(req)),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("empty_response", |b| {
+        b.iter_batched(
+            oprc_pb::InvocationResponse::default,
+            |resp| black_box(bench::response_round_trip(resp)),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, capabilities_header, conversions);
+criterion_main!(benches);
@@ -1 +1,21 @@
 from .oprc_py import *  # noqa: F403
+
+
+def entry_fields(**fields: int):
+    """Class decorator declaring the ObjectData entry key of each model field.
+
+    Used by ``ObjectData.to_model`` / ``ObjectData.from_model`` when no explicit
+    ``fields`` mapping is passed::
+
+        @entry_fields(name=0, profile=1)
+        @dataclass
+        class User:
+            name: str
+            profile: dict
+    """
+
+    def wrap(cls):
+        cls.__oprc_fields__ = dict(fields)
+        return cls
+
+    return wrap
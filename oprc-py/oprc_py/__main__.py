@@ -0,0 +1,177 @@
+"""Command line entry point: ``python -m oprc_py invoke ...``.
+
+Exit codes: ``0`` if the invocation returned ``Okay``, ``10 + status`` for any
+other response status (e.g. ``12`` for ``AppError``), ``1`` if the invocation
+failed to complete, ``124`` on timeout and ``2`` for usage errors.
+"""
+
+import argparse
+import json
+import sys
+
+from . import oprc_py
+
+EXIT_FAILURE = 1
+EXIT_USAGE = 2
+EXIT_STATUS_BASE = 10
+EXIT_TIMEOUT = 124
+
+
+def _parse_options(pairs):
+    options = {}
+    for pair in pairs or []:
+        key, sep, value = pair.partition("=")
+        if not sep or not key:
+            raise ValueError(f"invalid option '{pair}': expected KEY=VALUE")
+        options[key] = value
+    return options
+
+
+def _read_payload(args):
+    if args.data is not None and args.data_file is not None:
+        raise ValueError("--data and --data-file are mutually exclusive")
+    if args.data_file == "-":
+        return sys.stdin.buffer.read()
+    if args.data_file is not None:
+        with open(args.data_file, "rb") as f:
+            return f.read()
+    if args.data is not None:
+        return args.data.encode()
+    return b""
+
+
+def _build_from_target(args, payload, options):
+    if args.target.count("/") == 3:
+        req = oprc_py.ObjectInvocationRequest.parse(args.target, payload)
+    else:
+        req = oprc_py.InvocationRequest.parse(args.target, payload)
+        if args.partition is not None:
+            req.partition_id = args.partition
+    req.options = {**req.options, **options}
+    if args.timeout is not None:
+        req.timeout_ms = args.timeout
+    return req
+
+
+def _build_from_template(args, payload, options):
+    try:
+        from oaas_sdk2_py.templates import TemplateRegistry
+    except ImportError as e:
+        raise ValueError("--template requires the oaas-sdk2-py package") from e
+    if not args.templates:
+        raise ValueError("--template requires --templates FILE")
+    registry = TemplateRegistry.load(*args.templates)
+    return registry.instantiate(
+        args.template,
+        payload,
+        object_id=args.object_id,
+        partition=args.partition,
+        options=options,
+        timeout_ms=args.timeout,
+    )
+
+
+def _decode(payload: bytes, output: str) -> bytes:
+    if output == "raw":
+        return payload
+    if output == "json":
+        return (json.dumps(json.loads(payload), indent=2) + "\n").encode()
+    if output == "auto":
+        try:
+            return (json.dumps(json.loads(payload), indent=2) + "\n").encode()
+        except ValueError:
+            pass
+    text = payload.decode(errors="replace")
+    return (text if text.endswith("\n") or not text else text + "\n").encode()
+
+
+def _invoke(args) -> int:
+    try:
+        payload = _read_payload(args)
+        options = _parse_options(args.option)
+        if args.template is not None:
+            if args.target is not None:
+                raise ValueError("give either a TARGET or --template, not both")
+            req = _build_from_template(args, payload, options)
+        elif args.target is not None:
+            if args.object_id is not None:
+                raise ValueError(
+                    "--object-id is only used with --template; "
+                    "use cls_id/partition_id/object_id/fn_id as TARGET"
+                )
+            req = _build_from_target(args, payload, options)
+        else:
+            raise ValueError("a TARGET or --template is required")
+    except (ValueError, KeyError, OSError) as e:
+        print(f"error: {e}", file=sys.stderr)
+        return EXIT_USAGE
+
+    rpc = oprc_py.OaasEngine().rpc_manager
+    try:
+        if isinstance(req, oprc_py.ObjectInvocationRequest):
+            resp = rpc.invoke_obj(req)
+        else:
+            resp = rpc.invoke_fn(req)
+    except TimeoutError as e:
+        print(f"error: {e}", file=sys.stderr)
+        return EXIT_TIMEOUT
+    except Exception as e:
+        print(f"error: {e}", file=sys.stderr)
+        return EXIT_FAILURE
+
+    if args.verbose:
+        print(f"status: {resp.status}", file=sys.stderr)
+        for key, value in sorted(resp.header.items()):
+            print(f"{key}: {value}", file=sys.stderr)
+    try:
+        sys.stdout.buffer.write(_decode(bytes(resp.payload), args.output))
+    except ValueError as e:
+        print(f"error: response is not valid JSON: {e}", file=sys.stderr)
+        return EXIT_FAILURE
+    sys.stdout.flush()
+    return 0 if resp.status == 0 else EXIT_STATUS_BASE + resp.status
+
+
+def main(argv=None) -> int:
+    parser = argparse.ArgumentParser(prog="python -m oprc_py")
+    sub = parser.add_subparsers(dest="command", required=True)
+    invoke = sub.add_parser(
+        "invoke",
+        help="invoke a function and print its response",
+        description=__doc__,
+        formatter_class=argparse.RawDescriptionHelpFormatter,
+    )
+    invoke.add_argument(
+        "target",
+        nargs="?",
+        help="cls_id/fn_id[?partition=N] or cls_id/partition_id/object_id/fn_id",
+    )
+    invoke.add_argument("-t", "--template", help="name of an invocation template")
+    invoke.add_argument(
+        "--templates", action="append", metavar="FILE", help="template file (repeatable)"
+    )
+    invoke.add_argument("--object-id", type=int, help="object ID (with --template)")
+    invoke.add_argument("-p", "--partition", type=int, help="partition ID")
+    invoke.add_argument(
+        "-o", "--option", action="append", metavar="KEY=VALUE", help="request option (repeatable)"
+    )
+    invoke.add_argument("-d", "--data", help="payload as a string")
+    invoke.add_argument("-f", "--data-file", help="read the payload from FILE, or stdin with '-'")
+    invoke.add_argument("--timeout", type=int, metavar="MS", help="timeout in milliseconds")
+    invoke.add_argument(
+        "--output",
+        choices=["auto", "json", "text", "raw"],
+        default="auto",
+        help="how to print the response payload (default: auto)",
+    )
+    invoke.add_argument(
+        "-v", "--verbose", action="store_true", help="print status and headers to stderr"
+    )
+    args = parser.parse_args(argv)
+    if args.command == "invoke":
+        return _invoke(args)
+    return EXIT_USAGE
+
+
+if __name__ == "__main__":
+    sys.exit(main())
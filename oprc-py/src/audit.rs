@@ -0,0 +1,484 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use pyo3::{exceptions::PyTypeError, prelude::*};
+use pyo3_async_runtimes::tokio::get_runtime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncWriteExt};
+use tracing::{info, warn};
+
+/// Key expression prefix audit records and anchors are published under,
+/// followed by the session id of the engine.
+const AUDIT_PREFIX: &str = "oprc/_audit";
+/// Records between two anchors by default.
+pub const DEFAULT_ANCHOR_EVERY: u64 = 1000;
+/// `prev` of the first record of a log.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// Precedes the hash closing every record.
+const HASH_FIELD: &str = ",\"hash\":\"";
+
+/// What is recorded of one invocation. Payloads are only recorded as their
+/// SHA-256 digest.
+#[derive(Serialize)]
+pub(crate) struct Entry {
+    /// Unix time in milliseconds.
+    pub(crate) ts: u64,
+    pub(crate) cls_id: String,
+    pub(crate) fn_id: String,
+    pub(crate) object_id: Option<u64>,
+    pub(crate) tenant: String,
+    pub(crate) chain_id: Option<String>,
+    /// Response status; `None` if the handler raised.
+    pub(crate) status: Option<i32>,
+    pub(crate) exception: Option<String>,
+    pub(crate) request_sha256: Option<String>,
+    pub(crate) response_sha256: Option<String>,
+    /// `None` for invocations rejected on admission.
+    pub(crate) duration_us: Option<u64>,
+}
+
+/// A record as written, before its hash is appended. The hash covers the
+/// serialized record, `prev` included, which chains it to the record before.
+#[derive(Serialize)]
+struct Record<'a> {
+    seq: u64,
+    #[serde(flatten)]
+    entry: &'a Entry,
+    prev: &'a str,
+}
+
+/// The fields of a record verification needs.
+#[derive(Deserialize)]
+struct Link {
+    seq: u64,
+    prev: String,
+}
+
+/// A record split into the serialized part its hash covers and the hash.
+struct Sealed {
+    link: Link,
+    body: String,
+    hash: String,
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Hex encoded SHA-256 digest of `data`.
+pub(crate) fn digest(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Appends `hash` to the serialized record `body`, as the last field.
+fn seal(body: &str, hash: &str) -> String {
+    format!("{}{}{}\"}}", &body[..body.len() - 1], HASH_FIELD, hash)
+}
+
+fn unseal(line: &str) -> Result<Sealed, String> {
+    let at = line.rfind(HASH_FIELD).ok_or("record has no hash")?;
+    let hash = line[at + HASH_FIELD.len()..]
+        .strip_suffix("\"}")
+        .ok_or("record has a malformed hash")?;
+    let body = format!("{}}}", &line[..at]);
+    let link = serde_json::from_str(&body).map_err(|e| format!("malformed record: {}", e))?;
+    Ok(Sealed {
+        link,
+        body,
+        hash: hash.to_string(),
+    })
+}
+
+/// Where records are written.
+enum Sink {
+    /// Appended to a file, one per line.
+    File(File),
+    /// Published to `<key_prefix>/records/<seq>`, for a storage to keep.
+    DataPlane,
+}
+
+/// Writes records in order, keeping the head of the chain.
+struct Writer {
+    sink: Sink,
+    session: zenoh::Session,
+    key_prefix: String,
+    anchor_every: u64,
+    seq: u64,
+    head: String,
+}
+
+impl Writer {
+    async fn append(&mut self, entry: &Entry) -> Result<(), String> {
+        let seq = self.seq + 1;
+        let body = serde_json::to_string(&Record {
+            seq,
+            entry,
+            prev: &self.head,
+        })
+        .map_err(|e| e.to_string())?;
+        let hash = digest(body.as_bytes());
+        let record = seal(&body, &hash);
+        match &mut self.sink {
+            Sink::File(file) => {
+                file.write_all(format!("{}\n", record).as_bytes())
+                    .await
+                    .map_err(|e| e.to_string())?;
+                file.flush().await.map_err(|e| e.to_string())?;
+            }
+            Sink::DataPlane => {
+                self.session
+                    .put(format!("{}/records/{}", self.key_prefix, seq), record)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        self.seq = seq;
+        self.head = hash;
+        if self.anchor_every > 0 && seq % self.anchor_every == 0 {
+            self.anchor().await;
+        }
+        Ok(())
+    }
+
+    /// Publishes the head of the chain to `<key_prefix>/anchor`. Records
+    /// rewritten after an anchor no longer match it.
+    async fn anchor(&self) {
+        let anchor = serde_json::json!({
+            "seq": self.seq,
+            "hash": self.head,
+            "ts": now_ms(),
+        });
+        let key_expr = format!("{}/anchor", self.key_prefix);
+        match self.session.put(&key_expr, anchor.to_string()).await {
+            Ok(()) => info!("anchored audit log at record {}", self.seq),
+            Err(e) => warn!("failed to publish audit anchor to {}: {}", key_expr, e),
+        }
+    }
+}
+
+/// Append-only log of the invocations an engine handles. Each record holds
+/// the hash of the one before, so a record cannot be changed, removed or
+/// inserted without breaking the chain, and the head is published every
+/// `anchor_every` records, so the log cannot be rewritten as a whole either.
+pub(crate) struct AuditLog {
+    tx: flume::Sender<Entry>,
+    key_prefix: String,
+}
+
+impl AuditLog {
+    /// Starts writing to `path`, continuing the chain of the records already
+    /// in it, or to the data plane without a path.
+    pub(crate) async fn open(
+        session: zenoh::Session,
+        path: Option<PathBuf>,
+        anchor_every: u64,
+    ) -> Result<Arc<Self>, String> {
+        let key_prefix = format!("{}/{}", AUDIT_PREFIX, session.zid());
+        let (sink, seq, head) = match path {
+            Some(path) => {
+                let (seq, head) = resume(&path).await?;
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await
+                    .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+                (Sink::File(file), seq, head)
+            }
+            None => (Sink::DataPlane, 0, GENESIS.to_string()),
+        };
+        let mut writer = Writer {
+            sink,
+            session,
+            key_prefix: key_prefix.clone(),
+            anchor_every,
+            seq,
+            head,
+        };
+        let (tx, rx) = flume::unbounded::<Entry>();
+        get_runtime().spawn(async move {
+            while let Ok(entry) = rx.recv_async().await {
+                if let Err(e) = writer.append(&entry).await {
+                    warn!(
+                        "failed to write audit record of {}/{}: {}",
+                        entry.cls_id, entry.fn_id, e
+                    );
+                }
+            }
+            if writer.anchor_every > 0 && writer.seq % writer.anchor_every != 0 {
+                writer.anchor().await;
+            }
+        });
+        Ok(Arc::new(AuditLog { tx, key_prefix }))
+    }
+
+    /// Queues `entry` to be written. Never blocks the invocation.
+    pub(crate) fn record(&self, entry: Entry) {
+        if self.tx.send(entry).is_err() {
+            warn!("audit log closed; dropping record");
+        }
+    }
+
+    /// Key expression prefix the records and anchors of this log are
+    /// published under.
+    pub(crate) fn key_prefix(&self) -> &str {
+        &self.key_prefix
+    }
+}
+
+/// Returns the sequence number and hash of the last record in `path`.
+async fn resume(path: &Path) -> Result<(u64, String), String> {
+    let text = match tokio::fs::read_to_string(path).await {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((0, GENESIS.to_string()));
+        }
+        Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+    };
+    match text.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(line) => {
+            let last = unseal(line.trim())
+                .map_err(|e| format!("cannot continue {}: {}", path.display(), e))?;
+            Ok((last.link.seq, last.hash))
+        }
+        None => Ok((0, GENESIS.to_string())),
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// Outcome of verifying an audit log with `verify_audit_log`.
+pub struct AuditReport {
+    /// Records verified, from the first one on.
+    pub records: u64,
+    /// Hash of the last verified record, if any.
+    pub head: Option<String>,
+    /// Why verification stopped early, if it did.
+    pub error: Option<String>,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl AuditReport {
+    /// `True` if every record verified.
+    #[getter]
+    fn valid(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Returns a string representation of the `AuditReport`.
+    fn __str__(&self) -> String {
+        format!(
+            "AuditReport {{ records: {}, head: {:?}, error: {:?} }}",
+            self.records, self.head, self.error
+        )
+    }
+}
+
+/// Checks that `records` form one unbroken chain from the first record on,
+/// in any order, and that it contains the anchored record, if given.
+fn verify<'a>(records: impl Iterator<Item = &'a str>, anchor: Option<(u64, &str)>) -> AuditReport {
+    let mut report = AuditReport {
+        records: 0,
+        head: None,
+        error: None,
+    };
+    let mut sealed = Vec::new();
+    for (i, line) in records.map(str::trim).filter(|l| !l.is_empty()).enumerate() {
+        match unseal(line) {
+            Ok(record) => sealed.push(record),
+            Err(e) => {
+                report.error = Some(format!("line {}: {}", i + 1, e));
+                return report;
+            }
+        }
+    }
+    sealed.sort_by_key(|record| record.link.seq);
+    for record in sealed {
+        let seq = record.link.seq;
+        let prev = report.head.as_deref().unwrap_or(GENESIS);
+        let error = if seq != report.records + 1 {
+            Some(format!(
+                "expected record {}, found {}",
+                report.records + 1,
+                seq
+            ))
+        } else if record.link.prev != prev {
+            Some(format!("record {} does not follow the record before", seq))
+        } else if digest(record.body.as_bytes()) != record.hash {
+            Some(format!("record {} was modified", seq))
+        } else if anchor.is_some_and(|(at, hash)| at == seq && hash != record.hash) {
+            Some(format!("record {} does not match the anchor", seq))
+        } else {
+            None
+        };
+        if error.is_some() {
+            report.error = error;
+            return report;
+        }
+        report.records = seq;
+        report.head = Some(record.hash);
+    }
+    if let Some((at, _)) = anchor.filter(|(at, _)| *at > report.records) {
+        report.error = Some(format!(
+            "log ends at record {}, before the anchor at record {}",
+            report.records, at
+        ));
+    }
+    report
+}
+
+/// Verifies an audit log written by `OaasEngine.enable_audit_log`.
+///
+/// # Arguments
+///
+/// * `records` - The path of a log file, or the records of a log published
+///   to the data plane, as `str` or `bytes`, in any order.
+/// * `anchor` - `(seq, hash)` of a published anchor the log must contain.
+#[pyfunction]
+#[pyo3(signature = (records, anchor=None))]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn verify_audit_log(
+    py: Python<'_>,
+    records: &Bound<'_, PyAny>,
+    anchor: Option<(u64, String)>,
+) -> PyResult<AuditReport> {
+    let lines: Vec<String> = if let Ok(path) = records.extract::<PathBuf>() {
+        std::fs::read_to_string(&path)?
+            .lines()
+            .map(str::to_string)
+            .collect()
+    } else {
+        records
+            .try_iter()?
+            .map(|item| {
+                let item = item?;
+                match item.extract::<String>() {
+                    Ok(line) => Ok(line),
+                    Err(_) => item
+                        .extract::<Vec<u8>>()
+                        .ok()
+                        .and_then(|bytes| String::from_utf8(bytes).ok())
+                        .ok_or_else(|| {
+                            PyTypeError::new_err("audit records must be str or UTF-8 bytes")
+                        }),
+                }
+            })
+            .collect::<PyResult<_>>()?
+    };
+    let anchor = anchor.as_ref().map(|(seq, hash)| (*seq, hash.as_str()));
+    Ok(py.detach(|| verify(lines.iter().map(String::as_str), anchor)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `n` records the way `Writer::append` does.
+    fn chain(n: u64) -> Vec<String> {
+        let mut head = GENESIS.to_string();
+        (1..=n)
+            .map(|seq| {
+                let entry = Entry {
+                    ts: seq,
+                    cls_id: "cls".to_string(),
+                    fn_id: "fn".to_string(),
+                    object_id: Some(seq),
+                    tenant: String::new(),
+                    chain_id: None,
+                    status: Some(0),
+                    exception: None,
+                    request_sha256: Some(digest(b"request")),
+                    response_sha256: None,
+                    duration_us: Some(10),
+                };
+                let body = serde_json::to_string(&Record {
+                    seq,
+                    entry: &entry,
+                    prev: &head,
+                })
+                .unwrap();
+                head = digest(body.as_bytes());
+                seal(&body, &head)
+            })
+            .collect()
+    }
+
+    fn verify_all(records: &[String], anchor: Option<(u64, &str)>) -> AuditReport {
+        verify(records.iter().map(String::as_str), anchor)
+    }
+
+    #[test]
+    fn verifies_a_chain_in_any_order() {
+        let mut records = chain(3);
+        let head = unseal(&records[2]).unwrap().hash;
+        records.swap(0, 2);
+        let report = verify_all(&records, Some((2, &unseal(&records[1]).unwrap().hash)));
+        assert_eq!(report.error, None);
+        assert_eq!(report.records, 3);
+        assert_eq!(report.head, Some(head));
+        assert_eq!(verify_all(&[], None).records, 0);
+    }
+
+    #[test]
+    fn detects_a_modified_record() {
+        let mut records = chain(3);
+        records[1] = records[1].replace("\"tenant\":\"\"", "\"tenant\":\"x\"");
+        let report = verify_all(&records, None);
+        assert_eq!(report.error.as_deref(), Some("record 2 was modified"));
+        assert_eq!(report.records, 1);
+    }
+
+    #[test]
+    fn detects_a_missing_record() {
+        let mut records = chain(3);
+        records.remove(1);
+        let report = verify_all(&records, None);
+        assert_eq!(report.error.as_deref(), Some("expected record 2, found 3"));
+    }
+
+    #[test]
+    fn detects_a_rewritten_chain() {
+        // A record rehashed after an edit breaks the link of the next one.
+        let mut records = chain(3);
+        let sealed = unseal(&records[1]).unwrap();
+        let body = sealed.body.replace("\"tenant\":\"\"", "\"tenant\":\"x\"");
+        records[1] = seal(&body, &digest(body.as_bytes()));
+        let report = verify_all(&records, None);
+        assert_eq!(
+            report.error.as_deref(),
+            Some("record 3 does not follow the record before")
+        );
+    }
+
+    #[test]
+    fn checks_the_anchor() {
+        let records = chain(2);
+        let report = verify_all(&records, Some((2, GENESIS)));
+        assert_eq!(
+            report.error.as_deref(),
+            Some("record 2 does not match the anchor")
+        );
+        let report = verify_all(&records, Some((5, GENESIS)));
+        assert_eq!(
+            report.error.as_deref(),
+            Some("log ends at record 2, before the anchor at record 5")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let mut records = chain(1);
+        records.push("{\"seq\":2}".to_string());
+        let report = verify_all(&records, None);
+        assert_eq!(report.error.as_deref(), Some("line 2: record has no hash"));
+    }
+}
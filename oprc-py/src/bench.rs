@@ -0,0 +1,34 @@
+//! Entry points for the micro-benchmarks in `benches/`. They link the crate
+//! as an rlib and cannot reach its private modules otherwise; this is not a
+//! public API.
+
+use std::collections::HashMap;
+
+use crate::capabilities::{CAPABILITIES_HEADER, Capabilities};
+use crate::model;
+
+/// Adds the capabilities header, as every request and response gets it.
+pub fn stamp_capabilities(headers: &mut HashMap<String, String>) {
+    headers.insert(
+        CAPABILITIES_HEADER.to_string(),
+        Capabilities::local_header().to_string(),
+    );
+}
+
+/// [`stamp_capabilities`] formatting the header on each call, for comparison.
+pub fn stamp_capabilities_uncached(headers: &mut HashMap<String, String>) {
+    headers.insert(
+        CAPABILITIES_HEADER.to_string(),
+        Capabilities::local().to_header(),
+    );
+}
+
+/// Converts a request to its Python model and back.
+pub fn request_round_trip(req: oprc_pb::InvocationRequest) -> oprc_pb::InvocationRequest {
+    model::InvocationRequest::from(req).into()
+}
+
+/// Converts a response to its Python model and back.
+pub fn response_round_trip(resp: oprc_pb::InvocationResponse) -> oprc_pb::InvocationResponse {
+    model::InvocationResponse::from(resp).into()
+}
@@ -0,0 +1,193 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Default number of queued tasks before `run_blocking` starts rejecting.
+const DEFAULT_QUEUE_LIMIT: usize = 1024;
+
+#[derive(Default)]
+struct Counters {
+    active: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+struct Workers {
+    size: usize,
+    queue_limit: usize,
+    /// Started on first use; dropping it lets the workers drain and exit.
+    sender: Option<flume::Sender<Job>>,
+}
+
+impl Workers {
+    fn start(&mut self, counters: &Arc<Counters>) -> flume::Sender<Job> {
+        if let Some(sender) = &self.sender {
+            return sender.clone();
+        }
+        let (sender, receiver) = flume::bounded::<Job>(self.queue_limit);
+        for i in 0..self.size {
+            let receiver = receiver.clone();
+            let counters = counters.clone();
+            std::thread::Builder::new()
+                .name(format!("oprc-blocking-{}", i))
+                .spawn(move || {
+                    while let Ok(job) = receiver.recv() {
+                        counters.active.fetch_add(1, Ordering::Relaxed);
+                        job();
+                        counters.active.fetch_sub(1, Ordering::Relaxed);
+                        counters.completed.fetch_add(1, Ordering::Relaxed);
+                    }
+                })
+                .expect("failed to spawn blocking pool worker");
+        }
+        self.sender = Some(sender.clone());
+        sender
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+/// A dedicated pool of OS threads for CPU-heavy or blocking handler code, so
+/// it does not stall the asyncio loop or the Tokio workers serving requests.
+pub struct BlockingPool {
+    workers: Mutex<Workers>,
+    counters: Arc<Counters>,
+}
+
+impl Default for BlockingPool {
+    fn default() -> Self {
+        let size = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+        BlockingPool {
+            workers: Mutex::new(Workers {
+                size,
+                queue_limit: DEFAULT_QUEUE_LIMIT,
+                sender: None,
+            }),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+}
+
+impl BlockingPool {
+    /// Queues `job`, failing immediately if the queue is full.
+    fn submit(&self, job: Job) -> PyResult<()> {
+        let (sender, limit) = {
+            let mut workers = self.workers.lock().unwrap();
+            (workers.start(&self.counters), workers.queue_limit)
+        };
+        sender.try_send(job).map_err(|e| match e {
+            flume::TrySendError::Full(_) => {
+                self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                PyRuntimeError::new_err(format!("blocking pool queue is full (limit {})", limit))
+            }
+            flume::TrySendError::Disconnected(_) => {
+                PyRuntimeError::new_err("blocking pool is shut down")
+            }
+        })
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl BlockingPool {
+    /// Resizes the pool. Tasks already queued finish on the previous workers.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Number of worker threads.
+    /// * `queue_limit` - Maximum number of queued tasks before `run_blocking` raises.
+    #[pyo3(signature = (size, queue_limit=DEFAULT_QUEUE_LIMIT))]
+    pub fn configure(&self, size: usize, queue_limit: usize) -> PyResult<()> {
+        if size == 0 {
+            return Err(PyValueError::new_err("size must be > 0"));
+        }
+        if queue_limit == 0 {
+            return Err(PyValueError::new_err("queue_limit must be > 0"));
+        }
+        let mut workers = self.workers.lock().unwrap();
+        workers.size = size;
+        workers.queue_limit = queue_limit;
+        workers.sender = None;
+        Ok(())
+    }
+
+    /// Runs `callable(*args, **kwargs)` on the pool and returns an awaitable of its result.
+    ///
+    /// Raises `RuntimeError` if the queue is full.
+    #[pyo3(signature = (callable, *args, **kwargs))]
+    pub fn run_blocking<'py>(
+        &self,
+        py: Python<'py>,
+        callable: Py<PyAny>,
+        args: Py<PyTuple>,
+        kwargs: Option<Py<PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.submit(Box::new(move || {
+            let res = Python::attach(|py| {
+                callable.call(py, args.bind(py), kwargs.as_ref().map(|k| k.bind(py)))
+            });
+            let _ = tx.send(res);
+        }))?;
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            rx.await.map_err(|_| {
+                PyRuntimeError::new_err("blocking task was dropped before completing")
+            })?
+        })
+    }
+
+    /// Returns the current pool size and usage counters.
+    pub fn stats(&self) -> BlockingPoolStats {
+        let workers = self.workers.lock().unwrap();
+        BlockingPoolStats {
+            size: workers.size,
+            queue_limit: workers.queue_limit,
+            queued: workers.sender.as_ref().map(|s| s.len()).unwrap_or(0),
+            active: self.counters.active.load(Ordering::Relaxed),
+            completed: self.counters.completed.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// A snapshot of `BlockingPool` usage.
+pub struct BlockingPoolStats {
+    /// Number of worker threads.
+    pub size: usize,
+    /// Maximum number of queued tasks.
+    pub queue_limit: usize,
+    /// Tasks waiting for a worker.
+    pub queued: usize,
+    /// Tasks currently running.
+    pub active: usize,
+    /// Tasks finished since the pool was created.
+    pub completed: u64,
+    /// Tasks rejected because the queue was full.
+    pub rejected: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl BlockingPoolStats {
+    /// Returns a string representation of the `BlockingPoolStats`.
+    fn __str__(&self) -> String {
+        format!(
+            "BlockingPoolStats {{ size: {}, queue_limit: {}, queued: {}, active: {}, completed: {}, rejected: {} }}",
+            self.size, self.queue_limit, self.queued, self.active, self.completed, self.rejected
+        )
+    }
+}
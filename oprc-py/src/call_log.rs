@@ -0,0 +1,127 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use pyo3::prelude::*;
+
+use crate::audit::now_ms;
+
+/// Ring buffer of the last invocations sent or handled, kept in memory to
+/// debug without telemetry infrastructure. Holds nothing until given a
+/// capacity.
+#[derive(Default)]
+pub(crate) struct CallLog {
+    capacity: AtomicUsize,
+    records: Mutex<VecDeque<CallRecord>>,
+}
+
+impl CallLog {
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Keeps the last `capacity` invocations, dropping older ones; `0`
+    /// disables the log and clears it.
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut records = self.records.lock().unwrap();
+        let excess = records.len().saturating_sub(capacity);
+        records.drain(..excess);
+        records.shrink_to(capacity);
+    }
+
+    /// Whether invocations are recorded, so callers can skip building
+    /// records otherwise.
+    pub(crate) fn enabled(&self) -> bool {
+        self.capacity() > 0
+    }
+
+    /// Records an invocation that started `duration` ago, dropping the
+    /// oldest one if the log is full.
+    pub(crate) fn record(
+        &self,
+        cls_id: &str,
+        fn_id: &str,
+        object_id: Option<u64>,
+        outcome: Result<i32, String>,
+        duration: Duration,
+    ) {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        let (status, error) = match outcome {
+            Ok(status) => (Some(status), None),
+            Err(error) => (None, Some(error)),
+        };
+        let record = CallRecord {
+            timestamp_ms: now_ms().saturating_sub(duration.as_millis() as u64),
+            cls_id: cls_id.to_string(),
+            fn_id: fn_id.to_string(),
+            object_id,
+            status,
+            error,
+            duration_ms: duration.as_secs_f64() * 1000.0,
+        };
+        let mut records = self.records.lock().unwrap();
+        while records.len() >= capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// The last `limit` invocations recorded, or all of them, newest first.
+    pub(crate) fn recent(&self, limit: Option<usize>) -> Vec<CallRecord> {
+        let records = self.records.lock().unwrap();
+        records
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone, Default)]
+/// An invocation recorded in a call log, as returned by
+/// `RpcManager.recent_calls` and `OaasEngine.recent_calls`.
+pub struct CallRecord {
+    /// When the invocation started, in Unix epoch milliseconds.
+    pub timestamp_ms: u64,
+    pub cls_id: String,
+    pub fn_id: String,
+    /// The object invoked; `None` for stateless invocations.
+    pub object_id: Option<u64>,
+    /// Status of the response; `None` if the invocation raised instead.
+    pub status: Option<i32>,
+    /// The error the invocation raised, if any.
+    pub error: Option<String>,
+    /// Time from the start of the invocation to its outcome, retries
+    /// included.
+    pub duration_ms: f64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl CallRecord {
+    /// Returns a string representation of the `CallRecord`.
+    fn __str__(&self) -> String {
+        format!(
+            "CallRecord {{ timestamp_ms: {}, cls_id: {}, fn_id: {}, object_id: {:?}, status: {:?}, error: {:?}, duration_ms: {:.3} }}",
+            self.timestamp_ms,
+            self.cls_id,
+            self.fn_id,
+            self.object_id,
+            self.status,
+            self.error,
+            self.duration_ms
+        )
+    }
+}
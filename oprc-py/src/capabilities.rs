@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use crate::model::InvocationResponseCode;
+
+/// Request option / response header advertising the sender's protocol
+/// version and optional features, as `{version};{feature},{feature}`.
+pub const CAPABILITIES_HEADER: &str = "oprc-capabilities";
+/// Version of the invocation protocol implemented by this SDK.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// An optional protocol feature a peer may or may not understand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Capability {
+    /// Gzip payloads (`oprc-content-encoding: gzip`).
+    Gzip,
+    /// Zstandard payloads (`oprc-content-encoding: zstd`).
+    Zstd,
+    /// Payload checksums (`oprc-checksum`).
+    Checksum,
+    /// Payloads transferred out of band (`oprc-chunked`).
+    Chunked,
+    /// `google.protobuf.Any` payload envelopes (`oprc-type-url`).
+    Envelope,
+    /// Chunked payloads of unknown length, produced while they are sent.
+    Streaming,
+}
+
+impl Capability {
+    const ALL: [Capability; 6] = [
+        Capability::Gzip,
+        Capability::Zstd,
+        Capability::Checksum,
+        Capability::Chunked,
+        Capability::Envelope,
+        Capability::Streaming,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Capability::Gzip => "gzip",
+            Capability::Zstd => "zstd",
+            Capability::Checksum => "checksum",
+            Capability::Chunked => "chunked",
+            Capability::Envelope => "envelope",
+            Capability::Streaming => "streaming",
+        }
+    }
+}
+
+/// The protocol version and features advertised by a peer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Capabilities {
+    pub(crate) version: u32,
+    /// Feature names, including ones this SDK does not know.
+    pub(crate) features: Vec<String>,
+}
+
+impl Capabilities {
+    /// What this SDK supports.
+    pub(crate) fn local() -> Self {
+        Capabilities {
+            version: PROTOCOL_VERSION,
+            features: Capability::ALL
+                .iter()
+                .map(|c| c.name().to_string())
+                .collect(),
+        }
+    }
+
+    /// Parses a `oprc-capabilities` value. Unknown features are kept, so a
+    /// newer peer's value never fails to parse.
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (version, features) = value.split_once(';').unwrap_or((value, ""));
+        Some(Capabilities {
+            version: version.trim().parse().ok()?,
+            features: features
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// Reads the capabilities advertised in `headers`, if any.
+    pub(crate) fn of(headers: &HashMap<String, String>) -> Option<Self> {
+        headers
+            .get(CAPABILITIES_HEADER)
+            .and_then(|v| Self::parse(v))
+    }
+
+    pub(crate) fn supports(&self, capability: Capability) -> bool {
+        self.features.iter().any(|f| f == capability.name())
+    }
+
+    pub(crate) fn to_header(&self) -> String {
+        format!("{};{}", self.version, self.features.join(","))
+    }
+
+    /// `Capabilities::local().to_header()`, formatted once since it is sent
+    /// with every request and response. Headers are a `HashMap<String,
+    /// String>`, so each of them still copies it into a `String` of its own.
+    pub(crate) fn local_header() -> &'static str {
+        static HEADER: OnceLock<String> = OnceLock::new();
+        HEADER.get_or_init(|| Capabilities::local().to_header())
+    }
+}
+
+/// Capabilities last advertised by the servers of each class.
+#[derive(Clone, Default)]
+pub(crate) struct PeerTable {
+    peers: Arc<RwLock<HashMap<String, Capabilities>>>,
+}
+
+impl PeerTable {
+    /// Records what the server of `cls_id` advertised in `response`. A
+    /// successful response without the header comes from an SDK predating
+    /// the handshake; failed ones may come from layers in between and are
+    /// only taken into account with the header.
+    pub(crate) fn observe(&self, cls_id: &str, response: &oprc_pb::InvocationResponse) {
+        let caps = match Capabilities::of(&response.headers) {
+            Some(caps) => caps,
+            None if response.status == InvocationResponseCode::Okay as i32 => Capabilities {
+                version: 0,
+                features: Vec::new(),
+            },
+            None => return,
+        };
+        if self.peers.read().unwrap().get(cls_id) == Some(&caps) {
+            return;
+        }
+        let mut peers = self.peers.write().unwrap();
+        peers.insert(cls_id.to_string(), caps);
+    }
+
+    pub(crate) fn get(&self, cls_id: &str) -> Option<Capabilities> {
+        self.peers.read().unwrap().get(cls_id).cloned()
+    }
+
+    /// Whether the server of `cls_id` is known to support `capability`.
+    /// `false` until a response from that class has been seen.
+    pub(crate) fn supports(&self, cls_id: &str, capability: Capability) -> bool {
+        self.peers
+            .read()
+            .unwrap()
+            .get(cls_id)
+            .is_some_and(|caps| caps.supports(capability))
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use pyo3_async_runtimes::tokio::get_runtime;
+use serde_json::{Value, json};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::warn;
+use zenoh::query::ConsolidationMode;
+
+/// Key expression prefix engines answer catalog queries under, followed by
+/// their session id.
+const CATALOG_PREFIX: &str = "oprc/_functions";
+
+/// Descriptions of the functions an engine serves, for people and generic
+/// UIs to discover what can be invoked and with which payload.
+#[derive(Default)]
+pub(crate) struct Catalog {
+    /// Entries by key expression.
+    entries: Arc<RwLock<BTreeMap<String, Value>>>,
+    /// Task answering catalog queries, once a function is served.
+    queryable: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for Catalog {
+    fn drop(&mut self) {
+        if let Some(task) = self.queryable.get_mut().take() {
+            task.abort();
+        }
+    }
+}
+
+/// Builds the entry of the function served on `key_expr`. The class and
+/// function ids are taken from the key expression when it has the usual
+/// `oprc/<cls_id>/<partition>/.../invokes/<fn_id>` form.
+fn entry(key_expr: &str, description: Option<String>, schema: Option<Value>) -> Value {
+    let invokes = key_expr.rsplit_once("/invokes/");
+    json!({
+        "key_expr": key_expr,
+        "cls_id": invokes.and_then(|_| key_expr.split('/').nth(1)),
+        "fn_id": invokes.map(|(_, fn_id)| fn_id),
+        "description": description,
+        "schema": schema,
+    })
+}
+
+impl Catalog {
+    /// Records the description and parameter schema of the function served
+    /// on `key_expr`, replacing any previous entry.
+    pub(crate) fn insert(
+        &self,
+        key_expr: &str,
+        description: Option<String>,
+        schema: Option<Value>,
+    ) {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key_expr.to_string(), entry(key_expr, description, schema));
+    }
+
+    pub(crate) fn remove(&self, key_expr: &str) {
+        self.entries.write().unwrap().remove(key_expr);
+    }
+
+    /// Returns the entries, ordered by key expression.
+    pub(crate) fn list(&self) -> Vec<Value> {
+        self.entries.read().unwrap().values().cloned().collect()
+    }
+
+    /// Answers catalog queries on `oprc/_functions/<zid>` from now on. Does
+    /// nothing if already answering.
+    pub(crate) async fn expose(&self, session: &zenoh::Session) -> zenoh::Result<()> {
+        let mut task = self.queryable.lock().await;
+        if task.is_some() {
+            return Ok(());
+        }
+        let zid = session.zid().to_string();
+        let queryable = session
+            .declare_queryable(format!("{}/{}", CATALOG_PREFIX, zid))
+            .await?;
+        let entries = self.entries.clone();
+        *task = Some(get_runtime().spawn(async move {
+            while let Ok(query) = queryable.recv_async().await {
+                let list: Vec<Value> = entries
+                    .read()
+                    .unwrap()
+                    .values()
+                    .map(|entry| {
+                        let mut entry = entry.clone();
+                        entry["zid"] = Value::String(zid.clone());
+                        entry
+                    })
+                    .collect();
+                let payload = serde_json::to_vec(&list).unwrap_or_default();
+                if let Err(e) = query.reply(query.key_expr().clone(), payload).await {
+                    warn!("failed to answer catalog query: {}", e);
+                }
+            }
+        }));
+        Ok(())
+    }
+}
+
+/// Collects the catalog entries of every engine answering within `timeout`,
+/// each with the `zid` of its engine.
+pub(crate) async fn discover(
+    session: &zenoh::Session,
+    timeout: Duration,
+) -> zenoh::Result<Vec<Value>> {
+    let replies = session
+        .get(format!("{}/*", CATALOG_PREFIX))
+        .consolidation(ConsolidationMode::None)
+        .timeout(timeout)
+        .await?;
+    let mut entries = Vec::new();
+    while let Ok(reply) = replies.recv_async().await {
+        let Ok(sample) = reply.result() else {
+            continue;
+        };
+        match serde_json::from_slice::<Vec<Value>>(&sample.payload().to_bytes()) {
+            Ok(list) => entries.extend(list),
+            Err(e) => warn!(
+                "ignoring malformed catalog from {}: {}",
+                sample.key_expr(),
+                e
+            ),
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, future::Future, path::PathBuf};
+
+use oprc_invoke::proxy::ObjectProxy;
+use oprc_pb::{ObjData, ObjMeta, ValData, ValType};
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::{fs, sync::Mutex};
+
+/// Where a `CheckpointStore` keeps its checkpoints, as a JSON object mapping
+/// consumer names to the last sequence they processed.
+enum Backend {
+    /// A local file, replaced atomically on every commit.
+    File(PathBuf),
+    /// An entry of an object, so consumers on other hosts see the same
+    /// checkpoints. Other entries of the object are left untouched.
+    Object {
+        proxy: ObjectProxy,
+        meta: ObjMeta,
+        entry: u32,
+    },
+}
+
+impl Backend {
+    async fn read(&self) -> Result<BTreeMap<String, u64>, String> {
+        let raw = match self {
+            Backend::File(path) => match fs::read(path).await {
+                Ok(raw) => Some(raw),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+            },
+            Backend::Object { proxy, meta, entry } => proxy
+                .get_obj(meta)
+                .await
+                .map_err(|e| format!("failed to read checkpoint object: {}", e))?
+                .and_then(|obj| obj.entries.get(entry).map(|v| v.data.clone())),
+        };
+        match raw {
+            Some(raw) if !raw.is_empty() => {
+                serde_json::from_slice(&raw).map_err(|e| format!("corrupt checkpoints: {}", e))
+            }
+            _ => Ok(BTreeMap::new()),
+        }
+    }
+
+    async fn write(&self, checkpoints: &BTreeMap<String, u64>) -> Result<(), String> {
+        let raw = serde_json::to_vec(checkpoints).map_err(|e| e.to_string())?;
+        match self {
+            Backend::File(path) => {
+                let tmp = path.with_extension("tmp");
+                fs::write(&tmp, &raw)
+                    .await
+                    .map_err(|e| format!("failed to write {}: {}", tmp.display(), e))?;
+                fs::rename(&tmp, path)
+                    .await
+                    .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+            }
+            Backend::Object { proxy, meta, entry } => {
+                let mut obj = proxy
+                    .get_obj(meta)
+                    .await
+                    .map_err(|e| format!("failed to read checkpoint object: {}", e))?
+                    .unwrap_or_else(|| ObjData {
+                        metadata: Some(meta.clone()),
+                        ..Default::default()
+                    });
+                obj.entries.insert(
+                    *entry,
+                    ValData {
+                        data: raw,
+                        r#type: ValType::Byte as i32,
+                    },
+                );
+                proxy
+                    .set_obj(obj)
+                    .await
+                    .map_err(|e| format!("failed to write checkpoint object: {}", e))
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+/// Persists the last sequence each consumer of a watch or change feed
+/// processed, so a restarted consumer resumes after it instead of replaying
+/// or missing events.
+///
+/// Created with `CheckpointStore.file(path)` or `OaasEngine.checkpoint_store`
+/// for one kept in an object. Any object with the same `load`/`commit`
+/// methods can stand in for it.
+pub struct CheckpointStore {
+    backend: Backend,
+    /// Serializes read-modify-write cycles of this store.
+    lock: Mutex<()>,
+}
+
+impl CheckpointStore {
+    pub(crate) fn object(proxy: ObjectProxy, meta: ObjMeta, entry: u32) -> Self {
+        CheckpointStore {
+            backend: Backend::Object { proxy, meta, entry },
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn get(&self, consumer: &str) -> Result<Option<u64>, String> {
+        Ok(self.backend.read().await?.get(consumer).copied())
+    }
+
+    /// Applies `update` to the stored checkpoints and writes them back if it
+    /// returns `true`.
+    async fn update(
+        &self,
+        update: impl FnOnce(&mut BTreeMap<String, u64>) -> bool,
+    ) -> Result<bool, String> {
+        let _guard = self.lock.lock().await;
+        let mut checkpoints = self.backend.read().await?;
+        if !update(&mut checkpoints) {
+            return Ok(false);
+        }
+        self.backend.write(&checkpoints).await?;
+        Ok(true)
+    }
+
+    async fn advance(&self, consumer: &str, sequence: u64) -> Result<bool, String> {
+        self.update(|checkpoints| {
+            if checkpoints.get(consumer).is_some_and(|s| *s >= sequence) {
+                return false;
+            }
+            checkpoints.insert(consumer.to_string(), sequence);
+            true
+        })
+        .await
+    }
+
+    fn block<T: Send>(
+        &self,
+        py: Python<'_>,
+        fut: impl Future<Output = Result<T, String>> + Send,
+    ) -> PyResult<T> {
+        py.detach(|| get_runtime().block_on(fut))
+            .map_err(PyRuntimeError::new_err)
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl CheckpointStore {
+    /// Creates a store keeping its checkpoints in the JSON file at `path`.
+    #[staticmethod]
+    fn file(path: PathBuf) -> Self {
+        CheckpointStore {
+            backend: Backend::File(path),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Returns the last sequence `consumer` committed, or `None` if it never
+    /// committed one and should start from the beginning.
+    fn load(&self, py: Python<'_>, consumer: &str) -> PyResult<Option<u64>> {
+        self.block(py, self.get(consumer))
+    }
+
+    /// Records that `consumer` processed everything up to `sequence`.
+    ///
+    /// Checkpoints only move forward; returns `False` without writing if the
+    /// stored one is already at or past `sequence`.
+    fn commit(&self, py: Python<'_>, consumer: &str, sequence: u64) -> PyResult<bool> {
+        self.block(py, self.advance(consumer, sequence))
+    }
+
+    /// Forgets the checkpoint of `consumer`, so it starts over.
+    fn reset(&self, py: Python<'_>, consumer: &str) -> PyResult<()> {
+        self.block(
+            py,
+            self.update(|checkpoints| checkpoints.remove(consumer).is_some()),
+        )
+        .map(|_| ())
+    }
+
+    /// Returns the checkpoints of all consumers.
+    fn checkpoints(&self, py: Python<'_>) -> PyResult<BTreeMap<String, u64>> {
+        self.block(py, self.backend.read())
+    }
+
+    /// Asynchronous version of `load`.
+    async fn load_async(&self, consumer: String) -> PyResult<Option<u64>> {
+        self.get(&consumer).await.map_err(PyRuntimeError::new_err)
+    }
+
+    /// Asynchronous version of `commit`.
+    async fn commit_async(&self, consumer: String, sequence: u64) -> PyResult<bool> {
+        self.advance(&consumer, sequence)
+            .await
+            .map_err(PyRuntimeError::new_err)
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+
+/// Request option and response header carrying a payload checksum, formatted
+/// as `<algorithm>:<hex digest>`.
+pub const CHECKSUM_OPTION: &str = "oprc-checksum";
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass_enum)]
+#[pyclass(eq, eq_int)]
+#[derive(PartialEq, Clone, Copy, Debug)]
+/// Algorithm used for payload checksums. Both use hardware acceleration
+/// (SSE4.2/ARMv8 CRC instructions, SSE2/AVX2 for XXH3) where available.
+pub enum ChecksumAlgorithm {
+    Crc32c,
+    Xxh3,
+}
+
+impl ChecksumAlgorithm {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Crc32c => "crc32c",
+            ChecksumAlgorithm::Xxh3 => "xxh3",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "crc32c" => Some(ChecksumAlgorithm::Crc32c),
+            "xxh3" => Some(ChecksumAlgorithm::Xxh3),
+            _ => None,
+        }
+    }
+
+    fn digest(&self, payload: &[u8]) -> String {
+        match self {
+            ChecksumAlgorithm::Crc32c => format!("{:08x}", crc32c::crc32c(payload)),
+            ChecksumAlgorithm::Xxh3 => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(payload)),
+        }
+    }
+
+    /// Returns the `<algorithm>:<hex digest>` checksum value of `payload`.
+    pub fn value(&self, payload: &[u8]) -> String {
+        format!("{}:{}", self.name(), self.digest(payload))
+    }
+
+    /// Adds the checksum of `payload` to `map` under `CHECKSUM_OPTION`.
+    pub fn stamp(&self, map: &mut HashMap<String, String>, payload: &[u8]) {
+        map.insert(CHECKSUM_OPTION.to_string(), self.value(payload));
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ChecksumAlgorithm {
+    /// Computes the hex digest of `payload`.
+    #[pyo3(name = "digest")]
+    fn py_digest(&self, payload: &[u8]) -> String {
+        self.digest(payload)
+    }
+
+    fn __str__(&self) -> &'static str {
+        self.name()
+    }
+}
+
+/// Verifies `payload` against the checksum in `map`, if any.
+///
+/// Returns the algorithm used (so a reply can be stamped the same way), or
+/// `Err` with a description if the value is malformed or does not match.
+pub fn verify(
+    map: &HashMap<String, String>,
+    payload: &[u8],
+) -> Result<Option<ChecksumAlgorithm>, String> {
+    match map.get(CHECKSUM_OPTION) {
+        Some(value) => verify_value(value, payload).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Verifies `payload` against a `<algorithm>:<hex digest>` checksum value.
+pub fn verify_value(value: &str, payload: &[u8]) -> Result<ChecksumAlgorithm, String> {
+    let (name, expected) = value
+        .split_once(':')
+        .ok_or_else(|| format!("malformed {} '{}'", CHECKSUM_OPTION, value))?;
+    let algorithm = ChecksumAlgorithm::from_name(name)
+        .ok_or_else(|| format!("unsupported checksum algorithm '{}'", name))?;
+    let actual = algorithm.digest(payload);
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "payload checksum mismatch: expected {}:{}, computed {}:{}",
+            name, expected, name, actual
+        ));
+    }
+    Ok(algorithm)
+}
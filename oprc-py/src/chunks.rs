@@ -0,0 +1,431 @@
+use std::{
+    io::SeekFrom,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use pyo3::{exceptions::PyStopAsyncIteration, prelude::*, pybacked::PyBackedBytes};
+use pyo3_async_runtimes::TaskLocals;
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
+    task::JoinHandle,
+};
+use tracing::{debug, warn};
+use zenoh::query::ConsolidationMode;
+
+use crate::memory::Reservation;
+
+/// Header (or request option) marking a payload transferred out of band in
+/// chunks, as `{count};{bytes};{key_expr}`. Chunk `i` is served at
+/// `{key_expr}/{i}`; the inline payload is empty.
+///
+/// `count` and `bytes` are `?` for a payload of unknown length, which ends
+/// with the first empty chunk.
+pub const CHUNKED_HEADER: &str = "oprc-chunked";
+/// Size of the chunks a file is split into, unless given.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+/// Request payloads larger than this are sent in chunks, unless set with
+/// `RpcManager.chunk_threshold_bytes`.
+pub(crate) const DEFAULT_CHUNK_THRESHOLD: usize = 16 << 20;
+/// Time allowed for fetching a single chunk.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(30);
+/// Key expression prefix chunked payloads are served under.
+const CHUNKS_PREFIX: &str = "oprc/_chunks";
+
+/// Where and how large a chunked payload is.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ChunkRef {
+    pub(crate) key_expr: String,
+    pub(crate) count: Option<u64>,
+    pub(crate) bytes: Option<u64>,
+}
+
+impl ChunkRef {
+    /// Parses the value of a `oprc-chunked` header.
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid {} value '{}'", CHUNKED_HEADER, value);
+        let size = |part: Option<&str>| match part {
+            Some("?") => Ok(None),
+            Some(n) => n.parse().map(Some).map_err(|_| invalid()),
+            None => Err(invalid()),
+        };
+        let mut parts = value.splitn(3, ';');
+        let count = size(parts.next())?;
+        let bytes = size(parts.next())?;
+        let key_expr = parts.next().filter(|k| !k.is_empty()).ok_or_else(invalid)?;
+        Ok(ChunkRef {
+            key_expr: key_expr.to_string(),
+            count,
+            bytes,
+        })
+    }
+
+    /// Formats this reference as the value of a `oprc-chunked` header.
+    pub(crate) fn to_header(&self) -> String {
+        let size = |n: Option<u64>| n.map_or("?".to_string(), |n| n.to_string());
+        format!(
+            "{};{};{}",
+            size(self.count),
+            size(self.bytes),
+            self.key_expr
+        )
+    }
+}
+
+/// Why fetching a chunked payload failed.
+pub(crate) enum FetchError {
+    /// The memory budget cannot cover the payload.
+    Exhausted(String),
+    Failed(String),
+}
+
+impl From<FetchError> for String {
+    fn from(value: FetchError) -> Self {
+        match value {
+            FetchError::Exhausted(msg) | FetchError::Failed(msg) => msg,
+        }
+    }
+}
+
+/// Fetches the chunks of `chunks` in order and writes them to `out`. With a
+/// `reservation`, the bytes are taken from the memory budget before each
+/// chunk is written.
+///
+/// Fails if a chunk cannot be fetched, the budget is exhausted or the total
+/// size differs from the announced one.
+pub(crate) async fn fetch_into(
+    session: &zenoh::Session,
+    chunks: &ChunkRef,
+    out: &mut (impl AsyncWrite + Unpin),
+    mut reservation: Option<&mut Reservation>,
+) -> Result<u64, FetchError> {
+    let mut received = 0u64;
+    for i in 0.. {
+        if chunks.count.is_some_and(|count| i >= count) {
+            break;
+        }
+        let key = format!("{}/{}", chunks.key_expr, i);
+        let replies = session
+            .get(key.as_str())
+            .consolidation(ConsolidationMode::None)
+            .timeout(CHUNK_TIMEOUT)
+            .await
+            .map_err(|e| FetchError::Failed(format!("failed to fetch chunk {}: {}", key, e)))?;
+        let reply = replies
+            .recv_async()
+            .await
+            .map_err(|_| FetchError::Failed(format!("no reply for chunk {}", key)))?;
+        let sample = reply.result().map_err(|e| {
+            FetchError::Failed(format!(
+                "chunk {} failed: {}",
+                key,
+                e.payload().try_to_string().unwrap_or_default()
+            ))
+        })?;
+        let data = sample.payload().to_bytes();
+        if data.is_empty() && chunks.count.is_none() {
+            break;
+        }
+        if let Some(reservation) = reservation.as_deref_mut() {
+            reservation
+                .grow(data.len() as u64, "chunked payload")
+                .map_err(FetchError::Exhausted)?;
+        }
+        received += data.len() as u64;
+        out.write_all(&data)
+            .await
+            .map_err(|e| FetchError::Failed(format!("failed to write chunk {}: {}", key, e)))?;
+    }
+    if chunks.bytes.is_some_and(|bytes| bytes != received) {
+        return Err(FetchError::Failed(format!(
+            "chunked payload at {} has {} bytes, expected {:?}",
+            chunks.key_expr, received, chunks.bytes
+        )));
+    }
+    Ok(received)
+}
+
+/// Produces the chunks of a payload in order.
+pub(crate) enum ChunkSource {
+    /// A file read `chunk_size` bytes at a time.
+    File {
+        file: File,
+        len: u64,
+        chunk_size: usize,
+    },
+    /// A Python async iterator of `bytes`, driven on the event loop of `locals`.
+    Python { iter: Py<PyAny>, locals: TaskLocals },
+    /// A payload held in memory, split `chunk_size` bytes at a time.
+    Bytes {
+        data: Vec<u8>,
+        /// Offset of the next chunk.
+        pos: usize,
+        chunk_size: usize,
+    },
+}
+
+impl ChunkSource {
+    /// Opens the file at `path` as a chunk source.
+    pub(crate) async fn file(
+        path: &std::path::Path,
+        chunk_size: usize,
+    ) -> Result<ChunkSource, String> {
+        let file = File::open(path)
+            .await
+            .map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+        let len = file
+            .metadata()
+            .await
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?
+            .len();
+        Ok(ChunkSource::File {
+            file,
+            len,
+            chunk_size,
+        })
+    }
+
+    /// Wraps a Python async iterable of `bytes`, or a callable returning one.
+    pub(crate) fn python(producer: &Bound<'_, PyAny>, locals: TaskLocals) -> PyResult<Self> {
+        let iterable = if producer.hasattr("__aiter__")? {
+            producer.clone()
+        } else {
+            producer.call0()?
+        };
+        Ok(ChunkSource::Python {
+            iter: iterable.call_method0("__aiter__")?.unbind(),
+            locals,
+        })
+    }
+
+    /// Wraps a payload held in memory.
+    pub(crate) fn bytes(data: Vec<u8>, chunk_size: usize) -> Self {
+        ChunkSource::Bytes {
+            data,
+            pos: 0,
+            chunk_size,
+        }
+    }
+
+    /// Number of chunks and bytes, if known up front.
+    fn size(&self) -> (Option<u64>, Option<u64>) {
+        match self {
+            ChunkSource::File {
+                len, chunk_size, ..
+            } => (Some(len.div_ceil(*chunk_size as u64)), Some(*len)),
+            ChunkSource::Python { .. } => (None, None),
+            ChunkSource::Bytes {
+                data, chunk_size, ..
+            } => (
+                Some(data.len().div_ceil(*chunk_size) as u64),
+                Some(data.len() as u64),
+            ),
+        }
+    }
+
+    /// Starts over from the first chunk, if the source can.
+    async fn rewind(&mut self) -> bool {
+        match self {
+            ChunkSource::File { file, .. } => file.seek(SeekFrom::Start(0)).await.is_ok(),
+            ChunkSource::Python { .. } => false,
+            ChunkSource::Bytes { pos, .. } => {
+                *pos = 0;
+                true
+            }
+        }
+    }
+
+    /// Returns chunk `index` without reading the ones before, if the source
+    /// holds the payload in memory and has it.
+    fn chunk_at(&self, index: u64) -> Option<Vec<u8>> {
+        let ChunkSource::Bytes {
+            data, chunk_size, ..
+        } = self
+        else {
+            return None;
+        };
+        let start = usize::try_from(index).ok()?.checked_mul(*chunk_size)?;
+        if start >= data.len() {
+            return None;
+        }
+        let end = data.len().min(start + chunk_size);
+        Some(data[start..end].to_vec())
+    }
+
+    /// Reads the whole source if it fits into a single chunk.
+    pub(crate) async fn read_small(&mut self) -> Result<Option<Vec<u8>>, String> {
+        let small = matches!(
+            self,
+            ChunkSource::File { len, chunk_size, .. } if *len <= *chunk_size as u64
+        );
+        if small {
+            self.next().await.map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the remaining chunks into a single payload.
+    pub(crate) async fn read_all(&mut self) -> Result<Vec<u8>, String> {
+        let mut payload = Vec::new();
+        loop {
+            let chunk = self.next().await?;
+            if chunk.is_empty() {
+                return Ok(payload);
+            }
+            payload.extend_from_slice(&chunk);
+        }
+    }
+
+    /// Returns the next chunk, or an empty one at the end.
+    async fn next(&mut self) -> Result<Vec<u8>, String> {
+        match self {
+            ChunkSource::File {
+                file, chunk_size, ..
+            } => {
+                let mut chunk = Vec::with_capacity(*chunk_size);
+                (&mut *file)
+                    .take(*chunk_size as u64)
+                    .read_to_end(&mut chunk)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(chunk)
+            }
+            ChunkSource::Python { iter, locals } => {
+                let next = Python::attach(|py| {
+                    let awaitable = iter.bind(py).call_method0("__anext__")?;
+                    pyo3_async_runtimes::into_future_with_locals(locals, awaitable)
+                })
+                .map_err(|e| e.to_string())?;
+                match next.await {
+                    Ok(chunk) => Python::attach(|py| {
+                        chunk
+                            .extract::<PyBackedBytes>(py)
+                            .map(|b| b.to_vec())
+                            .map_err(|e| format!("producer yielded a non-bytes chunk: {}", e))
+                    }),
+                    Err(e) if Python::attach(|py| e.is_instance_of::<PyStopAsyncIteration>(py)) => {
+                        Ok(Vec::new())
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            ChunkSource::Bytes {
+                data,
+                pos,
+                chunk_size,
+            } => {
+                let start = *pos;
+                *pos = data.len().min(start + *chunk_size);
+                Ok(data[start..*pos].to_vec())
+            }
+        }
+    }
+}
+
+/// Serves the chunks of a payload until dropped.
+pub(crate) struct ChunkServer {
+    chunks: ChunkRef,
+    task: JoinHandle<()>,
+}
+
+impl ChunkServer {
+    /// Reference to put in the `oprc-chunked` option of the request.
+    pub(crate) fn chunks(&self) -> &ChunkRef {
+        &self.chunks
+    }
+}
+
+impl Drop for ChunkServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serves the chunks of `source` under a new key expression, one at a time
+/// as the receiver asks for them. Only the next chunk, the last one again
+/// (for a retried fetch) or, for files, the first one (for a retried
+/// invocation) can be fetched. A payload held in memory serves any chunk.
+pub(crate) async fn serve(
+    session: &zenoh::Session,
+    mut source: ChunkSource,
+) -> Result<ChunkServer, String> {
+    static NEXT_STREAM: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let key_expr = format!(
+        "{}/{}/{:x}-{}",
+        CHUNKS_PREFIX,
+        session.zid(),
+        nanos,
+        NEXT_STREAM.fetch_add(1, Ordering::Relaxed)
+    );
+    let queryable = session
+        .declare_queryable(format!("{}/*", key_expr))
+        .await
+        .map_err(|e| format!("failed to serve chunks at {}: {}", key_expr, e))?;
+    let (count, bytes) = source.size();
+    let chunks = ChunkRef {
+        key_expr,
+        count,
+        bytes,
+    };
+    let task = tokio::spawn(async move {
+        let mut last: Option<(u64, Vec<u8>)> = None;
+        while let Ok(query) = queryable.recv_async().await {
+            let index = query
+                .key_expr()
+                .as_str()
+                .rsplit('/')
+                .next()
+                .and_then(|i| i.parse::<u64>().ok());
+            // Fetches from several receivers, e.g. a mirrored copy, need not
+            // go in order for a payload in memory.
+            if let Some(chunk) = index.and_then(|i| source.chunk_at(i)) {
+                if let Err(e) = query.reply(query.key_expr().clone(), chunk).await {
+                    debug!("failed to reply to chunk query: {}", e);
+                }
+                continue;
+            }
+            let next = last.as_ref().map_or(0, |(i, _)| i + 1);
+            let reply = match index {
+                Some(i) if last.as_ref().is_some_and(|(l, _)| *l == i) => Ok(()),
+                Some(0) if source.rewind().await => match source.next().await {
+                    Ok(chunk) => {
+                        last = Some((0, chunk));
+                        Ok(())
+                    }
+                    Err(e) => Err(format!("failed to read chunk 0: {}", e)),
+                },
+                Some(i) if i == next => match source.next().await {
+                    Ok(chunk) => {
+                        last = Some((i, chunk));
+                        Ok(())
+                    }
+                    Err(e) => Err(format!("failed to read chunk {}: {}", i, e)),
+                },
+                _ => Err(format!(
+                    "chunk {:?} is not available; next is {}",
+                    index, next
+                )),
+            };
+            let sent = match (reply, &last) {
+                (Ok(()), Some((_, chunk))) => {
+                    query.reply(query.key_expr().clone(), chunk.clone()).await
+                }
+                (Err(e), _) => {
+                    warn!("{}", e);
+                    query.reply_err(e).await
+                }
+                (Ok(()), None) => unreachable!(),
+            };
+            if let Err(e) = sent {
+                debug!("failed to reply to chunk query: {}", e);
+            }
+        }
+    });
+    Ok(ChunkServer { chunks, task })
+}
@@ -0,0 +1,186 @@
+use pyo3::{
+    Bound, IntoPyObjectExt, PyAny, PyResult, Python,
+    exceptions::{PyTypeError, PyValueError},
+    intern,
+    prelude::*,
+    types::{PyBool, PyBytes, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple},
+};
+use serde_json::{Map, Number, Value};
+
+/// Converts a Python value into a JSON value.
+///
+/// Supports `None`, `bool`, `int`, `float`, `str`, `dict`, `list`, `tuple`,
+/// pydantic models (via `model_dump(mode="json")`) and dataclasses.
+pub(crate) fn py_to_json(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let py = obj.py();
+    if obj.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(b) = obj.downcast::<PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if obj.is_instance_of::<PyInt>() {
+        if let Ok(v) = obj.extract::<i64>() {
+            return Ok(Value::from(v));
+        }
+        if let Ok(v) = obj.extract::<u64>() {
+            return Ok(Value::from(v));
+        }
+        return Err(PyValueError::new_err(
+            "integer out of range for JSON encoding",
+        ));
+    }
+    if let Ok(f) = obj.downcast::<PyFloat>() {
+        return Number::from_f64(f.value())
+            .map(Value::Number)
+            .ok_or_else(|| PyValueError::new_err("cannot encode non-finite float as JSON"));
+    }
+    if let Ok(s) = obj.downcast::<PyString>() {
+        return Ok(Value::String(s.to_str()?.to_owned()));
+    }
+    if let Ok(d) = obj.downcast::<PyDict>() {
+        let mut map = Map::with_capacity(d.len());
+        for (k, v) in d.iter() {
+            let key = match k.downcast::<PyString>() {
+                Ok(s) => s.to_str()?.to_owned(),
+                Err(_) => k.str()?.to_str()?.to_owned(),
+            };
+            map.insert(key, py_to_json(&v)?);
+        }
+        return Ok(Value::Object(map));
+    }
+    if let Ok(l) = obj.downcast::<PyList>() {
+        return l
+            .iter()
+            .map(|v| py_to_json(&v))
+            .collect::<PyResult<_>>()
+            .map(Value::Array);
+    }
+    if let Ok(t) = obj.downcast::<PyTuple>() {
+        return t
+            .iter()
+            .map(|v| py_to_json(&v))
+            .collect::<PyResult<_>>()
+            .map(Value::Array);
+    }
+    if obj.hasattr(intern!(py, "model_dump"))? {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item(intern!(py, "mode"), intern!(py, "json"))?;
+        let dumped = obj.call_method(intern!(py, "model_dump"), (), Some(&kwargs))?;
+        return py_to_json(&dumped);
+    }
+    let dataclasses = py.import(intern!(py, "dataclasses"))?;
+    if dataclasses
+        .call_method1(intern!(py, "is_dataclass"), (obj,))?
+        .is_truthy()?
+    {
+        let dumped = dataclasses.call_method1(intern!(py, "asdict"), (obj,))?;
+        return py_to_json(&dumped);
+    }
+    Err(PyTypeError::new_err(format!(
+        "Object of type {} is not JSON serializable",
+        obj.get_type().name()?
+    )))
+}
+
+/// Converts a JSON value into the equivalent Python value.
+pub(crate) fn json_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Value::Null => Ok(py.None().into_bound(py)),
+        Value::Bool(b) => (*b).into_bound_py_any(py),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                i.into_bound_py_any(py)
+            } else if let Some(u) = n.as_u64() {
+                u.into_bound_py_any(py)
+            } else {
+                n.as_f64().unwrap_or(f64::NAN).into_bound_py_any(py)
+            }
+        }
+        Value::String(s) => s.as_str().into_bound_py_any(py),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            Ok(list.into_any())
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            Ok(dict.into_any())
+        }
+    }
+}
+
+/// Encodes a Python value as JSON bytes.
+pub(crate) fn encode_json(obj: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+    let value = py_to_json(obj)?;
+    serde_json::to_vec(&value).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Decodes JSON bytes into a Python value.
+pub(crate) fn decode_json<'py>(py: Python<'py>, bytes: &[u8]) -> PyResult<Bound<'py, PyAny>> {
+    let value: Value =
+        serde_json::from_slice(bytes).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    json_to_py(py, &value)
+}
+
+/// How the `invoke_*_typed` methods of `RpcManager` encode a value into a
+/// payload and decode the response payload.
+#[derive(Clone, Copy)]
+pub(crate) enum Codec {
+    Json,
+    /// MessagePack, for the values JSON supports.
+    Msgpack,
+    /// Python's `pickle`, for any picklable value. Only for trusted peers,
+    /// as unpickling can run arbitrary code.
+    Pickle,
+}
+
+impl std::str::FromStr for Codec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Codec::Json),
+            "msgpack" => Ok(Codec::Msgpack),
+            "pickle" => Ok(Codec::Pickle),
+            other => Err(format!(
+                "unknown codec '{}', expected 'json', 'msgpack' or 'pickle'",
+                other
+            )),
+        }
+    }
+}
+
+impl Codec {
+    pub(crate) fn encode(self, obj: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+        let py = obj.py();
+        match self {
+            Codec::Json => encode_json(obj),
+            Codec::Msgpack => rmp_serde::to_vec(&py_to_json(obj)?)
+                .map_err(|e| PyValueError::new_err(e.to_string())),
+            Codec::Pickle => py
+                .import(intern!(py, "pickle"))?
+                .call_method1(intern!(py, "dumps"), (obj,))?
+                .extract(),
+        }
+    }
+
+    pub(crate) fn decode<'py>(self, py: Python<'py>, bytes: &[u8]) -> PyResult<Bound<'py, PyAny>> {
+        match self {
+            Codec::Json => decode_json(py, bytes),
+            Codec::Msgpack => {
+                let value: Value = rmp_serde::from_slice(bytes)
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?;
+                json_to_py(py, &value)
+            }
+            Codec::Pickle => py
+                .import(intern!(py, "pickle"))?
+                .call_method1(intern!(py, "loads"), (PyBytes::new(py, bytes),)),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use oprc_invoke::handler::InvocationExecutor;
+use oprc_zenoh::util::Handler;
+use prost::{
+    DecodeError, Message,
+    encoding::{DecodeContext, decode_key, skip_field},
+};
+use tracing::{debug, warn};
+use zenoh::query::Query;
+
+/// Decodes `bytes` as `M`, accepting messages of an earlier revision of the
+/// `oprc_pb` schema.
+///
+/// Messages of the current schema decode as usual. Otherwise the message is
+/// read field by field: a field the current schema reads differently, such
+/// as one whose type changed or whose number was reused, is dropped and
+/// left at its default. Fields missing from the message are left at their
+/// default too, and unknown ones are ignored as always. Returns the message
+/// and the numbers of the fields dropped; fails only if the bytes are not a
+/// protobuf message at all.
+pub(crate) fn decode<M: Message + Default + Clone>(
+    bytes: &[u8],
+) -> Result<(M, Vec<u32>), DecodeError> {
+    if let Ok(message) = M::decode(bytes) {
+        return Ok((message, Vec::new()));
+    }
+    let mut message = M::default();
+    let mut dropped = Vec::new();
+    let mut buf = bytes;
+    while !buf.is_empty() {
+        let start = buf;
+        let (tag, wire_type) = decode_key(&mut buf)?;
+        skip_field(wire_type, tag, &mut buf, DecodeContext::default())?;
+        let field = &start[..start.len() - buf.len()];
+        // Merged into a copy, so a field failing halfway leaves no trace.
+        let mut next = message.clone();
+        match next.merge(field) {
+            Ok(()) => message = next,
+            Err(_) => dropped.push(tag),
+        }
+    }
+    Ok((message, dropped))
+}
+
+/// `decode` for an invocation response; dropped fields are logged.
+pub(crate) fn decode_response(bytes: &[u8]) -> Result<oprc_pb::InvocationResponse, DecodeError> {
+    let (response, dropped) = decode(bytes)?;
+    if !dropped.is_empty() {
+        debug!(
+            ?dropped,
+            "decoded invocation response of another schema revision"
+        );
+    }
+    Ok(response)
+}
+
+/// Wraps the query handler of a function so requests of an earlier schema
+/// revision, which it would reject as malformed, are decoded with `decode`
+/// and run on `executor` directly. Requests of the current schema go to the
+/// wrapped handler untouched.
+pub struct CompatHandler<E, H> {
+    executor: Arc<E>,
+    inner: H,
+}
+
+// Not derived, which would require `E: Clone`.
+impl<E, H: Clone> Clone for CompatHandler<E, H> {
+    fn clone(&self) -> Self {
+        CompatHandler {
+            executor: self.executor.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E, H> CompatHandler<E, H> {
+    pub fn new(executor: Arc<E>, inner: H) -> Self {
+        CompatHandler { executor, inner }
+    }
+}
+
+impl<E, H> CompatHandler<E, H>
+where
+    E: InvocationExecutor + Send + Sync,
+{
+    /// Runs the request in `payload` if only `decode` can read it. Returns
+    /// `None` for requests the wrapped handler takes.
+    async fn invoke(
+        &self,
+        object: bool,
+        payload: &[u8],
+    ) -> Option<Result<oprc_pb::InvocationResponse, String>> {
+        let (result, dropped) = if object {
+            let (req, dropped) = decode::<oprc_pb::ObjectInvocationRequest>(payload).ok()?;
+            if dropped.is_empty() {
+                return None;
+            }
+            (self.executor.invoke_obj(req).await, dropped)
+        } else {
+            let (req, dropped) = decode::<oprc_pb::InvocationRequest>(payload).ok()?;
+            if dropped.is_empty() {
+                return None;
+            }
+            (self.executor.invoke_fn(req).await, dropped)
+        };
+        debug!(
+            ?dropped,
+            "served invocation request of another schema revision"
+        );
+        Some(result.map_err(|e| e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl<E, H> Handler<Query> for CompatHandler<E, H>
+where
+    E: InvocationExecutor + Send + Sync,
+    H: Handler<Query> + Send + Sync,
+{
+    async fn handle(&self, query: Query) {
+        let result = match query.payload() {
+            Some(payload) => {
+                let object = query.key_expr().as_str().contains("/objects/");
+                self.invoke(object, &payload.to_bytes()).await
+            }
+            None => None,
+        };
+        let sent = match result {
+            None => return self.inner.handle(query).await,
+            Some(Ok(resp)) => {
+                query
+                    .reply(query.key_expr().clone(), resp.encode_to_vec())
+                    .await
+            }
+            Some(Err(e)) => query.reply_err(e).await,
+        };
+        if let Err(e) = sent {
+            warn!("failed to reply to invocation: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An earlier revision of `Current`, with `count` still a number.
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Earlier {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(uint64, tag = "2")]
+        count: u64,
+        #[prost(string, tag = "9")]
+        removed: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Current {
+        #[prost(string, tag = "1")]
+        name: String,
+        #[prost(string, tag = "2")]
+        count: String,
+        #[prost(bool, tag = "3")]
+        added: bool,
+    }
+
+    #[test]
+    fn decodes_the_current_schema_as_usual() {
+        let current = Current {
+            name: "a".to_string(),
+            count: "3".to_string(),
+            added: true,
+        };
+        let (decoded, dropped) = decode::<Current>(&current.encode_to_vec()).unwrap();
+        assert_eq!(decoded, current);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn drops_fields_read_differently() {
+        let earlier = Earlier {
+            name: "a".to_string(),
+            count: 3,
+            removed: "gone".to_string(),
+        };
+        let (decoded, dropped) = decode::<Current>(&earlier.encode_to_vec()).unwrap();
+        assert_eq!(decoded.name, "a");
+        assert_eq!(decoded.count, "");
+        assert!(!decoded.added);
+        assert_eq!(dropped, [2]);
+    }
+
+    #[test]
+    fn rejects_bytes_that_are_no_message() {
+        assert!(decode::<Current>(&[0xff]).is_err());
+        assert!(decode::<Current>(&[0x0a, 0x05, b'a']).is_err());
+    }
+}
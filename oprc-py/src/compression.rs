@@ -0,0 +1,160 @@
+use std::io::{Read, Write};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+use crate::capabilities::Capability;
+
+/// Request option / response header naming the encoding of the payload.
+pub const CONTENT_ENCODING: &str = "oprc-content-encoding";
+/// Request option listing the response encodings the caller can decode.
+pub const ACCEPT_ENCODING: &str = "oprc-accept-encoding";
+pub const GZIP: &str = "gzip";
+pub const ZSTD: &str = "zstd";
+/// Payloads smaller than this are not compressed by default.
+pub const DEFAULT_COMPRESSION_MIN_BYTES: usize = 1024;
+
+/// A payload encoding, named as in `CONTENT_ENCODING`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Zstd,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => GZIP,
+            Encoding::Zstd => ZSTD,
+        }
+    }
+
+    /// The capability a peer advertises to accept this encoding.
+    pub(crate) fn capability(self) -> Capability {
+        match self {
+            Encoding::Gzip => Capability::Gzip,
+            Encoding::Zstd => Capability::Zstd,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            GZIP => Some(Encoding::Gzip),
+            ZSTD => Some(Encoding::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn encode(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder =
+                    GzEncoder::new(Vec::with_capacity(data.len() / 2), Compression::fast());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Zstd => zstd::encode_all(data, 1),
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut out = Vec::with_capacity(data.len() * 2);
+                GzDecoder::new(data).read_to_end(&mut out)?;
+                Ok(out)
+            }
+            Encoding::Zstd => zstd::decode_all(data),
+        }
+    }
+
+    /// Decodes `data` into at most `limit` bytes. Returns `None` as soon as
+    /// the output goes past `limit`, without inflating the rest.
+    pub fn decode_limited(self, data: &[u8], limit: u64) -> std::io::Result<Option<Vec<u8>>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Encoding::Gzip => Box::new(GzDecoder::new(data)),
+            Encoding::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(data)?),
+        };
+        let mut out = Vec::with_capacity(data.len().saturating_mul(2).min(limit as usize));
+        decoder
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut out)?;
+        Ok((out.len() as u64 <= limit).then_some(out))
+    }
+}
+
+/// Returns the encodings an `ACCEPT_ENCODING` value lists, in its order.
+pub fn accepted(value: Option<&String>) -> impl Iterator<Item = Encoding> + '_ {
+    value
+        .into_iter()
+        .flat_map(|v| v.split(','))
+        .filter_map(Encoding::parse)
+}
+
+/// Encodes `payload` with `encoding` if it is at least `min_bytes` long and
+/// gets smaller. Returns whether it was encoded.
+pub fn compress(
+    payload: &mut Vec<u8>,
+    encoding: Encoding,
+    min_bytes: usize,
+) -> std::io::Result<bool> {
+    if payload.len() < min_bytes {
+        return Ok(false);
+    }
+    let compressed = encoding.encode(payload)?;
+    if compressed.len() >= payload.len() {
+        return Ok(false);
+    }
+    *payload = compressed;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let data = b"hello hello hello hello hello".repeat(100);
+        for encoding in [Encoding::Gzip, Encoding::Zstd] {
+            let encoded = encoding.encode(&data).unwrap();
+            assert!(encoded.len() < data.len());
+            assert_eq!(encoding.decode(&encoded).unwrap(), data);
+            let limit = data.len() as u64;
+            assert_eq!(
+                encoding.decode_limited(&encoded, limit).unwrap(),
+                Some(data.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn decode_limited_stops_at_the_limit() {
+        // A few KB that inflate to 64 MiB.
+        let bomb = vec![0u8; 64 << 20];
+        for encoding in [Encoding::Gzip, Encoding::Zstd] {
+            let encoded = encoding.encode(&bomb).unwrap();
+            assert!(encoded.len() < 1 << 20);
+            assert_eq!(encoding.decode_limited(&encoded, 1 << 20).unwrap(), None);
+            assert_eq!(encoding.decode_limited(&encoded, 0).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn compress_skips_small_and_incompressible_payloads() {
+        let mut small = b"abc".to_vec();
+        assert!(!compress(&mut small, Encoding::Gzip, 16).unwrap());
+        assert_eq!(small, b"abc");
+
+        let mut large = vec![7u8; 4096];
+        assert!(compress(&mut large, Encoding::Zstd, 16).unwrap());
+        assert_eq!(Encoding::Zstd.decode(&large).unwrap(), vec![7u8; 4096]);
+    }
+
+    #[test]
+    fn parses_accepted_encodings_in_order() {
+        let value = "zstd, br,gzip".to_string();
+        let listed: Vec<_> = accepted(Some(&value)).collect();
+        assert_eq!(listed, [Encoding::Zstd, Encoding::Gzip]);
+        assert_eq!(accepted(None).count(), 0);
+    }
+}
@@ -1,26 +1,138 @@
 use crate::telemetry;
 use oprc_pb::ObjMeta;
-use pyo3::{IntoPyObjectExt, Py, PyAny, PyResult, Python, exceptions::PyRuntimeError};
+use pyo3::{IntoPyObjectExt, Py, PyAny, PyErr, PyResult, Python, exceptions::{PyNotImplementedError, PyRuntimeError, PyValueError}};
 pub(crate) use zenoh::Session;
 
-use crate::obj::ObjectData;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    data_stats::{self, DataCounters, DataStats},
+    gc::{self, GcReport, GcRules},
+    history::{self, At, EntryVersion},
+    ids,
+    keys::EntryKey,
+    obj::{ObjectData, ObjectMetadata},
+    replica::{ReadPreference, ReadResult, ReadSettings, Reader},
+};
+#[cfg(feature = "arrow")]
+use crate::export::{self, ExportReport};
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
 #[pyo3::pyclass]
 /// Manages data operations for objects, interacting with an object proxy.
 pub struct DataManager {
     proxy: oprc_invoke::proxy::ObjectProxy,
+    /// Routes object reads according to the read preference.
+    reader: Reader,
+    /// Object data traffic per class, reported by `stats()`.
+    counters: Arc<DataCounters>,
 }
 
 impl DataManager {
+    /// Merges `entries` into the object described by `meta`: the object is
+    /// read, the entries are set on it and it is written back with one
+    /// `set_obj`, as the data plane only writes whole objects.
+    ///
+    /// The read and the write are not guarded, so a write by someone else in
+    /// between is lost. `atomic` is rejected until the data plane offers a
+    /// conditional write.
+    async fn put_many_inner(
+        proxy: oprc_invoke::proxy::ObjectProxy,
+        counters: Arc<DataCounters>,
+        meta: ObjMeta,
+        entries: HashMap<u32, Vec<u8>>,
+        atomic: bool,
+    ) -> PyResult<()> {
+        if atomic {
+            return Err(PyNotImplementedError::new_err(format!(
+                "the data plane of {}/{} has no conditional write for an atomic put_many",
+                meta.cls_id, meta.partition_id
+            )));
+        }
+        let current = proxy
+            .get_obj(&meta)
+            .await
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        counters.record_read(&meta.cls_id, current.as_ref());
+        let mut obj = current.unwrap_or_default();
+        obj.metadata = Some(meta);
+        for (key, data) in entries {
+            obj.entries.insert(
+                key,
+                oprc_pb::ValData {
+                    data,
+                    r#type: oprc_pb::ValType::Byte as i32,
+                },
+            );
+        }
+        let (cls_id, bytes) = data_stats::write_of(&obj);
+        proxy
+            .set_obj(obj)
+            .await
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        counters.record_write(&cls_id, bytes);
+        Ok(())
+    }
+
+    /// Resolves entry names against the registry of `meta`'s class.
+    fn resolve_entries(
+        meta: &ObjectMetadata,
+        entries: HashMap<EntryKey, Vec<u8>>,
+    ) -> PyResult<HashMap<u32, Vec<u8>>> {
+        let cls_id = meta.into_proto().cls_id;
+        entries
+            .into_iter()
+            .map(|(k, v)| Ok((k.resolve(&cls_id)?, v)))
+            .collect()
+    }
+
+    /// Selects the version `get_at` reads from its mutually exclusive arguments.
+    fn resolve_at(timestamp_ms: Option<u64>, version: Option<u64>) -> PyResult<At> {
+        match (timestamp_ms, version) {
+            (Some(ts), None) => Ok(At::Timestamp(ts)),
+            (None, Some(v)) => Ok(At::Version(v)),
+            _ => Err(PyValueError::new_err(
+                "exactly one of timestamp_ms and version must be given",
+            )),
+        }
+    }
+
+    /// Validates garbage collection criteria; at least one is required.
+    fn gc_rules(referenced: Option<HashSet<u64>>, retention_ms: Option<u64>) -> PyResult<GcRules> {
+        if referenced.is_none() && retention_ms.is_none() {
+            return Err(PyValueError::new_err(
+                "at least one of referenced and retention_ms must be given",
+            ));
+        }
+        Ok(GcRules {
+            referenced,
+            retention: retention_ms.map(Duration::from_millis),
+        })
+    }
+
     /// Creates a new `DataManager` instance.
     ///
     /// # Arguments
     ///
     /// * `z_session`: A Zenoh session used for communication.
     pub fn new(z_session: Session) -> Self {
-        let proxy = oprc_invoke::proxy::ObjectProxy::new(z_session);
-        DataManager { proxy }
+        let proxy = oprc_invoke::proxy::ObjectProxy::new(z_session.clone());
+        let counters = Arc::new(DataCounters::default());
+        let reader = Reader {
+            proxy: proxy.clone(),
+            session: z_session,
+            settings: Arc::new(Mutex::new(ReadSettings::default())),
+            counters: counters.clone(),
+        };
+        DataManager {
+            proxy,
+            reader,
+            counters,
+        }
     }
 }
 
@@ -43,34 +155,39 @@ impl DataManager {
         &self,
         py: Python<'_>,
         cls_id: String,
-        partition_id: u32,
-        obj_id: u64,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
     ) -> PyResult<Py<PyAny>> {
-        let proxy = self.proxy.clone();
+        let reader = self.reader.clone();
         let runtime = pyo3_async_runtimes::tokio::get_runtime();
 
         let res = py.detach(|| {
             runtime.block_on(async move {
                 telemetry::instrument(
                     async move {
-                        proxy
-                            .get_obj(&ObjMeta {
-                                cls_id: cls_id.to_string(),
-                                partition_id,
-                                object_id: obj_id,
-                            })
+                        reader
+                            .get(
+                                &ObjMeta {
+                                    cls_id: cls_id.to_string(),
+                                    partition_id,
+                                    object_id: obj_id,
+                                },
+                                None,
+                            )
                             .await
-                            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+                            .map_err(PyRuntimeError::new_err)
                     },
                     "data.get_obj",
                 )
                 .await
+                // Convert while the GIL is released; only the wrapping below needs it.
+                .map(|routed| routed.obj.map(ObjectData::from))
             })
         });
 
         let obj_opt = res?;
         if let Some(obj_val) = obj_opt {
-            Ok(ObjectData::from(obj_val).into_py_any(py)?)
+            Ok(obj_val.into_py_any(py)?)
         } else {
             Ok(py.None())
         }
@@ -91,26 +208,30 @@ impl DataManager {
     pub async fn get_obj_async(
         &self,
         cls_id: String,
-        partition_id: u32,
-        obj_id: u64,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
     ) -> PyResult<Py<PyAny>> {
-        let proxy = self.proxy.clone();
+        let reader = self.reader.clone();
 
         let res = telemetry::instrument(
-            proxy.get_obj(&ObjMeta {
-                cls_id: cls_id.to_string(),
-                partition_id,
-                object_id: obj_id,
-            }),
+            reader.get(
+                &ObjMeta {
+                    cls_id: cls_id.to_string(),
+                    partition_id,
+                    object_id: obj_id,
+                },
+                None,
+            ),
             "data.get_obj_async",
         )
         .await
-        .map_err(|e| PyRuntimeError::new_err(e.to_string()));
+        .map_err(PyRuntimeError::new_err)
+        .map(|routed| routed.obj.map(ObjectData::from));
 
         Python::attach(|py| {
             let obj = res?;
             if let Some(obj) = obj {
-                Ok(ObjectData::from(obj).into_py_any(py)?)
+                Ok(obj.into_py_any(py)?)
             } else {
                 Ok(py.None())
             }
@@ -134,6 +255,7 @@ impl DataManager {
             let obj_borrowed = obj.borrow(py);
             obj_borrowed.into_proto()
         };
+        let (cls_id, bytes) = data_stats::write_of(&proto);
 
         py.detach(|| {
             runtime.block_on(async move {
@@ -149,6 +271,7 @@ impl DataManager {
                 .await
             })
         })?;
+        self.counters.record_write(&cls_id, bytes);
         Ok(())
     }
 
@@ -166,9 +289,11 @@ impl DataManager {
             let obj = obj.borrow(py);
             obj.into_proto()
         });
+        let (cls_id, bytes) = data_stats::write_of(&proto);
         telemetry::instrument(self.proxy.set_obj(proto), "data.set_obj_async")
             .await
             .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        self.counters.record_write(&cls_id, bytes);
         Ok(())
     }
 
@@ -187,11 +312,12 @@ impl DataManager {
         &self,
         py: Python<'_>,
         cls_id: String,
-        partition_id: u32,
-        obj_id: u64,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
     ) -> PyResult<()> {
         let proxy = self.proxy.clone();
         let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let counters = self.counters.clone();
 
         py.detach(|| {
             runtime.block_on(async move {
@@ -204,7 +330,9 @@ impl DataManager {
                                 object_id: obj_id,
                             })
                             .await
-                            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+                            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                        counters.record_delete(&cls_id);
+                        Ok::<_, PyErr>(())
                     },
                     "data.del_obj",
                 )
@@ -228,8 +356,8 @@ impl DataManager {
     pub async fn del_obj_async(
         &self,
         cls_id: String,
-        partition_id: u32,
-        obj_id: u64,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
     ) -> PyResult<()> {
         telemetry::instrument(
             self.proxy.del_obj(&ObjMeta {
@@ -241,6 +369,561 @@ impl DataManager {
         )
         .await
         .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        self.counters.record_delete(&cls_id);
         Ok(())
     }
+
+    /// Retrieves a single entry of an object by index or registered name. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class ID of the object.
+    /// * `partition_id`: The partition ID where the object resides.
+    /// * `obj_id`: The unique ID of the object.
+    /// * `key`: The entry index, or a name registered with `register_entry_keys`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing the entry bytes, or `None` if the object or entry does not exist.
+    pub fn get_entry(
+        &self,
+        py: Python<'_>,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
+        key: EntryKey,
+    ) -> PyResult<Option<Vec<u8>>> {
+        let index = key.resolve(&cls_id)?;
+        let reader = self.reader.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+
+        let obj = py.detach(|| {
+            runtime.block_on(async move {
+                telemetry::instrument(
+                    async move {
+                        reader
+                            .get(
+                                &ObjMeta {
+                                    cls_id,
+                                    partition_id,
+                                    object_id: obj_id,
+                                },
+                                None,
+                            )
+                            .await
+                            .map(|routed| routed.obj)
+                            .map_err(PyRuntimeError::new_err)
+                    },
+                    "data.get_entry",
+                )
+                .await
+            })
+        })?;
+        Ok(obj.and_then(|mut o| o.entries.remove(&index)).map(|v| v.data))
+    }
+
+    /// Retrieves a single entry of an object by index or registered name. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class ID of the object.
+    /// * `partition_id`: The partition ID where the object resides.
+    /// * `obj_id`: The unique ID of the object.
+    /// * `key`: The entry index, or a name registered with `register_entry_keys`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing the entry bytes, or `None` if the object or entry does not exist.
+    pub async fn get_entry_async(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
+        key: EntryKey,
+    ) -> PyResult<Option<Vec<u8>>> {
+        let index = key.resolve(&cls_id)?;
+        let obj = telemetry::instrument(
+            self.reader.get(
+                &ObjMeta {
+                    cls_id,
+                    partition_id,
+                    object_id: obj_id,
+                },
+                None,
+            ),
+            "data.get_entry_async",
+        )
+        .await
+        .map_err(PyRuntimeError::new_err)?
+        .obj;
+        Ok(obj.and_then(|mut o| o.entries.remove(&index)).map(|v| v.data))
+    }
+
+    /// Writes several entries of one object, keeping its other entries. (Synchronous)
+    ///
+    /// The object is read and written back with the entries set, in two round
+    /// trips. A write by someone else in between is lost.
+    ///
+    /// # Arguments
+    ///
+    /// * `meta`: The metadata of the target object.
+    /// * `entries`: Entry values keyed by index or registered name.
+    /// * `atomic`: Raises `NotImplementedError` when true, as the data plane has
+    ///   no conditional write to apply the entries all or nothing.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` indicating success or failure.
+    #[pyo3(signature = (meta, entries, atomic=false))]
+    pub fn put_many(
+        &self,
+        py: Python<'_>,
+        meta: ObjectMetadata,
+        entries: HashMap<EntryKey, Vec<u8>>,
+        atomic: bool,
+    ) -> PyResult<()> {
+        let entries = Self::resolve_entries(&meta, entries)?;
+        let proxy = self.proxy.clone();
+        let counters = self.counters.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let meta = meta.into_proto();
+        py.detach(|| {
+            runtime.block_on(telemetry::instrument(
+                Self::put_many_inner(proxy, counters, meta, entries, atomic),
+                "data.put_many",
+            ))
+        })
+    }
+
+    /// Writes several entries of one object, keeping its other entries. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `meta`: The metadata of the target object.
+    /// * `entries`: Entry values keyed by index or registered name.
+    /// * `atomic`: See `put_many`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` indicating success or failure.
+    #[pyo3(signature = (meta, entries, atomic=false))]
+    pub async fn put_many_async(
+        &self,
+        meta: ObjectMetadata,
+        entries: HashMap<EntryKey, Vec<u8>>,
+        atomic: bool,
+    ) -> PyResult<()> {
+        let entries = Self::resolve_entries(&meta, entries)?;
+        telemetry::instrument(
+            Self::put_many_inner(
+                self.proxy.clone(),
+                self.counters.clone(),
+                meta.into_proto(),
+                entries,
+                atomic,
+            ),
+            "data.put_many_async",
+        )
+        .await
+    }
+
+    /// Returns the object data traffic of this manager per class, sorted by
+    /// class: reads, writes and deletes that succeeded, and the entry bytes
+    /// they moved.
+    pub fn stats(&self) -> Vec<DataStats> {
+        self.counters.stats()
+    }
+
+    /// Where reads (`get_obj`, `get_entry` and their async variants) are served from.
+    #[getter]
+    pub fn get_read_preference(&self) -> ReadPreference {
+        self.reader.settings.lock().unwrap().preference
+    }
+
+    #[setter]
+    pub fn set_read_preference(&self, preference: ReadPreference) {
+        self.reader.settings.lock().unwrap().preference = preference;
+    }
+
+    /// Retrieves an object and reports where it was served from. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class ID of the object.
+    /// * `partition_id`: The partition ID where the object resides.
+    /// * `obj_id`: The unique ID of the object.
+    /// * `preference`: Overrides `read_preference` for this read.
+    ///
+    /// # Returns
+    ///
+    /// A `ReadResult` with the object (or `None`) and the serving replica.
+    #[pyo3(signature = (cls_id, partition_id, obj_id, preference=None))]
+    pub fn read_obj(
+        &self,
+        py: Python<'_>,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
+        preference: Option<ReadPreference>,
+    ) -> PyResult<ReadResult> {
+        let reader = self.reader.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let (obj, routed) = py.detach(|| {
+            runtime.block_on(telemetry::instrument(
+                async move {
+                    let meta = ObjMeta {
+                        cls_id,
+                        partition_id,
+                        object_id: obj_id,
+                    };
+                    let mut routed = reader
+                        .get(&meta, preference)
+                        .await
+                        .map_err(PyRuntimeError::new_err)?;
+                    Ok::<_, PyErr>((routed.obj.take().map(ObjectData::from), routed))
+                },
+                "data.read_obj",
+            ))
+        })?;
+        ReadResult::new(py, obj, routed)
+    }
+
+    /// Retrieves an object and reports where it was served from. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// See `read_obj`.
+    #[pyo3(signature = (cls_id, partition_id, obj_id, preference=None))]
+    pub async fn read_obj_async(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] obj_id: u64,
+        preference: Option<ReadPreference>,
+    ) -> PyResult<ReadResult> {
+        let meta = ObjMeta {
+            cls_id,
+            partition_id,
+            object_id: obj_id,
+        };
+        let mut routed = telemetry::instrument(
+            self.reader.get(&meta, preference),
+            "data.read_obj_async",
+        )
+        .await
+        .map_err(PyRuntimeError::new_err)?;
+        let obj = routed.obj.take().map(ObjectData::from);
+        Python::attach(|py| ReadResult::new(py, obj, routed))
+    }
+
+    /// Returns whether the data plane serving the partition retains entry
+    /// versions, i.e. whether `get_at` and `history` are available.
+    pub async fn supports_history(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+    ) -> bool {
+        history::supported(&self.reader.session, &cls_id, partition_id).await
+    }
+
+    /// Reads a past version of an entry. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `meta`: The metadata of the object.
+    /// * `key`: The entry index, or a name registered with `register_entry_keys`.
+    /// * `timestamp_ms`: Returns the latest version written at or before this Unix time.
+    /// * `version`: Returns exactly this version. Exactly one of `timestamp_ms` and `version` must be given.
+    ///
+    /// # Returns
+    ///
+    /// The `EntryVersion`, or `None` if no such version exists. Raises
+    /// `NotImplementedError` if the data plane does not retain versions.
+    #[pyo3(signature = (meta, key, timestamp_ms=None, version=None))]
+    pub fn get_at(
+        &self,
+        py: Python<'_>,
+        meta: ObjectMetadata,
+        key: EntryKey,
+        timestamp_ms: Option<u64>,
+        version: Option<u64>,
+    ) -> PyResult<Option<EntryVersion>> {
+        let meta = meta.into_proto();
+        let index = key.resolve(&meta.cls_id)?;
+        let at = Self::resolve_at(timestamp_ms, version)?;
+        let session = self.reader.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(|| {
+            runtime.block_on(telemetry::instrument(
+                async move { history::get_at(&session, &meta, index, at).await },
+                "data.get_at",
+            ))
+        })
+    }
+
+    /// Reads a past version of an entry. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// See `get_at`.
+    #[pyo3(signature = (meta, key, timestamp_ms=None, version=None))]
+    pub async fn get_at_async(
+        &self,
+        meta: ObjectMetadata,
+        key: EntryKey,
+        timestamp_ms: Option<u64>,
+        version: Option<u64>,
+    ) -> PyResult<Option<EntryVersion>> {
+        let meta = meta.into_proto();
+        let index = key.resolve(&meta.cls_id)?;
+        let at = Self::resolve_at(timestamp_ms, version)?;
+        telemetry::instrument(
+            history::get_at(&self.reader.session, &meta, index, at),
+            "data.get_at_async",
+        )
+        .await
+    }
+
+    /// Lists the retained versions of an entry, newest first. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `meta`: The metadata of the object.
+    /// * `key`: The entry index, or a name registered with `register_entry_keys`.
+    /// * `limit`: Maximum number of versions to return.
+    ///
+    /// # Returns
+    ///
+    /// The versions. Raises `NotImplementedError` if the data plane does not retain versions.
+    #[pyo3(signature = (meta, key, limit=10))]
+    pub fn history(
+        &self,
+        py: Python<'_>,
+        meta: ObjectMetadata,
+        key: EntryKey,
+        limit: usize,
+    ) -> PyResult<Vec<EntryVersion>> {
+        let meta = meta.into_proto();
+        let index = key.resolve(&meta.cls_id)?;
+        let session = self.reader.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(|| {
+            runtime.block_on(telemetry::instrument(
+                async move { history::history(&session, &meta, index, limit).await },
+                "data.history",
+            ))
+        })
+    }
+
+    /// Lists the retained versions of an entry, newest first. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// See `history`.
+    #[pyo3(signature = (meta, key, limit=10))]
+    pub async fn history_async(
+        &self,
+        meta: ObjectMetadata,
+        key: EntryKey,
+        limit: usize,
+    ) -> PyResult<Vec<EntryVersion>> {
+        let meta = meta.into_proto();
+        let index = key.resolve(&meta.cls_id)?;
+        telemetry::instrument(
+            history::history(&self.reader.session, &meta, index, limit),
+            "data.history_async",
+        )
+        .await
+    }
+
+    /// Finds objects of a class that are orphaned or expired and deletes them,
+    /// one partition at a time. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class ID of the objects.
+    /// * `partitions`: The partitions to scan.
+    /// * `referenced`: IDs of objects still referenced (e.g. by an index); any other object is orphaned.
+    /// * `retention_ms`: Objects last written longer ago than this are expired.
+    ///   The write time is the Zenoh timestamp the data plane puts on the object
+    ///   when scanned, which must be the time of the stored write. If replies are
+    ///   stamped when sent, nothing expires; if a replica re-stamps objects it
+    ///   copied with a skewed clock, live objects can be deleted. Check with a
+    ///   dry run first.
+    /// * `dry_run`: When true (default), only reports what would be deleted.
+    ///
+    /// # Returns
+    ///
+    /// A `GcReport` listing the garbage found and what was deleted.
+    #[pyo3(signature = (cls_id, partitions, referenced=None, retention_ms=None, dry_run=true))]
+    pub fn collect_garbage(
+        &self,
+        py: Python<'_>,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partitions: Vec<u32>,
+        referenced: Option<HashSet<u64>>,
+        retention_ms: Option<u64>,
+        dry_run: bool,
+    ) -> PyResult<GcReport> {
+        let rules = Self::gc_rules(referenced, retention_ms)?;
+        let session = self.reader.session.clone();
+        let proxy = self.proxy.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        Ok(py.detach(|| {
+            runtime.block_on(telemetry::instrument(
+                gc::collect(session, proxy, cls_id, partitions, rules, dry_run),
+                "data.collect_garbage",
+            ))
+        }))
+    }
+
+    /// Finds objects of a class that are orphaned or expired and deletes them,
+    /// one partition at a time. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// See `collect_garbage`.
+    #[pyo3(signature = (cls_id, partitions, referenced=None, retention_ms=None, dry_run=true))]
+    pub async fn collect_garbage_async(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partitions: Vec<u32>,
+        referenced: Option<HashSet<u64>>,
+        retention_ms: Option<u64>,
+        dry_run: bool,
+    ) -> PyResult<GcReport> {
+        let rules = Self::gc_rules(referenced, retention_ms)?;
+        Ok(telemetry::instrument(
+            gc::collect(
+                self.reader.session.clone(),
+                self.proxy.clone(),
+                cls_id,
+                partitions,
+                rules,
+                dry_run,
+            ),
+            "data.collect_garbage_async",
+        )
+        .await)
+    }
+
+    /// Scans the objects of a class into a `pyarrow.Table` with one row per
+    /// object entry, without passing rows through Python. (Synchronous)
+    ///
+    /// Only available when built with the `arrow` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class ID of the objects.
+    /// * `partitions`: The partitions to scan, one at a time.
+    ///
+    /// # Returns
+    ///
+    /// A `pyarrow.Table` with columns `cls_id`, `partition_id`, `object_id`,
+    /// `entry`, `value` and `written_ms`.
+    #[cfg(feature = "arrow")]
+    pub fn scan_to_arrow(
+        &self,
+        py: Python<'_>,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partitions: Vec<u32>,
+    ) -> PyResult<Py<PyAny>> {
+        let session = self.reader.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let batches = py
+            .detach(|| {
+                runtime.block_on(telemetry::instrument(
+                    export::to_batches(session, cls_id, partitions),
+                    "data.scan_to_arrow",
+                ))
+            })
+            .map_err(PyRuntimeError::new_err)?;
+        export::to_table(py, batches)
+    }
+
+    /// Scans the objects of a class into a `pyarrow.Table`. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// See `scan_to_arrow`.
+    #[cfg(feature = "arrow")]
+    pub async fn scan_to_arrow_async(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partitions: Vec<u32>,
+    ) -> PyResult<Py<PyAny>> {
+        let batches = telemetry::instrument(
+            export::to_batches(self.reader.session.clone(), cls_id, partitions),
+            "data.scan_to_arrow_async",
+        )
+        .await
+        .map_err(PyRuntimeError::new_err)?;
+        Python::attach(|py| export::to_table(py, batches))
+    }
+
+    /// Scans the objects of a class straight into a Parquet file, one row per
+    /// object entry. Rows are written in batches as they arrive. (Synchronous)
+    ///
+    /// Only available when built with the `arrow` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class ID of the objects.
+    /// * `partitions`: The partitions to scan, one at a time.
+    /// * `path`: The file to write; overwritten if it exists.
+    /// * `compression`: `"zstd"` (default), `"snappy"` or `"none"`.
+    ///
+    /// # Returns
+    ///
+    /// An `ExportReport` with the number of objects and rows written.
+    #[cfg(feature = "arrow")]
+    #[pyo3(signature = (cls_id, partitions, path, compression="zstd"))]
+    pub fn scan_to_parquet(
+        &self,
+        py: Python<'_>,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partitions: Vec<u32>,
+        path: String,
+        compression: &str,
+    ) -> PyResult<ExportReport> {
+        let compression = export::compression(compression)?;
+        let session = self.reader.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(|| {
+            runtime.block_on(telemetry::instrument(
+                export::to_parquet(session, cls_id, partitions, path, compression),
+                "data.scan_to_parquet",
+            ))
+        })
+        .map_err(PyRuntimeError::new_err)
+    }
+
+    /// Scans the objects of a class straight into a Parquet file. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// See `scan_to_parquet`.
+    #[cfg(feature = "arrow")]
+    #[pyo3(signature = (cls_id, partitions, path, compression="zstd".to_string()))]
+    pub async fn scan_to_parquet_async(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partitions: Vec<u32>,
+        path: String,
+        compression: String,
+    ) -> PyResult<ExportReport> {
+        let compression = export::compression(&compression)?;
+        telemetry::instrument(
+            export::to_parquet(
+                self.reader.session.clone(),
+                cls_id,
+                partitions,
+                path,
+                compression,
+            ),
+            "data.scan_to_parquet_async",
+        )
+        .await
+        .map_err(PyRuntimeError::new_err)
+    }
 }
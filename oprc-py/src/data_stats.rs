@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use oprc_pb::ObjData;
+
+use crate::intern::intern;
+
+/// Object data traffic of a `DataManager` per class, so storage load can be
+/// attributed to the classes causing it. Keys are interned so recording does
+/// not allocate.
+#[derive(Default)]
+pub(crate) struct DataCounters {
+    classes: Mutex<HashMap<Arc<str>, ClassCounters>>,
+}
+
+#[derive(Default)]
+struct ClassCounters {
+    reads: u64,
+    writes: u64,
+    deletes: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+/// Size of the entries of `obj`, the bytes it moves to or from the data plane.
+pub(crate) fn entry_bytes(obj: &ObjData) -> u64 {
+    obj.entries.values().map(|v| v.data.len() as u64).sum()
+}
+
+/// Class and entry bytes of `obj`, to record once it is written.
+pub(crate) fn write_of(obj: &ObjData) -> (String, u64) {
+    let cls_id = obj
+        .metadata
+        .as_ref()
+        .map(|meta| meta.cls_id.clone())
+        .unwrap_or_default();
+    (cls_id, entry_bytes(obj))
+}
+
+impl DataCounters {
+    fn update(&self, cls_id: &str, f: impl FnOnce(&mut ClassCounters)) {
+        let mut classes = self.classes.lock().unwrap();
+        f(classes.entry(intern(cls_id)).or_default());
+    }
+
+    /// Records a read of an object of `cls_id` that returned `obj`.
+    pub(crate) fn record_read(&self, cls_id: &str, obj: Option<&ObjData>) {
+        let bytes = obj.map_or(0, entry_bytes);
+        self.update(cls_id, |c| {
+            c.reads += 1;
+            c.bytes_read += bytes;
+        });
+    }
+
+    /// Records a write of `bytes` of entries to an object of `cls_id`.
+    pub(crate) fn record_write(&self, cls_id: &str, bytes: u64) {
+        self.update(cls_id, |c| {
+            c.writes += 1;
+            c.bytes_written += bytes;
+        });
+    }
+
+    /// Records the deletion of an object of `cls_id`.
+    pub(crate) fn record_delete(&self, cls_id: &str) {
+        self.update(cls_id, |c| c.deletes += 1);
+    }
+
+    /// Returns the counters of every class, sorted by class.
+    pub(crate) fn stats(&self) -> Vec<DataStats> {
+        let classes = self.classes.lock().unwrap();
+        let mut stats: Vec<DataStats> = classes
+            .iter()
+            .map(|(cls_id, c)| DataStats {
+                cls_id: cls_id.to_string(),
+                reads: c.reads,
+                writes: c.writes,
+                deletes: c.deletes,
+                bytes_read: c.bytes_read,
+                bytes_written: c.bytes_written,
+            })
+            .collect();
+        stats.sort_by(|a, b| a.cls_id.cmp(&b.cls_id));
+        stats
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Object data traffic of a single class through a `DataManager`.
+pub struct DataStats {
+    pub cls_id: String,
+    /// Objects read, found or not.
+    pub reads: u64,
+    /// Objects written.
+    pub writes: u64,
+    /// Objects deleted.
+    pub deletes: u64,
+    /// Entry bytes of the objects read.
+    pub bytes_read: u64,
+    /// Entry bytes of the objects written.
+    pub bytes_written: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl DataStats {
+    /// Returns a string representation of the `DataStats`.
+    fn __str__(&self) -> String {
+        format!(
+            "DataStats {{ {}: reads: {}, writes: {}, deletes: {}, bytes_read: {}, bytes_written: {} }}",
+            self.cls_id, self.reads, self.writes, self.deletes, self.bytes_read, self.bytes_written
+        )
+    }
+}
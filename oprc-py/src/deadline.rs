@@ -0,0 +1,63 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::model::{DEADLINE_OPTION, TIMEOUT_OPTION};
+
+/// Returns the deadline in the `oprc-deadline` option of a request, if it
+/// carries a valid one.
+fn of(options: &HashMap<String, String>) -> Option<SystemTime> {
+    let ms = options.get(DEADLINE_OPTION)?.parse::<u64>().ok()?;
+    Some(UNIX_EPOCH + Duration::from_millis(ms))
+}
+
+/// Time left until the deadline of a request: `None` without a deadline,
+/// `Err` with how long ago it passed once it has.
+pub(crate) fn remaining(options: &HashMap<String, String>) -> Option<Result<Duration, Duration>> {
+    let deadline = of(options)?;
+    Some(match deadline.duration_since(SystemTime::now()) {
+        Ok(left) if !left.is_zero() => Ok(left),
+        Ok(_) => Err(Duration::ZERO),
+        Err(e) => Err(e.duration()),
+    })
+}
+
+/// Sets or, with `None`, removes the deadline of a request.
+pub(crate) fn set(options: &mut HashMap<String, String>, deadline_ms: Option<u64>) {
+    match deadline_ms {
+        Some(ms) => {
+            options.insert(DEADLINE_OPTION.to_string(), ms.to_string());
+        }
+        None => {
+            options.remove(DEADLINE_OPTION);
+        }
+    }
+}
+
+/// Replaces the deadline of a request about to be sent with the `budget`
+/// left, so the servers it reaches learn the caller's budget without
+/// comparing clocks.
+pub(crate) fn send_budget(options: &mut HashMap<String, String>, budget: Duration) {
+    options.remove(DEADLINE_OPTION);
+    // Rounded up, as a budget of 0 ms would read as spent.
+    let ms = budget.as_micros().div_ceil(1000).max(1) as u64;
+    options.insert(TIMEOUT_OPTION.to_string(), ms.to_string());
+}
+
+/// Turns the budget a received request carries into a deadline on this
+/// host's clock. A deadline sent as is, by an older caller, is kept.
+pub(crate) fn rebase(options: &mut HashMap<String, String>) {
+    let Some(ms) = options.remove(TIMEOUT_OPTION) else {
+        return;
+    };
+    let Ok(ms) = ms.parse::<u64>() else {
+        return;
+    };
+    let at = SystemTime::now() + Duration::from_millis(ms);
+    let ms = at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    set(options, Some(ms));
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, sync::Arc};
+
+use oprc_invoke::handler::InvocationExecutor;
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use pyo3_async_runtimes::tokio::get_runtime;
+
+use crate::{errors::ErrorCode, handler::AsyncInvocationHandler};
+
+/// Response header holding the `InvocationResponseCode` of the invocation.
+pub const STATUS_HEADER: &str = "oprc-status";
+
+/// The invocation an HTTP path names.
+pub(crate) enum Target {
+    /// `/<cls_id>/<partition_id>/invokes/<fn_id>`
+    Fn {
+        cls_id: String,
+        partition_id: u32,
+        fn_id: String,
+    },
+    /// `/<cls_id>/<partition_id>/objects/<object_id>/invokes/<fn_id>`
+    Obj {
+        cls_id: String,
+        partition_id: u32,
+        object_id: u64,
+        fn_id: String,
+    },
+}
+
+impl Target {
+    /// Parses a path laid out like the Zenoh key expression of the function,
+    /// without the `oprc/` prefix.
+    pub(crate) fn parse(path: &str) -> Result<Self, String> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let partition = |s: &str| {
+            s.parse::<u32>()
+                .map_err(|_| format!("invalid partition id '{}'", s))
+        };
+        match segments.as_slice() {
+            [cls_id, partition_id, "invokes", fn_id] => Ok(Target::Fn {
+                cls_id: cls_id.to_string(),
+                partition_id: partition(partition_id)?,
+                fn_id: fn_id.to_string(),
+            }),
+            [cls_id, partition_id, "objects", object_id, "invokes", fn_id] => Ok(Target::Obj {
+                cls_id: cls_id.to_string(),
+                partition_id: partition(partition_id)?,
+                object_id: object_id
+                    .parse()
+                    .map_err(|_| format!("invalid object id '{}'", object_id))?,
+                fn_id: fn_id.to_string(),
+            }),
+            _ => Err(format!(
+                "no function at '{}'; expected /<cls_id>/<partition_id>/invokes/<fn_id> \
+                 or /<cls_id>/<partition_id>/objects/<object_id>/invokes/<fn_id>",
+                path
+            )),
+        }
+    }
+
+    /// The class and function invoked.
+    pub(crate) fn function(&self) -> (&str, &str) {
+        match self {
+            Target::Fn { cls_id, fn_id, .. } | Target::Obj { cls_id, fn_id, .. } => (cls_id, fn_id),
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+/// Runs invocations received over HTTP by another server, such as an ASGI
+/// app, on a handler of an `OaasEngine`. Obtained with
+/// `OaasEngine.http_dispatcher`; `oaas_sdk2_py.asgi` builds on it.
+///
+/// Invocations pass the same server-side policy as those of the servers the
+/// engine starts: interceptors, quotas, idempotency, metrics and the call log.
+pub struct HttpDispatcher {
+    handler: Arc<AsyncInvocationHandler>,
+}
+
+impl HttpDispatcher {
+    pub(crate) fn new(handler: AsyncInvocationHandler) -> Self {
+        HttpDispatcher {
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl HttpDispatcher {
+    /// Runs the invocation `path` names with `body` as payload and returns
+    /// the HTTP status, headers and body of the reply.
+    ///
+    /// The status is `200` for an `Okay` response and follows the error
+    /// code otherwise, e.g. `400` for `InvalidRequest` and `500` for
+    /// `AppError`. The headers are those of the response plus
+    /// `oprc-status`, the response status. A path naming no function gets
+    /// `404`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - `/<cls_id>/<partition_id>/invokes/<fn_id>` or
+    ///   `/<cls_id>/<partition_id>/objects/<object_id>/invokes/<fn_id>`,
+    ///   relative to where the dispatcher is mounted.
+    /// * `body` - The request payload.
+    /// * `options` - The request options.
+    #[pyo3(signature = (path, body, options=HashMap::new()))]
+    async fn dispatch(
+        &self,
+        path: String,
+        body: Vec<u8>,
+        options: HashMap<String, String>,
+    ) -> PyResult<(u16, HashMap<String, String>, Vec<u8>)> {
+        let target = match Target::parse(&path) {
+            Ok(target) => target,
+            Err(msg) => return Ok((404, HashMap::new(), msg.into_bytes())),
+        };
+        let handler = self.handler.clone();
+        let response = get_runtime()
+            .spawn(async move {
+                match target {
+                    Target::Fn {
+                        cls_id,
+                        partition_id,
+                        fn_id,
+                    } => {
+                        let req = oprc_pb::InvocationRequest {
+                            partition_id,
+                            cls_id,
+                            fn_id,
+                            options,
+                            payload: body,
+                        };
+                        InvocationExecutor::invoke_fn(&*handler, req).await
+                    }
+                    Target::Obj {
+                        cls_id,
+                        partition_id,
+                        object_id,
+                        fn_id,
+                    } => {
+                        let req = oprc_pb::ObjectInvocationRequest {
+                            partition_id,
+                            object_id,
+                            cls_id,
+                            fn_id,
+                            options,
+                            payload: body,
+                        };
+                        InvocationExecutor::invoke_obj(&*handler, req).await
+                    }
+                }
+            })
+            .await
+            .map_err(|e| PyRuntimeError::new_err(format!("dispatch task failed: {}", e)))?
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let status = ErrorCode::of_status(response.status).map_or(200, ErrorCode::http_status);
+        let mut headers = response.headers;
+        headers.insert(STATUS_HEADER.to_string(), response.status.to_string());
+        Ok((status, headers, response.payload.unwrap_or_default()))
+    }
+}
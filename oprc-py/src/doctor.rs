@@ -0,0 +1,460 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use envconfig::Envconfig;
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::proxy::Proxy;
+
+/// Environment variable holding the control plane (ODGM) HTTP endpoint.
+const CONTROL_PLANE_ENV: &str = "OPRC_ODGM_URL";
+/// Environment variable holding the port the gRPC server listens on.
+const GRPC_PORT_ENV: &str = "HTTP_PORT";
+/// Clock skew above which the check warns.
+const SKEW_WARN: Duration = Duration::from_secs(5);
+/// Clock skew above which the check fails.
+const SKEW_FAIL: Duration = Duration::from_secs(60);
+
+const OK: &str = "ok";
+const WARN: &str = "warn";
+const FAIL: &str = "fail";
+const SKIP: &str = "skip";
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// Outcome of a single `doctor()` check.
+pub struct DoctorCheck {
+    /// The check name (`zenoh`, `control_plane`, `grpc_port`, `telemetry`, `clock_skew`).
+    pub name: String,
+    /// One of `ok`, `warn`, `fail` or `skip`.
+    pub status: String,
+    /// Human readable explanation of the outcome.
+    pub detail: String,
+}
+
+impl DoctorCheck {
+    fn new(name: &str, status: &str, detail: impl Into<String>) -> Self {
+        DoctorCheck {
+            name: name.to_string(),
+            status: status.to_string(),
+            detail: detail.into(),
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl DoctorCheck {
+    /// Returns a string representation of the `DoctorCheck`.
+    fn __str__(&self) -> String {
+        format!("[{:>4}] {}: {}", self.status, self.name, self.detail)
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// Structured result of `doctor()`.
+pub struct DoctorReport {
+    /// The individual checks, in the order they ran.
+    pub checks: Vec<DoctorCheck>,
+    /// `True` if no check failed (warnings are allowed).
+    pub ok: bool,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl DoctorReport {
+    /// Returns the checks with the given status.
+    fn by_status(&self, status: &str) -> Vec<DoctorCheck> {
+        self.checks
+            .iter()
+            .filter(|c| c.status == status)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a multi-line, human readable report.
+    fn __str__(&self) -> String {
+        let mut out: Vec<String> = self.checks.iter().map(|c| c.__str__()).collect();
+        out.push(if self.ok {
+            "No problems found.".to_string()
+        } else {
+            "Some checks failed.".to_string()
+        });
+        out.join("\n")
+    }
+}
+
+/// Checks the local configuration and the reachability of the services an
+/// OaaS function depends on, without starting a server.
+///
+/// # Arguments
+///
+/// * `grpc_port` - Port the gRPC server will bind; defaults to `HTTP_PORT`, skipped if unset.
+/// * `timeout_ms` - Timeout of each network check in milliseconds.
+///
+/// # Returns
+///
+/// A `DoctorReport` with one entry per check.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+#[pyo3(signature = (grpc_port=None, timeout_ms=3000))]
+pub fn doctor(py: Python<'_>, grpc_port: Option<u16>, timeout_ms: u64) -> DoctorReport {
+    let timeout = Duration::from_millis(timeout_ms.max(1));
+    let grpc_port = grpc_port.or_else(|| {
+        std::env::var(GRPC_PORT_ENV)
+            .ok()
+            .and_then(|v| v.parse().ok())
+    });
+    py.detach(|| get_runtime().block_on(run(grpc_port, timeout)))
+}
+
+async fn run(grpc_port: Option<u16>, timeout: Duration) -> DoctorReport {
+    let (control_plane, server_date) = check_control_plane(timeout).await;
+    let checks = vec![
+        check_zenoh(timeout).await,
+        control_plane,
+        check_grpc_port(grpc_port),
+        check_telemetry(timeout).await,
+        check_clock_skew(server_date),
+    ];
+    let ok = checks.iter().all(|c| c.status != FAIL);
+    DoctorReport { checks, ok }
+}
+
+async fn check_zenoh(timeout: Duration) -> DoctorCheck {
+    const NAME: &str = "zenoh";
+    let conf = match oprc_zenoh::OprcZenohConfig::init_from_env() {
+        Ok(c) => c,
+        Err(e) => return DoctorCheck::new(NAME, FAIL, format!("invalid configuration: {}", e)),
+    };
+    let session = match tokio::time::timeout(timeout, zenoh::open(conf.create_zenoh())).await {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => {
+            return DoctorCheck::new(NAME, FAIL, format!("failed to open session: {}", e));
+        }
+        Err(_) => {
+            return DoctorCheck::new(
+                NAME,
+                FAIL,
+                format!("timed out opening session after {} ms", timeout.as_millis()),
+            );
+        }
+    };
+    let info = session.info();
+    let routers = info.routers_zid().await.count();
+    let peers = info.peers_zid().await.count();
+    let _ = session.close().await;
+    if routers + peers == 0 {
+        DoctorCheck::new(
+            NAME,
+            WARN,
+            "session opened but no router or peer is connected; check OPRC_ZENOH_PEERS",
+        )
+    } else {
+        DoctorCheck::new(
+            NAME,
+            OK,
+            format!("connected to {} router(s) and {} peer(s)", routers, peers),
+        )
+    }
+}
+
+/// Probes the control plane and returns its `Date` header for the clock check.
+async fn check_control_plane(timeout: Duration) -> (DoctorCheck, Option<u64>) {
+    const NAME: &str = "control_plane";
+    let Ok(url) = std::env::var(CONTROL_PLANE_ENV) else {
+        return (
+            DoctorCheck::new(NAME, SKIP, format!("{} is not set", CONTROL_PLANE_ENV)),
+            None,
+        );
+    };
+    let endpoint = match Endpoint::parse(&url) {
+        Ok(e) => e,
+        Err(msg) => {
+            return (
+                DoctorCheck::new(
+                    NAME,
+                    FAIL,
+                    format!("invalid {} '{}': {}", CONTROL_PLANE_ENV, url, msg),
+                ),
+                None,
+            );
+        }
+    };
+    if endpoint.scheme == "https" {
+        let check = match connect(&endpoint, timeout).await {
+            Ok(_) => DoctorCheck::new(NAME, OK, format!("{} is reachable", url)),
+            Err(e) => DoctorCheck::new(NAME, FAIL, format!("{} is unreachable: {}", url, e)),
+        };
+        return (check, None);
+    }
+    match tokio::time::timeout(timeout, http_head(&endpoint)).await {
+        Ok(Ok((status, date))) => (
+            DoctorCheck::new(NAME, OK, format!("{} answered with HTTP {}", url, status)),
+            date,
+        ),
+        Ok(Err(e)) => (
+            DoctorCheck::new(NAME, FAIL, format!("{} is unreachable: {}", url, e)),
+            None,
+        ),
+        Err(_) => (
+            DoctorCheck::new(
+                NAME,
+                FAIL,
+                format!("{} did not answer within {} ms", url, timeout.as_millis()),
+            ),
+            None,
+        ),
+    }
+}
+
+fn check_grpc_port(port: Option<u16>) -> DoctorCheck {
+    const NAME: &str = "grpc_port";
+    let Some(port) = port else {
+        return DoctorCheck::new(
+            NAME,
+            SKIP,
+            format!("no port given and {} is not set", GRPC_PORT_ENV),
+        );
+    };
+    match std::net::TcpListener::bind(("0.0.0.0", port)) {
+        Ok(_) => DoctorCheck::new(NAME, OK, format!("port {} is available", port)),
+        Err(e) => DoctorCheck::new(NAME, FAIL, format!("cannot bind port {}: {}", port, e)),
+    }
+}
+
+async fn check_telemetry(timeout: Duration) -> DoctorCheck {
+    const NAME: &str = "telemetry";
+    let Ok(url) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        return DoctorCheck::new(NAME, SKIP, "OTEL_EXPORTER_OTLP_ENDPOINT is not set");
+    };
+    let protocol = std::env::var("OTEL_EXPORTER_OTLP_PROTOCOL").ok();
+    let grpc = match protocol.as_deref() {
+        None | Some("http/protobuf") | Some("http/json") => false,
+        Some("grpc") => true,
+        Some(other) => {
+            return DoctorCheck::new(
+                NAME,
+                FAIL,
+                format!("unsupported OTEL_EXPORTER_OTLP_PROTOCOL '{}'", other),
+            );
+        }
+    };
+    let endpoint = match Endpoint::parse(&url) {
+        Ok(e) => e,
+        Err(msg) => {
+            return DoctorCheck::new(
+                NAME,
+                FAIL,
+                format!("invalid OTEL_EXPORTER_OTLP_ENDPOINT '{}': {}", url, msg),
+            );
+        }
+    };
+    if let Err(e) = connect(&endpoint, timeout).await {
+        return DoctorCheck::new(NAME, FAIL, format!("{} is unreachable: {}", url, e));
+    }
+    // 4317 and 4318 are the OTLP defaults for gRPC and HTTP respectively.
+    match (grpc, endpoint.port) {
+        (true, 4318) => DoctorCheck::new(
+            NAME,
+            WARN,
+            "protocol is grpc but the endpoint uses the OTLP/HTTP port 4318",
+        ),
+        (false, 4317) => DoctorCheck::new(
+            NAME,
+            WARN,
+            "protocol is http but the endpoint uses the OTLP/gRPC port 4317; set OTEL_EXPORTER_OTLP_PROTOCOL=grpc",
+        ),
+        _ => DoctorCheck::new(NAME, OK, format!("{} is reachable", url)),
+    }
+}
+
+fn check_clock_skew(server_date: Option<u64>) -> DoctorCheck {
+    const NAME: &str = "clock_skew";
+    let Some(server) = server_date else {
+        return DoctorCheck::new(NAME, SKIP, "no reference time from the control plane");
+    };
+    let local = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let skew = Duration::from_secs(local.abs_diff(server));
+    let detail = format!(
+        "local clock differs from the control plane by {} s",
+        skew.as_secs()
+    );
+    if skew > SKEW_FAIL {
+        DoctorCheck::new(NAME, FAIL, detail)
+    } else if skew > SKEW_WARN {
+        DoctorCheck::new(NAME, WARN, detail)
+    } else {
+        DoctorCheck::new(NAME, OK, detail)
+    }
+}
+
+/// The parts of an `http(s)://host[:port][/path]` URL the checks need.
+struct Endpoint {
+    scheme: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, String> {
+        let (scheme, rest) = url.split_once("://").ok_or("missing scheme")?;
+        let default_port = match scheme {
+            "http" => 80,
+            "https" => 443,
+            other => return Err(format!("unsupported scheme '{}'", other)),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        // IPv6 literals are bracketed: `[::1]:4317`.
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(v6) => {
+                let (h, rest) = v6.split_once(']').ok_or("unterminated IPv6 host")?;
+                (h, rest.strip_prefix(':'))
+            }
+            None => match authority.rsplit_once(':') {
+                Some((h, p)) => (h, Some(p)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(p) => p
+                .parse::<u16>()
+                .map_err(|_| format!("invalid port '{}'", p))?,
+            None => default_port,
+        };
+        if host.is_empty() {
+            return Err("missing host".to_string());
+        }
+        Ok(Endpoint {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// The proxy connections to the endpoint go through, if any.
+    fn proxy(&self) -> Option<Proxy> {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        crate::proxy::for_url(&format!("{}://{}", self.scheme, host))
+    }
+}
+
+async fn connect(endpoint: &Endpoint, timeout: Duration) -> Result<TcpStream, String> {
+    let proxy = endpoint.proxy();
+    match tokio::time::timeout(
+        timeout,
+        crate::proxy::connect(proxy.as_ref(), &endpoint.host, endpoint.port),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(_) => Err(format!(
+            "connect timed out after {} ms",
+            timeout.as_millis()
+        )),
+    }
+}
+
+/// Sends a plain HTTP/1.0 `HEAD` request and returns the status code and the
+/// `Date` header as Unix seconds. Through a proxy, the request is sent to the
+/// proxy with the absolute URL.
+async fn http_head(endpoint: &Endpoint) -> Result<(u16, Option<u64>), String> {
+    let (mut stream, target, auth) = match endpoint.proxy() {
+        Some(proxy) => (
+            proxy.connect_proxy().await?,
+            format!(
+                "http://{}:{}{}",
+                endpoint.host, endpoint.port, endpoint.path
+            ),
+            proxy.auth_header(),
+        ),
+        None => (
+            TcpStream::connect((endpoint.host.as_str(), endpoint.port))
+                .await
+                .map_err(|e| e.to_string())?,
+            endpoint.path.clone(),
+            String::new(),
+        ),
+    };
+    let req = format!(
+        "HEAD {} HTTP/1.0\r\nHost: {}\r\n{}Connection: close\r\n\r\n",
+        target, endpoint.host, auth
+    );
+    stream
+        .write_all(req.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut buf = Vec::with_capacity(1024);
+    stream
+        .take(16 * 1024)
+        .read_to_end(&mut buf)
+        .await
+        .map_err(|e| e.to_string())?;
+    let head = String::from_utf8_lossy(&buf);
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or("not an HTTP response")?;
+    let date = lines
+        .take_while(|l| !l.is_empty())
+        .filter_map(|l| l.split_once(':'))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("date"))
+        .and_then(|(_, v)| parse_http_date(v.trim()));
+    Ok((status, date))
+}
+
+/// Parses an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) into Unix seconds.
+fn parse_http_date(s: &str) -> Option<u64> {
+    let mut parts = s.split_whitespace().skip(1);
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut hms = parts.next()?.split(':').map(|v| v.parse::<u64>().ok());
+    let (h, m, sec) = (hms.next()??, hms.next()??, hms.next()??);
+    // Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's algorithm).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days)
+        .ok()
+        .map(|d| d * 86_400 + h * 3600 + m * 60 + sec)
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng},
+};
+use hkdf::Hkdf;
+use pyo3::pyfunction;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::model::InvocationResponseCode;
+
+/// Request option / response header naming the scheme the payload is
+/// encrypted with.
+pub const ENCRYPTION_OPTION: &str = "oprc-encryption";
+/// Request option carrying the caller's ephemeral public key, hex encoded.
+pub const EPHEMERAL_KEY_OPTION: &str = "oprc-encryption-key";
+/// Environment variable with the hex encoded private key of a server.
+pub const PRIVATE_KEY_ENV: &str = "OPRC_E2E_PRIVATE_KEY";
+/// The only scheme currently supported: an X25519 exchange between an
+/// ephemeral key of the caller and the server's static key, HKDF-SHA256 and
+/// ChaCha20-Poly1305 with a random nonce prefixed to the ciphertext.
+const SCHEME: &str = "x25519-chacha20poly1305";
+const NONCE_LEN: usize = 12;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn key_bytes(bytes: &[u8], what: &str) -> Result<[u8; 32], String> {
+    bytes
+        .try_into()
+        .map_err(|_| format!("{} must be 32 bytes, got {}", what, bytes.len()))
+}
+
+/// Parses a public key given as 32 bytes.
+pub(crate) fn public_key(bytes: &[u8]) -> Result<PublicKey, String> {
+    key_bytes(bytes, "public key").map(PublicKey::from)
+}
+
+/// Derives the request and response keys of one invocation.
+fn derive(
+    shared: &[u8; 32],
+    ephemeral: &PublicKey,
+    server: &PublicKey,
+) -> (PayloadKey, PayloadKey) {
+    let salt = [ephemeral.as_bytes().as_slice(), server.as_bytes()].concat();
+    let mut okm = [0u8; 64];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(b"oprc-e2e v1", &mut okm)
+        .expect("64 bytes is a valid HKDF output length");
+    (
+        PayloadKey(*Key::from_slice(&okm[..32])),
+        PayloadKey(*Key::from_slice(&okm[32..])),
+    )
+}
+
+/// Key of one direction of an encrypted invocation. The caller keeps the
+/// response key to open the response; the server seals it with the same.
+pub(crate) struct PayloadKey(Key);
+
+impl PayloadKey {
+    fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.0)
+            .encrypt(&nonce, plaintext)
+            .expect("encrypting into a Vec cannot fail");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LEN {
+            return Err("encrypted payload is truncated".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        ChaCha20Poly1305::new(&self.0)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "failed to decrypt payload".to_string())
+    }
+}
+
+/// Encrypts `payload` for the server holding the private key of `server`,
+/// recording the scheme and the ephemeral key in `options`. Returns the key
+/// to open the response with.
+pub(crate) fn seal_request(
+    server: &PublicKey,
+    options: &mut HashMap<String, String>,
+    payload: &mut Vec<u8>,
+) -> PayloadKey {
+    let secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&secret);
+    let shared = secret.diffie_hellman(server);
+    let (request, response) = derive(shared.as_bytes(), &ephemeral, server);
+    *payload = request.seal(payload);
+    options.insert(ENCRYPTION_OPTION.to_string(), SCHEME.to_string());
+    options.insert(
+        EPHEMERAL_KEY_OPTION.to_string(),
+        to_hex(ephemeral.as_bytes()),
+    );
+    response
+}
+
+/// Decrypts a response sealed by the server. A response the server did not
+/// seal is only accepted if it is not `Okay`, as admission rejections are
+/// sent before the payload could be decrypted.
+pub(crate) fn open_response(
+    key: &PayloadKey,
+    response: &mut oprc_pb::InvocationResponse,
+) -> Result<(), String> {
+    if response.headers.remove(ENCRYPTION_OPTION).is_none() {
+        return if response.status == InvocationResponseCode::Okay as i32 {
+            Err("response to an encrypted invocation was not encrypted".to_string())
+        } else {
+            Ok(())
+        };
+    }
+    if let Some(payload) = &response.payload {
+        response.payload = Some(key.open(payload)?);
+    }
+    Ok(())
+}
+
+/// Public keys of the classes whose invocations an `RpcManager` encrypts.
+#[derive(Clone, Default)]
+pub(crate) struct KeyTable(Arc<RwLock<HashMap<String, PublicKey>>>);
+
+impl KeyTable {
+    /// Sets the public key of `cls_id`, or removes it with `None`.
+    pub(crate) fn set(&self, cls_id: String, key: Option<PublicKey>) {
+        let mut keys = self.0.write().unwrap();
+        match key {
+            Some(key) => keys.insert(cls_id, key),
+            None => keys.remove(&cls_id),
+        };
+    }
+
+    pub(crate) fn get(&self, cls_id: &str) -> Option<PublicKey> {
+        self.0.read().unwrap().get(cls_id).copied()
+    }
+}
+
+/// The static key pair of a server accepting encrypted invocations.
+pub(crate) struct ServerKey {
+    secret: StaticSecret,
+    public: PublicKey,
+    /// Whether unencrypted invocations are rejected.
+    pub(crate) required: bool,
+}
+
+impl ServerKey {
+    pub(crate) fn new(private_key: &[u8], required: bool) -> Result<Self, String> {
+        let secret = StaticSecret::from(key_bytes(private_key, "private key")?);
+        Ok(ServerKey {
+            public: PublicKey::from(&secret),
+            secret,
+            required,
+        })
+    }
+
+    /// Reads the key from `OPRC_E2E_PRIVATE_KEY`, if set. Encryption is not
+    /// required then.
+    pub(crate) fn from_env() -> Result<Option<Self>, String> {
+        let Ok(hex) = std::env::var(PRIVATE_KEY_ENV) else {
+            return Ok(None);
+        };
+        let bytes = from_hex(hex.trim())
+            .ok_or_else(|| format!("{} is not a hex encoded key", PRIVATE_KEY_ENV))?;
+        Self::new(&bytes, false).map(Some)
+    }
+
+    pub(crate) fn public_key(&self) -> &[u8; 32] {
+        self.public.as_bytes()
+    }
+
+    /// Decrypts the payload of a request sealed for this server, removing
+    /// the encryption options. Returns the key to seal the response with, or
+    /// `None` if the request was not encrypted.
+    pub(crate) fn open_request(
+        &self,
+        options: &mut HashMap<String, String>,
+        payload: &mut Vec<u8>,
+    ) -> Result<Option<PayloadKey>, String> {
+        let Some(scheme) = options.remove(ENCRYPTION_OPTION) else {
+            return Ok(None);
+        };
+        if scheme != SCHEME {
+            return Err(format!("unsupported encryption scheme '{}'", scheme));
+        }
+        let ephemeral = options
+            .remove(EPHEMERAL_KEY_OPTION)
+            .and_then(|hex| from_hex(&hex))
+            .ok_or_else(|| format!("missing or malformed {} option", EPHEMERAL_KEY_OPTION))?;
+        let ephemeral = public_key(&ephemeral)?;
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        let (request, response) = derive(shared.as_bytes(), &ephemeral, &self.public);
+        *payload = request.open(payload)?;
+        Ok(Some(response))
+    }
+}
+
+/// Seals the payload of a response to an encrypted invocation.
+pub(crate) fn seal_response(key: &PayloadKey, response: &mut oprc_pb::InvocationResponse) {
+    if let Some(payload) = &response.payload {
+        response.payload = Some(key.seal(payload));
+    }
+    response
+        .headers
+        .insert(ENCRYPTION_OPTION.to_string(), SCHEME.to_string());
+}
+
+/// Generates a key pair for end-to-end payload encryption. Returns the
+/// private key, for `OaasEngine.set_encryption_key` or, hex encoded, for
+/// `OPRC_E2E_PRIVATE_KEY`, and the public key to give to callers for
+/// `RpcManager.set_encryption_key`, 32 bytes each.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn generate_encryption_key() -> (Vec<u8>, Vec<u8>) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    (secret.to_bytes().to_vec(), public.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> ServerKey {
+        let (private, _) = generate_encryption_key();
+        ServerKey::new(&private, true).unwrap()
+    }
+
+    fn response(status: InvocationResponseCode, payload: &[u8]) -> oprc_pb::InvocationResponse {
+        oprc_pb::InvocationResponse {
+            payload: Some(payload.to_vec()),
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+
+    /// Seals `payload` for `server` as a caller would, returning the sealed
+    /// payload, its options and the key to open the response with.
+    fn sealed(
+        server: &ServerKey,
+        payload: &[u8],
+    ) -> (Vec<u8>, HashMap<String, String>, PayloadKey) {
+        let mut options = HashMap::new();
+        let mut payload = payload.to_vec();
+        let public = public_key(server.public_key()).unwrap();
+        let key = seal_request(&public, &mut options, &mut payload);
+        (payload, options, key)
+    }
+
+    #[test]
+    fn round_trips_request_and_response() {
+        let server = server();
+        let (mut payload, mut options, caller) = sealed(&server, b"secret");
+        assert_ne!(payload, b"secret");
+        assert_eq!(options[ENCRYPTION_OPTION], SCHEME);
+        let key = server
+            .open_request(&mut options, &mut payload)
+            .unwrap()
+            .unwrap();
+        assert_eq!(payload, b"secret");
+        assert!(options.is_empty());
+
+        let mut resp = response(InvocationResponseCode::Okay, b"answer");
+        seal_response(&key, &mut resp);
+        assert_ne!(resp.payload.as_deref(), Some(b"answer".as_slice()));
+        open_response(&caller, &mut resp).unwrap();
+        assert_eq!(resp.payload.as_deref(), Some(b"answer".as_slice()));
+        assert!(resp.headers.is_empty());
+    }
+
+    #[test]
+    fn rejects_tampered_ciphertext() {
+        let server = server();
+        let (mut payload, mut options, caller) = sealed(&server, b"secret");
+        *payload.last_mut().unwrap() ^= 1;
+        assert!(server.open_request(&mut options, &mut payload).is_err());
+
+        let (mut payload, mut options, _) = sealed(&server, b"secret");
+        let key = server
+            .open_request(&mut options, &mut payload)
+            .unwrap()
+            .unwrap();
+        let mut resp = response(InvocationResponseCode::Okay, b"answer");
+        seal_response(&key, &mut resp);
+        resp.payload.as_mut().unwrap()[0] ^= 1;
+        assert!(open_response(&caller, &mut resp).is_err());
+
+        let mut resp = response(InvocationResponseCode::Okay, b"");
+        seal_response(&key, &mut resp);
+        resp.payload.as_mut().unwrap().truncate(NONCE_LEN - 1);
+        assert!(open_response(&caller, &mut resp).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_keys() {
+        let server = server();
+        let (mut payload, mut options, caller) = sealed(&server, b"secret");
+        let other = self::server();
+        assert!(
+            other
+                .open_request(&mut options.clone(), &mut payload.clone())
+                .is_err()
+        );
+
+        // A response only opens with the key of its own exchange.
+        let key = server
+            .open_request(&mut options, &mut payload)
+            .unwrap()
+            .unwrap();
+        let (_, _, stranger) = sealed(&server, b"other");
+        let mut resp = response(InvocationResponseCode::Okay, b"answer");
+        seal_response(&key, &mut resp);
+        assert!(open_response(&stranger, &mut resp.clone()).is_err());
+        assert!(open_response(&caller, &mut resp).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_request_options() {
+        let server = server();
+        let (mut payload, mut options, _) = sealed(&server, b"secret");
+        options.insert(ENCRYPTION_OPTION.to_string(), "rot13".to_string());
+        assert!(server.open_request(&mut options, &mut payload).is_err());
+
+        let (mut payload, mut options, _) = sealed(&server, b"secret");
+        options.insert(EPHEMERAL_KEY_OPTION.to_string(), "zz".to_string());
+        assert!(server.open_request(&mut options, &mut payload).is_err());
+
+        let mut options = HashMap::new();
+        let mut payload = b"plain".to_vec();
+        assert!(
+            server
+                .open_request(&mut options, &mut payload)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(payload, b"plain");
+    }
+
+    #[test]
+    fn unsealed_responses_are_only_accepted_as_errors() {
+        let server = server();
+        let (_, _, caller) = sealed(&server, b"secret");
+        let mut resp = response(InvocationResponseCode::Okay, b"forged");
+        assert!(open_response(&caller, &mut resp).is_err());
+
+        // Admission rejections are sent before the request is decrypted, so
+        // an unsealed error passes through. Its payload is whatever the
+        // sender put there and must not be taken as the handler's output.
+        let mut resp = response(InvocationResponseCode::InvalidRequest, b"rejected");
+        open_response(&caller, &mut resp).unwrap();
+        assert_eq!(resp.payload.as_deref(), Some(b"rejected".as_slice()));
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes = [0x00, 0x7f, 0xff, 0x10];
+        assert_eq!(to_hex(&bytes), "007fff10");
+        assert_eq!(from_hex("007fff10").unwrap(), bytes);
+        assert_eq!(from_hex("007FFF10").unwrap(), bytes);
+        assert!(from_hex("abc").is_none());
+        assert!(from_hex("zz").is_none());
+    }
+}
@@ -1,28 +1,52 @@
-use flume::Receiver;
-use oprc_invoke::handler::InvocationZenohHandler;
+use oprc_invoke::proxy::ObjectProxy;
 use std::{
     collections::HashMap,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     sync::Arc,
 };
-use tokio::sync::{Mutex, oneshot};
-use zenoh::query::{Query, Queryable};
+use tokio::sync::oneshot;
 use std::sync::OnceLock;
 
 use crate::{
+    audit::{AuditLog, DEFAULT_ANCHOR_EVERY},
+    blocking::BlockingPool,
+    call_log::{CallLog, CallRecord},
+    catalog::Catalog,
+    checkpoint::CheckpointStore,
+    codec,
     data::DataManager,
-    handler::{AsyncInvocationHandler, SyncInvocationHandler},
-    rpc::RpcManager,
+    data_stats::DataStats,
+    dispatch::HttpDispatcher,
+    e2e::{PRIVATE_KEY_ENV, ServerKey},
+    firewall::ZenohFirewall,
+    handoff::{self, HandoffReport, Outgoing},
+    ids,
+    handler::{
+        AsyncInvocationHandler, HandlerPolicy, INTERCEPTORS_ENV, IdempotencyCache, Interceptor,
+        StateHook, SyncInvocationHandler,
+    },
+    keys::EntryKey,
+    metrics::{ExceptionStats, FunctionStats, HotObject, MetricPoint, ServerMetrics, ServerMetricsSnapshot, SNAPSHOT_PATH_ENV},
+    obj::ObjectData,
+    partitions::{self, PartitionHooks},
+    payload_limit::PayloadLimit,
+    quota::{QuotaTracker, TenantUsage},
+    rpc::{self, RpcManager},
+    session_events::{self, SessionHooks},
+    standby::{self, Functions},
+    warmup::WarmUp,
 };
 pub use envconfig::Envconfig;
 use oprc_pb::oprc_function_server::{OprcFunction, OprcFunctionServer};
 use pyo3::{
-    exceptions::{PyRuntimeError, PyTypeError},
+    exceptions::{PyRuntimeError, PyTypeError, PyValueError},
     prelude::*,
 };
 use pyo3_async_runtimes::{TaskLocals, tokio::get_runtime};
 use tokio::runtime::Builder;
 use tonic::transport::Server;
+use tonic_web::GrpcWebLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
 #[pyclass]
@@ -31,9 +55,76 @@ pub struct OaasEngine {
     // Lazily created components
     data_manager: Option<Py<DataManager>>,
     rpc_manager: Option<Py<RpcManager>>,
+    blocking_pool: Option<Py<BlockingPool>>,
     session: OnceLock<zenoh::Session>,
     shutdown_sender: Option<oneshot::Sender<()>>, // shutdown sender for gRPC server
-    queryable_table: Arc<Mutex<HashMap<String, Queryable<Receiver<Query>>>>>,
+    /// Functions served over Zenoh, declared unless the engine is in standby.
+    functions: Arc<Functions>,
+    /// Descriptions of the functions served, answered to catalog queries.
+    catalog: Catalog,
+    quota: Arc<QuotaTracker>,
+    /// Responses replayed to duplicate deliveries, shared by all servers.
+    idempotency: Arc<IdempotencyCache>,
+    metrics: Arc<ServerMetrics>,
+    /// The last invocations handled by servers of this engine.
+    calls: Arc<CallLog>,
+    /// Largest request payload the servers of this engine admit.
+    max_payload: Arc<PayloadLimit>,
+    /// Sample invocations run by servers before they take invocations.
+    warmup: Arc<WarmUp>,
+    /// Interceptor specs and the pipeline parsed from them.
+    interceptor_specs: Vec<String>,
+    interceptors: Vec<Interceptor>,
+    /// Hooks run around object invocations of servers started afterwards.
+    state_hooks: Vec<StateHook>,
+    /// Key pair opening encrypted invocations of servers started afterwards.
+    encryption: Option<Arc<ServerKey>>,
+    /// Log functions served afterwards record their invocations in.
+    audit: Option<Arc<AuditLog>>,
+    /// gRPC-Web settings of servers started afterwards; `None` serves plain gRPC only.
+    grpc_web: Option<GrpcWebConfig>,
+    /// Key expression rules applied to functions served over Zenoh afterwards.
+    firewall: Arc<ZenohFirewall>,
+    /// Background task reporting session connectivity changes, if watching.
+    session_watch: Option<tokio::task::JoinHandle<()>>,
+    /// Background tasks reporting partition assignment changes, by class.
+    partition_watches: HashMap<String, tokio::task::JoinHandle<()>>,
+    /// Background tasks receiving handed off objects, by handoff key.
+    handoff_receivers: HashMap<String, tokio::task::JoinHandle<()>>,
+    /// Background task demoting functions other engines take over, if watching.
+    takeover_watch: Option<tokio::task::JoinHandle<()>>,
+}
+
+/// Settings of the gRPC-Web translation layer on the embedded gRPC server.
+#[derive(Clone)]
+struct GrpcWebConfig {
+    /// Origins allowed by CORS; empty allows any origin.
+    allow_origins: Vec<http::HeaderValue>,
+}
+
+impl GrpcWebConfig {
+    /// Builds the CORS policy browsers need to call the server with gRPC-Web.
+    fn cors(&self) -> CorsLayer {
+        let origin = if self.allow_origins.is_empty() {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(self.allow_origins.clone())
+        };
+        CorsLayer::new()
+            .allow_origin(origin)
+            .allow_methods([http::Method::POST, http::Method::OPTIONS])
+            .allow_headers([
+                http::header::CONTENT_TYPE,
+                http::HeaderName::from_static("x-grpc-web"),
+                http::HeaderName::from_static("x-user-agent"),
+                http::HeaderName::from_static("grpc-timeout"),
+            ])
+            .expose_headers([
+                http::HeaderName::from_static("grpc-status"),
+                http::HeaderName::from_static("grpc-message"),
+                http::HeaderName::from_static("grpc-status-details-bin"),
+            ])
+    }
 }
 
 // Internal (non-Python exposed) helper methods
@@ -48,6 +139,7 @@ impl OaasEngine {
                 PyErr::new::<PyRuntimeError, _>(format!("Failed to open zenoh session: {}", e))
             })
         })?;
+        crate::shutdown::track_session(&new_session);
         let _ = self.session.set(new_session);
         Ok(self.session.get().expect("session just initialized"))
     }
@@ -61,6 +153,34 @@ impl OaasEngine {
         Ok(())
     }
 
+    /// Builds the server-side policy for a new handler. Data-dependent checks
+    /// are enabled only when a Zenoh session is already open.
+    fn handler_policy(&self) -> HandlerPolicy {
+        let policy = HandlerPolicy::default()
+            .with_quota(self.quota.clone())
+            .with_idempotency(self.idempotency.clone())
+            .with_metrics(self.metrics.clone())
+            .with_call_log(self.calls.clone())
+            .with_payload_limit(self.max_payload.clone())
+            .with_interceptors(self.interceptors.clone())
+            .with_state_hooks(self.state_hooks.clone());
+        let policy = match &self.encryption {
+            Some(key) => policy.with_encryption(key.clone()),
+            None => policy,
+        };
+        let policy = match &self.audit {
+            Some(log) => policy.with_audit(log.clone()),
+            None => policy,
+        };
+        match self.session.get() {
+            Some(s) => policy
+                .with_data(ObjectProxy::new(s.clone()))
+                .with_chunks(s.clone())
+                .with_replica(s.zid().to_string()),
+            None => policy,
+        }
+    }
+
     fn ensure_rpc_manager(&mut self) -> PyResult<()> {
         if self.rpc_manager.is_none() {
             let session = self.ensure_session()?.clone();
@@ -83,12 +203,45 @@ impl OaasEngine {
         pyo3_async_runtimes::tokio::init(builder);
         // If telemetry was initialized early without a runtime, upgrade to batch now.
         crate::telemetry::upgrade_batch_if_runtime();
+        let metrics = Arc::new(ServerMetrics::default());
+        if let Ok(path) = std::env::var(SNAPSHOT_PATH_ENV) {
+            if let Err(e) = metrics.enable_snapshot(path.into()) {
+                eprintln!("Failed to restore metrics snapshot: {}", e);
+            }
+        }
+        let interceptor_specs: Vec<String> = std::env::var(INTERCEPTORS_ENV)
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+        let interceptors = Interceptor::parse_all(&interceptor_specs, &metrics)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", INTERCEPTORS_ENV, e)))?;
+        let encryption = ServerKey::from_env()
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", PRIVATE_KEY_ENV, e)))?
+            .map(Arc::new);
         Ok(OaasEngine {
             data_manager: None,
             rpc_manager: None,
+            blocking_pool: None,
             session: OnceLock::new(),
             shutdown_sender: None,
-            queryable_table: Arc::new(Mutex::new(HashMap::new())),
+            functions: Arc::new(Functions::default()),
+            catalog: Catalog::default(),
+            quota: Arc::new(QuotaTracker::default()),
+            idempotency: Arc::new(IdempotencyCache::default()),
+            metrics,
+            calls: Arc::new(CallLog::default()),
+            max_payload: Arc::new(PayloadLimit::default()),
+            warmup: Arc::new(WarmUp::default()),
+            interceptor_specs,
+            interceptors,
+            state_hooks: Vec::new(),
+            encryption,
+            audit: None,
+            grpc_web: None,
+            firewall: Arc::new(ZenohFirewall::default()),
+            session_watch: None,
+            partition_watches: HashMap::new(),
+            handoff_receivers: HashMap::new(),
+            takeover_watch: None,
         })
     }
     
@@ -105,7 +258,17 @@ impl OaasEngine {
         Ok(self.rpc_manager.as_ref().unwrap().clone_ref(py))
     }
 
-    /// Starts a gRPC server on the specified port.
+    /// The thread pool handlers can use via `run_blocking` for CPU-heavy work.
+    #[getter]
+    fn blocking_pool<'py>(&'py mut self, py: Python<'py>) -> PyResult<Py<BlockingPool>> {
+        if self.blocking_pool.is_none() {
+            self.blocking_pool = Some(Py::new(py, BlockingPool::default())?);
+        }
+        Ok(self.blocking_pool.as_ref().unwrap().clone_ref(py))
+    }
+
+    /// Starts a gRPC server on the specified port, once the warm-up
+    /// invocations set with `set_warmup` ran.
     ///
     /// # Arguments
     ///
@@ -121,14 +284,20 @@ impl OaasEngine {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel(); // Create a shutdown channel
         self.shutdown_sender = Some(shutdown_sender); // Store the sender for later use
 
+        let policy = self.handler_policy();
+        let grpc_web = self.grpc_web.clone();
+        let warmup = self.warmup.clone();
         Python::attach(|py| {
             let l = event_loop.into_bound(py);
+            // Warm-up invocations skip the policy, so they are not counted.
+            let warmer = AsyncInvocationHandler::new(callback.clone_ref(py), TaskLocals::new(l.clone()));
             let task_locals = TaskLocals::new(l);
             py.detach(|| {
-                let service = AsyncInvocationHandler::new(callback, task_locals);
+                let service = AsyncInvocationHandler::new(callback, task_locals).with_policy(policy);
                 let runtime = get_runtime();
                 runtime.spawn(async move {
-                    if let Err(e) = start_tonic(port, service, shutdown_receiver).await {
+                    warmup.run_all(&warmer).await;
+                    if let Err(e) = start_tonic(port, service, shutdown_receiver, grpc_web).await {
                         eprintln!("Server error: {}", e);
                     }
                 });
@@ -137,7 +306,8 @@ impl OaasEngine {
         })
     }
 
-    /// Starts a gRPC server on the specified port.
+    /// Starts a gRPC server on the specified port, once the warm-up
+    /// invocations set with `set_warmup` ran.
     ///
     /// # Arguments
     ///
@@ -147,12 +317,18 @@ impl OaasEngine {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel(); // Create a shutdown channel
         self.shutdown_sender = Some(shutdown_sender); // Store the sender for later use
 
+        let policy = self.handler_policy();
+        let grpc_web = self.grpc_web.clone();
+        let warmup = self.warmup.clone();
         Python::attach(|py| {
+            // Warm-up invocations skip the policy, so they are not counted.
+            let warmer = SyncInvocationHandler::new(callback.clone_ref(py));
             py.detach(|| {
-                let service = SyncInvocationHandler::new(callback);
+                let service = SyncInvocationHandler::new(callback).with_policy(policy);
                 let runtime = get_runtime();
                 runtime.spawn(async move {
-                    if let Err(e) = start_tonic(port, service, shutdown_receiver).await {
+                    warmup.run_all(&warmer).await;
+                    if let Err(e) = start_tonic(port, service, shutdown_receiver, grpc_web).await {
                         eprintln!("Server error: {}", e);
                     }
                 });
@@ -161,84 +337,773 @@ impl OaasEngine {
         })
     }
 
-    /// Serves a function over Zenoh.
+    /// Serves a function over Zenoh, after running its warm-up invocations
+    /// set with `set_warmup`. In standby, the function is only declared once
+    /// the engine is promoted.
     ///
     /// # Arguments
     ///
     /// * `key_expr` - The Zenoh key expression to serve the function on.
     /// * `event_loop` - The Python event loop.
     /// * `callback` - The Python callback function to handle invocations.
+    /// * `description` - Human-readable documentation of the function, listed
+    ///   by `list_functions`.
+    /// * `schema` - JSON schema of the function's payload, for generic UIs to
+    ///   render invocation forms.
+    #[pyo3(signature = (key_expr, event_loop, callback, description=None, schema=None))]
     async fn serve_function(
         &self,
         key_expr: String,
         event_loop: Py<PyAny>,
         callback: Py<PyAny>,
+        description: Option<String>,
+        schema: Option<Py<PyAny>>,
     ) -> PyResult<()> {
-        let handler = Python::attach(|py| {
+        let schema = schema
+            .map(|schema| Python::attach(|py| codec::py_to_json(schema.bind(py))))
+            .transpose()?;
+        let z_session = self.ensure_session()?.clone();
+        let policy = self.handler_policy();
+        let (handler, warmer) = Python::attach(|py| {
             let l = event_loop.into_bound(py);
+            let warmer = AsyncInvocationHandler::new(callback.clone_ref(py), TaskLocals::new(l.clone()));
             let task_locals = TaskLocals::new(l);
-            let service = AsyncInvocationHandler::new(callback, task_locals);
-            service
+            (AsyncInvocationHandler::new(callback, task_locals).with_policy(policy), warmer)
         });
+        let warmup = self.warmup.clone();
+        let key = key_expr.clone();
+        get_runtime()
+            .spawn(async move { warmup.run_key(&warmer, &key).await })
+            .await
+            .map_err(|e| PyRuntimeError::new_err(format!("warm-up task failed: {}", e)))?;
+        self.functions
+            .serve(&z_session, &self.firewall, key_expr.clone(), Arc::new(handler))
+            .await
+            .map_err(PyRuntimeError::new_err)?;
+        self.catalog.insert(&key_expr, description, schema);
+        self.catalog.expose(&z_session).await.map_err(|e| {
+            PyErr::new::<PyRuntimeError, _>(format!("Failed to serve function catalog: {}", e))
+        })
+    }
 
-    let z_session = self.ensure_session()?.clone();
-        let ke = key_expr.clone();
-        let z_handler = InvocationZenohHandler::new("".to_string(), Arc::new(handler));
-        let runtime = get_runtime();
-        let conf = oprc_zenoh::util::ManagedConfig::new(ke, 1, 65536);
-        let q = runtime
-            .spawn(async move {
-                oprc_zenoh::util::declare_managed_queryable(&z_session, conf, z_handler).await
+    /// Returns an `HttpDispatcher` running invocations on `callback`, for
+    /// serving functions from an existing web app instead of a second
+    /// listener. Invocations pass the same policy as those of the servers
+    /// started by this engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `event_loop` - The Python event loop the callback runs on.
+    /// * `callback` - The Python callback function to handle invocations.
+    fn http_dispatcher(&self, py: Python<'_>, event_loop: Py<PyAny>, callback: Py<PyAny>) -> HttpDispatcher {
+        let task_locals = TaskLocals::new(event_loop.into_bound(py));
+        let handler = AsyncInvocationHandler::new(callback, task_locals).with_policy(self.handler_policy());
+        HttpDispatcher::new(handler)
+    }
+
+    /// Lists the functions this engine serves, in or out of standby, as
+    /// dicts with `key_expr`, `cls_id`, `fn_id`, `description` and `schema`.
+    /// Other processes get the same with `RpcManager.list_functions`.
+    fn list_functions<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        self.catalog
+            .list()
+            .iter()
+            .map(|entry| codec::json_to_py(py, entry))
+            .collect()
+    }
+
+    /// Sets sample payloads to warm `fn_id` of `cls_id` up with: servers
+    /// started and functions served afterwards invoke it `invocations`
+    /// times, cycling through `payloads`, before they take invocations.
+    /// Warm-up invocations carry the `oprc-warmup` option and skip quotas,
+    /// metrics and the call log; failures are logged. No payloads stops
+    /// warming the function up.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id` - The class of the function.
+    /// * `fn_id` - The function to warm up.
+    /// * `payloads` - The sample payloads.
+    /// * `invocations` - Number of warm-up invocations; one per payload if `None`.
+    /// * `partition_id` - Partition of the invocations on the gRPC server.
+    ///   Functions served over Zenoh use the partition of their key expression.
+    /// * `object_id` - Object invoked on the gRPC server, which warms up
+    ///   stateless invocations if `None`. Functions served over Zenoh use the
+    ///   object of their key expression.
+    #[pyo3(signature = (cls_id, fn_id, payloads, invocations=None, partition_id=0, object_id=None))]
+    fn set_warmup(
+        &self,
+        cls_id: String,
+        fn_id: String,
+        payloads: Vec<Vec<u8>>,
+        invocations: Option<usize>,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::optional_object_id)] object_id: Option<u64>,
+    ) {
+        let invocations = invocations.unwrap_or(payloads.len());
+        self.warmup
+            .set(cls_id, fn_id, payloads, invocations, partition_id, object_id);
+    }
+
+    /// Whether the engine is in standby: functions it serves are ready but
+    /// not declared on Zenoh until `promote` is called.
+    #[getter]
+    fn standby(&self) -> bool {
+        self.functions.is_standby()
+    }
+
+    /// Puts the engine in standby, so functions served afterwards wait for
+    /// `promote`. Opens the session right away. Raises `RuntimeError` if a
+    /// function is already declared; use `demote` for a serving engine.
+    async fn enter_standby(&self) -> PyResult<()> {
+        self.ensure_session()?;
+        self.functions
+            .enter_standby()
+            .await
+            .map_err(PyRuntimeError::new_err)
+    }
+
+    /// Declares the functions served in standby and leaves standby. Engines
+    /// serving the same functions that called `watch_takeover` demote them
+    /// after their grace period, so no invocation finds the function
+    /// undeclared.
+    ///
+    /// # Arguments
+    ///
+    /// * `announce` - Whether to announce the takeover to watching engines.
+    ///
+    /// # Returns
+    ///
+    /// The key expressions declared.
+    #[pyo3(signature = (announce=true))]
+    async fn promote(&self, announce: bool) -> PyResult<Vec<String>> {
+        let z_session = self.ensure_session()?.clone();
+        let declared = self
+            .functions
+            .promote(&z_session, &self.firewall)
+            .await
+            .map_err(PyRuntimeError::new_err)?;
+        if announce && !declared.is_empty() {
+            standby::announce(&z_session, &declared).await.map_err(|e| {
+                PyErr::new::<PyRuntimeError, _>(format!("Failed to announce takeover: {}", e))
+            })?;
+        }
+        Ok(declared)
+    }
+
+    /// Undeclares all functions and enters standby. Invocations already
+    /// running complete; `promote` declares the functions again.
+    ///
+    /// # Returns
+    ///
+    /// The key expressions undeclared.
+    async fn demote(&self) -> Vec<String> {
+        self.functions.demote(None).await
+    }
+
+    /// Demotes the functions another engine takes over with `promote`,
+    /// `grace_ms` after its announcement, replacing any previous watch.
+    /// Opens the session if needed. Only the functions taken over are
+    /// undeclared; the engine does not enter standby.
+    ///
+    /// # Arguments
+    ///
+    /// * `grace_ms` - How long both engines serve the functions before this one stops.
+    #[pyo3(signature = (grace_ms=1000))]
+    fn watch_takeover(&mut self, grace_ms: u64) -> PyResult<()> {
+        let session = self.ensure_session()?.clone();
+        let functions = self.functions.clone();
+        let watch = Python::attach(|py| {
+            py.detach(|| {
+                get_runtime().block_on(standby::watch(
+                    session,
+                    functions,
+                    std::time::Duration::from_millis(grace_ms),
+                ))
             })
+        })
+        .map_err(|e| {
+            PyErr::new::<PyRuntimeError, _>(format!("Failed to subscribe to takeovers: {}", e))
+        })?;
+        if let Some(prev) = self.takeover_watch.replace(watch) {
+            prev.abort();
+        }
+        Ok(())
+    }
+
+    /// Stops demoting functions started by `watch_takeover`.
+    fn unwatch_takeover(&mut self) {
+        if let Some(watch) = self.takeover_watch.take() {
+            watch.abort();
+        }
+    }
+
+    /// Reports Zenoh connectivity changes to Python callbacks, replacing any
+    /// previous watch. Opens the session if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_connect` - Called with the connected zids when the first router or peer connects.
+    /// * `on_disconnect` - Called when the last router or peer disconnects.
+    /// * `on_peer_change` - Called with `(added, removed)` zid lists on every change.
+    /// * `event_loop` - If given, callbacks run on this asyncio loop via `call_soon_threadsafe`.
+    /// * `interval_ms` - How often the connected routers and peers are checked.
+    #[pyo3(signature = (on_connect=None, on_disconnect=None, on_peer_change=None, event_loop=None, interval_ms=1000))]
+    fn watch_session(
+        &mut self,
+        on_connect: Option<Py<PyAny>>,
+        on_disconnect: Option<Py<PyAny>>,
+        on_peer_change: Option<Py<PyAny>>,
+        event_loop: Option<Py<PyAny>>,
+        interval_ms: u64,
+    ) -> PyResult<()> {
+        if interval_ms == 0 {
+            return Err(PyValueError::new_err("interval_ms must be > 0"));
+        }
+        let session = self.ensure_session()?.clone();
+        let hooks = SessionHooks {
+            on_connect,
+            on_disconnect,
+            on_peer_change,
+            event_loop,
+        };
+        if let Some(prev) = self.session_watch.take() {
+            prev.abort();
+        }
+        self.session_watch = Some(session_events::watch(
+            session,
+            hooks,
+            std::time::Duration::from_millis(interval_ms),
+        ));
+        Ok(())
+    }
+
+    /// Stops reporting connectivity changes started by `watch_session`.
+    fn unwatch_session(&mut self) {
+        if let Some(watch) = self.session_watch.take() {
+            watch.abort();
+        }
+    }
+
+    /// Reports partition reassignments of `cls_id` to `on_change`, replacing any
+    /// previous watch of that class. Opens the session if needed.
+    ///
+    /// The platform publishes `{"epoch": N, "partitions": [...]}` on
+    /// `oprc/<cls_id>/assignment`; `on_change` is called with `(assigned, revoked, epoch)`
+    /// for every newer epoch that changes the set of partitions served here.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id` - The class whose assignment to follow.
+    /// * `on_change` - Called with the partitions gained and lost and the new epoch.
+    /// * `event_loop` - If given, `on_change` runs on this asyncio loop via `call_soon_threadsafe`.
+    #[pyo3(signature = (cls_id, on_change, event_loop=None))]
+    fn watch_partitions(
+        &mut self,
+        cls_id: String,
+        on_change: Py<PyAny>,
+        event_loop: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let session = self.ensure_session()?.clone();
+        let hooks = PartitionHooks {
+            on_change,
+            event_loop,
+        };
+        let cls = cls_id.clone();
+        let watch = Python::attach(|py| {
+            py.detach(|| get_runtime().block_on(partitions::watch(session, cls, hooks)))
+        })
+        .map_err(|e| {
+            PyErr::new::<PyRuntimeError, _>(format!("Failed to subscribe to partition assignment: {}", e))
+        })?;
+        if let Some(prev) = self.partition_watches.insert(cls_id, watch) {
+            prev.abort();
+        }
+        Ok(())
+    }
+
+    /// Stops reporting partition reassignments of `cls_id`.
+    fn unwatch_partitions(&mut self, cls_id: &str) {
+        if let Some(watch) = self.partition_watches.remove(cls_id) {
+            watch.abort();
+        }
+    }
+
+    /// Hands objects of a partition off to the replica taking it over, which
+    /// must be accepting with `accept_handoff`. Objects are sent one at a time
+    /// and each counts as verified once the receiver acknowledged its checksum.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id` - The class of the partition.
+    /// * `partition_id` - The partition being moved.
+    /// * `objects` - The objects to transfer.
+    /// * `entries` - If given, only these entries (index or registered name) are transferred.
+    /// * `on_progress` - Called with `(done, total)` after each object.
+    /// * `timeout_ms` - How long to wait for each acknowledgement.
+    #[pyo3(signature = (cls_id, partition_id, objects, entries=None, on_progress=None, timeout_ms=10_000))]
+    async fn handoff(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        objects: Vec<Py<ObjectData>>,
+        entries: Option<Vec<EntryKey>>,
+        on_progress: Option<Py<PyAny>>,
+        timeout_ms: u64,
+    ) -> PyResult<HandoffReport> {
+        let session = self.ensure_session()?.clone();
+        let entries = entries
+            .map(|keys| keys.iter().map(|k| k.resolve(&cls_id)).collect::<PyResult<Vec<u32>>>())
+            .transpose()?;
+        let outgoing = Python::attach(|py| {
+            objects
+                .iter()
+                .map(|obj| {
+                    let mut proto = obj.borrow(py).into_proto();
+                    if let Some(keep) = &entries {
+                        proto.entries.retain(|k, _| keep.contains(k));
+                    }
+                    proto
+                })
+                .collect::<Vec<_>>()
+        });
+        let outgoing = outgoing.into_iter().map(Outgoing::new).collect();
+        let key = handoff::handoff_key(&cls_id, partition_id);
+        let timeout = std::time::Duration::from_millis(timeout_ms);
+        get_runtime()
+            .spawn(handoff::send(session, key, outgoing, on_progress, timeout))
             .await
-            .map_err(|e| {
-                PyErr::new::<PyRuntimeError, _>(format!("Failed to spawn queryable: {}", e))
-            })?
-            .map_err(|e| PyErr::new::<PyRuntimeError, _>(e.to_string()))?;
-        // let q = oprc_zenoh::util::declare_managed_queryable(
-        //         &z_session,
-        //         key_expr.to_owned(),
-        //         z_handler,
-        //         1,
-        //         65536,
-        //     )
-        //     .await
-        //     .map_err(|e| PyErr::new::<PyRuntimeError, _>(e.to_string()))?;
-        {
-            let mut table = self.queryable_table.lock().await;
-            table.insert(key_expr.clone(), q);
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("Handoff task failed: {}", e)))
+    }
+
+    /// Receives objects handed off for a partition this process takes over.
+    /// Opens the session if needed and replaces any previous receiver of the partition.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id` - The class of the partition.
+    /// * `partition_id` - The partition being moved.
+    /// * `on_object` - Called with each verified `ObjectData` from a blocking thread;
+    ///   the object is acknowledged only if it returns without raising.
+    /// * `event_loop` - The asyncio loop coroutines returned by `on_object` are
+    ///   awaited on. Without it, an `async def` callback rejects every object.
+    #[pyo3(signature = (cls_id, partition_id, on_object, event_loop=None))]
+    fn accept_handoff(&mut self, py: Python<'_>, cls_id: &str, #[pyo3(from_py_with = ids::partition_id)] partition_id: u32, on_object: Py<PyAny>, event_loop: Option<Py<PyAny>>) -> PyResult<()> {
+        let session = self.ensure_session()?.clone();
+        let key = handoff::handoff_key(cls_id, partition_id);
+        let k = key.clone();
+        let locals = event_loop.map(|l| TaskLocals::new(l.into_bound(py)));
+        let receiver = py
+            .detach(|| get_runtime().block_on(handoff::accept(session, k, on_object, locals)))
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("Failed to accept handoff: {}", e)))?;
+        if let Some(prev) = self.handoff_receivers.insert(key, receiver) {
+            prev.abort();
         }
         Ok(())
     }
 
+    /// Stops receiving objects handed off for a partition.
+    fn close_handoff(&mut self, cls_id: &str, #[pyo3(from_py_with = ids::partition_id)] partition_id: u32) {
+        if let Some(receiver) = self.handoff_receivers.remove(&handoff::handoff_key(cls_id, partition_id)) {
+            receiver.abort();
+        }
+    }
+
+    /// Sets accept/reject key expression patterns for functions served over Zenoh
+    /// afterwards. Queries are checked before their payload is decoded and
+    /// rejected ones get an error reply.
+    ///
+    /// # Arguments
+    ///
+    /// * `accept` - If non-empty, a query key must be included in one of these
+    ///   (e.g. `oprc/my-cls/1/**` to restrict to own class and partition).
+    /// * `reject` - A query key overlapping any of these is rejected.
+    #[pyo3(signature = (accept=vec![], reject=vec![]))]
+    fn set_zenoh_firewall(&mut self, accept: Vec<String>, reject: Vec<String>) -> PyResult<()> {
+        let firewall = ZenohFirewall::new(&accept, &reject).map_err(PyValueError::new_err)?;
+        self.firewall = Arc::new(firewall);
+        Ok(())
+    }
+
     /// Stops a function being served over Zenoh.
     ///
     /// # Arguments
     ///
     /// * `key_expr` - The Zenoh key expression of the function to stop.
     async fn stop_function(&self, key_expr: String) -> PyResult<()> {
-        let q = {
-            let mut table = self.queryable_table.lock().await;
-            table.remove(&key_expr)
-        };
-        if let Some(q) = q {
-            q.undeclare().await.map_err(|e| {
-                PyErr::new::<PyRuntimeError, _>(format!("Failed to undeclare queryable: {}", e))
-            })?;
-        } else {
+        let served = self
+            .functions
+            .stop(&key_expr)
+            .await
+            .map_err(PyRuntimeError::new_err)?;
+        if !served {
             return Err(PyErr::new::<PyTypeError, _>(format!(
                 "No queryable found for key_expr: {}",
                 key_expr
             )));
         }
+        self.catalog.remove(&key_expr);
         Ok(())
     }
 
+    /// Sets the invocation quota of a tenant on servers started by this engine.
+    ///
+    /// Invocations are accounted to the tenant named by the `oprc-tenant` request
+    /// option (or `default`). Passing no limits removes the quota while keeping
+    /// the accounting.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant` - The tenant name.
+    /// * `max_invocations` - Maximum invocations per window.
+    /// * `max_payload_bytes` - Maximum request payload bytes per window.
+    /// * `window_ms` - Length of the quota window in milliseconds.
+    #[pyo3(signature = (tenant, max_invocations=None, max_payload_bytes=None, window_ms=60_000))]
+    fn set_quota(
+        &self,
+        tenant: &str,
+        max_invocations: Option<u64>,
+        max_payload_bytes: Option<u64>,
+        window_ms: u64,
+    ) -> PyResult<()> {
+        if window_ms == 0 {
+            return Err(PyErr::new::<PyTypeError, _>("window_ms must be > 0"));
+        }
+        self.quota.set_quota(
+            tenant,
+            max_invocations,
+            max_payload_bytes,
+            std::time::Duration::from_millis(window_ms),
+        );
+        Ok(())
+    }
+
+    /// Returns the invocation consumption of `tenant`, or of every tenant seen so far.
+    #[pyo3(signature = (tenant=None))]
+    fn quota_usage(&self, tenant: Option<&str>) -> Vec<TenantUsage> {
+        self.quota.usage(tenant)
+    }
+
+    /// Sets how many idempotency keys servers of this engine remember,
+    /// 10,000 by default.
+    ///
+    /// Invocations sent with an `oprc-idempotency-key` option run once per
+    /// key; duplicate deliveries get the response of the first one. The least
+    /// recently used keys are forgotten beyond `size`, and `0` disables
+    /// de-duplication. Applies to functions already served.
+    fn set_idempotency_cache_size(&self, size: usize) {
+        self.idempotency.set_capacity(size);
+    }
+
+    /// Sets the built-in interceptor pipeline of servers started afterwards.
+    ///
+    /// Each spec is `name` or `name?key=value&key=value`; available interceptors are
+    /// `logging`, `metrics` (or `metrics?object_window=N` to also track hot objects),
+    /// `auth?tokens=a|b`, `jwt?issuer=URL&audience=A` (validates ID tokens
+    /// against the issuer's JWKS and exposes their claims as `req.claims`),
+    /// `payload_guard?max_bytes=N` and `compression?min_bytes=N`.
+    /// Defaults to the comma-separated `OPRC_INTERCEPTORS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `specs` - Interceptor specs, in the order they run on requests.
+    fn set_interceptors(&mut self, specs: Vec<String>) -> PyResult<()> {
+        self.interceptors =
+            Interceptor::parse_all(&specs, &self.metrics).map_err(PyValueError::new_err)?;
+        self.interceptor_specs = specs;
+        Ok(())
+    }
+
+    /// Returns the configured interceptor specs.
+    #[getter]
+    fn interceptors(&self) -> Vec<String> {
+        self.interceptor_specs.clone()
+    }
+
+    /// Registers Python hooks run with the managed state of the target object
+    /// around every object invocation of servers started afterwards.
+    ///
+    /// `pre(req, obj)` runs before the function and rejects the invocation by
+    /// raising; `post(req, obj, resp)` runs after the function returned a
+    /// response. `obj` is an `ObjectData` (empty if the object does not exist
+    /// yet); changes made to it are written back. Hooks need an open Zenoh
+    /// session when the server starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `pre` - Called with the `ObjectInvocationRequest` and the object.
+    /// * `post` - Called with the request, the object and the `InvocationResponse`.
+    #[pyo3(signature = (pre=None, post=None))]
+    fn add_state_hook(&mut self, pre: Option<Py<PyAny>>, post: Option<Py<PyAny>>) -> PyResult<()> {
+        if pre.is_none() && post.is_none() {
+            return Err(PyValueError::new_err("add_state_hook needs pre or post"));
+        }
+        self.state_hooks.push(StateHook::Python {
+            pre: pre.map(Arc::new),
+            post: post.map(Arc::new),
+        });
+        Ok(())
+    }
+
+    /// Writes the time of every successful object invocation (Unix
+    /// milliseconds, as decimal text) to `entry` of the object.
+    fn stamp_last_modified(&mut self, entry: EntryKey) {
+        self.state_hooks.push(StateHook::LastModified(entry));
+    }
+
+    /// Appends a JSON record `{"fn_id", "status", "ts"}` of every object
+    /// invocation to the list in `entry` of the object, keeping the last `keep`.
+    #[pyo3(signature = (entry, keep=100))]
+    fn audit_invocations(&mut self, entry: EntryKey, keep: usize) -> PyResult<()> {
+        if keep == 0 {
+            return Err(PyValueError::new_err("keep must be > 0"));
+        }
+        self.state_hooks.push(StateHook::Audit { entry, keep });
+        Ok(())
+    }
+
+    /// Removes all state hooks, for servers started afterwards.
+    fn clear_state_hooks(&mut self) {
+        self.state_hooks.clear();
+    }
+
+    /// Sets the private key that functions served afterwards open encrypted
+    /// invocations with, as made by `generate_encryption_key`. Their
+    /// responses are encrypted for the caller. `None` removes the key, and
+    /// encrypted invocations are rejected. Defaults to `OPRC_E2E_PRIVATE_KEY`.
+    ///
+    /// # Arguments
+    ///
+    /// * `private_key` - The 32-byte X25519 private key.
+    /// * `required` - Reject invocations that are not encrypted.
+    #[pyo3(signature = (private_key=None, required=false))]
+    fn set_encryption_key(&mut self, private_key: Option<&[u8]>, required: bool) -> PyResult<()> {
+        self.encryption = private_key
+            .map(|key| ServerKey::new(key, required).map(Arc::new))
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        Ok(())
+    }
+
+    /// The public key callers encrypt invocations with, to publish to them,
+    /// or `None` without a private key.
+    #[getter]
+    fn encryption_public_key(&self) -> Option<Vec<u8>> {
+        self.encryption.as_ref().map(|key| key.public_key().to_vec())
+    }
+
+    /// Records every invocation of functions served afterwards, admitted or
+    /// rejected, in a hash-chained audit log. Check it with
+    /// `verify_audit_log`. Opens the session if needed; the log cannot be
+    /// disabled or replaced once enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to append the records to, continuing the records
+    ///   already in it. `None` publishes them to the data plane under
+    ///   `audit_log_key`.
+    /// * `anchor_every` - Publish the head of the chain to
+    ///   `<audit_log_key>/anchor` every this many records; `0` disables anchors.
+    #[pyo3(signature = (path=None, anchor_every=DEFAULT_ANCHOR_EVERY))]
+    fn enable_audit_log(&mut self, path: Option<std::path::PathBuf>, anchor_every: u64) -> PyResult<()> {
+        if self.audit.is_some() {
+            return Err(PyRuntimeError::new_err("the audit log is already enabled"));
+        }
+        let session = self.ensure_session()?.clone();
+        let log = get_runtime()
+            .block_on(AuditLog::open(session, path, anchor_every))
+            .map_err(PyRuntimeError::new_err)?;
+        self.audit = Some(log);
+        Ok(())
+    }
+
+    /// Key expression prefix the audit log publishes its anchors, and without
+    /// a file its records, under; `None` if it is not enabled.
+    #[getter]
+    fn audit_log_key(&self) -> Option<String> {
+        self.audit.as_ref().map(|log| log.key_prefix().to_string())
+    }
+
+    /// Returns a checkpoint store kept in an entry of an object, so consumers
+    /// resume from the same checkpoints wherever they restart. Opens the
+    /// session if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id` - The class of the object holding the checkpoints.
+    /// * `object_id` - The object holding the checkpoints; created on the first commit.
+    /// * `entry` - The entry (index or registered name) they are stored in.
+    /// * `partition_id` - The partition of the object.
+    #[pyo3(signature = (cls_id, object_id, entry, partition_id=0))]
+    fn checkpoint_store(
+        &self,
+        cls_id: String,
+        #[pyo3(from_py_with = ids::object_id)] object_id: u64,
+        entry: EntryKey,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+    ) -> PyResult<CheckpointStore> {
+        let entry = entry.resolve(&cls_id)?;
+        let proxy = ObjectProxy::new(self.ensure_session()?.clone());
+        let meta = oprc_pb::ObjMeta {
+            cls_id,
+            partition_id,
+            object_id,
+        };
+        Ok(CheckpointStore::object(proxy, meta, entry))
+    }
+
+    /// Returns per-function counters recorded by the `metrics` interceptor.
+    fn function_metrics(&self) -> Vec<FunctionStats> {
+        self.metrics.function_stats()
+    }
+
+    /// Returns the object data traffic per class of the engine's
+    /// `DataManager`, as its `stats()` does; empty until it is created.
+    fn data_metrics(&self, py: Python<'_>) -> Vec<DataStats> {
+        self.data_manager
+            .as_ref()
+            .map(|dm| dm.borrow(py).stats())
+            .unwrap_or_default()
+    }
+
+    /// Returns how often each served function failed with each Python
+    /// exception class, so validation errors can be told apart from crashes.
+    ///
+    /// Counts both exceptions escaping the handler and those the SDK catches
+    /// and reports in the `oprc-exception-type` response header.
+    fn exception_metrics(&self) -> Vec<ExceptionStats> {
+        self.metrics.exception_stats()
+    }
+
+    /// Returns the `k` most invoked objects of each class (or only of `cls_id`)
+    /// over the tracking window, hottest first, to spot skewed keys that need
+    /// re-partitioning.
+    ///
+    /// Objects are tracked by the `metrics` interceptor once enabled with
+    /// `track_hot_objects` or `metrics?object_window=N`; empty otherwise.
+    #[pyo3(signature = (cls_id=None, k=10))]
+    fn hot_objects(&self, cls_id: Option<&str>, k: usize) -> Vec<HotObject> {
+        self.metrics.hot_objects(cls_id, k)
+    }
+
+    /// Tracks per-object invocation counts over the last `window` object
+    /// invocations of each class (`0` disables tracking). Requires the
+    /// `metrics` interceptor.
+    #[pyo3(signature = (window=4096))]
+    fn track_hot_objects(&self, window: usize) {
+        self.metrics.track_objects(window);
+    }
+
+    /// Returns the invocation counters of servers started by this engine.
+    fn server_metrics(&self) -> ServerMetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Returns the `oprc.server.inflight` and `oprc.server.queue_depth`
+    /// gauges of every function served by this engine, labelled with `cls`
+    /// and `fn`. Names and labels are stable across SDK releases.
+    ///
+    /// An invocation is queued from its arrival until its Python callback
+    /// starts, which covers admission and waiting for the GIL, then in flight
+    /// until its response is sent.
+    fn export_metrics(&self) -> Vec<MetricPoint> {
+        self.metrics.export()
+    }
+
+    /// Number of recent invocations handled by servers of this engine kept
+    /// for `recent_calls`; `0` (the default) keeps none. Applies to
+    /// functions already served.
+    #[getter]
+    fn get_call_log_size(&self) -> usize {
+        self.calls.capacity()
+    }
+
+    #[setter]
+    fn set_call_log_size(&self, size: usize) {
+        self.calls.set_capacity(size);
+    }
+
+    /// Largest request payload in bytes the servers of this engine admit, or
+    /// `None` (the default) for no limit. Larger invocations are rejected
+    /// with `ResourceExhausted` and a message giving both sizes, before the
+    /// handler runs; chunked and compressed payloads are checked again once
+    /// fetched and inflated. Applies to functions already served.
+    #[getter]
+    fn get_max_payload_bytes(&self) -> Option<usize> {
+        self.max_payload.get()
+    }
+
+    #[setter]
+    fn set_max_payload_bytes(&self, max_payload_bytes: Option<usize>) -> PyResult<()> {
+        if max_payload_bytes == Some(0) {
+            return Err(PyValueError::new_err("max_payload_bytes must be > 0"));
+        }
+        self.max_payload.set(max_payload_bytes);
+        Ok(())
+    }
+
+    /// Returns the last invocations handled by servers of this engine, newest
+    /// first, rejected ones included.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - At most this many invocations; all kept if `None`.
+    #[pyo3(signature = (limit=None))]
+    fn recent_calls(&self, limit: Option<usize>) -> Vec<CallRecord> {
+        self.calls.recent(limit)
+    }
+
+    /// Persists cumulative invocation counters to `path` and restores them from
+    /// it now if the file exists. The snapshot is saved again when the server stops.
+    ///
+    /// Can also be enabled with the `OPRC_METRICS_SNAPSHOT` environment variable.
+    fn enable_metrics_snapshot(&self, path: std::path::PathBuf) -> PyResult<()> {
+        self.metrics
+            .enable_snapshot(path)
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("Failed to restore metrics snapshot: {}", e)))
+    }
+
+    /// Writes the cumulative invocation counters to the snapshot file now.
+    ///
+    /// Returns `False` if snapshots are not enabled.
+    fn save_metrics_snapshot(&self) -> PyResult<bool> {
+        self.metrics
+            .save_snapshot()
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("Failed to save metrics snapshot: {}", e)))
+    }
+
+    /// Enables gRPC-Web on gRPC servers started afterwards, so browser clients
+    /// can invoke functions directly without an Envoy proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow_origins` - Origins allowed by CORS (e.g. `http://localhost:3000`); empty allows any.
+    #[pyo3(signature = (allow_origins=vec![]))]
+    fn enable_grpc_web(&mut self, allow_origins: Vec<String>) -> PyResult<()> {
+        let allow_origins = allow_origins
+            .iter()
+            .map(|o| {
+                http::HeaderValue::from_str(o)
+                    .map_err(|_| PyValueError::new_err(format!("Invalid origin '{}'", o)))
+            })
+            .collect::<PyResult<_>>()?;
+        self.grpc_web = Some(GrpcWebConfig { allow_origins });
+        Ok(())
+    }
+
+    /// Disables gRPC-Web on gRPC servers started afterwards.
+    fn disable_grpc_web(&mut self) {
+        self.grpc_web = None;
+    }
+
     /// Stops the gRPC server.
     fn stop_server(&mut self) -> PyResult<()> {
         if let Some(sender) = self.shutdown_sender.take() {
             let _ = sender.send(());
         }
+        if let Err(e) = self.metrics.save_snapshot() {
+            eprintln!("Failed to save metrics snapshot: {}", e);
+        }
         Ok(())
     }
 }
@@ -251,26 +1116,44 @@ impl OaasEngine {
 /// * `port` - The port number to bind the gRPC server to.
 /// * `service` - The InvocationHandler service.
 /// * `shutdown_receiver` - A oneshot receiver to signal server shutdown.
+/// * `grpc_web` - If set, also accepts gRPC-Web (HTTP/1.1) requests with CORS.
 async fn start_tonic<T>(
     port: u16,
     service: T,
     mut shutdown_receiver: oneshot::Receiver<()>,
+    grpc_web: Option<GrpcWebConfig>,
 ) -> PyResult<()>
 where
     T: OprcFunction,
 {
     let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
-    let server = OprcFunctionServer::new(service);
-    Server::builder()
-        .add_service(server.max_decoding_message_size(usize::MAX))
-        .serve_with_shutdown(socket, async {
-            tokio::select! {
-                _ = shutdown_signal() => {},
-                _ = &mut shutdown_receiver => {}, // Wait for the shutdown signal
-            }
-        })
-        .await
-        .map_err(|e| PyErr::new::<PyTypeError, _>(e.to_string()))?;
+    let server = OprcFunctionServer::new(service).max_decoding_message_size(usize::MAX);
+    let mut stopped = crate::shutdown::subscribe();
+    let shutdown = async {
+        tokio::select! {
+            _ = shutdown_signal() => {},
+            _ = &mut shutdown_receiver => {}, // Wait for the shutdown signal
+            _ = stopped.wait_for(|stop| *stop) => {}, // oprc_py.shutdown()
+        }
+    };
+    match grpc_web {
+        None => {
+            Server::builder()
+                .add_service(server)
+                .serve_with_shutdown(socket, shutdown)
+                .await
+        }
+        Some(web) => {
+            Server::builder()
+                .accept_http1(true)
+                .layer(web.cors())
+                .layer(GrpcWebLayer::new())
+                .add_service(server)
+                .serve_with_shutdown(socket, shutdown)
+                .await
+        }
+    }
+    .map_err(|e| PyErr::new::<PyTypeError, _>(e.to_string()))?;
     Ok(())
 }
 
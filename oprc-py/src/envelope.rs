@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+use prost::Message;
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+
+/// Request option / response header naming the type URL of an `Any`-wrapped payload.
+pub const TYPE_URL_OPTION: &str = "oprc-type-url";
+
+/// Decoders registered per type URL; each is called with the unwrapped value bytes.
+static DECODERS: OnceLock<RwLock<HashMap<String, Py<PyAny>>>> = OnceLock::new();
+
+fn decoders() -> &'static RwLock<HashMap<String, Py<PyAny>>> {
+    DECODERS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Wraps `value` in a `google.protobuf.Any` and encodes it.
+pub(crate) fn pack(type_url: &str, value: Vec<u8>) -> Vec<u8> {
+    prost_types::Any {
+        type_url: type_url.to_string(),
+        value,
+    }
+    .encode_to_vec()
+}
+
+/// Decodes a `google.protobuf.Any` envelope.
+pub(crate) fn unpack(payload: &[u8]) -> PyResult<prost_types::Any> {
+    prost_types::Any::decode(payload)
+        .map_err(|e| PyValueError::new_err(format!("Payload is not a protobuf Any: {}", e)))
+}
+
+/// Unwraps `payload` and decodes its value with the decoder registered for its type URL.
+///
+/// If `expected_type_url` is given (from the `oprc-type-url` option or header),
+/// it must match the type URL inside the envelope.
+pub(crate) fn decode<'py>(
+    py: Python<'py>,
+    payload: &[u8],
+    expected_type_url: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let any = unpack(payload)?;
+    if let Some(expected) = expected_type_url.filter(|expected| *expected != any.type_url) {
+        return Err(PyValueError::new_err(format!(
+            "Payload type URL mismatch: header says '{}', envelope contains '{}'",
+            expected, any.type_url
+        )));
+    }
+    let decoder = decoders()
+        .read()
+        .unwrap()
+        .get(&any.type_url)
+        .map(|d| d.clone_ref(py))
+        .ok_or_else(|| {
+            PyKeyError::new_err(format!(
+                "No decoder registered for type URL '{}'",
+                any.type_url
+            ))
+        })?;
+    decoder
+        .call1(py, (PyBytes::new(py, &any.value),))
+        .map(|d| d.into_bound(py))
+}
+
+/// Registers the decoder used by `decode_any` and `unpack_payload` for a type URL,
+/// e.g. `register_payload_type("type.googleapis.com/acme.Order", Order.FromString)`.
+///
+/// Re-registering a type URL replaces its decoder.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn register_payload_type(type_url: String, decoder: Py<PyAny>) {
+    decoders().write().unwrap().insert(type_url, decoder);
+}
+
+/// Returns the type URLs that have a registered decoder.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn payload_types() -> Vec<String> {
+    let mut urls: Vec<String> = decoders().read().unwrap().keys().cloned().collect();
+    urls.sort();
+    urls
+}
+
+/// Wraps `value` in a `google.protobuf.Any` with the given type URL.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn pack_any(type_url: &str, value: Vec<u8>) -> Vec<u8> {
+    pack(type_url, value)
+}
+
+/// Splits a `google.protobuf.Any` into its type URL and value bytes.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn unpack_any(payload: &[u8]) -> PyResult<(String, Vec<u8>)> {
+    let any = unpack(payload)?;
+    Ok((any.type_url, any.value))
+}
+
+/// Decodes a `google.protobuf.Any` payload with the decoder registered for its type URL.
+///
+/// Raises `KeyError` if no decoder is registered, `ValueError` if the payload is
+/// not an `Any` or its type URL differs from `type_url`.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+#[pyo3(signature = (payload, type_url=None))]
+pub fn decode_any<'py>(
+    py: Python<'py>,
+    payload: &[u8],
+    type_url: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    decode(py, payload, type_url)
+}
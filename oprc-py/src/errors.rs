@@ -0,0 +1,262 @@
+use std::{collections::HashMap, fmt};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTimeoutError, PyValueError},
+    prelude::*,
+};
+use tonic::Code;
+
+use crate::model::InvocationResponseCode;
+use crate::payload_limit::{self, PayloadTooLarge};
+use crate::rpc::CircuitOpenError;
+
+/// Attribute of the exceptions raised by invocations holding their `ErrorCode`.
+const CODE_ATTR: &str = "code";
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass_enum)]
+#[pyclass(eq, eq_int)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// Stable code of a failed invocation, the same whichever transport carried
+/// it. The hundreds digit is the category. Values are never reassigned; new
+/// codes may be added.
+pub enum ErrorCode {
+    /// No handler answered the invocation.
+    Unavailable = 100,
+    /// The invocation did not complete within its timeout.
+    Timeout = 101,
+    /// The connection to the handler failed or broke.
+    TransportError = 102,
+    /// The request was malformed or used an unsupported feature.
+    InvalidRequest = 200,
+    /// The response failed its checksum, decryption or decoding.
+    InvalidResponse = 201,
+    /// The handler does not serve the invoked function.
+    NotFound = 202,
+    /// The caller did not present valid credentials.
+    Unauthorized = 300,
+    /// The caller's tenant exceeded its invocation quota.
+    QuotaExceeded = 301,
+    /// The caller's deadline passed.
+    DeadlineExceeded = 302,
+    /// A memory budget could not cover the payload.
+    ResourceExhausted = 303,
+    /// The circuit of the invoked function is open.
+    CircuitOpen = 304,
+    /// The object's state changed since the version the caller expected.
+    Conflict = 305,
+    /// The request payload is over the size limit.
+    PayloadTooLarge = 306,
+    /// The handler failed.
+    AppError = 400,
+    /// The runtime serving the handler failed.
+    SystemError = 401,
+    /// A failure no other code describes.
+    Unknown = 499,
+}
+
+impl ErrorCode {
+    /// The code of a response status, `None` for `Okay`.
+    pub(crate) fn of_status(status: i32) -> Option<Self> {
+        use InvocationResponseCode as S;
+        let code = match status {
+            s if s == S::Okay as i32 => return None,
+            s if s == S::InvalidRequest as i32 => ErrorCode::InvalidRequest,
+            s if s == S::AppError as i32 => ErrorCode::AppError,
+            s if s == S::SystemError as i32 => ErrorCode::SystemError,
+            s if s == S::Conflict as i32 => ErrorCode::Conflict,
+            s if s == S::QuotaExceeded as i32 => ErrorCode::QuotaExceeded,
+            s if s == S::Unauthorized as i32 => ErrorCode::Unauthorized,
+            s if s == S::DeadlineExceeded as i32 => ErrorCode::DeadlineExceeded,
+            s if s == S::ResourceExhausted as i32 => ErrorCode::ResourceExhausted,
+            _ => ErrorCode::Unknown,
+        };
+        Some(code)
+    }
+
+    /// The code of a response with `status` and `headers`, `None` for
+    /// `Okay`. A payload rejected by the handler's size limit is
+    /// `PayloadTooLarge` rather than `ResourceExhausted`.
+    pub(crate) fn of_response(status: i32, headers: &HashMap<String, String>) -> Option<Self> {
+        match Self::of_status(status)? {
+            ErrorCode::ResourceExhausted if payload_limit::rejected(headers).is_some() => {
+                Some(ErrorCode::PayloadTooLarge)
+            }
+            code => Some(code),
+        }
+    }
+
+    /// The code of a gRPC status, `None` for `OK`.
+    pub(crate) fn of_grpc(code: Code) -> Option<Self> {
+        let code = match code {
+            Code::Ok => return None,
+            Code::Unavailable => ErrorCode::Unavailable,
+            Code::Cancelled | Code::Aborted => ErrorCode::TransportError,
+            Code::InvalidArgument | Code::OutOfRange => ErrorCode::InvalidRequest,
+            Code::NotFound | Code::Unimplemented => ErrorCode::NotFound,
+            Code::Unauthenticated | Code::PermissionDenied => ErrorCode::Unauthorized,
+            Code::DeadlineExceeded => ErrorCode::DeadlineExceeded,
+            Code::ResourceExhausted => ErrorCode::ResourceExhausted,
+            Code::AlreadyExists | Code::FailedPrecondition => ErrorCode::Conflict,
+            Code::Internal | Code::DataLoss => ErrorCode::SystemError,
+            Code::Unknown => ErrorCode::Unknown,
+        };
+        Some(code)
+    }
+
+    /// The code of an HTTP status, `None` below 400.
+    pub(crate) fn of_http(status: u16) -> Option<Self> {
+        let code = match status {
+            0..400 => return None,
+            401 | 403 => ErrorCode::Unauthorized,
+            404 | 405 | 501 => ErrorCode::NotFound,
+            408 | 504 => ErrorCode::Timeout,
+            409 | 412 => ErrorCode::Conflict,
+            413 => ErrorCode::ResourceExhausted,
+            429 => ErrorCode::QuotaExceeded,
+            502 | 503 => ErrorCode::Unavailable,
+            400..500 => ErrorCode::InvalidRequest,
+            500..600 => ErrorCode::SystemError,
+            _ => ErrorCode::Unknown,
+        };
+        Some(code)
+    }
+
+    /// The HTTP status reporting this code, the inverse of `of_http` where
+    /// it has one.
+    pub(crate) fn http_status(self) -> u16 {
+        match self {
+            ErrorCode::Unavailable | ErrorCode::CircuitOpen => 503,
+            ErrorCode::Timeout | ErrorCode::DeadlineExceeded => 504,
+            ErrorCode::TransportError | ErrorCode::InvalidResponse => 502,
+            ErrorCode::InvalidRequest => 400,
+            ErrorCode::NotFound => 404,
+            ErrorCode::Unauthorized => 401,
+            ErrorCode::QuotaExceeded => 429,
+            ErrorCode::ResourceExhausted | ErrorCode::PayloadTooLarge => 413,
+            ErrorCode::Conflict => 409,
+            ErrorCode::AppError | ErrorCode::SystemError | ErrorCode::Unknown => 500,
+        }
+    }
+
+    /// The name of the code, e.g. `"QUOTA_EXCEEDED"`.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ErrorCode::Unavailable => "UNAVAILABLE",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::TransportError => "TRANSPORT_ERROR",
+            ErrorCode::InvalidRequest => "INVALID_REQUEST",
+            ErrorCode::InvalidResponse => "INVALID_RESPONSE",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::DeadlineExceeded => "DEADLINE_EXCEEDED",
+            ErrorCode::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            ErrorCode::CircuitOpen => "CIRCUIT_OPEN",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::AppError => "APP_ERROR",
+            ErrorCode::SystemError => "SYSTEM_ERROR",
+            ErrorCode::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// Sets this code on the exception `err`.
+    pub(crate) fn tag(self, err: PyErr) -> PyErr {
+        Python::attach(|py| {
+            if let Err(e) = err.value(py).setattr(CODE_ATTR, self) {
+                tracing::debug!("failed to set the error code of an exception: {}", e);
+            }
+        });
+        err
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ErrorCode {
+    /// `"transport"`, `"protocol"`, `"policy"` or `"application"`.
+    #[getter]
+    fn category(&self) -> &'static str {
+        match *self as u32 / 100 {
+            1 => "transport",
+            2 => "protocol",
+            3 => "policy",
+            _ => "application",
+        }
+    }
+
+    /// The code of a response status, `None` for `Okay`.
+    #[staticmethod]
+    fn from_status(status: i32) -> Option<Self> {
+        Self::of_status(status)
+    }
+
+    /// The code of a gRPC status code, `None` for `OK`.
+    #[staticmethod]
+    fn from_grpc_status(code: i32) -> Option<Self> {
+        Self::of_grpc(Code::from(code))
+    }
+
+    /// The code of an HTTP status, `None` below 400.
+    #[staticmethod]
+    fn from_http_status(status: u16) -> Option<Self> {
+        Self::of_http(status)
+    }
+
+    /// The code of an exception raised by an invocation. Exceptions raised
+    /// before the code was set, or by other calls, are classified by type.
+    #[staticmethod]
+    fn of(err: &Bound<'_, PyAny>) -> Self {
+        if let Ok(code) = err
+            .getattr(CODE_ATTR)
+            .and_then(|code| code.extract::<ErrorCode>())
+        {
+            return code;
+        }
+        if err.is_instance_of::<CircuitOpenError>() {
+            ErrorCode::CircuitOpen
+        } else if err.is_instance_of::<PayloadTooLarge>() {
+            ErrorCode::PayloadTooLarge
+        } else if err.is_instance_of::<PyTimeoutError>() {
+            ErrorCode::Timeout
+        } else if err.is_instance_of::<PyValueError>() {
+            ErrorCode::InvalidRequest
+        } else {
+            ErrorCode::Unknown
+        }
+    }
+
+    /// The name of the code, e.g. `"QUOTA_EXCEEDED"`, stable like its value.
+    fn __str__(&self) -> &'static str {
+        self.name()
+    }
+}
+
+/// A failed invocation attempt, described for the caller.
+#[derive(Clone, Debug)]
+pub(crate) struct CodedError {
+    pub(crate) code: ErrorCode,
+    pub(crate) message: String,
+}
+
+impl CodedError {
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        CodedError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<CodedError> for PyErr {
+    /// A `RuntimeError` carrying the code.
+    fn from(e: CodedError) -> Self {
+        e.code.tag(PyRuntimeError::new_err(e.message))
+    }
+}
@@ -0,0 +1,240 @@
+use std::{fs::File, sync::Arc, time::UNIX_EPOCH};
+
+use arrow::{
+    array::{ArrayRef, BinaryBuilder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    pyarrow::ToPyArrow,
+    record_batch::RecordBatch,
+};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyList};
+use tracing::info;
+
+use crate::scan::{self, Scanned};
+
+/// Rows buffered before they are turned into a record batch.
+const BATCH_ROWS: usize = 8192;
+
+/// Schema of exported scans: one row per object entry. Objects without
+/// entries get a single row with a null `entry` and `value`.
+pub(crate) fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("cls_id", DataType::Utf8, false),
+        Field::new("partition_id", DataType::UInt32, false),
+        Field::new("object_id", DataType::UInt64, false),
+        Field::new("entry", DataType::UInt32, true),
+        Field::new("value", DataType::Binary, true),
+        Field::new("written_ms", DataType::UInt64, true),
+    ]))
+}
+
+/// Parses a Parquet compression name.
+pub(crate) fn compression(name: &str) -> PyResult<Compression> {
+    match name.to_ascii_lowercase().as_str() {
+        "none" | "uncompressed" => Ok(Compression::UNCOMPRESSED),
+        "snappy" => Ok(Compression::SNAPPY),
+        "zstd" => Ok(Compression::ZSTD(ZstdLevel::default())),
+        other => Err(PyValueError::new_err(format!(
+            "unsupported compression '{}'; expected 'none', 'snappy' or 'zstd'",
+            other
+        ))),
+    }
+}
+
+/// Where record batches go.
+enum Sink {
+    /// Kept in memory, to be returned as a `pyarrow.Table`.
+    Table(Vec<RecordBatch>),
+    /// Written to a Parquet file as they are produced.
+    Parquet(ArrowWriter<File>),
+}
+
+impl Sink {
+    fn parquet(path: &str, compression: Compression) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+        let props = WriterProperties::builder()
+            .set_compression(compression)
+            .build();
+        ArrowWriter::try_new(file, schema(), Some(props))
+            .map(Sink::Parquet)
+            .map_err(|e| e.to_string())
+    }
+
+    fn write(&mut self, batch: RecordBatch) -> Result<(), String> {
+        match self {
+            Sink::Table(batches) => {
+                batches.push(batch);
+                Ok(())
+            }
+            Sink::Parquet(writer) => writer.write(&batch).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Column builders for the rows not yet written.
+#[derive(Default)]
+struct Rows {
+    cls_id: StringBuilder,
+    partition_id: UInt32Builder,
+    object_id: UInt64Builder,
+    entry: UInt32Builder,
+    value: BinaryBuilder,
+    written_ms: UInt64Builder,
+    len: usize,
+}
+
+impl Rows {
+    fn push(&mut self, cls_id: &str, partition_id: u32, obj: &Scanned) {
+        let written_ms = obj
+            .written
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64);
+        let mut entries: Vec<_> = obj
+            .data
+            .iter()
+            .flat_map(|d| d.entries.iter())
+            .map(|(k, v)| (Some(*k), Some(v.data.as_slice())))
+            .collect();
+        entries.sort_by_key(|(k, _)| *k);
+        if entries.is_empty() {
+            entries.push((None, None));
+        }
+        for (entry, value) in entries {
+            self.cls_id.append_value(cls_id);
+            self.partition_id.append_value(partition_id);
+            self.object_id.append_value(obj.object_id);
+            self.entry.append_option(entry);
+            self.value.append_option(value);
+            self.written_ms.append_option(written_ms);
+            self.len += 1;
+        }
+    }
+
+    fn finish(&mut self) -> Result<RecordBatch, String> {
+        self.len = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.cls_id.finish()),
+            Arc::new(self.partition_id.finish()),
+            Arc::new(self.object_id.finish()),
+            Arc::new(self.entry.finish()),
+            Arc::new(self.value.finish()),
+            Arc::new(self.written_ms.finish()),
+        ];
+        RecordBatch::try_new(schema(), columns).map_err(|e| e.to_string())
+    }
+}
+
+/// Scans the objects of `cls_id`, one partition at a time, into `sink`.
+///
+/// Fails on the first partition that cannot be scanned, so an export is never
+/// silently incomplete.
+async fn export(
+    session: zenoh::Session,
+    cls_id: String,
+    partitions: Vec<u32>,
+    mut sink: Sink,
+) -> Result<(Sink, ExportReport), String> {
+    let mut report = ExportReport::default();
+    let mut rows = Rows::default();
+    for partition_id in partitions {
+        let mut error = None;
+        scan::partition(&session, &cls_id, partition_id, |obj| {
+            if error.is_some() {
+                return;
+            }
+            rows.push(&cls_id, partition_id, &obj);
+            report.objects += 1;
+            if rows.len >= BATCH_ROWS {
+                report.rows += rows.len as u64;
+                if let Err(e) = rows.finish().and_then(|batch| sink.write(batch)) {
+                    error = Some(e);
+                }
+            }
+        })
+        .await
+        .map_err(|e| format!("failed to scan {}/{}: {}", cls_id, partition_id, e))?;
+        if let Some(e) = error {
+            return Err(e);
+        }
+    }
+    if rows.len > 0 {
+        report.rows += rows.len as u64;
+        sink.write(rows.finish()?)?;
+    }
+    info!(
+        "exported {} object(s) of {} as {} row(s)",
+        report.objects, cls_id, report.rows
+    );
+    Ok((sink, report))
+}
+
+/// Scans the objects of `cls_id` into memory as record batches.
+pub(crate) async fn to_batches(
+    session: zenoh::Session,
+    cls_id: String,
+    partitions: Vec<u32>,
+) -> Result<Vec<RecordBatch>, String> {
+    match export(session, cls_id, partitions, Sink::Table(Vec::new())).await? {
+        (Sink::Table(batches), _) => Ok(batches),
+        (Sink::Parquet(_), _) => unreachable!(),
+    }
+}
+
+/// Scans the objects of `cls_id` into the Parquet file at `path`.
+pub(crate) async fn to_parquet(
+    session: zenoh::Session,
+    cls_id: String,
+    partitions: Vec<u32>,
+    path: String,
+    compression: Compression,
+) -> Result<ExportReport, String> {
+    let sink = Sink::parquet(&path, compression)?;
+    let (sink, mut report) = export(session, cls_id, partitions, sink).await?;
+    if let Sink::Parquet(writer) = sink {
+        writer.close().map_err(|e| e.to_string())?;
+    }
+    report.path = Some(path);
+    Ok(report)
+}
+
+/// Converts record batches into a `pyarrow.Table`.
+pub(crate) fn to_table(py: Python<'_>, batches: Vec<RecordBatch>) -> PyResult<Py<PyAny>> {
+    let batches = batches
+        .iter()
+        .map(|b| b.to_pyarrow(py))
+        .collect::<PyResult<Vec<_>>>()?;
+    let table = py.import("pyarrow")?.getattr("Table")?.call_method1(
+        "from_batches",
+        (PyList::new(py, batches)?, schema().to_pyarrow(py)?),
+    )?;
+    Ok(table.unbind())
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone, Default)]
+/// Outcome of exporting a scan to a Parquet file.
+pub struct ExportReport {
+    /// Objects exported.
+    pub objects: u64,
+    /// Rows written, one per object entry.
+    pub rows: u64,
+    /// The file written.
+    pub path: Option<String>,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ExportReport {
+    /// Returns a string representation of the `ExportReport`.
+    fn __str__(&self) -> String {
+        format!(
+            "ExportReport {{ objects: {}, rows: {}, path: {:?} }}",
+            self.objects, self.rows, self.path
+        )
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use oprc_zenoh::util::Handler;
+use tracing::warn;
+use zenoh::{
+    key_expr::{KeyExpr, OwnedKeyExpr},
+    query::Query,
+};
+
+use crate::sticky;
+
+/// Accept/reject rules on the key expressions of incoming Zenoh queries,
+/// evaluated before the query payload is decoded.
+#[derive(Clone, Default)]
+pub struct ZenohFirewall {
+    /// If non-empty, a query must fall entirely within one of these.
+    accept: Vec<OwnedKeyExpr>,
+    /// A query overlapping any of these is rejected.
+    reject: Vec<OwnedKeyExpr>,
+}
+
+impl ZenohFirewall {
+    pub fn new(accept: &[String], reject: &[String]) -> Result<Self, String> {
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| {
+                    OwnedKeyExpr::autocanonize(p.clone())
+                        .map_err(|e| format!("invalid key expression '{}': {}", p, e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(ZenohFirewall {
+            accept: parse(accept)?,
+            reject: parse(reject)?,
+        })
+    }
+
+    /// Returns `Err` with the reason if a query on `key_expr` must be rejected.
+    /// Invocations pinned to a replica are checked against the key
+    /// expression of their function.
+    pub fn check(&self, key_expr: &KeyExpr<'_>) -> Result<(), String> {
+        let unpinned = sticky::unpinned(key_expr);
+        let key_expr = unpinned.as_ref().unwrap_or(key_expr);
+        if let Some(pattern) = self.reject.iter().find(|p| p.intersects(key_expr)) {
+            return Err(format!(
+                "key expression '{}' matches reject pattern '{}'",
+                key_expr, pattern
+            ));
+        }
+        if !self.accept.is_empty() && !self.accept.iter().any(|p| p.includes(key_expr)) {
+            return Err(format!("key expression '{}' is not accepted", key_expr));
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a query handler so queries rejected by the firewall are answered
+/// with an error reply and never reach it.
+#[derive(Clone)]
+pub struct FirewalledHandler<H> {
+    firewall: Arc<ZenohFirewall>,
+    inner: H,
+}
+
+impl<H> FirewalledHandler<H> {
+    pub fn new(firewall: Arc<ZenohFirewall>, inner: H) -> Self {
+        FirewalledHandler { firewall, inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl<H> Handler<Query> for FirewalledHandler<H>
+where
+    H: Handler<Query> + Send + Sync,
+{
+    async fn handle(&self, query: Query) {
+        if let Err(reason) = self.firewall.check(query.key_expr()) {
+            warn!("zenoh firewall rejected query: {}", reason);
+            if let Err(e) = query.reply_err(reason).await {
+                warn!("failed to reply to rejected query: {}", e);
+            }
+            return;
+        }
+        self.inner.handle(query).await
+    }
+}
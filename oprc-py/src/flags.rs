@@ -0,0 +1,130 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{OnceLock, RwLock},
+};
+
+use pyo3::pyfunction;
+use tracing::warn;
+
+/// Environment variable with comma-separated flags to enable (`name`) or
+/// disable (`-name`).
+pub const FLAGS_ENV: &str = "OPRC_FEATURE_FLAGS";
+/// Environment variable naming a JSON file with a `{"name": bool}` object.
+pub const FLAGS_FILE_ENV: &str = "OPRC_FEATURE_FLAGS_FILE";
+
+/// Sends large payloads out of band in chunks (`invoke_from_file`,
+/// `invoke_from_stream_async` and payloads over
+/// `RpcManager.chunk_threshold_bytes`); when off, they are sent inline.
+pub const CHUNKED_PAYLOADS: &str = "chunked_payloads";
+/// Makes invocations fired by a trigger link to the originating span instead
+/// of becoming its child.
+pub const TRIGGER_SPAN_LINKS: &str = "trigger_span_links";
+/// Keeps async handlers serving on an internal event loop thread once the
+/// loop they were registered with is closed.
+pub const EVENT_LOOP_FALLBACK: &str = "event_loop_fallback";
+
+/// Flags the runtime knows, with their defaults. Other names are accepted
+/// (and default to off) so applications can gate their own behaviors.
+const KNOWN: &[(&str, bool)] = &[
+    (CHUNKED_PAYLOADS, true),
+    (TRIGGER_SPAN_LINKS, true),
+    (EVENT_LOOP_FALLBACK, false),
+];
+
+struct Flags {
+    /// Values from the defaults, the flags file and the environment.
+    configured: HashMap<String, bool>,
+    /// Values set at runtime, which take precedence.
+    overrides: HashMap<String, bool>,
+}
+
+fn flags() -> &'static RwLock<Flags> {
+    static FLAGS: OnceLock<RwLock<Flags>> = OnceLock::new();
+    FLAGS.get_or_init(|| {
+        RwLock::new(Flags {
+            configured: load(),
+            overrides: HashMap::new(),
+        })
+    })
+}
+
+/// Loads the configured flags: defaults < `OPRC_FEATURE_FLAGS_FILE` < `OPRC_FEATURE_FLAGS`.
+fn load() -> HashMap<String, bool> {
+    let mut configured: HashMap<String, bool> = KNOWN
+        .iter()
+        .map(|(name, default)| (name.to_string(), *default))
+        .collect();
+    if let Ok(path) = std::env::var(FLAGS_FILE_ENV) {
+        match std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| {
+                serde_json::from_slice::<HashMap<String, bool>>(&raw).map_err(|e| e.to_string())
+            }) {
+            Ok(file) => configured.extend(file),
+            Err(e) => warn!("ignoring feature flags file {}: {}", path, e),
+        }
+    }
+    if let Ok(list) = std::env::var(FLAGS_ENV) {
+        for flag in list.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            match flag.strip_prefix('-') {
+                Some(name) => configured.insert(name.to_string(), false),
+                None => configured.insert(flag.to_string(), true),
+            };
+        }
+    }
+    configured
+}
+
+/// Returns whether the feature flag `name` is on.
+pub(crate) fn enabled(name: &str) -> bool {
+    let flags = flags().read().unwrap();
+    flags
+        .overrides
+        .get(name)
+        .or_else(|| flags.configured.get(name))
+        .copied()
+        .unwrap_or(false)
+}
+
+/// Returns the names of the flags that are on, sorted.
+pub(crate) fn enabled_names() -> Vec<String> {
+    feature_flags()
+        .into_iter()
+        .filter_map(|(name, on)| on.then_some(name))
+        .collect()
+}
+
+/// Returns whether the feature flag `name` is on. Unknown flags are off
+/// unless enabled in `OPRC_FEATURE_FLAGS`, the flags file or at runtime.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn feature_enabled(name: &str) -> bool {
+    enabled(name)
+}
+
+/// Turns the feature flag `name` on or off at runtime, or back to its
+/// configured value with `None`.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+#[pyo3(signature = (name, enabled))]
+pub fn set_feature_flag(name: String, enabled: Option<bool>) {
+    let mut flags = flags().write().unwrap();
+    match enabled {
+        Some(on) => flags.overrides.insert(name, on),
+        None => flags.overrides.remove(&name),
+    };
+}
+
+/// Returns every known or configured flag and whether it is on.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn feature_flags() -> BTreeMap<String, bool> {
+    let flags = flags().read().unwrap();
+    let mut all: BTreeMap<String, bool> = flags
+        .configured
+        .iter()
+        .map(|(k, v)| (k.clone(), *v))
+        .collect();
+    all.extend(flags.overrides.iter().map(|(k, v)| (k.clone(), *v)));
+    all
+}
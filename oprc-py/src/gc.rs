@@ -0,0 +1,171 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+use oprc_invoke::proxy::ObjectProxy;
+use oprc_pb::ObjMeta;
+use pyo3::prelude::*;
+use tracing::{info, warn};
+
+use crate::{obj::ObjectMetadata, scan};
+
+/// What makes an object garbage.
+pub(crate) struct GcRules {
+    /// Object IDs still referenced; any other object is orphaned.
+    pub(crate) referenced: Option<HashSet<u64>>,
+    /// Objects last written longer ago than this are expired. The write time
+    /// is the timestamp of the scan reply (see `Scanned::written`); objects
+    /// without one never expire.
+    pub(crate) retention: Option<Duration>,
+}
+
+impl GcRules {
+    fn is_garbage(&self, object_id: u64, age: Option<Duration>) -> bool {
+        let orphaned = self
+            .referenced
+            .as_ref()
+            .is_some_and(|r| !r.contains(&object_id));
+        let expired = self
+            .retention
+            .is_some_and(|retention| age.is_some_and(|age| age > retention));
+        orphaned || expired
+    }
+}
+
+/// Finds the garbage objects of `cls_id`, one partition at a time, and deletes
+/// them unless `dry_run` is set.
+pub(crate) async fn collect(
+    session: zenoh::Session,
+    proxy: ObjectProxy,
+    cls_id: String,
+    partitions: Vec<u32>,
+    rules: GcRules,
+    dry_run: bool,
+) -> GcReport {
+    let mut report = GcReport {
+        dry_run,
+        ..Default::default()
+    };
+    for partition_id in partitions {
+        let mut objects = Vec::new();
+        let scanned = scan::partition(&session, &cls_id, partition_id, |obj| {
+            let age = obj
+                .written
+                .map(|t| SystemTime::now().duration_since(t).unwrap_or_default());
+            objects.push((obj.object_id, age));
+        })
+        .await;
+        if let Err(e) = scanned {
+            warn!("failed to scan {}/{}: {}", cls_id, partition_id, e);
+            report.failed_partitions.push(partition_id);
+            continue;
+        }
+        report.scanned += objects.len() as u64;
+        for (object_id, age) in objects {
+            if !rules.is_garbage(object_id, age) {
+                continue;
+            }
+            let meta = ObjectMetadata::new(cls_id.clone(), partition_id, object_id);
+            if !dry_run {
+                let proto: ObjMeta = (&meta).into();
+                if let Err(e) = proxy.del_obj(&proto).await {
+                    warn!(
+                        "failed to delete {}/{}/{}: {}",
+                        cls_id, partition_id, object_id, e
+                    );
+                    report.failed.push(meta);
+                    continue;
+                }
+                report.deleted += 1;
+            }
+            report.garbage.push(meta);
+        }
+        info!(
+            "gc of {}/{}: {} garbage object(s) so far, dry_run={}",
+            cls_id,
+            partition_id,
+            report.garbage.len(),
+            dry_run
+        );
+    }
+    report
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone, Default)]
+/// Outcome of a garbage collection run.
+pub struct GcReport {
+    /// `True` if nothing was deleted and `garbage` lists what would have been.
+    pub dry_run: bool,
+    /// Objects found in the scanned partitions.
+    pub scanned: u64,
+    /// Objects found to be orphaned or expired (and deleted, unless a dry run).
+    pub garbage: Vec<ObjectMetadata>,
+    /// Objects deleted.
+    pub deleted: u64,
+    /// Garbage objects whose deletion failed.
+    pub failed: Vec<ObjectMetadata>,
+    /// Partitions that could not be scanned.
+    pub failed_partitions: Vec<u32>,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl GcReport {
+    /// Returns a string representation of the `GcReport`.
+    fn __str__(&self) -> String {
+        format!(
+            "GcReport {{ dry_run: {}, scanned: {}, garbage: {}, deleted: {}, failed: {}, failed_partitions: {:?} }}",
+            self.dry_run,
+            self.scanned,
+            self.garbage.len(),
+            self.deleted,
+            self.failed.len(),
+            self.failed_partitions
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn unreferenced_objects_are_orphaned() {
+        let rules = GcRules {
+            referenced: Some(HashSet::from([1, 2])),
+            retention: None,
+        };
+        assert!(!rules.is_garbage(1, None));
+        assert!(rules.is_garbage(3, None));
+        assert!(rules.is_garbage(3, Some(Duration::ZERO)));
+    }
+
+    #[test]
+    fn objects_older_than_the_retention_expire() {
+        let rules = GcRules {
+            referenced: None,
+            retention: Some(MINUTE),
+        };
+        assert!(!rules.is_garbage(1, Some(MINUTE)));
+        assert!(rules.is_garbage(1, Some(MINUTE * 2)));
+        // Without a timestamp the age is unknown, so the object is kept.
+        assert!(!rules.is_garbage(1, None));
+    }
+
+    #[test]
+    fn either_rule_makes_garbage() {
+        let rules = GcRules {
+            referenced: Some(HashSet::from([1])),
+            retention: Some(MINUTE),
+        };
+        assert!(!rules.is_garbage(1, Some(Duration::ZERO)));
+        assert!(rules.is_garbage(1, Some(MINUTE * 2)));
+        assert!(rules.is_garbage(2, Some(Duration::ZERO)));
+        assert!(rules.is_garbage(2, None));
+    }
+}
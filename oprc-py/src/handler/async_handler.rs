@@ -1,18 +1,46 @@
-use std::ops::Deref;
+use std::{
+    future::Future,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
 
 use oprc_invoke::handler::InvocationExecutor;
 use oprc_pb::{
     oprc_function_server::OprcFunction, InvocationRequest, InvocationResponse,
     ObjectInvocationRequest, ResponseStatus,
 };
-use pyo3::{intern, types::PyTuple, Py, PyAny, PyRef, PyResult, Python};
+use pyo3::{
+    intern,
+    types::{PyAnyMethods, PyDict, PyModule, PyTuple},
+    Bound, Py, PyAny, PyRef, PyResult, Python,
+};
 use pyo3_async_runtimes::{into_future_with_locals, TaskLocals};
 use tonic::{Request, Response, Status};
-use tracing::{debug, info};
+use tracing::{debug, info, warn, Instrument};
+
+use super::policy::reject;
+use super::{FnCall, HandlerPolicy};
+use crate::flags;
+use crate::metrics::{GilTiming, attach_timed};
+use crate::model::InvocationResponseCode;
+
+/// Message of the `SystemError` response of a handler whose event loop is closed.
+pub(crate) const LOOP_CLOSED: &str = "handler event loop closed";
+
+/// Event loop shared by all async handlers whose own loop is closed, when the
+/// `event_loop_fallback` flag is on. Started on first use.
+static FALLBACK_LOOP: Mutex<Option<TaskLocals>> = Mutex::new(None);
 
 pub struct AsyncInvocationHandler {
     callback: Py<PyAny>,
     task_locals: TaskLocals,
+    policy: HandlerPolicy,
+    /// Set once the loop in `task_locals` is seen closed; a closed loop
+    /// never reopens.
+    loop_closed: AtomicBool,
 }
 
 impl AsyncInvocationHandler {
@@ -20,8 +48,65 @@ impl AsyncInvocationHandler {
         AsyncInvocationHandler {
             callback,
             task_locals: locals,
+            policy: HandlerPolicy::default(),
+            loop_closed: AtomicBool::new(false),
         }
     }
+
+    /// Schedules the callback's coroutine on the handler's event loop. The
+    /// loop is only checked for being closed when scheduling fails, so the
+    /// common path makes no extra Python call. On a closed loop the coroutine
+    /// moves to the fallback loop, or the invocation gets the response to
+    /// send back.
+    fn schedule(
+        &self,
+        coro: Bound<'_, PyAny>,
+    ) -> PyResult<
+        Result<
+            impl Future<Output = PyResult<Py<PyAny>>> + Send + 'static + use<>,
+            oprc_pb::InvocationResponse,
+        >,
+    > {
+        let py = coro.py();
+        if !self.loop_closed.load(Ordering::Relaxed) {
+            let err = match into_future_with_locals(&self.task_locals, coro.clone()) {
+                Ok(fut) => return Ok(Ok(fut)),
+                Err(err) => err,
+            };
+            let closed = self
+                .task_locals
+                .event_loop(py)
+                .call_method0(intern!(py, "is_closed"))
+                .and_then(|c| c.extract::<bool>())
+                .unwrap_or(false);
+            if !closed {
+                return Err(err);
+            }
+            if !self.loop_closed.swap(true, Ordering::Relaxed) {
+                warn!("{}", LOOP_CLOSED);
+            }
+        }
+        let fallback = if flags::enabled(flags::EVENT_LOOP_FALLBACK) {
+            fallback_locals(py)
+                .map_err(|e| format!("{}; failed to start the fallback loop: {}", LOOP_CLOSED, e))
+        } else {
+            Err(LOOP_CLOSED.to_string())
+        };
+        match fallback {
+            Ok(locals) => into_future_with_locals(&locals, coro).map(Ok),
+            Err(msg) => {
+                // Never scheduled, so close it to avoid a "never awaited" warning.
+                let _ = coro.call_method0(intern!(py, "close"));
+                Ok(Err(reject(InvocationResponseCode::SystemError, msg)))
+            }
+        }
+    }
+
+    /// Sets the server-side checks applied before dispatching to Python.
+    pub fn with_policy(mut self, policy: HandlerPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
 }
 
 #[tonic::async_trait]
@@ -39,7 +124,7 @@ impl OprcFunction for AsyncInvocationHandler {
                 invocation_request.cls_id, invocation_request.fn_id
             );
         }
-        match invoke_fn_async(&self.task_locals, &self.callback, invocation_request).await {
+        match invoke_fn_async(self, invocation_request).await {
             Ok(output) => Ok(Response::new(output)),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -70,7 +155,7 @@ impl OprcFunction for AsyncInvocationHandler {
             );
         }
 
-        match invoke_obj_async(&self.task_locals, &self.callback, invocation_request).await {
+        match invoke_obj_async(self, invocation_request).await {
             Ok(output) => Ok(Response::new(output)),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -99,7 +184,7 @@ impl InvocationExecutor for AsyncInvocationHandler {
                 invocation_request.cls_id, invocation_request.fn_id
             );
         }
-        match invoke_fn_async(&self.task_locals, &self.callback, invocation_request).await {
+        match invoke_fn_async(self, invocation_request).await {
             Ok(output) => Ok(output),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -128,7 +213,7 @@ impl InvocationExecutor for AsyncInvocationHandler {
             );
         }
 
-        match invoke_obj_async(&self.task_locals, &self.callback, invocation_request).await {
+        match invoke_obj_async(self, invocation_request).await {
             Ok(output) => Ok(output),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -143,53 +228,103 @@ impl InvocationExecutor for AsyncInvocationHandler {
     }
 }
 
+/// Returns the task locals of the fallback loop, starting it on a daemon
+/// thread if this is the first handler to need it.
+fn fallback_locals(py: Python<'_>) -> PyResult<TaskLocals> {
+    let mut fallback = FALLBACK_LOOP.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(locals) = fallback.as_ref() {
+        return Ok(locals.clone_ref(py));
+    }
+    let event_loop = PyModule::import(py, "asyncio")?.call_method0("new_event_loop")?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("target", event_loop.getattr("run_forever")?)?;
+    kwargs.set_item("name", "oprc-fallback-loop")?;
+    kwargs.set_item("daemon", true)?;
+    PyModule::import(py, "threading")?
+        .getattr("Thread")?
+        .call((), Some(&kwargs))?
+        .call_method0("start")?;
+    warn!("serving async handlers on the fallback event loop");
+    let locals = TaskLocals::new(event_loop);
+    *fallback = Some(locals.clone_ref(py));
+    Ok(locals)
+}
 
 async fn invoke_fn_async(
-    locals: &TaskLocals,
-    callback: &Py<PyAny>,
-    req: oprc_pb::InvocationRequest,
+    handler: &AsyncInvocationHandler,
+    mut req: oprc_pb::InvocationRequest,
 ) -> PyResult<oprc_pb::InvocationResponse> {
-    let res = Python::attach(|py| {
-        let req = crate::model::InvocationRequest::from(req);
-        let args = PyTuple::new(py, [req])?;
-        let any = into_future_with_locals(
-            locals,
-            callback
-                .call_method1(py, intern!(py, "invoke_fn"), args)?
-                .into_bound(py),
-        );
-        any
-    });
-    let res = res?.await.map(|any| {
-        Python::attach(|py| {
-            any.extract::<PyRef<crate::model::InvocationResponse>>(py)
-                .map(|r| r.deref().into())
+    let policy = &handler.policy;
+    let admission = match policy.admit_fn(&mut req).await {
+        Ok(admission) => admission,
+        Err(rejection) => return Ok(rejection),
+    };
+    let mut gil = GilTiming::default();
+    let res = admission.span().in_scope(|| {
+        FnCall::from_proto(req).and_then(|call| {
+            attach_timed(&mut gil, |py| {
+                admission.start();
+                let (method, args) = call.into_call(py)?;
+                handler.schedule(
+                    handler
+                        .callback
+                        .call_method1(py, method, args)?
+                        .into_bound(py),
+                )
+            })
         })
-    })?;
+    });
+    let mut res = await_response(res, &mut gil)
+        .instrument(admission.span().clone())
+        .await;
+    admission.finish(&mut res);
+    policy.observe(&admission, &res, gil);
     res
 }
 
 async fn invoke_obj_async(
-    locals: &TaskLocals,
-    callback: &Py<PyAny>,
-    req: oprc_pb::ObjectInvocationRequest,
+    handler: &AsyncInvocationHandler,
+    mut req: oprc_pb::ObjectInvocationRequest,
 ) -> PyResult<oprc_pb::InvocationResponse> {
-    let res = Python::attach(|py| {
-        let req = crate::model::ObjectInvocationRequest::from(req);
-        let args = PyTuple::new(py, [req])?;
-        let any = into_future_with_locals(
-            locals,
-            callback
-                .call_method1(py, intern!(py, "invoke_obj"), args)?
-                .into_bound(py),
-        );
-        any
-    });
-    let res = res?.await.map(|any| {
-        Python::attach(|py| {
-            any.extract::<PyRef<crate::model::InvocationResponse>>(py)
-                .map(|r| r.deref().into())
+    let policy = &handler.policy;
+    let admission = match policy.admit_obj(&mut req).await {
+        Ok(admission) => admission,
+        Err(rejection) => return Ok(rejection),
+    };
+    let mut gil = GilTiming::default();
+    let req = crate::model::ObjectInvocationRequest::from(req);
+    let res = admission.span().in_scope(|| {
+        attach_timed(&mut gil, |py| {
+            admission.start();
+            let args = PyTuple::new(py, [req])?;
+            handler.schedule(
+                handler
+                    .callback
+                    .call_method1(py, intern!(py, "invoke_obj"), args)?
+                    .into_bound(py),
+            )
         })
-    })?;
+    });
+    let mut res = await_response(res, &mut gil)
+        .instrument(admission.span().clone())
+        .await;
+    admission.after(&res).await;
+    admission.finish(&mut res);
+    policy.observe(&admission, &res, gil);
     res
 }
+
+/// Awaits the coroutine returned by the callback and converts its result.
+async fn await_response(
+    fut: PyResult<Result<impl Future<Output = PyResult<Py<PyAny>>>, oprc_pb::InvocationResponse>>,
+    gil: &mut GilTiming,
+) -> PyResult<oprc_pb::InvocationResponse> {
+    let any = match fut? {
+        Ok(fut) => fut.await?,
+        Err(rejection) => return Ok(rejection),
+    };
+    attach_timed(gil, |py| {
+        any.extract::<PyRef<crate::model::InvocationResponse>>(py)
+            .map(|r| r.deref().into())
+    })
+}
@@ -0,0 +1,35 @@
+use pyo3::{
+    Bound, PyResult, Python, intern,
+    types::{PyString, PyTuple},
+};
+
+use crate::model::{InvocationRequest, MultiObjectInvocationRequest};
+
+/// A function invocation converted to the Python model it is dispatched with.
+///
+/// Built before the GIL is taken so payload copies and option parsing do not
+/// hold it; only the Python object creation and the call itself do.
+pub(crate) enum FnCall {
+    Fn(InvocationRequest),
+    Multi(MultiObjectInvocationRequest),
+}
+
+impl FnCall {
+    pub(crate) fn from_proto(req: oprc_pb::InvocationRequest) -> PyResult<Self> {
+        match MultiObjectInvocationRequest::from_fn_request(&req) {
+            Some(multi) => multi.map(FnCall::Multi),
+            None => Ok(FnCall::Fn(req.into())),
+        }
+    }
+
+    /// Returns the callback method to call and its arguments.
+    pub(crate) fn into_call<'py>(
+        self,
+        py: Python<'py>,
+    ) -> PyResult<(&'py Bound<'py, PyString>, Bound<'py, PyTuple>)> {
+        match self {
+            FnCall::Fn(req) => Ok((intern!(py, "invoke_fn"), PyTuple::new(py, [req])?)),
+            FnCall::Multi(req) => Ok((intern!(py, "invoke_multi"), PyTuple::new(py, [req])?)),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use oprc_invoke::proxy::ObjectProxy;
+use oprc_pb::{InvocationResponse, ObjData, ObjMeta, ObjectInvocationRequest};
+use pyo3::prelude::*;
+use serde_json::{Value, json};
+
+use crate::{
+    keys::EntryKey,
+    model::{self, InvocationResponseCode},
+    obj::ObjectData,
+};
+
+/// A hook run around every object invocation with the object's managed state.
+///
+/// Hooks read the object before the Python callback is called (`pre`) and
+/// after it returned a response (`post`), and write it back only if they
+/// changed it. The write is not atomic with the invocation's own writes.
+#[derive(Clone)]
+pub enum StateHook {
+    /// Writes the Unix time in milliseconds, as decimal text, to an entry
+    /// after every successful invocation.
+    LastModified(EntryKey),
+    /// Appends `{"fn_id", "status", "ts"}` to a JSON list in an entry after
+    /// every invocation, keeping the last `keep` records.
+    Audit { entry: EntryKey, keep: usize },
+    /// Python `pre(req, obj)` and `post(req, obj, resp)` callables. `obj` is
+    /// an `ObjectData` the callables may modify; `pre` rejects the invocation
+    /// by raising.
+    Python {
+        pre: Option<Arc<Py<PyAny>>>,
+        post: Option<Arc<Py<PyAny>>>,
+    },
+}
+
+impl StateHook {
+    fn has_pre(&self) -> bool {
+        matches!(self, StateHook::Python { pre: Some(_), .. })
+    }
+
+    fn pre(&self, req: &ObjectInvocationRequest, obj: &mut ObjectData) -> Result<(), String> {
+        match self {
+            StateHook::Python { pre: Some(pre), .. } => call_python(pre, req, obj, None),
+            _ => Ok(()),
+        }
+    }
+
+    fn post(
+        &self,
+        req: &ObjectInvocationRequest,
+        obj: &mut ObjectData,
+        resp: &InvocationResponse,
+    ) -> Result<(), String> {
+        let okay = resp.status == InvocationResponseCode::Okay as i32;
+        match self {
+            StateHook::LastModified(entry) if okay => {
+                let index = entry.resolve(&req.cls_id).map_err(|e| e.to_string())?;
+                obj.entries.insert(index, now_ms().to_string().into_bytes());
+                Ok(())
+            }
+            StateHook::LastModified(_) => Ok(()),
+            StateHook::Audit { entry, keep } => {
+                let index = entry.resolve(&req.cls_id).map_err(|e| e.to_string())?;
+                let mut records = obj
+                    .entries
+                    .get(&index)
+                    .and_then(|raw| serde_json::from_slice::<Vec<Value>>(raw).ok())
+                    .unwrap_or_default();
+                records.push(json!({
+                    "fn_id": req.fn_id,
+                    "status": resp.status,
+                    "ts": now_ms(),
+                }));
+                let excess = records.len().saturating_sub(*keep);
+                records.drain(..excess);
+                let raw = serde_json::to_vec(&records).map_err(|e| e.to_string())?;
+                obj.entries.insert(index, raw);
+                Ok(())
+            }
+            StateHook::Python {
+                post: Some(post), ..
+            } => call_python(post, req, obj, Some(resp)),
+            StateHook::Python { .. } => Ok(()),
+        }
+    }
+}
+
+/// Calls a Python hook with copies of the request, the object and the
+/// response, then takes back the object's entries.
+fn call_python(
+    hook: &Py<PyAny>,
+    req: &ObjectInvocationRequest,
+    obj: &mut ObjectData,
+    resp: Option<&InvocationResponse>,
+) -> Result<(), String> {
+    Python::attach(|py| -> PyResult<()> {
+        let py_req = Py::new(py, model::ObjectInvocationRequest::from(req.clone()))?;
+        let py_obj = Py::new(py, obj.copy())?;
+        match resp {
+            Some(resp) => {
+                let py_resp = Py::new(py, model::InvocationResponse::from(resp.clone()))?;
+                hook.call1(py, (py_req, py_obj.clone_ref(py), py_resp))?
+            }
+            None => hook.call1(py, (py_req, py_obj.clone_ref(py)))?,
+        };
+        obj.entries = py_obj.borrow(py).entries.clone();
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Runs the `pre` side of `hooks` and writes the object back if they changed it.
+pub(crate) async fn before(
+    proxy: &ObjectProxy,
+    hooks: &[StateHook],
+    req: &ObjectInvocationRequest,
+) -> Result<(), String> {
+    if !hooks.iter().any(StateHook::has_pre) {
+        return Ok(());
+    }
+    apply(proxy, req, |obj| {
+        hooks.iter().try_for_each(|hook| hook.pre(req, obj))
+    })
+    .await
+}
+
+/// Runs the `post` side of `hooks` and writes the object back if they changed it.
+pub(crate) async fn after(
+    proxy: &ObjectProxy,
+    hooks: &[StateHook],
+    req: &ObjectInvocationRequest,
+    resp: &InvocationResponse,
+) -> Result<(), String> {
+    apply(proxy, req, |obj| {
+        hooks.iter().try_for_each(|hook| hook.post(req, obj, resp))
+    })
+    .await
+}
+
+/// Reads the target object of `req` (empty if it does not exist yet), runs
+/// `run` on it and writes it back if its entries changed.
+async fn apply(
+    proxy: &ObjectProxy,
+    req: &ObjectInvocationRequest,
+    run: impl FnOnce(&mut ObjectData) -> Result<(), String>,
+) -> Result<(), String> {
+    let meta = ObjMeta {
+        cls_id: req.cls_id.clone(),
+        partition_id: req.partition_id,
+        object_id: req.object_id,
+    };
+    let current = proxy
+        .get_obj(&meta)
+        .await
+        .map_err(|e| format!("failed to read object state: {}", e))?
+        .unwrap_or_else(|| ObjData {
+            metadata: Some(meta.clone()),
+            ..Default::default()
+        });
+    let mut obj = ObjectData::from(current);
+    let before: HashMap<u32, Vec<u8>> = obj.entries.clone();
+    run(&mut obj)?;
+    if obj.entries == before {
+        return Ok(());
+    }
+    proxy
+        .set_obj(obj.into_proto())
+        .await
+        .map_err(|e| format!("failed to write object state: {}", e))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
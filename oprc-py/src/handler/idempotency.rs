@@ -0,0 +1,302 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use oprc_pb::InvocationResponse;
+use tokio::sync::watch;
+
+/// Number of idempotency keys whose responses are kept by default.
+const DEFAULT_IDEMPOTENCY_CACHE_SIZE: usize = 10_000;
+
+/// A delivery of an invocation sent with an idempotency key: its class,
+/// function, tenant and key.
+pub(crate) type DeliveryKey = (Arc<str>, Arc<str>, String, String);
+
+enum Slot {
+    /// The first delivery is still running; its response is sent on the
+    /// channel once it completes.
+    Running {
+        claim: u64,
+        response: watch::Receiver<Option<InvocationResponse>>,
+    },
+    Done(InvocationResponse),
+}
+
+struct Entry {
+    slot: Slot,
+    /// Tick of the last use, the key of the entry in `Lru::order`.
+    used: u64,
+}
+
+#[derive(Default)]
+struct Lru {
+    capacity: usize,
+    entries: HashMap<DeliveryKey, Entry>,
+    order: BTreeMap<u64, DeliveryKey>,
+    tick: u64,
+}
+
+impl Lru {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Marks the entry of `key` as the most recently used.
+    fn touch(&mut self, key: &DeliveryKey) {
+        let tick = self.next_tick();
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.order.remove(&entry.used);
+            entry.used = tick;
+            self.order.insert(tick, key.clone());
+        }
+    }
+
+    fn insert(&mut self, key: DeliveryKey, slot: Slot) {
+        let used = self.next_tick();
+        if let Some(old) = self.entries.insert(key.clone(), Entry { slot, used }) {
+            self.order.remove(&old.used);
+        }
+        self.order.insert(used, key);
+        self.evict();
+    }
+
+    fn remove(&mut self, key: &DeliveryKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+
+    /// Drops the least recently used entries beyond the capacity.
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Responses of the invocations sent with an idempotency key, so duplicate
+/// deliveries replay the response instead of running the invocation again.
+/// Bounded to the most recently used keys; a key evicted before its
+/// duplicate arrives runs again.
+pub struct IdempotencyCache {
+    lru: Mutex<Lru>,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        IdempotencyCache::new(DEFAULT_IDEMPOTENCY_CACHE_SIZE)
+    }
+}
+
+/// Outcome of claiming a delivery.
+pub(crate) enum Claim {
+    /// The first delivery, to run and complete.
+    First(Pending),
+    /// A duplicate, answered with the response of the first delivery.
+    Replay(InvocationResponse),
+}
+
+impl IdempotencyCache {
+    pub fn new(capacity: usize) -> Self {
+        IdempotencyCache {
+            lru: Mutex::new(Lru {
+                capacity,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Sets how many keys are kept, evicting the least recently used ones
+    /// beyond it. `0` disables de-duplication.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut lru = self.lru.lock().unwrap();
+        lru.capacity = capacity;
+        lru.evict();
+    }
+
+    /// Claims the delivery `key`. A duplicate of a delivery still running
+    /// waits for its response; if that delivery is abandoned, one of its
+    /// duplicates runs instead. Returns `None` if de-duplication is disabled.
+    pub(crate) async fn claim(self: &Arc<Self>, key: DeliveryKey) -> Option<Claim> {
+        loop {
+            let mut response = {
+                let mut lru = self.lru.lock().unwrap();
+                if lru.capacity == 0 {
+                    return None;
+                }
+                lru.touch(&key);
+                match lru.entries.get(&key).map(|entry| &entry.slot) {
+                    Some(Slot::Done(resp)) => return Some(Claim::Replay(resp.clone())),
+                    Some(Slot::Running { response, .. }) => response.clone(),
+                    None => {
+                        let claim = lru.next_tick();
+                        let (tx, rx) = watch::channel(None);
+                        lru.insert(
+                            key.clone(),
+                            Slot::Running {
+                                claim,
+                                response: rx,
+                            },
+                        );
+                        return Some(Claim::First(Pending {
+                            cache: self.clone(),
+                            key,
+                            claim,
+                            tx,
+                        }));
+                    }
+                }
+            };
+            if let Ok(resp) = response.wait_for(Option::is_some).await {
+                return resp.clone().map(Claim::Replay);
+            }
+        }
+    }
+}
+
+/// The first delivery of a key. Dropping it before it completes releases the
+/// key, so a retry runs the invocation.
+pub(crate) struct Pending {
+    cache: Arc<IdempotencyCache>,
+    key: DeliveryKey,
+    claim: u64,
+    tx: watch::Sender<Option<InvocationResponse>>,
+}
+
+impl Pending {
+    /// Stores the response of the delivery and passes it to the duplicates
+    /// waiting for it.
+    pub(crate) fn complete(&self, resp: &InvocationResponse) {
+        let mut lru = self.cache.lru.lock().unwrap();
+        if lru.capacity > 0 {
+            lru.insert(self.key.clone(), Slot::Done(resp.clone()));
+        }
+        self.tx.send_replace(Some(resp.clone()));
+    }
+
+    /// Releases the key without storing a response, so the next delivery
+    /// runs the invocation. Duplicates waiting for this delivery claim the
+    /// key again once it is dropped.
+    pub(crate) fn release(&self) {
+        let mut lru = self.cache.lru.lock().unwrap();
+        if self.running(&lru) {
+            lru.remove(&self.key);
+        }
+    }
+
+    /// Whether the slot of the key is still the one of this delivery.
+    fn running(&self, lru: &Lru) -> bool {
+        matches!(
+            lru.entries.get(&self.key).map(|entry| &entry.slot),
+            Some(Slot::Running { claim, .. }) if *claim == self.claim
+        )
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> DeliveryKey {
+        ("cls".into(), "fn".into(), String::new(), name.to_string())
+    }
+
+    fn response(payload: &str) -> InvocationResponse {
+        InvocationResponse {
+            payload: Some(payload.as_bytes().to_vec()),
+            ..Default::default()
+        }
+    }
+
+    fn claim(cache: &Arc<IdempotencyCache>, name: &str) -> Option<Claim> {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(cache.claim(key(name)))
+    }
+
+    fn replayed(claim: Option<Claim>) -> Option<Vec<u8>> {
+        match claim {
+            Some(Claim::Replay(resp)) => resp.payload,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn lru_evicts_the_least_recently_used() {
+        let mut lru = Lru {
+            capacity: 2,
+            ..Default::default()
+        };
+        lru.insert(key("a"), Slot::Done(response("a")));
+        lru.insert(key("b"), Slot::Done(response("b")));
+        lru.touch(&key("a"));
+        lru.insert(key("c"), Slot::Done(response("c")));
+        assert!(lru.entries.contains_key(&key("a")));
+        assert!(!lru.entries.contains_key(&key("b")));
+        assert!(lru.entries.contains_key(&key("c")));
+        assert_eq!(lru.order.len(), 2);
+    }
+
+    #[test]
+    fn lru_reinsert_replaces_the_entry() {
+        let mut lru = Lru {
+            capacity: 2,
+            ..Default::default()
+        };
+        lru.insert(key("a"), Slot::Done(response("a")));
+        lru.insert(key("a"), Slot::Done(response("a2")));
+        assert_eq!(lru.entries.len(), 1);
+        assert_eq!(lru.order.len(), 1);
+        lru.remove(&key("a"));
+        assert!(lru.entries.is_empty() && lru.order.is_empty());
+    }
+
+    #[test]
+    fn completed_deliveries_are_replayed() {
+        let cache = Arc::new(IdempotencyCache::new(10));
+        let Some(Claim::First(pending)) = claim(&cache, "k") else {
+            panic!("first delivery not claimed");
+        };
+        pending.complete(&response("done"));
+        drop(pending);
+        assert_eq!(replayed(claim(&cache, "k")), Some(b"done".to_vec()));
+    }
+
+    #[test]
+    fn released_and_abandoned_keys_run_again() {
+        let cache = Arc::new(IdempotencyCache::new(10));
+        let Some(Claim::First(pending)) = claim(&cache, "k") else {
+            panic!("first delivery not claimed");
+        };
+        pending.release();
+        drop(pending);
+        let Some(Claim::First(pending)) = claim(&cache, "k") else {
+            panic!("released key not claimed again");
+        };
+        drop(pending);
+        assert!(matches!(claim(&cache, "k"), Some(Claim::First(_))));
+    }
+
+    #[test]
+    fn zero_capacity_disables_deduplication() {
+        let cache = Arc::new(IdempotencyCache::new(10));
+        let Some(Claim::First(pending)) = claim(&cache, "k") else {
+            panic!("first delivery not claimed");
+        };
+        pending.complete(&response("done"));
+        cache.set_capacity(0);
+        assert!(claim(&cache, "k").is_none());
+    }
+}
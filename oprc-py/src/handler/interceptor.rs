@@ -0,0 +1,303 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use oprc_pb::InvocationResponse;
+use pyo3::PyResult;
+use tracing::{info, warn};
+
+use super::jwt::{DEFAULT_JWKS_CACHE, JWT_ISSUER_ENV, JwtValidator};
+use super::policy::{is_failure, reject, too_large};
+use crate::{
+    compression::{self, CONTENT_ENCODING, DEFAULT_COMPRESSION_MIN_BYTES, Encoding},
+    metrics::ServerMetrics,
+    model::{CLAIMS_OPTION, InvocationResponseCode},
+};
+
+/// Environment variable with a comma-separated interceptor pipeline.
+pub const INTERCEPTORS_ENV: &str = "OPRC_INTERCEPTORS";
+/// Request option carrying the caller's token for the `auth` and `jwt`
+/// interceptors. A `Bearer ` prefix is accepted.
+pub const AUTH_TOKEN_OPTION: &str = "oprc-auth-token";
+/// Environment variable with comma-separated tokens accepted by `auth`.
+const AUTH_TOKENS_ENV: &str = "OPRC_AUTH_TOKENS";
+
+/// Mutable view of an incoming request, common to both request kinds.
+pub(crate) struct RequestParts<'a> {
+    pub(crate) options: &'a mut HashMap<String, String>,
+    pub(crate) payload: &'a mut Vec<u8>,
+}
+
+/// Identity and timing of an admitted invocation, used on the response path.
+pub(crate) struct CallInfo {
+    /// Interned, so admitting an invocation does not copy its ids.
+    pub(crate) cls_id: Arc<str>,
+    pub(crate) fn_id: Arc<str>,
+    pub(crate) object_id: Option<u64>,
+    pub(crate) started: Instant,
+    /// Encoding the caller prefers for the response payload, if any.
+    pub(crate) accept_encoding: Option<Encoding>,
+}
+
+/// A built-in server-side interceptor. Interceptors run in pipeline order on
+/// the request and in reverse order on the response, entirely in Rust.
+#[derive(Clone)]
+pub enum Interceptor {
+    /// Logs every invocation and its outcome.
+    Logging,
+    /// Records per-function invocation counts and latency, and per-object
+    /// counts when object tracking is enabled.
+    Metrics(Arc<ServerMetrics>),
+    /// Rejects invocations without an accepted `oprc-auth-token` option. An
+    /// accepted token stays in the options, so the handler can forward it.
+    Auth(Arc<HashSet<String>>),
+    /// Rejects invocations without a valid ID token from the configured
+    /// issuer, and passes the token's claims on in the `oprc-claims` option
+    /// next to the token itself.
+    Jwt(Arc<JwtValidator>),
+    /// Rejects request payloads larger than the given number of bytes, like
+    /// `max_payload_bytes` of the engine.
+    PayloadGuard(usize),
+    /// Compresses responses of at least the given size for callers that
+    /// accept it, with zstd if they do, else gzip. Compressed request
+    /// payloads are inflated on admission, with or without it.
+    Compression(usize),
+}
+
+impl Interceptor {
+    /// Parses an interceptor spec of the form `name` or `name?key=value&key=value`.
+    ///
+    /// Supported: `logging`, `metrics` (`metrics?object_window=N` also tracks
+    /// the hottest objects over the last `N` invocations of each class),
+    /// `auth?tokens=a|b` (defaults to `OPRC_AUTH_TOKENS`),
+    /// `jwt?issuer=URL&audience=A&jwks_uri=URL&cache_secs=N` (the issuer
+    /// defaults to `OPRC_JWT_ISSUER`, the JWKS location to OpenID discovery),
+    /// `payload_guard?max_bytes=N` and `compression?min_bytes=N`.
+    pub fn parse(spec: &str, metrics: &Arc<ServerMetrics>) -> Result<Self, String> {
+        let spec = spec.trim();
+        let (name, query) = spec.split_once('?').unwrap_or((spec, ""));
+        let mut params = HashMap::new();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (k, v) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("invalid parameter '{}' in interceptor '{}'", pair, spec))?;
+            params.insert(k, v);
+        }
+        let interceptor = match name {
+            "logging" => Interceptor::Logging,
+            "metrics" => {
+                if params.contains_key("object_window") {
+                    metrics.track_objects(take_size(&mut params, name, "object_window", None)?);
+                }
+                Interceptor::Metrics(metrics.clone())
+            }
+            "auth" => {
+                let tokens: HashSet<String> = match params.remove("tokens") {
+                    Some(t) => t.split('|').map(str::to_string).collect(),
+                    None => std::env::var(AUTH_TOKENS_ENV)
+                        .unwrap_or_default()
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect(),
+                };
+                if tokens.is_empty() {
+                    return Err(format!(
+                        "interceptor 'auth' needs a 'tokens' parameter or {}",
+                        AUTH_TOKENS_ENV
+                    ));
+                }
+                Interceptor::Auth(Arc::new(tokens))
+            }
+            "jwt" => {
+                let issuer = match params.remove("issuer") {
+                    Some(i) => i.to_string(),
+                    None => std::env::var(JWT_ISSUER_ENV).map_err(|_| {
+                        format!(
+                            "interceptor 'jwt' needs an 'issuer' parameter or {}",
+                            JWT_ISSUER_ENV
+                        )
+                    })?,
+                };
+                let audience = params.remove("audience").map(str::to_string);
+                let jwks_uri = params.remove("jwks_uri").map(str::to_string);
+                let cache_secs = take_size(
+                    &mut params,
+                    name,
+                    "cache_secs",
+                    Some(DEFAULT_JWKS_CACHE.as_secs() as usize),
+                )?;
+                let validator = JwtValidator::new(
+                    issuer,
+                    audience,
+                    jwks_uri,
+                    Duration::from_secs(cache_secs as u64),
+                )?;
+                Interceptor::Jwt(Arc::new(validator))
+            }
+            "payload_guard" => {
+                Interceptor::PayloadGuard(take_size(&mut params, name, "max_bytes", None)?)
+            }
+            "compression" => Interceptor::Compression(take_size(
+                &mut params,
+                name,
+                "min_bytes",
+                Some(DEFAULT_COMPRESSION_MIN_BYTES),
+            )?),
+            other => return Err(format!("unknown interceptor '{}'", other)),
+        };
+        if let Some(key) = params.keys().next() {
+            return Err(format!(
+                "unknown parameter '{}' for interceptor '{}'",
+                key, name
+            ));
+        }
+        Ok(interceptor)
+    }
+
+    /// Parses a pipeline from a list of specs, see [`Interceptor::parse`].
+    pub fn parse_all(specs: &[String], metrics: &Arc<ServerMetrics>) -> Result<Vec<Self>, String> {
+        specs
+            .iter()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| Interceptor::parse(s, metrics))
+            .collect()
+    }
+
+    /// Returns `Err` with the response to send back if the call must not proceed.
+    pub(crate) async fn on_request(
+        &self,
+        call: &CallInfo,
+        req: &mut RequestParts<'_>,
+    ) -> Result<(), InvocationResponse> {
+        match self {
+            Interceptor::Logging => {
+                info!(
+                    cls_id = %call.cls_id,
+                    fn_id = %call.fn_id,
+                    object_id = ?call.object_id,
+                    payload_bytes = req.payload.len(),
+                    "invocation received"
+                );
+                Ok(())
+            }
+            Interceptor::Metrics(_) | Interceptor::Compression(_) => Ok(()),
+            Interceptor::Auth(tokens) => match req.options.get(AUTH_TOKEN_OPTION) {
+                Some(token) if tokens.contains(bearer(token)) => Ok(()),
+                Some(_) => Err(reject(
+                    InvocationResponseCode::Unauthorized,
+                    "invalid auth token".to_string(),
+                )),
+                None => Err(reject(
+                    InvocationResponseCode::Unauthorized,
+                    format!("missing {} option", AUTH_TOKEN_OPTION),
+                )),
+            },
+            Interceptor::Jwt(validator) => {
+                let Some(token) = req.options.get(AUTH_TOKEN_OPTION) else {
+                    return Err(reject(
+                        InvocationResponseCode::Unauthorized,
+                        format!("missing {} option", AUTH_TOKEN_OPTION),
+                    ));
+                };
+                let claims = validator.validate(bearer(token)).await.map_err(|e| {
+                    warn!(cls_id = %call.cls_id, fn_id = %call.fn_id, "rejected token: {}", e);
+                    reject(InvocationResponseCode::Unauthorized, e)
+                })?;
+                req.options.insert(
+                    CLAIMS_OPTION.to_string(),
+                    serde_json::Value::Object(claims).to_string(),
+                );
+                Ok(())
+            }
+            Interceptor::PayloadGuard(max) => {
+                if req.payload.len() > *max {
+                    return Err(too_large(
+                        format!(
+                            "payload of {} bytes exceeds the limit of {} bytes",
+                            req.payload.len(),
+                            max
+                        ),
+                        *max as u64,
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn on_response(&self, call: &CallInfo, result: &mut PyResult<InvocationResponse>) {
+        match self {
+            Interceptor::Logging => match result {
+                Ok(resp) => info!(
+                    cls_id = %call.cls_id,
+                    fn_id = %call.fn_id,
+                    status = resp.status,
+                    latency_ms = call.started.elapsed().as_millis() as u64,
+                    "invocation completed"
+                ),
+                Err(e) => warn!(
+                    cls_id = %call.cls_id,
+                    fn_id = %call.fn_id,
+                    error = %e,
+                    "invocation failed"
+                ),
+            },
+            Interceptor::Metrics(metrics) => {
+                metrics.record_function(
+                    &call.cls_id,
+                    &call.fn_id,
+                    is_failure(result),
+                    call.started.elapsed(),
+                );
+                if let Some(object_id) = call.object_id {
+                    metrics.record_object(&call.cls_id, object_id);
+                }
+            }
+            Interceptor::Compression(min_bytes) => {
+                let Some(encoding) = call.accept_encoding else {
+                    return;
+                };
+                let Ok(resp) = result else { return };
+                let Some(payload) = &mut resp.payload else {
+                    return;
+                };
+                if resp.headers.contains_key(CONTENT_ENCODING) {
+                    return;
+                }
+                match compression::compress(payload, encoding, *min_bytes) {
+                    Ok(true) => {
+                        resp.headers
+                            .insert(CONTENT_ENCODING.to_string(), encoding.name().to_string());
+                    }
+                    Ok(false) => {}
+                    Err(e) => warn!("failed to compress response: {}", e),
+                }
+            }
+            Interceptor::Auth(_) | Interceptor::Jwt(_) | Interceptor::PayloadGuard(_) => {}
+        }
+    }
+}
+
+/// Strips an optional `Bearer ` prefix from a token.
+fn bearer(token: &str) -> &str {
+    token.strip_prefix("Bearer ").unwrap_or(token)
+}
+
+fn take_size(
+    params: &mut HashMap<&str, &str>,
+    name: &str,
+    key: &str,
+    default: Option<usize>,
+) -> Result<usize, String> {
+    match params.remove(key) {
+        Some(v) => v
+            .parse()
+            .map_err(|_| format!("invalid {} '{}' for interceptor '{}'", key, v, name)),
+        None => {
+            default.ok_or_else(|| format!("interceptor '{}' needs a '{}' parameter", name, key))
+        }
+    }
+}
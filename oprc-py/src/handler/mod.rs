@@ -1,5 +1,17 @@
 mod async_handler;
+mod call;
+mod hooks;
+mod idempotency;
+mod interceptor;
+mod jwt;
+mod policy;
 mod sync_handler;
 
 pub use async_handler::AsyncInvocationHandler;
-pub use sync_handler::SyncInvocationHandler;
\ No newline at end of file
+pub(crate) use async_handler::LOOP_CLOSED;
+pub(crate) use call::FnCall;
+pub use hooks::StateHook;
+pub use idempotency::IdempotencyCache;
+pub use interceptor::{AUTH_TOKEN_OPTION, INTERCEPTORS_ENV, Interceptor};
+pub use policy::HandlerPolicy;
+pub use sync_handler::SyncInvocationHandler;
@@ -0,0 +1,855 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use oprc_invoke::proxy::ObjectProxy;
+use oprc_pb::{InvocationRequest, InvocationResponse, ObjMeta, ObjectInvocationRequest};
+use pyo3::{Python, types::PyTypeMethods};
+use tracing::{Span, warn};
+
+use super::hooks::{self, StateHook};
+use super::idempotency::{Claim, IdempotencyCache};
+use super::interceptor::{CallInfo, Interceptor, RequestParts};
+use crate::audit::{self, AuditLog, Entry};
+use crate::call_log::CallLog;
+use crate::capabilities::{CAPABILITIES_HEADER, Capabilities};
+use crate::checksum::{self, ChecksumAlgorithm};
+use crate::chunks::{self, CHUNKED_HEADER, ChunkRef, FetchError};
+use crate::compression::{self, ACCEPT_ENCODING, CONTENT_ENCODING, Encoding};
+use crate::deadline;
+use crate::e2e::{self, ENCRYPTION_OPTION, PayloadKey, ServerKey};
+use crate::flags;
+use crate::intern::intern;
+use crate::memory::{self, Reservation};
+use crate::metrics::{GilTiming, ServerMetrics, Slot};
+use crate::model::{
+    CLAIMS_OPTION, EXCEPTION_TYPE_HEADER, EXPECTED_VERSION_OPTION, IDEMPOTENCY_KEY_OPTION,
+    InvocationResponseCode, REPLICA_HEADER,
+};
+use crate::obj::state_version;
+use crate::payload_limit::{PAYLOAD_LIMIT_HEADER, PayloadLimit};
+use crate::quota::{QuotaTracker, tenant_of};
+use crate::telemetry::{self, CHAIN_ID_OPTION, TRIGGER_SOURCE_OPTION};
+
+/// Server-side checks applied to incoming invocations before the Python
+/// callback is called. Shared by the sync and async handlers.
+#[derive(Clone, Default)]
+pub struct HandlerPolicy {
+    /// Data access used to read object state for conditional invocations.
+    data: Option<ObjectProxy>,
+    /// Session chunked request payloads are fetched over.
+    session: Option<zenoh::Session>,
+    /// Per-tenant invocation accounting and quotas.
+    quota: Option<Arc<QuotaTracker>>,
+    /// Counters of handled invocations.
+    metrics: Option<Arc<ServerMetrics>>,
+    /// Built-in interceptors, in pipeline order.
+    interceptors: Arc<[Interceptor]>,
+    /// Hooks run with the object's state around object invocations.
+    hooks: Arc<[StateHook]>,
+    /// Key pair encrypted invocations are opened with.
+    e2e: Option<Arc<ServerKey>>,
+    /// Log every handled invocation is recorded in.
+    audit: Option<Arc<AuditLog>>,
+    /// The last handled invocations, reported by `OaasEngine.recent_calls()`.
+    calls: Option<Arc<CallLog>>,
+    /// Responses replayed to duplicate deliveries of an idempotency key.
+    idempotency: Option<Arc<IdempotencyCache>>,
+    /// Id of this replica, reported on responses for callers to pin to.
+    replica: Option<Arc<str>>,
+    /// Largest request payload admitted, inflated size included.
+    max_payload: Option<Arc<PayloadLimit>>,
+}
+
+impl HandlerPolicy {
+    /// Enables checks that need to read object state through `proxy`.
+    pub fn with_data(mut self, proxy: ObjectProxy) -> Self {
+        self.data = Some(proxy);
+        self
+    }
+
+    /// Accepts chunked request payloads, fetched over `session` on admission.
+    pub fn with_chunks(mut self, session: zenoh::Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Accounts every dispatched invocation to its tenant in `quota`.
+    pub fn with_quota(mut self, quota: Arc<QuotaTracker>) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Records the outcome of every handled invocation in `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<ServerMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Runs `interceptors` around every invocation, in order.
+    pub fn with_interceptors(mut self, interceptors: Vec<Interceptor>) -> Self {
+        self.interceptors = interceptors.into();
+        self
+    }
+
+    /// Opens invocations encrypted for `key` and encrypts their responses.
+    pub fn with_encryption(mut self, key: Arc<ServerKey>) -> Self {
+        self.e2e = Some(key);
+        self
+    }
+
+    /// Records every invocation, admitted or rejected, in `log`.
+    pub fn with_audit(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Records the last handled invocations, admitted or rejected, in `log`.
+    pub fn with_call_log(mut self, log: Arc<CallLog>) -> Self {
+        self.calls = Some(log);
+        self
+    }
+
+    /// Answers duplicate deliveries of an idempotency key from `cache`.
+    pub fn with_idempotency(mut self, cache: Arc<IdempotencyCache>) -> Self {
+        self.idempotency = Some(cache);
+        self
+    }
+
+    /// Reports `replica` in the `oprc-replica` header of every response,
+    /// unless the callback set it.
+    pub fn with_replica(mut self, replica: String) -> Self {
+        self.replica = Some(replica.into());
+        self
+    }
+
+    /// Rejects invocations whose payload is over `limit`.
+    pub fn with_payload_limit(mut self, limit: Arc<PayloadLimit>) -> Self {
+        self.max_payload = Some(limit);
+        self
+    }
+
+    /// Runs `hooks` before and after every object invocation, in order.
+    /// Needs data access; hooks are skipped without it.
+    pub fn with_state_hooks(mut self, hooks: Vec<StateHook>) -> Self {
+        self.hooks = hooks.into();
+        self
+    }
+
+    /// Records the outcome of a dispatched invocation, its GIL time and the
+    /// type of the exception it failed with, if any.
+    pub(crate) fn observe(
+        &self,
+        admission: &Admission,
+        result: &pyo3::PyResult<InvocationResponse>,
+        gil: GilTiming,
+    ) {
+        tracing::trace!(
+            gil_wait_us = gil.wait.as_micros() as u64,
+            gil_held_us = gil.held.as_micros() as u64,
+            "invocation GIL usage"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.record(is_failure(result), gil);
+            if let Some(exception) = exception_type(result) {
+                let call = &admission.call;
+                metrics.record_exception(&call.cls_id, &call.fn_id, &exception);
+            }
+        }
+    }
+
+    /// Returns `Err` with the response to send back if `req` must not be dispatched.
+    pub(crate) async fn admit_fn(
+        &self,
+        req: &mut InvocationRequest,
+    ) -> Result<Admission, InvocationResponse> {
+        let started = Instant::now();
+        let call = CallInfo {
+            cls_id: intern(&req.cls_id),
+            fn_id: intern(&req.fn_id),
+            object_id: None,
+            started,
+            accept_encoding: accepted_encoding(&req.options),
+        };
+        let admitted = async {
+            let admission = self
+                .admit(
+                    call,
+                    RequestParts {
+                        options: &mut req.options,
+                        payload: &mut req.payload,
+                    },
+                )
+                .await?;
+            if admission.replays() {
+                return Ok(admission);
+            }
+            self.charge_quota(&req.options, req.payload.len())?;
+            Ok(admission)
+        }
+        .await;
+        if let Err(rejection) = &admitted {
+            let (cls_id, fn_id) = (&req.cls_id, &req.fn_id);
+            self.record_rejection(cls_id, fn_id, None, &req.options, started, rejection);
+        }
+        admitted.and_then(Admission::replay)
+    }
+
+    /// Returns `Err` with the response to send back if `req` must not be dispatched.
+    pub(crate) async fn admit_obj(
+        &self,
+        req: &mut ObjectInvocationRequest,
+    ) -> Result<Admission, InvocationResponse> {
+        let started = Instant::now();
+        let call = CallInfo {
+            cls_id: intern(&req.cls_id),
+            fn_id: intern(&req.fn_id),
+            object_id: Some(req.object_id),
+            started,
+            accept_encoding: accepted_encoding(&req.options),
+        };
+        let admitted = async {
+            let mut admission = self
+                .admit(
+                    call,
+                    RequestParts {
+                        options: &mut req.options,
+                        payload: &mut req.payload,
+                    },
+                )
+                .await?;
+            if admission.replays() {
+                return Ok(admission);
+            }
+            self.check_expected_version(req).await?;
+            self.charge_quota(&req.options, req.payload.len())?;
+            admission.hooks = self.run_pre_hooks(req).await?;
+            Ok(admission)
+        }
+        .await;
+        if let Err(rejection) = &admitted {
+            let (cls_id, fn_id, object_id) = (&req.cls_id, &req.fn_id, Some(req.object_id));
+            self.record_rejection(cls_id, fn_id, object_id, &req.options, started, rejection);
+        }
+        admitted.and_then(Admission::replay)
+    }
+
+    /// Records an invocation rejected on admission in the call log and the
+    /// audit log, if any.
+    fn record_rejection(
+        &self,
+        cls_id: &str,
+        fn_id: &str,
+        object_id: Option<u64>,
+        options: &HashMap<String, String>,
+        started: Instant,
+        rejection: &InvocationResponse,
+    ) {
+        if let Some(calls) = self.calls.as_ref().filter(|calls| calls.enabled()) {
+            let outcome = Ok(rejection.status);
+            calls.record(cls_id, fn_id, object_id, outcome, started.elapsed());
+        }
+        let Some(log) = &self.audit else {
+            return;
+        };
+        log.record(Entry {
+            ts: audit::now_ms(),
+            cls_id: cls_id.to_string(),
+            fn_id: fn_id.to_string(),
+            object_id,
+            tenant: tenant_of(options).to_string(),
+            chain_id: options.get(CHAIN_ID_OPTION).cloned(),
+            status: Some(rejection.status),
+            exception: None,
+            request_sha256: None,
+            response_sha256: rejection.payload.as_deref().map(audit::digest),
+            duration_us: None,
+        });
+    }
+
+    /// Counts the call as queued for its function until its callback starts.
+    /// Turns the caller's budget into a deadline on this host's clock and
+    /// rejects calls whose deadline passed, verifies the payload checksum,
+    /// runs the request side of the interceptors and claims the idempotency
+    /// key of the call, if any.
+    ///
+    /// If an interceptor rejects the call, the ones before it still see the
+    /// rejection on their response side. Claims are only passed on as set by
+    /// the `jwt` interceptor, never as sent by the caller. A chunked payload
+    /// is fetched, decrypted and inflated, in that order, before the
+    /// interceptors run. Payloads over the size limit, as received, once
+    /// fetched or while inflated, are rejected with `ResourceExhausted`. The
+    /// payload, inflated size included, is held against the memory budget
+    /// until the admission is dropped; calls it cannot cover are rejected
+    /// with `ResourceExhausted`.
+    async fn admit(
+        &self,
+        call: CallInfo,
+        mut req: RequestParts<'_>,
+    ) -> Result<Admission, InvocationResponse> {
+        let slot = self
+            .metrics
+            .as_ref()
+            .map(|metrics| metrics.enqueue(&call.cls_id, &call.fn_id));
+        deadline::rebase(req.options);
+        if let Some(Err(ago)) = deadline::remaining(req.options) {
+            return Err(reject(
+                InvocationResponseCode::DeadlineExceeded,
+                format!("deadline passed {} ms ago", ago.as_millis()),
+            ));
+        }
+        let checksum = checksum::verify(req.options, req.payload)
+            .map_err(|msg| reject(InvocationResponseCode::InvalidRequest, msg))?;
+        req.options.remove(CLAIMS_OPTION);
+        self.check_payload_size(req.payload.len())?;
+        let mut memory = memory::try_reserve(req.payload.len() as u64, "request payload")
+            .map_err(|msg| reject(InvocationResponseCode::ResourceExhausted, msg))?;
+        if let Some(chunked) = req.options.remove(CHUNKED_HEADER) {
+            *req.payload = self.fetch_chunks(&chunked, &mut memory).await?;
+            self.check_payload_size(req.payload.len())?;
+        }
+        let e2e = self.open(&mut req)?;
+        if let Some(encoding) = req.options.remove(CONTENT_ENCODING) {
+            *req.payload = self.inflate(&encoding, req.payload, &mut memory)?;
+        }
+        for (i, interceptor) in self.interceptors.iter().enumerate() {
+            if let Err(rejection) = interceptor.on_request(&call, &mut req).await {
+                let mut res = Ok(rejection);
+                for prev in self.interceptors[..i].iter().rev() {
+                    prev.on_response(&call, &mut res);
+                }
+                return Err(res.unwrap_or_else(|e| {
+                    reject(InvocationResponseCode::SystemError, e.to_string())
+                }));
+            }
+        }
+        let (span, chain_id) = trace(&call, req.options);
+        let audit = self.audit.as_ref().map(|log| Audited {
+            log: log.clone(),
+            tenant: tenant_of(req.options).to_string(),
+            request_sha256: audit::digest(req.payload),
+        });
+        let idempotency = self.claim(&call, req.options).await;
+        Ok(Admission {
+            checksum,
+            call,
+            interceptors: self.interceptors.clone(),
+            span,
+            chain_id,
+            hooks: None,
+            e2e,
+            audit,
+            calls: self.calls.clone(),
+            idempotency,
+            replica: self.replica.clone(),
+            slot,
+            _memory: memory,
+        })
+    }
+
+    /// Rejects a request payload of `len` bytes if it is over the limit,
+    /// see [`too_large`].
+    fn check_payload_size(&self, len: usize) -> Result<(), InvocationResponse> {
+        let exceeded = self
+            .max_payload
+            .as_ref()
+            .and_then(|limit| limit.exceeded(len));
+        match exceeded {
+            Some(max) => Err(too_large(
+                format!(
+                    "request payload is {} bytes, over the limit of {} bytes of this server",
+                    len, max
+                ),
+                max as u64,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Decodes a request payload sent with `oprc-content-encoding`, taking the
+    /// inflated bytes from `memory`. Decoding stops as soon as the output
+    /// goes past the payload limit or what is left of the memory budget, so a
+    /// small compressed payload cannot inflate into more than either allows.
+    fn inflate(
+        &self,
+        encoding: &str,
+        payload: &[u8],
+        memory: &mut Reservation,
+    ) -> Result<Vec<u8>, InvocationResponse> {
+        let Some(encoding) = Encoding::parse(encoding) else {
+            return Err(reject(
+                InvocationResponseCode::InvalidRequest,
+                format!("unsupported content encoding '{}'", encoding),
+            ));
+        };
+        let max_payload = self
+            .max_payload
+            .as_ref()
+            .and_then(|limit| limit.get())
+            .map(|max| max as u64);
+        let available = memory::available();
+        let limit = max_payload.into_iter().chain(available).min();
+        let inflated = encoding
+            .decode_limited(payload, limit.unwrap_or(u64::MAX))
+            .map_err(|e| {
+                reject(
+                    InvocationResponseCode::InvalidRequest,
+                    format!("failed to decompress payload: {}", e),
+                )
+            })?;
+        let Some(inflated) = inflated else {
+            let limit = limit.unwrap_or(u64::MAX);
+            if max_payload == Some(limit) {
+                return Err(too_large(
+                    format!(
+                        "request payload inflates to over {} bytes, the limit of this server",
+                        limit
+                    ),
+                    limit,
+                ));
+            }
+            return Err(reject(
+                InvocationResponseCode::ResourceExhausted,
+                memory::exhausted(limit.saturating_add(1), "decompressed payload"),
+            ));
+        };
+        memory
+            .grow(inflated.len() as u64, "decompressed payload")
+            .map_err(|msg| reject(InvocationResponseCode::ResourceExhausted, msg))?;
+        Ok(inflated)
+    }
+
+    /// Claims the delivery of a call sent with an `oprc-idempotency-key`
+    /// option. Keys are scoped to the class, function and tenant.
+    async fn claim(&self, call: &CallInfo, options: &HashMap<String, String>) -> Option<Claim> {
+        let cache = self.idempotency.as_ref()?;
+        let key = options.get(IDEMPOTENCY_KEY_OPTION)?;
+        let delivery = (
+            call.cls_id.clone(),
+            call.fn_id.clone(),
+            tenant_of(options).to_string(),
+            key.clone(),
+        );
+        cache.claim(delivery).await
+    }
+
+    /// Decrypts an encrypted request payload. Returns the key to encrypt the
+    /// response with, or `None` for a request sent in the clear, which is
+    /// rejected if this server requires encryption.
+    fn open(&self, req: &mut RequestParts<'_>) -> Result<Option<PayloadKey>, InvocationResponse> {
+        let encrypted = req.options.contains_key(ENCRYPTION_OPTION);
+        match &self.e2e {
+            Some(key) if encrypted => key
+                .open_request(req.options, req.payload)
+                .map_err(|msg| reject(InvocationResponseCode::InvalidRequest, msg)),
+            Some(key) if key.required => Err(reject(
+                InvocationResponseCode::InvalidRequest,
+                "this server only accepts encrypted invocations".to_string(),
+            )),
+            None if encrypted => Err(reject(
+                InvocationResponseCode::InvalidRequest,
+                "this server does not accept encrypted invocations".to_string(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// Fetches a request payload announced by an `oprc-chunked` option,
+    /// taking its bytes from `memory`. A payload of known size is reserved
+    /// before any chunk is fetched.
+    async fn fetch_chunks(
+        &self,
+        chunked: &str,
+        memory: &mut Reservation,
+    ) -> Result<Vec<u8>, InvocationResponse> {
+        let chunks = ChunkRef::parse(chunked)
+            .map_err(|msg| reject(InvocationResponseCode::InvalidRequest, msg))?;
+        let Some(session) = &self.session else {
+            return Err(reject(
+                InvocationResponseCode::InvalidRequest,
+                "this server does not accept chunked payloads".to_string(),
+            ));
+        };
+        let exhausted = |msg| reject(InvocationResponseCode::ResourceExhausted, msg);
+        let mut payload = Vec::new();
+        let fetched = match chunks.bytes {
+            Some(bytes) => {
+                memory.grow(bytes, "chunked payload").map_err(exhausted)?;
+                chunks::fetch_into(session, &chunks, &mut payload, None).await
+            }
+            None => chunks::fetch_into(session, &chunks, &mut payload, Some(memory)).await,
+        };
+        fetched.map_err(|e| match e {
+            FetchError::Exhausted(msg) => exhausted(msg),
+            FetchError::Failed(msg) => reject(InvocationResponseCode::SystemError, msg),
+        })?;
+        Ok(payload)
+    }
+
+    /// Rejects the call with `QuotaExceeded` if its tenant is over quota.
+    fn charge_quota(
+        &self,
+        options: &HashMap<String, String>,
+        payload_len: usize,
+    ) -> Result<(), InvocationResponse> {
+        let Some(quota) = &self.quota else {
+            return Ok(());
+        };
+        quota
+            .charge(tenant_of(options), payload_len)
+            .map_err(|msg| reject(InvocationResponseCode::QuotaExceeded, msg))
+    }
+
+    /// Runs the `pre` side of the state hooks, rejecting the call with
+    /// `AppError` if one fails. Returns what the `post` side needs.
+    async fn run_pre_hooks(
+        &self,
+        req: &ObjectInvocationRequest,
+    ) -> Result<Option<HookedCall>, InvocationResponse> {
+        if self.hooks.is_empty() {
+            return Ok(None);
+        }
+        let Some(proxy) = &self.data else {
+            warn!(
+                "state hooks configured but the server has no data access; skipping them for {}/{}",
+                req.cls_id, req.fn_id
+            );
+            return Ok(None);
+        };
+        hooks::before(proxy, &self.hooks, req)
+            .await
+            .map_err(|msg| {
+                reject(
+                    InvocationResponseCode::AppError,
+                    format!("state hook rejected the invocation: {}", msg),
+                )
+            })?;
+        Ok(Some(HookedCall {
+            proxy: proxy.clone(),
+            hooks: self.hooks.clone(),
+            req: req.clone(),
+        }))
+    }
+
+    /// Rejects the call with `Conflict` if the object's state no longer matches
+    /// the version the caller expected, and with `InvalidRequest` if this
+    /// server cannot read it.
+    ///
+    /// The check runs before the callback, so a write landing in between is
+    /// not detected. The version is a hash of the entries (see
+    /// `state_version`), so state changed and then changed back still matches.
+    async fn check_expected_version(
+        &self,
+        req: &ObjectInvocationRequest,
+    ) -> Result<(), InvocationResponse> {
+        let Some(raw) = req.options.get(EXPECTED_VERSION_OPTION) else {
+            return Ok(());
+        };
+        let expected = raw.parse::<u64>().map_err(|_| {
+            reject(
+                InvocationResponseCode::InvalidRequest,
+                format!("invalid {} option: '{}'", EXPECTED_VERSION_OPTION, raw),
+            )
+        })?;
+        let Some(proxy) = &self.data else {
+            return Err(reject(
+                InvocationResponseCode::InvalidRequest,
+                format!(
+                    "{} set on {}/{} but the server has no data access to check it",
+                    EXPECTED_VERSION_OPTION, req.cls_id, req.fn_id
+                ),
+            ));
+        };
+        let meta = ObjMeta {
+            cls_id: req.cls_id.clone(),
+            partition_id: req.partition_id,
+            object_id: req.object_id,
+        };
+        let current = proxy
+            .get_obj(&meta)
+            .await
+            .map_err(|e| {
+                reject(
+                    InvocationResponseCode::SystemError,
+                    format!("failed to read object state: {}", e),
+                )
+            })?
+            .map(|obj| state_version(obj.entries.iter().map(|(k, v)| (*k, v.data.as_slice()))))
+            .unwrap_or(0);
+        if current != expected {
+            return Err(reject(
+                InvocationResponseCode::Conflict,
+                format!(
+                    "object {} version conflict: expected {}, found {}",
+                    req.object_id, expected, current
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Opens the span of an admitted invocation and assigns it to a causal chain.
+///
+/// The caller's trace context becomes the span's parent, except for
+/// invocations fired by a trigger, which only link to it. The chain id is
+/// taken from the request or newly assigned, and written back to the options
+/// so the Python callback can pass it on. So is the span's own trace context,
+/// making it the parent of the invocations the callback makes.
+fn trace(call: &CallInfo, options: &mut HashMap<String, String>) -> (Span, String) {
+    let chain_id = options
+        .entry(CHAIN_ID_OPTION.to_string())
+        .or_insert_with(telemetry::new_chain_id)
+        .clone();
+    let trigger = options.get(TRIGGER_SOURCE_OPTION);
+    let span = tracing::info_span!(
+        "invocation",
+        otel.name = "handler.invoke",
+        cls_id = %call.cls_id,
+        fn_id = %call.fn_id,
+        oprc.chain_id = %chain_id,
+        oprc.trigger_source = trigger.map(String::as_str).unwrap_or_default(),
+    );
+    let link_only = trigger.is_some() && flags::enabled(flags::TRIGGER_SPAN_LINKS);
+    telemetry::adopt_remote(&span, options, link_only);
+    telemetry::inject(&span, options);
+    (span, chain_id)
+}
+
+/// Per-invocation state established on admission and applied to the response.
+pub(crate) struct Admission {
+    /// Checksum algorithm the caller used, echoed on the response payload.
+    checksum: Option<ChecksumAlgorithm>,
+    call: CallInfo,
+    interceptors: Arc<[Interceptor]>,
+    /// Span the Python callback runs in.
+    span: Span,
+    chain_id: String,
+    /// State hooks still to run after an object invocation.
+    hooks: Option<HookedCall>,
+    /// Key the response is encrypted with, if the request was encrypted.
+    e2e: Option<PayloadKey>,
+    /// Where the invocation is recorded, if audited.
+    audit: Option<Audited>,
+    /// The call log the invocation is recorded in, if any.
+    calls: Option<Arc<CallLog>>,
+    /// Claim of the idempotency key the call was sent with, if any.
+    idempotency: Option<Claim>,
+    /// Id of this replica, reported on the response.
+    replica: Option<Arc<str>>,
+    /// Counts the invocation in the queue and in-flight gauges of its function.
+    slot: Option<Slot>,
+    /// Memory budget held by the request payload.
+    _memory: Reservation,
+}
+
+/// An admitted invocation to record in the audit log, with what its record
+/// needs from the request.
+struct Audited {
+    log: Arc<AuditLog>,
+    tenant: String,
+    /// Digest of the payload the callback sees, decrypted and inflated.
+    request_sha256: String,
+}
+
+/// An admitted object invocation with state hooks.
+struct HookedCall {
+    proxy: ObjectProxy,
+    hooks: Arc<[StateHook]>,
+    req: ObjectInvocationRequest,
+}
+
+impl Admission {
+    pub(crate) fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Marks the Python callback as started: the invocation leaves the queue
+    /// of its function and counts as in flight until the admission is dropped.
+    pub(crate) fn start(&self) {
+        if let Some(slot) = &self.slot {
+            slot.start();
+        }
+    }
+
+    /// Whether the call is a duplicate delivery of an idempotency key.
+    fn replays(&self) -> bool {
+        matches!(self.idempotency, Some(Claim::Replay(_)))
+    }
+
+    /// Completes a duplicate delivery with the response of the first one,
+    /// returned as `Err` as it must not be dispatched.
+    fn replay(self) -> Result<Self, InvocationResponse> {
+        let Some(Claim::Replay(resp)) = &self.idempotency else {
+            return Ok(self);
+        };
+        let mut result = Ok(resp.clone());
+        self.finish(&mut result);
+        Err(result.unwrap_or_else(|e| failed(&e)))
+    }
+
+    /// Runs the `post` side of the state hooks if the callback returned a
+    /// response. A failing hook is logged; the response is kept.
+    pub(crate) async fn after(&self, result: &pyo3::PyResult<InvocationResponse>) {
+        let (Some(hooked), Ok(resp)) = (&self.hooks, result) else {
+            return;
+        };
+        if let Err(e) = hooks::after(&hooked.proxy, &hooked.hooks, &hooked.req, resp).await {
+            warn!(
+                "state hook failed after {}/{} on object {}: {}",
+                hooked.req.cls_id, hooked.req.fn_id, hooked.req.object_id, e
+            );
+        }
+    }
+
+    /// Completes the response of an admitted invocation.
+    ///
+    /// The response carries the trace context and chain id of the invocation,
+    /// for the data plane to pass on to the triggers it fires, and the
+    /// capabilities and replica id of this server. The response to an encrypted invocation
+    /// is encrypted after the interceptors ran, so the checksum covers the
+    /// ciphertext. The audit record is written before, so it holds the
+    /// digest of the response payload in the clear. The response kept for
+    /// duplicate deliveries is taken first, as the callback returned it.
+    pub(crate) fn finish(&self, result: &mut pyo3::PyResult<InvocationResponse>) {
+        if let Some(Claim::First(pending)) = &self.idempotency {
+            match result {
+                Ok(resp) if replayable(resp) => pending.complete(resp),
+                _ => pending.release(),
+            }
+        }
+        for interceptor in self.interceptors.iter().rev() {
+            interceptor.on_response(&self.call, result);
+        }
+        if let Some(audited) = &self.audit {
+            self.record(audited, result);
+        }
+        if let Some(calls) = self.calls.as_ref().filter(|calls| calls.enabled()) {
+            let call = &self.call;
+            calls.record(
+                &call.cls_id,
+                &call.fn_id,
+                call.object_id,
+                result
+                    .as_ref()
+                    .map(|resp| resp.status)
+                    .map_err(|e| e.to_string()),
+                call.started.elapsed(),
+            );
+        }
+        if let Ok(resp) = result {
+            telemetry::inject(&self.span, &mut resp.headers);
+            resp.headers
+                .insert(CHAIN_ID_OPTION.to_string(), self.chain_id.clone());
+            resp.headers.insert(
+                CAPABILITIES_HEADER.to_string(),
+                Capabilities::local_header().to_string(),
+            );
+            if let Some(replica) = &self.replica {
+                resp.headers
+                    .entry(REPLICA_HEADER.to_string())
+                    .or_insert_with(|| replica.to_string());
+            }
+        }
+        if let (Some(key), Ok(resp)) = (&self.e2e, result.as_mut()) {
+            e2e::seal_response(key, resp);
+        }
+        if let (Some(algorithm), Ok(resp)) = (self.checksum, result) {
+            let payload = resp.payload.as_deref().unwrap_or_default();
+            algorithm.stamp(&mut resp.headers, payload);
+        }
+    }
+
+    /// Writes the audit record of the invocation.
+    fn record(&self, audited: &Audited, result: &pyo3::PyResult<InvocationResponse>) {
+        let resp = result.as_ref().ok();
+        audited.log.record(Entry {
+            ts: audit::now_ms(),
+            cls_id: self.call.cls_id.to_string(),
+            fn_id: self.call.fn_id.to_string(),
+            object_id: self.call.object_id,
+            tenant: audited.tenant.clone(),
+            chain_id: Some(self.chain_id.clone()),
+            status: resp.map(|resp| resp.status),
+            exception: exception_type(result),
+            request_sha256: Some(audited.request_sha256.clone()),
+            response_sha256: resp
+                .and_then(|resp| resp.payload.as_deref())
+                .map(audit::digest),
+            duration_us: Some(self.call.started.elapsed().as_micros() as u64),
+        });
+    }
+}
+
+/// Returns the encoding to compress responses to the caller with: the first
+/// one it lists in `oprc-accept-encoding`, else zstd or gzip if advertised in
+/// its capabilities.
+fn accepted_encoding(options: &HashMap<String, String>) -> Option<Encoding> {
+    compression::accepted(options.get(ACCEPT_ENCODING))
+        .next()
+        .or_else(|| {
+            let caps = Capabilities::of(options)?;
+            [Encoding::Zstd, Encoding::Gzip]
+                .into_iter()
+                .find(|e| caps.supports(e.capability()))
+        })
+}
+
+/// Returns true if the invocation raised or returned a non-`Okay` status.
+pub(crate) fn is_failure(result: &pyo3::PyResult<InvocationResponse>) -> bool {
+    match result {
+        Ok(resp) => resp.status != InvocationResponseCode::Okay as i32,
+        Err(_) => true,
+    }
+}
+
+/// Returns true if `resp` is the outcome of the invocation itself, to replay
+/// to duplicate deliveries: `Okay`, or an `AppError` the callback returned
+/// rather than an exception it raised. Anything else may pass on a retry.
+fn replayable(resp: &InvocationResponse) -> bool {
+    resp.status == InvocationResponseCode::Okay as i32
+        || (resp.status == InvocationResponseCode::AppError as i32
+            && !resp.headers.contains_key(EXCEPTION_TYPE_HEADER))
+}
+
+/// Returns the class name of the Python exception a failed invocation raised,
+/// or the one the SDK reported in `oprc-exception-type` after catching it.
+fn exception_type(result: &pyo3::PyResult<InvocationResponse>) -> Option<String> {
+    match result {
+        Err(e) => Python::attach(|py| e.get_type(py).name().ok().map(|n| n.to_string())),
+        Ok(resp) if resp.status != InvocationResponseCode::Okay as i32 => {
+            resp.headers.get(EXCEPTION_TYPE_HEADER).cloned()
+        }
+        Ok(_) => None,
+    }
+}
+
+/// Builds the response the handlers send for a callback that raised `e`.
+fn failed(e: &pyo3::PyErr) -> InvocationResponse {
+    InvocationResponse {
+        payload: Some(e.to_string().into_bytes()),
+        status: InvocationResponseCode::AppError as i32,
+        ..Default::default()
+    }
+}
+
+/// Builds the rejection of a request payload over the limit of `max` bytes:
+/// `ResourceExhausted` with the limit in `oprc-payload-limit`, which callers
+/// raise as `PayloadTooLarge`.
+pub(crate) fn too_large(message: String, max: u64) -> InvocationResponse {
+    let mut resp = reject(InvocationResponseCode::ResourceExhausted, message);
+    resp.headers
+        .insert(PAYLOAD_LIMIT_HEADER.to_string(), max.to_string());
+    resp
+}
+
+/// Builds a rejection response with `status` and a textual payload.
+pub(crate) fn reject(status: InvocationResponseCode, message: String) -> InvocationResponse {
+    InvocationResponse {
+        payload: Some(message.into_bytes()),
+        status: status as i32,
+        headers: HashMap::from([(
+            CAPABILITIES_HEADER.to_string(),
+            Capabilities::local_header().to_string(),
+        )]),
+        ..Default::default()
+    }
+}
@@ -2,22 +2,32 @@ use std::ops::Deref;
 
 use oprc_invoke::handler::InvocationExecutor;
 use oprc_pb::{oprc_function_server::OprcFunction, InvocationRequest, InvocationResponse, ObjectInvocationRequest, ResponseStatus};
-use pyo3::{intern, types::PyTuple, Py, PyAny, PyRef, PyResult, Python};
+use pyo3::{intern, types::PyTuple, Py, PyAny, PyRef, PyResult};
 use tonic::{Request, Response, Status};
 use tracing::{debug, info};
 
+use super::{FnCall, HandlerPolicy};
+use crate::metrics::{GilTiming, attach_timed};
 
 
 pub struct SyncInvocationHandler {
     callback: Py<PyAny>,
+    policy: HandlerPolicy,
 }
 
 impl SyncInvocationHandler {
     pub fn new(callback: Py<PyAny>) -> Self {
         SyncInvocationHandler {
-            callback
+            callback,
+            policy: HandlerPolicy::default(),
         }
     }
+
+    /// Sets the server-side checks applied before dispatching to Python.
+    pub fn with_policy(mut self, policy: HandlerPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
 }
 
 #[tonic::async_trait]
@@ -35,7 +45,7 @@ impl OprcFunction for SyncInvocationHandler {
                 invocation_request.cls_id, invocation_request.fn_id
             );
         }
-        match invoke_fn(&self.callback, invocation_request).await {
+        match invoke_fn(&self.callback, &self.policy, invocation_request).await {
             Ok(output) => Ok(Response::new(output)),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -66,7 +76,7 @@ impl OprcFunction for SyncInvocationHandler {
             );
         }
 
-        match invoke_obj(&self.callback, invocation_request).await {
+        match invoke_obj(&self.callback, &self.policy, invocation_request).await {
             Ok(output) => Ok(Response::new(output)),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -95,7 +105,7 @@ impl InvocationExecutor for SyncInvocationHandler {
                 invocation_request.cls_id, invocation_request.fn_id
             );
         }
-        match invoke_fn(&self.callback, invocation_request).await {
+        match invoke_fn(&self.callback, &self.policy, invocation_request).await {
             Ok(output) => Ok(output),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -124,7 +134,7 @@ impl InvocationExecutor for SyncInvocationHandler {
             );
         }
 
-        match invoke_obj(&self.callback, invocation_request).await {
+        match invoke_obj(&self.callback, &self.policy, invocation_request).await {
             Ok(output) => Ok(output),
             Err(err) => {
                 let resp = InvocationResponse {
@@ -144,33 +154,54 @@ impl InvocationExecutor for SyncInvocationHandler {
 
 async fn invoke_obj(
     callback: &Py<PyAny>,
-    req: oprc_pb::ObjectInvocationRequest,
+    policy: &HandlerPolicy,
+    mut req: oprc_pb::ObjectInvocationRequest,
 ) -> PyResult<oprc_pb::InvocationResponse> {
+    let admission = match policy.admit_obj(&mut req).await {
+        Ok(admission) => admission,
+        Err(rejection) => return Ok(rejection),
+    };
     
-    let res = Python::attach(|py| {
-        let req = crate::model::ObjectInvocationRequest::from(req);
-        let args = PyTuple::new(py, [req])?;
-        let any = callback.call_method1(py, intern!(py, "invoke_obj"), args)?;
-        any.extract::<PyRef<crate::model::InvocationResponse>>(py)
-            .map(|r| r.deref().into())
-    
+    let mut gil = GilTiming::default();
+    let req = crate::model::ObjectInvocationRequest::from(req);
+    let mut res = admission.span().in_scope(|| {
+        attach_timed(&mut gil, |py| {
+            admission.start();
+            let args = PyTuple::new(py, [req])?;
+            let any = callback.call_method1(py, intern!(py, "invoke_obj"), args)?;
+            any.extract::<PyRef<crate::model::InvocationResponse>>(py)
+                .map(|r| r.deref().into())
+        })
     });
-
+    admission.after(&res).await;
+    admission.finish(&mut res);
+    policy.observe(&admission, &res, gil);
     res
 }
 
 
 async fn invoke_fn(
     callback: &Py<PyAny>,
-    req: oprc_pb::InvocationRequest,
+    policy: &HandlerPolicy,
+    mut req: oprc_pb::InvocationRequest,
 ) -> PyResult<oprc_pb::InvocationResponse> {
-    let res = Python::attach(|py| {
-        let req = crate::model::InvocationRequest::from(req);
-        let args = PyTuple::new(py, [req])?;
-        let any = callback.call_method1(py, intern!(py, "invoke_fn"), args)?;
-        any.extract::<PyRef<crate::model::InvocationResponse>>(py)
-            .map(|r| r.deref().into())
-    
+    let admission = match policy.admit_fn(&mut req).await {
+        Ok(admission) => admission,
+        Err(rejection) => return Ok(rejection),
+    };
+    let mut gil = GilTiming::default();
+    let mut res = admission.span().in_scope(|| {
+        FnCall::from_proto(req).and_then(|call| {
+            attach_timed(&mut gil, |py| {
+                admission.start();
+                let (method, args) = call.into_call(py)?;
+                let any = callback.call_method1(py, method, args)?;
+                any.extract::<PyRef<crate::model::InvocationResponse>>(py)
+                    .map(|r| r.deref().into())
+            })
+        })
     });
+    admission.finish(&mut res);
+    policy.observe(&admission, &res, gil);
     res
-}
\ No newline at end of file
+}
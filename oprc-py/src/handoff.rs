@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use oprc_pb::ObjData;
+use prost::Message;
+use pyo3::{exceptions::PyTypeError, intern, prelude::*, types::PyTuple};
+use pyo3_async_runtimes::{TaskLocals, into_future_with_locals, tokio::get_runtime};
+use tokio::task::JoinHandle;
+use tracing::warn;
+use zenoh::query::Query;
+
+use crate::{
+    checksum::{self, ChecksumAlgorithm},
+    obj::ObjectData,
+    session_events::notify,
+};
+
+/// Key expression a new replica receives handed off objects of a partition on.
+pub(crate) fn handoff_key(cls_id: &str, partition_id: u32) -> String {
+    format!("oprc/{}/{}/handoff", cls_id, partition_id)
+}
+
+/// An object prepared for handoff.
+pub(crate) struct Outgoing {
+    pub(crate) object_id: u64,
+    /// The encoded `ObjData`.
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Outgoing {
+    pub(crate) fn new(obj: ObjData) -> Self {
+        Outgoing {
+            object_id: obj
+                .metadata
+                .as_ref()
+                .map(|m| m.object_id)
+                .unwrap_or_default(),
+            bytes: obj.encode_to_vec(),
+        }
+    }
+}
+
+/// Receives handed off objects on `key`, verifies their checksum and passes
+/// them to `on_object`, until the returned task is aborted.
+///
+/// An object is acknowledged only once `on_object` returned without raising.
+/// A coroutine it returns is awaited on `locals`' loop first, and rejects the
+/// object without one.
+pub(crate) async fn accept(
+    session: zenoh::Session,
+    key: String,
+    on_object: Py<PyAny>,
+    locals: Option<TaskLocals>,
+) -> zenoh::Result<JoinHandle<()>> {
+    let queryable = session.declare_queryable(key.clone()).await?;
+    Ok(get_runtime().spawn(async move {
+        while let Ok(query) = queryable.recv_async().await {
+            let res = match receive(&query, &on_object, locals.as_ref()).await {
+                Ok(ack) => query.reply(query.key_expr().clone(), ack).await,
+                Err(msg) => {
+                    warn!("rejected handed off object on {}: {}", key, msg);
+                    query.reply_err(msg).await
+                }
+            };
+            if let Err(e) = res {
+                warn!("failed to acknowledge handoff on {}: {}", key, e);
+            }
+        }
+    }))
+}
+
+/// Returns the checksum to echo back as acknowledgement.
+async fn receive(
+    query: &Query,
+    on_object: &Py<PyAny>,
+    locals: Option<&TaskLocals>,
+) -> Result<String, String> {
+    let payload = query.payload().map(|p| p.to_bytes()).unwrap_or_default();
+    let checksum = query
+        .attachment()
+        .and_then(|a| a.try_to_string().ok())
+        .ok_or_else(|| "missing checksum attachment".to_string())?
+        .into_owned();
+    checksum::verify_value(&checksum, &payload)?;
+    let obj = ObjData::decode(payload.as_ref()).map_err(|e| format!("invalid object: {}", e))?;
+    let obj = ObjectData::from(obj);
+    let (on_object, locals) =
+        Python::attach(|py| (on_object.clone_ref(py), locals.map(|l| l.clone_ref(py))));
+    // The callback runs on a blocking thread, so a slow one does not stall
+    // the runtime worker.
+    let pending = tokio::task::spawn_blocking(move || {
+        Python::attach(|py| {
+            let result = on_object.bind(py).call1((obj,))?;
+            if !result.hasattr(intern!(py, "__await__"))? {
+                return Ok(None);
+            }
+            match &locals {
+                Some(locals) => into_future_with_locals(locals, result).map(Some),
+                None => {
+                    let _ = result.call_method0(intern!(py, "close"));
+                    Err(PyTypeError::new_err(
+                        "on_object returned a coroutine; pass event_loop to accept_handoff to await it",
+                    ))
+                }
+            }
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("on_object raised: {}", e))?;
+    if let Some(pending) = pending {
+        pending
+            .await
+            .map_err(|e| format!("on_object raised: {}", e))?;
+    }
+    Ok(checksum)
+}
+
+/// Sends `objects` one by one to the replica listening on `key`, reporting
+/// `(done, total)` to `on_progress` after each.
+pub(crate) async fn send(
+    session: zenoh::Session,
+    key: String,
+    objects: Vec<Outgoing>,
+    on_progress: Option<Py<PyAny>>,
+    timeout: Duration,
+) -> HandoffReport {
+    let mut report = HandoffReport {
+        total: objects.len(),
+        verified: 0,
+        failed: Vec::new(),
+        bytes: 0,
+    };
+    for (done, obj) in objects.into_iter().enumerate() {
+        let len = obj.bytes.len() as u64;
+        match transfer(&session, &key, obj.bytes, timeout).await {
+            Ok(()) => {
+                report.verified += 1;
+                report.bytes += len;
+            }
+            Err(msg) => {
+                warn!(
+                    "handoff of object {} on {} failed: {}",
+                    obj.object_id, key, msg
+                );
+                report.failed.push(obj.object_id);
+            }
+        }
+        if let Some(callback) = &on_progress {
+            let total = report.total;
+            notify(callback, None, |py| PyTuple::new(py, [done + 1, total]));
+        }
+    }
+    report
+}
+
+/// Sends one encoded object and checks that the receiver echoed its checksum.
+async fn transfer(
+    session: &zenoh::Session,
+    key: &str,
+    bytes: Vec<u8>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let checksum = ChecksumAlgorithm::Crc32c.value(&bytes);
+    let replies = session
+        .get(key)
+        .payload(bytes)
+        .attachment(checksum.clone())
+        .timeout(timeout)
+        .await
+        .map_err(|e| e.to_string())?;
+    let reply = replies
+        .recv_async()
+        .await
+        .map_err(|_| "no replica acknowledged the object".to_string())?;
+    match reply.result() {
+        Ok(sample) => {
+            let ack = sample
+                .payload()
+                .try_to_string()
+                .map_err(|e| e.to_string())?;
+            if ack != checksum {
+                return Err(format!(
+                    "acknowledged checksum {} does not match {}",
+                    ack, checksum
+                ));
+            }
+            Ok(())
+        }
+        Err(e) => Err(e
+            .payload()
+            .try_to_string()
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| "replica rejected the object".to_string())),
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// Outcome of handing objects off to another replica.
+pub struct HandoffReport {
+    /// Objects to hand off.
+    pub total: usize,
+    /// Objects the receiver acknowledged with a matching checksum.
+    pub verified: usize,
+    /// IDs of objects that were not acknowledged.
+    pub failed: Vec<u64>,
+    /// Encoded bytes of the verified objects.
+    pub bytes: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl HandoffReport {
+    /// `True` if every object was verified by the receiver.
+    #[getter]
+    fn ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Returns a string representation of the `HandoffReport`.
+    fn __str__(&self) -> String {
+        format!(
+            "HandoffReport {{ total: {}, verified: {}, failed: {:?}, bytes: {} }}",
+            self.total, self.verified, self.failed, self.bytes
+        )
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use oprc_pb::ObjMeta;
+use pyo3::{
+    exceptions::{PyNotImplementedError, PyRuntimeError},
+    prelude::*,
+};
+use zenoh::{query::ConsolidationMode, sample::SampleKind};
+
+use crate::replica::object_key;
+
+/// How long to wait for data plane nodes to announce version retention.
+const CAPABILITY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Liveliness token declared by data plane nodes that retain entry versions
+/// of a partition.
+fn capability_key(cls_id: &str, partition_id: u32) -> String {
+    format!("oprc/{}/{}/capabilities/versions", cls_id, partition_id)
+}
+
+/// Which past state of an entry to read.
+pub(crate) enum At {
+    Version(u64),
+    /// Unix time in milliseconds; the latest version at or before it is returned.
+    Timestamp(u64),
+}
+
+/// Returns whether any data plane node serving the partition retains versions.
+pub(crate) async fn supported(session: &zenoh::Session, cls_id: &str, partition_id: u32) -> bool {
+    let Ok(replies) = session
+        .liveliness()
+        .get(capability_key(cls_id, partition_id))
+        .timeout(CAPABILITY_TIMEOUT)
+        .await
+    else {
+        return false;
+    };
+    while let Ok(reply) = replies.recv_async().await {
+        if reply.result().is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Reads the version of entry `key` selected by `at`.
+pub(crate) async fn get_at(
+    session: &zenoh::Session,
+    meta: &ObjMeta,
+    key: u32,
+    at: At,
+) -> PyResult<Option<EntryVersion>> {
+    let selector = match at {
+        At::Version(v) => format!("entry={}&version={}", key, v),
+        At::Timestamp(ts) => format!("entry={}&at={}", key, ts),
+    };
+    Ok(query(session, meta, selector).await?.into_iter().next())
+}
+
+/// Reads up to `limit` versions of entry `key`, newest first.
+pub(crate) async fn history(
+    session: &zenoh::Session,
+    meta: &ObjMeta,
+    key: u32,
+    limit: usize,
+) -> PyResult<Vec<EntryVersion>> {
+    let mut versions = query(session, meta, format!("entry={}&limit={}", key, limit)).await?;
+    versions.truncate(limit);
+    Ok(versions)
+}
+
+/// Queries the versions endpoint of the object. Each reply is one version:
+/// the entry value as payload (a delete sample if the entry was removed) and
+/// the version number as attachment.
+async fn query(
+    session: &zenoh::Session,
+    meta: &ObjMeta,
+    parameters: String,
+) -> PyResult<Vec<EntryVersion>> {
+    if !supported(session, &meta.cls_id, meta.partition_id).await {
+        return Err(PyNotImplementedError::new_err(format!(
+            "the data plane of {}/{} does not retain object versions",
+            meta.cls_id, meta.partition_id
+        )));
+    }
+    let selector = format!("{}/versions?{}", object_key(meta), parameters);
+    let replies = session
+        .get(selector.as_str())
+        .consolidation(ConsolidationMode::None)
+        .await
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    let mut versions = Vec::new();
+    while let Ok(reply) = replies.recv_async().await {
+        let sample = reply.result().map_err(|e| {
+            PyRuntimeError::new_err(
+                e.payload()
+                    .try_to_string()
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| "version query failed".to_string()),
+            )
+        })?;
+        let version = sample
+            .attachment()
+            .and_then(|a| a.try_to_string().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| PyRuntimeError::new_err("version reply without a version"))?;
+        versions.push(EntryVersion {
+            version,
+            timestamp_ms: sample
+                .timestamp()
+                .map(|ts| ts.get_time().to_duration().as_millis() as u64),
+            value: match sample.kind() {
+                SampleKind::Put => Some(sample.payload().to_bytes().into_owned()),
+                SampleKind::Delete => None,
+            },
+        });
+    }
+    versions.sort_by(|a, b| b.version.cmp(&a.version));
+    Ok(versions)
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// A past state of an object entry.
+pub struct EntryVersion {
+    /// Version number assigned by the data plane.
+    pub version: u64,
+    /// When the version was written, in Unix milliseconds, if known.
+    pub timestamp_ms: Option<u64>,
+    /// The entry value, or `None` if the entry was deleted in this version.
+    pub value: Option<Vec<u8>>,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl EntryVersion {
+    /// Returns a string representation of the `EntryVersion`.
+    fn __str__(&self) -> String {
+        format!(
+            "EntryVersion {{ version: {}, timestamp_ms: {:?}, size: {:?} }}",
+            self.version,
+            self.timestamp_ms,
+            self.value.as_ref().map(|v| v.len())
+        )
+    }
+}
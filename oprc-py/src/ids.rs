@@ -0,0 +1,154 @@
+use pyo3::{
+    PyTypeInfo,
+    exceptions::{PyTypeError, PyValueError},
+    intern,
+    prelude::*,
+    types::{PyBool, PyInt},
+};
+
+/// Id of a partition of a class. Wherever the Python API takes a partition
+/// id, it accepts a `PartitionId` or a plain `int` in range.
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(frozen, eq, ord, hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PartitionId(u32);
+
+/// Id of an object within its class. Wherever the Python API takes an object
+/// id, it accepts an `ObjectId` or a plain `int` in range.
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(frozen, eq, ord, hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectId(u64);
+
+/// Reads an id named `kind` from an `int`, or an instance of `Own`. Other
+/// types, including the other id type and `bool`, raise `TypeError`; values
+/// out of `0..=max` raise `ValueError` instead of wrapping around.
+fn checked<Own: PyTypeInfo, Other: PyTypeInfo>(
+    value: &Bound<'_, PyAny>,
+    kind: &str,
+    max: u64,
+) -> PyResult<u64> {
+    let type_name = || {
+        value
+            .get_type()
+            .name()
+            .map(|n| n.to_string())
+            .unwrap_or_default()
+    };
+    if value.is_instance_of::<PyBool>()
+        || value.is_instance_of::<Other>()
+        || !(value.is_instance_of::<PyInt>() || value.is_instance_of::<Own>())
+    {
+        return Err(PyTypeError::new_err(format!(
+            "{} must be an int, got {}",
+            kind,
+            type_name()
+        )));
+    }
+    let value = if value.is_instance_of::<Own>() {
+        value.call_method0(intern!(value.py(), "__index__"))?
+    } else {
+        value.clone()
+    };
+    let id = value.extract::<i128>().map_err(|_| {
+        PyValueError::new_err(format!("{} {} is out of range 0..={}", kind, value, max))
+    })?;
+    if id < 0 {
+        return Err(PyValueError::new_err(format!(
+            "{} must not be negative, got {}",
+            kind, id
+        )));
+    }
+    if id > i128::from(max) {
+        return Err(PyValueError::new_err(format!(
+            "{} {} is out of range 0..={}",
+            kind, id, max
+        )));
+    }
+    Ok(id as u64)
+}
+
+/// Extracts a partition id argument; see [`PartitionId`].
+pub(crate) fn partition_id(value: &Bound<'_, PyAny>) -> PyResult<u32> {
+    checked::<PartitionId, ObjectId>(value, "partition_id", u64::from(u32::MAX)).map(|id| id as u32)
+}
+
+/// Extracts an object id argument; see [`ObjectId`].
+pub(crate) fn object_id(value: &Bound<'_, PyAny>) -> PyResult<u64> {
+    checked::<ObjectId, PartitionId>(value, "object_id", u64::MAX)
+}
+
+/// Extracts an optional object id argument.
+pub(crate) fn optional_object_id(value: &Bound<'_, PyAny>) -> PyResult<Option<u64>> {
+    if value.is_none() {
+        Ok(None)
+    } else {
+        object_id(value).map(Some)
+    }
+}
+
+/// Extracts a sequence of object ids.
+pub(crate) fn object_ids(value: &Bound<'_, PyAny>) -> PyResult<Vec<u64>> {
+    value.try_iter()?.map(|id| object_id(&id?)).collect()
+}
+
+/// Extracts a sequence of partition ids.
+pub(crate) fn partition_ids(value: &Bound<'_, PyAny>) -> PyResult<Vec<u32>> {
+    value.try_iter()?.map(|id| partition_id(&id?)).collect()
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl PartitionId {
+    #[new]
+    /// Creates a `PartitionId`, raising `ValueError` if `value` is negative
+    /// or does not fit in 32 bits.
+    pub fn new(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        partition_id(value).map(PartitionId)
+    }
+
+    #[getter]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    fn __int__(&self) -> u32 {
+        self.0
+    }
+
+    fn __index__(&self) -> u32 {
+        self.0
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PartitionId({})", self.0)
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl ObjectId {
+    #[new]
+    /// Creates an `ObjectId`, raising `ValueError` if `value` is negative or
+    /// does not fit in 64 bits.
+    pub fn new(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        object_id(value).map(ObjectId)
+    }
+
+    #[getter]
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    fn __int__(&self) -> u64 {
+        self.0
+    }
+
+    fn __index__(&self) -> u64 {
+        self.0
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ObjectId({})", self.0)
+    }
+}
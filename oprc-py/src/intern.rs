@@ -0,0 +1,32 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+/// Most distinct strings kept. Class and function ids are few; past this the
+/// ids are likely caller-controlled, so new ones are no longer retained.
+const MAX_ENTRIES: usize = 4096;
+
+fn registry() -> &'static RwLock<HashSet<Arc<str>>> {
+    static REGISTRY: OnceLock<RwLock<HashSet<Arc<str>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Returns a shared copy of `s`, such as a class or function id.
+///
+/// Each distinct string is allocated once; later calls only bump a reference
+/// count, so per-invocation bookkeeping keyed by ids does not allocate.
+pub(crate) fn intern(s: &str) -> Arc<str> {
+    if let Some(interned) = registry().read().unwrap().get(s) {
+        return interned.clone();
+    }
+    let mut registry = registry().write().unwrap();
+    if let Some(interned) = registry.get(s) {
+        return interned.clone();
+    }
+    let interned: Arc<str> = Arc::from(s);
+    if registry.len() < MAX_ENTRIES {
+        registry.insert(interned.clone());
+    }
+    interned
+}
@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+use pyo3::{
+    FromPyObject, PyResult,
+    exceptions::{PyKeyError, PyValueError},
+    pyfunction,
+};
+
+/// Per-class mapping of entry names to the compact `u32` keys used on the wire.
+static REGISTRY: OnceLock<RwLock<HashMap<String, HashMap<String, u32>>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<String, HashMap<String, u32>>> {
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// An entry key given either as its wire index or as a registered name.
+#[derive(FromPyObject, Clone, PartialEq, Eq, Hash)]
+pub enum EntryKey {
+    Index(u32),
+    Name(String),
+}
+
+#[cfg(feature = "stub-gen")]
+impl pyo3_stub_gen::PyStubType for EntryKey {
+    fn type_output() -> pyo3_stub_gen::TypeInfo {
+        <u32 as pyo3_stub_gen::PyStubType>::type_output()
+            | <String as pyo3_stub_gen::PyStubType>::type_output()
+    }
+}
+
+impl EntryKey {
+    /// Resolves this key to its wire index using the registry of `cls_id`.
+    pub fn resolve(&self, cls_id: &str) -> PyResult<u32> {
+        match self {
+            EntryKey::Index(i) => Ok(*i),
+            EntryKey::Name(name) => lookup(cls_id, name).ok_or_else(|| {
+                PyKeyError::new_err(format!(
+                    "Unknown entry key '{}' for class '{}'",
+                    name, cls_id
+                ))
+            }),
+        }
+    }
+}
+
+/// Looks up the wire index of `name` for `cls_id`.
+pub(crate) fn lookup(cls_id: &str, name: &str) -> Option<u32> {
+    registry()
+        .read()
+        .unwrap()
+        .get(cls_id)
+        .and_then(|keys| keys.get(name).copied())
+}
+
+/// Returns the registered name to index mapping of `cls_id`, if any.
+pub(crate) fn names_of(cls_id: &str) -> Option<HashMap<String, u32>> {
+    registry().read().unwrap().get(cls_id).cloned()
+}
+
+/// Registers entry names for a class, so `ObjectData` and the data APIs accept
+/// `obj["profile"]` while the wire keeps compact integer keys.
+///
+/// Names are merged into any existing registration. Raises `ValueError` if two
+/// names would map to the same index or a name is re-registered with a different index.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn register_entry_keys(cls_id: String, keys: HashMap<String, u32>) -> PyResult<()> {
+    let mut registry = registry().write().unwrap();
+    let existing = registry.entry(cls_id.clone()).or_default();
+    let mut merged = existing.clone();
+    for (name, index) in keys {
+        if let Some(prev) = merged.get(&name) {
+            if *prev != index {
+                return Err(PyValueError::new_err(format!(
+                    "Entry key '{}' of class '{}' is already registered as {}",
+                    name, cls_id, prev
+                )));
+            }
+            continue;
+        }
+        if let Some((other, _)) = merged.iter().find(|(_, i)| **i == index) {
+            return Err(PyValueError::new_err(format!(
+                "Entry index {} of class '{}' is already registered as '{}'",
+                index, cls_id, other
+            )));
+        }
+        merged.insert(name, index);
+    }
+    *existing = merged;
+    Ok(())
+}
+
+/// Returns the registered entry names of a class (empty if none).
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn entry_keys(cls_id: &str) -> HashMap<String, u32> {
+    names_of(cls_id).unwrap_or_default()
+}
@@ -1,11 +1,56 @@
 use pyo3::prelude::*;
+mod audit;
+#[doc(hidden)]
+pub mod bench;
+mod blocking;
+mod call_log;
+mod capabilities;
+mod catalog;
+mod checkpoint;
+mod checksum;
+mod chunks;
+mod codec;
+mod compat;
+mod compression;
+mod dispatch;
+mod doctor;
+mod e2e;
 mod engine;
+mod envelope;
+mod errors;
+#[cfg(feature = "arrow")]
+mod export;
+mod firewall;
+mod flags;
+mod gc;
+mod handoff;
+mod history;
+mod ids;
 mod handler;
+mod intern;
+mod keys;
+mod memory;
+mod metrics;
 mod model;
 mod data;
+mod data_stats;
+mod deadline;
 mod rpc;
+mod session_events;
+mod shutdown;
 mod obj;
+mod payload_limit;
+mod partitions;
+mod proxy;
+mod quota;
+mod replica;
+mod request_builder;
+mod scan;
+mod standby;
+mod sticky;
 pub mod telemetry;
+mod timefmt;
+mod warmup;
 use engine::OaasEngine;
 use tracing_subscriber::util::SubscriberInitExt;
 
@@ -36,12 +81,48 @@ fn init_logger(level: &str, raise_error: bool) -> PyResult<()> {
 #[pymodule(gil_used = false)]
 fn oprc_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(init_logger, m)?)?;
+    m.add_function(wrap_pyfunction!(rpc::set_default_timeout, m)?)?;
+    m.add_function(wrap_pyfunction!(rpc::get_default_timeout, m)?)?;
+    m.add_function(wrap_pyfunction!(keys::register_entry_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(keys::entry_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(doctor::doctor, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::register_payload_type, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::payload_types, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::pack_any, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::unpack_any, m)?)?;
+    m.add_function(wrap_pyfunction!(envelope::decode_any, m)?)?;
+    m.add_function(wrap_pyfunction!(flags::feature_enabled, m)?)?;
+    m.add_function(wrap_pyfunction!(flags::set_feature_flag, m)?)?;
+    m.add_function(wrap_pyfunction!(flags::feature_flags, m)?)?;
+    m.add_function(wrap_pyfunction!(proxy::set_proxy, m)?)?;
+    m.add_function(wrap_pyfunction!(memory::set_memory_budget, m)?)?;
+    m.add_function(wrap_pyfunction!(memory::memory_budget, m)?)?;
+    m.add_function(wrap_pyfunction!(e2e::generate_encryption_key, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::verify_audit_log, m)?)?;
+    m.add_function(wrap_pyfunction!(timefmt::py_format_timestamp, m)?)?;
+    m.add_function(wrap_pyfunction!(timefmt::py_parse_timestamp, m)?)?;
+    m.add_function(wrap_pyfunction!(timefmt::py_format_duration, m)?)?;
+    m.add_function(wrap_pyfunction!(timefmt::py_parse_duration, m)?)?;
+    m.add_function(wrap_pyfunction!(shutdown::shutdown, m)?)?;
     // Telemetry helpers
     #[pyfunction]
     fn init_telemetry_py(service_name: Option<String>, service_version: Option<String>) {
         telemetry::init(service_name, service_version);
     }
     #[pyfunction]
+    #[pyo3(signature = (endpoint=None, protocol=None, sampler=None, sampler_arg=None, service_name=None, service_version=None))]
+    fn reinit_telemetry_py(
+        endpoint: Option<String>,
+        protocol: Option<String>,
+        sampler: Option<String>,
+        sampler_arg: Option<f64>,
+        service_name: Option<String>,
+        service_version: Option<String>,
+    ) -> PyResult<()> {
+        telemetry::reinit(endpoint, protocol, sampler, sampler_arg, service_name, service_version)
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+    #[pyfunction]
     fn forward_log_py(level: u32, message: String, module: Option<String>, line: Option<u32>, thread: Option<String>) {
         telemetry::forward_log(level, message, module, line, thread);
     }
@@ -49,16 +130,66 @@ fn oprc_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     fn shutdown_telemetry_py() {
         telemetry::shutdown();
     }
+    #[pyfunction]
+    fn telemetry_stats_py() -> telemetry::TelemetryStats {
+        telemetry::stats()
+    }
     m.add_function(wrap_pyfunction!(init_telemetry_py, m)?)?;
+    m.add_function(wrap_pyfunction!(reinit_telemetry_py, m)?)?;
     m.add_function(wrap_pyfunction!(forward_log_py, m)?)?;
     m.add_function(wrap_pyfunction!(shutdown_telemetry_py, m)?)?;
+    m.add_function(wrap_pyfunction!(telemetry_stats_py, m)?)?;
+    m.add_class::<telemetry::TelemetryStats>()?;
     m.add_class::<OaasEngine>()?;
     m.add_class::<data::DataManager>()?;
     m.add_class::<rpc::RpcManager>()?;
+    m.add_class::<rpc::RetryBudgetStats>()?;
+    m.add_class::<rpc::InvocationStats>()?;
+    m.add_class::<rpc::InvocationPlan>()?;
+    m.add_class::<rpc::ClassDefaults>()?;
+    m.add_class::<rpc::RetryDecision>()?;
+    m.add_class::<rpc::LoadBalancing>()?;
+    m.add_class::<rpc::LoadReport>()?;
+    m.add_class::<rpc::ResponseStream>()?;
+    m.add_class::<rpc::ObjectResultStream>()?;
+    m.add("CircuitOpenError", m.py().get_type::<rpc::CircuitOpenError>())?;
+    m.add("PayloadTooLarge", m.py().get_type::<payload_limit::PayloadTooLarge>())?;
+    m.add_class::<checksum::ChecksumAlgorithm>()?;
+    m.add_class::<blocking::BlockingPool>()?;
+    m.add_class::<checkpoint::CheckpointStore>()?;
+    m.add_class::<blocking::BlockingPoolStats>()?;
+    m.add_class::<handoff::HandoffReport>()?;
+    m.add_class::<audit::AuditReport>()?;
+    m.add_class::<replica::ReadPreference>()?;
+    m.add_class::<replica::ReadResult>()?;
+    m.add_class::<history::EntryVersion>()?;
+    m.add_class::<ids::PartitionId>()?;
+    m.add_class::<ids::ObjectId>()?;
+    m.add_class::<gc::GcReport>()?;
+    m.add_class::<shutdown::ShutdownReport>()?;
+    #[cfg(feature = "arrow")]
+    m.add_class::<export::ExportReport>()?;
     m.add_class::<model::InvocationRequest>()?;
     m.add_class::<model::InvocationResponseCode>()?;
+    m.add_class::<errors::ErrorCode>()?;
     m.add_class::<model::InvocationResponse>()?;
+    m.add_class::<model::DetailedInvocationResponse>()?;
+    m.add_class::<model::InvocationAttempt>()?;
     m.add_class::<model::ObjectInvocationRequest>()?;
+    m.add_class::<request_builder::ObjectInvocationRequestBuilder>()?;
+    m.add_class::<model::MultiObjectInvocationRequest>()?;
+    m.add_class::<quota::TenantUsage>()?;
+    m.add_class::<memory::MemoryBudgetStats>()?;
+    m.add_class::<metrics::ServerMetricsSnapshot>()?;
+    m.add_class::<metrics::FunctionStats>()?;
+    m.add_class::<metrics::ExceptionStats>()?;
+    m.add_class::<metrics::HotObject>()?;
+    m.add_class::<metrics::MetricPoint>()?;
+    m.add_class::<call_log::CallRecord>()?;
+    m.add_class::<data_stats::DataStats>()?;
+    m.add_class::<dispatch::HttpDispatcher>()?;
+    m.add_class::<doctor::DoctorCheck>()?;
+    m.add_class::<doctor::DoctorReport>()?;
     m.add_class::<obj::ObjectMetadata>()?; 
     m.add_class::<obj::ObjectData>()?;  
     m.add_class::<obj::PyObjectEvent>()?; 
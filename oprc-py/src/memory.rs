@@ -0,0 +1,194 @@
+use std::sync::{
+    OnceLock,
+    atomic::{AtomicU64, Ordering},
+};
+
+use pyo3::pyfunction;
+use tokio::sync::Notify;
+
+/// Environment variable seeding the memory budget, in bytes.
+const MEMORY_BUDGET_ENV: &str = "OPRC_MEMORY_BUDGET_BYTES";
+
+/// Process-wide budget for payloads buffered in the Rust layer: payloads of
+/// outgoing invocations until they complete, and payloads of incoming ones,
+/// chunked ones included, until the handler returns.
+struct Budget {
+    /// Limit in bytes; `0` means unlimited.
+    max_bytes: AtomicU64,
+    in_use: AtomicU64,
+    peak: AtomicU64,
+    rejected: AtomicU64,
+    /// Wakes callers waiting for bytes to be released.
+    released: Notify,
+}
+
+fn budget() -> &'static Budget {
+    static BUDGET: OnceLock<Budget> = OnceLock::new();
+    BUDGET.get_or_init(|| Budget {
+        max_bytes: AtomicU64::new(
+            std::env::var(MEMORY_BUDGET_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+        ),
+        in_use: AtomicU64::new(0),
+        peak: AtomicU64::new(0),
+        rejected: AtomicU64::new(0),
+        released: Notify::new(),
+    })
+}
+
+impl Budget {
+    /// Takes `bytes` from the budget if they fit.
+    fn take(&self, bytes: u64) -> bool {
+        let max = self.max_bytes.load(Ordering::Relaxed);
+        let taken = self
+            .in_use
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                let next = used.saturating_add(bytes);
+                (max == 0 || next <= max).then_some(next)
+            });
+        match taken {
+            Ok(used) => {
+                self.peak.fetch_max(used + bytes, Ordering::Relaxed);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn exhausted(&self, bytes: u64, what: &str) -> String {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        format!(
+            "memory budget exhausted: {} needs {} bytes, {} of {} in use",
+            what,
+            bytes,
+            self.in_use.load(Ordering::Relaxed),
+            self.max_bytes.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// Bytes taken from the memory budget, given back when dropped.
+#[derive(Default)]
+pub(crate) struct Reservation(u64);
+
+impl Reservation {
+    /// Takes `bytes` more for this reservation, failing right away if the
+    /// budget cannot cover them.
+    pub(crate) fn grow(&mut self, bytes: u64, what: &str) -> Result<(), String> {
+        let budget = budget();
+        if !budget.take(bytes) {
+            return Err(budget.exhausted(bytes, what));
+        }
+        self.0 += bytes;
+        Ok(())
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.0 > 0 {
+            let budget = budget();
+            budget.in_use.fetch_sub(self.0, Ordering::AcqRel);
+            budget.released.notify_waiters();
+        }
+    }
+}
+
+/// Bytes the budget can still cover, or `None` if it is unlimited.
+pub(crate) fn available() -> Option<u64> {
+    let budget = budget();
+    match budget.max_bytes.load(Ordering::Relaxed) {
+        0 => None,
+        max => Some(max.saturating_sub(budget.in_use.load(Ordering::Relaxed))),
+    }
+}
+
+/// Counts `what` as rejected for needing more than `bytes`, and describes
+/// the rejection.
+pub(crate) fn exhausted(bytes: u64, what: &str) -> String {
+    budget().exhausted(bytes, what)
+}
+
+/// Reserves `bytes` for `what`, failing right away if the budget cannot
+/// cover them.
+pub(crate) fn try_reserve(bytes: u64, what: &str) -> Result<Reservation, String> {
+    let mut reservation = Reservation::default();
+    reservation.grow(bytes, what)?;
+    Ok(reservation)
+}
+
+/// Reserves `bytes` for `what`, waiting while other reservations hold the
+/// budget. Fails right away only if `bytes` exceed the whole budget.
+pub(crate) async fn reserve(bytes: u64, what: &str) -> Result<Reservation, String> {
+    let budget = budget();
+    loop {
+        let released = budget.released.notified();
+        if budget.take(bytes) {
+            return Ok(Reservation(bytes));
+        }
+        let max = budget.max_bytes.load(Ordering::Relaxed);
+        if max != 0 && bytes > max {
+            return Err(budget.exhausted(bytes, what));
+        }
+        released.await;
+    }
+}
+
+/// Sets the process-wide budget for payloads buffered in the Rust layer, in
+/// bytes. `None` or `0` removes the limit.
+///
+/// Outgoing invocations wait for their payload to fit, within their timeout.
+/// Incoming invocations that do not fit are answered with
+/// `InvocationResponseCode.ResourceExhausted`.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+#[pyo3(signature = (max_bytes=None))]
+pub fn set_memory_budget(max_bytes: Option<u64>) {
+    let budget = budget();
+    budget
+        .max_bytes
+        .store(max_bytes.unwrap_or(0), Ordering::Relaxed);
+    budget.released.notify_waiters();
+}
+
+/// Usage of the memory budget, as returned by `memory_budget()`.
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+pub struct MemoryBudgetStats {
+    /// The limit in bytes, or `None` if unlimited.
+    pub max_bytes: Option<u64>,
+    /// Bytes of buffered payloads right now.
+    pub in_use_bytes: u64,
+    /// Highest `in_use_bytes` seen since the process started.
+    pub peak_bytes: u64,
+    /// Payloads rejected because the budget could not cover them.
+    pub rejected: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl MemoryBudgetStats {
+    fn __str__(&self) -> String {
+        format!(
+            "MemoryBudgetStats {{ max_bytes: {:?}, in_use_bytes: {}, peak_bytes: {}, rejected: {} }}",
+            self.max_bytes, self.in_use_bytes, self.peak_bytes, self.rejected
+        )
+    }
+}
+
+/// Returns the limit and usage of the memory budget.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn memory_budget() -> MemoryBudgetStats {
+    let budget = budget();
+    let max = budget.max_bytes.load(Ordering::Relaxed);
+    MemoryBudgetStats {
+        max_bytes: (max != 0).then_some(max),
+        in_use_bytes: budget.in_use.load(Ordering::Relaxed),
+        peak_bytes: budget.peak.load(Ordering::Relaxed),
+        rejected: budget.rejected.load(Ordering::Relaxed),
+    }
+}
@@ -0,0 +1,566 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::AddAssign,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use pyo3::Python;
+use serde_json::json;
+
+use crate::intern::intern;
+
+/// Environment variable enabling metrics snapshots at the given path.
+pub const SNAPSHOT_PATH_ENV: &str = "OPRC_METRICS_SNAPSHOT";
+
+// Names of the metrics reported by `export_metrics()`. They are part of the
+// API: dashboards and alerts built on them must keep working across SDK
+// releases, so they are only ever added to, never renamed.
+
+/// Invocations of a function whose Python callback is running.
+pub const SERVER_INFLIGHT: &str = "oprc.server.inflight";
+/// Invocations of a function received but whose Python callback has not
+/// started yet: being admitted or waiting for the GIL.
+pub const SERVER_QUEUE_DEPTH: &str = "oprc.server.queue_depth";
+/// Latency of the invocations of a function sent by a client, in
+/// milliseconds, reported per `quantile`.
+pub const CLIENT_LATENCY: &str = "oprc.client.latency";
+
+/// Label of the class id on per-function metrics.
+pub const CLS_LABEL: &str = "cls";
+/// Label of the function id on per-function metrics.
+pub const FN_LABEL: &str = "fn";
+/// Label of the quantile a latency value is of, e.g. `0.99`.
+pub const QUANTILE_LABEL: &str = "quantile";
+
+/// Cumulative counters of a serving process, optionally persisted to a local
+/// file so short-lived workers still report lifetime totals.
+#[derive(Default)]
+pub struct ServerMetrics {
+    invocations: AtomicU64,
+    errors: AtomicU64,
+    restored_invocations: AtomicU64,
+    restored_errors: AtomicU64,
+    gil_wait_ns: AtomicU64,
+    gil_held_ns: AtomicU64,
+    max_gil_held_ns: AtomicU64,
+    /// Per `(cls_id, fn_id)` counters, filled by the `metrics` interceptor.
+    /// Keys are interned so recording does not allocate.
+    functions: Mutex<HashMap<(Arc<str>, Arc<str>), FunctionCounters>>,
+    /// Queued and running invocations per `(cls_id, fn_id)`.
+    occupancy: Mutex<HashMap<(Arc<str>, Arc<str>), Arc<Occupancy>>>,
+    /// Failed invocations per `(cls_id, fn_id, exception type)`.
+    exceptions: Mutex<HashMap<(Arc<str>, Arc<str>, Arc<str>), u64>>,
+    /// Recent object invocations per class, if object tracking is enabled.
+    objects: Mutex<Option<ObjectTracker>>,
+    snapshot_path: Mutex<Option<PathBuf>>,
+}
+
+/// The last `window` object invocations of each class, with per-object counts
+/// over that window, so the hottest objects reflect recent traffic.
+struct ObjectTracker {
+    window: usize,
+    classes: HashMap<String, ObjectWindow>,
+}
+
+#[derive(Default)]
+struct ObjectWindow {
+    ring: VecDeque<u64>,
+    counts: HashMap<u64, u64>,
+}
+
+impl ObjectWindow {
+    fn push(&mut self, object_id: u64, window: usize) {
+        while self.ring.len() >= window {
+            let Some(evicted) = self.ring.pop_front() else {
+                break;
+            };
+            if let Some(count) = self.counts.get_mut(&evicted) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&evicted);
+                }
+            }
+        }
+        self.ring.push_back(object_id);
+        *self.counts.entry(object_id).or_default() += 1;
+    }
+}
+
+#[derive(Default)]
+struct FunctionCounters {
+    invocations: u64,
+    errors: u64,
+    total_latency: Duration,
+    max_latency: Duration,
+}
+
+/// Gauges of the invocations of one function currently being handled.
+#[derive(Default)]
+struct Occupancy {
+    queued: AtomicU64,
+    inflight: AtomicU64,
+}
+
+/// An invocation counted in the gauges of its function: queued until
+/// `start` is called, then in flight until dropped.
+pub(crate) struct Slot {
+    occupancy: Arc<Occupancy>,
+    running: AtomicBool,
+}
+
+impl Slot {
+    /// Moves the invocation from the queue to the in-flight invocations, once.
+    pub(crate) fn start(&self) {
+        if !self.running.swap(true, Ordering::Relaxed) {
+            self.occupancy.queued.fetch_sub(1, Ordering::Relaxed);
+            self.occupancy.inflight.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let gauge = if self.running.load(Ordering::Relaxed) {
+            &self.occupancy.inflight
+        } else {
+            &self.occupancy.queued
+        };
+        gauge.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ServerMetrics {
+    /// Counts a received invocation of `cls_id/fn_id` as queued until the
+    /// returned slot is started or dropped.
+    pub(crate) fn enqueue(&self, cls_id: &Arc<str>, fn_id: &Arc<str>) -> Slot {
+        let occupancy = self
+            .occupancy
+            .lock()
+            .unwrap()
+            .entry((cls_id.clone(), fn_id.clone()))
+            .or_default()
+            .clone();
+        occupancy.queued.fetch_add(1, Ordering::Relaxed);
+        Slot {
+            occupancy,
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the `oprc.server.inflight` and `oprc.server.queue_depth`
+    /// gauges of every function invoked since this process started, sorted
+    /// by class and function.
+    pub fn export(&self) -> Vec<MetricPoint> {
+        let occupancy = self.occupancy.lock().unwrap();
+        let mut functions: Vec<_> = occupancy.iter().collect();
+        functions.sort_by(|a, b| a.0.cmp(b.0));
+        functions
+            .into_iter()
+            .flat_map(|((cls_id, fn_id), o)| {
+                [
+                    (SERVER_INFLIGHT, &o.inflight),
+                    (SERVER_QUEUE_DEPTH, &o.queued),
+                ]
+                .map(|(name, gauge)| {
+                    MetricPoint::function(name, cls_id, fn_id, gauge.load(Ordering::Relaxed) as f64)
+                })
+            })
+            .collect()
+    }
+
+    /// Records one handled invocation and the GIL time it took.
+    pub fn record(&self, is_error: bool, gil: GilTiming) {
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+        if is_error {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let held = gil.held.as_nanos() as u64;
+        self.gil_wait_ns
+            .fetch_add(gil.wait.as_nanos() as u64, Ordering::Relaxed);
+        self.gil_held_ns.fetch_add(held, Ordering::Relaxed);
+        self.max_gil_held_ns.fetch_max(held, Ordering::Relaxed);
+    }
+
+    /// Records one invocation of `cls_id/fn_id` and its latency.
+    pub fn record_function(&self, cls_id: &str, fn_id: &str, is_error: bool, latency: Duration) {
+        let mut functions = self.functions.lock().unwrap();
+        let counters = functions
+            .entry((intern(cls_id), intern(fn_id)))
+            .or_default();
+        counters.invocations += 1;
+        if is_error {
+            counters.errors += 1;
+        }
+        counters.total_latency += latency;
+        counters.max_latency = counters.max_latency.max(latency);
+    }
+
+    /// Records one invocation of `cls_id/fn_id` that failed with a Python
+    /// exception of class `exception_type`.
+    pub fn record_exception(&self, cls_id: &str, fn_id: &str, exception_type: &str) {
+        let mut exceptions = self.exceptions.lock().unwrap();
+        *exceptions
+            .entry((intern(cls_id), intern(fn_id), intern(exception_type)))
+            .or_default() += 1;
+    }
+
+    /// Returns the failure counts per exception type, sorted by class,
+    /// function and exception type.
+    pub fn exception_stats(&self) -> Vec<ExceptionStats> {
+        let exceptions = self.exceptions.lock().unwrap();
+        let mut stats: Vec<ExceptionStats> = exceptions
+            .iter()
+            .map(|((cls_id, fn_id, exception_type), count)| ExceptionStats {
+                cls_id: cls_id.to_string(),
+                fn_id: fn_id.to_string(),
+                exception_type: exception_type.to_string(),
+                count: *count,
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            (&a.cls_id, &a.fn_id, &a.exception_type).cmp(&(&b.cls_id, &b.fn_id, &b.exception_type))
+        });
+        stats
+    }
+
+    /// Starts counting object invocations over the last `window` invocations of
+    /// each class, discarding previous counts. A `window` of `0` disables it.
+    pub fn track_objects(&self, window: usize) {
+        *self.objects.lock().unwrap() = (window > 0).then(|| ObjectTracker {
+            window,
+            classes: HashMap::new(),
+        });
+    }
+
+    /// Records one invocation of object `object_id` of `cls_id`, if object
+    /// tracking is enabled.
+    pub fn record_object(&self, cls_id: &str, object_id: u64) {
+        let mut objects = self.objects.lock().unwrap();
+        let Some(tracker) = objects.as_mut() else {
+            return;
+        };
+        let window = tracker.window;
+        match tracker.classes.get_mut(cls_id) {
+            Some(w) => w.push(object_id, window),
+            None => tracker
+                .classes
+                .entry(cls_id.to_string())
+                .or_default()
+                .push(object_id, window),
+        }
+    }
+
+    /// Returns the `k` most invoked objects of each class (or only of
+    /// `cls_id`) within the tracking window, hottest first.
+    pub fn hot_objects(&self, cls_id: Option<&str>, k: usize) -> Vec<HotObject> {
+        let objects = self.objects.lock().unwrap();
+        let Some(tracker) = objects.as_ref() else {
+            return Vec::new();
+        };
+        let mut classes: Vec<_> = tracker
+            .classes
+            .iter()
+            .filter(|(cls, _)| cls_id.is_none_or(|c| c == cls.as_str()))
+            .collect();
+        classes.sort_by(|a, b| a.0.cmp(b.0));
+        let mut hot = Vec::new();
+        for (cls, window) in classes {
+            let mut counts: Vec<_> = window.counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let total = window.ring.len().max(1) as f64;
+            hot.extend(counts.into_iter().take(k).map(|(id, n)| HotObject {
+                cls_id: cls.clone(),
+                object_id: *id,
+                invocations: *n,
+                share: *n as f64 / total,
+            }));
+        }
+        hot
+    }
+
+    /// Returns the per-function counters, sorted by class and function.
+    pub fn function_stats(&self) -> Vec<FunctionStats> {
+        let functions = self.functions.lock().unwrap();
+        let mut stats: Vec<FunctionStats> = functions
+            .iter()
+            .map(|((cls_id, fn_id), c)| FunctionStats {
+                cls_id: cls_id.to_string(),
+                fn_id: fn_id.to_string(),
+                invocations: c.invocations,
+                errors: c.errors,
+                avg_latency_ms: c.total_latency.as_secs_f64() * 1000.0
+                    / c.invocations.max(1) as f64,
+                max_latency_ms: c.max_latency.as_secs_f64() * 1000.0,
+            })
+            .collect();
+        stats.sort_by(|a, b| (&a.cls_id, &a.fn_id).cmp(&(&b.cls_id, &b.fn_id)));
+        stats
+    }
+
+    /// Sets the snapshot file and restores the lifetime counters from it if it exists.
+    pub fn enable_snapshot(&self, path: PathBuf) -> std::io::Result<()> {
+        if path.exists() {
+            let raw = std::fs::read(&path)?;
+            let value: serde_json::Value = serde_json::from_slice(&raw)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let read = |k: &str| value.get(k).and_then(|v| v.as_u64()).unwrap_or(0);
+            self.restored_invocations
+                .store(read("invocations"), Ordering::Relaxed);
+            self.restored_errors
+                .store(read("errors"), Ordering::Relaxed);
+        }
+        *self.snapshot_path.lock().unwrap() = Some(path);
+        Ok(())
+    }
+
+    /// Writes the lifetime counters to the snapshot file, if one is configured.
+    ///
+    /// Returns `Ok(false)` when snapshots are disabled.
+    pub fn save_snapshot(&self) -> std::io::Result<bool> {
+        let Some(path) = self.snapshot_path.lock().unwrap().clone() else {
+            return Ok(false);
+        };
+        let snapshot = self.snapshot();
+        let saved_at_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let body = json!({
+            "invocations": snapshot.lifetime_invocations,
+            "errors": snapshot.lifetime_errors,
+            "saved_at_ms": saved_at_ms,
+        });
+        write_atomically(&path, body.to_string().as_bytes())?;
+        Ok(true)
+    }
+
+    pub fn snapshot(&self) -> ServerMetricsSnapshot {
+        let invocations = self.invocations.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+        ServerMetricsSnapshot {
+            invocations,
+            errors,
+            lifetime_invocations: invocations + self.restored_invocations.load(Ordering::Relaxed),
+            lifetime_errors: errors + self.restored_errors.load(Ordering::Relaxed),
+            gil_wait_us: self.gil_wait_ns.load(Ordering::Relaxed) / 1_000,
+            gil_held_us: self.gil_held_ns.load(Ordering::Relaxed) / 1_000,
+            max_gil_held_us: self.max_gil_held_ns.load(Ordering::Relaxed) / 1_000,
+        }
+    }
+}
+
+/// Time an invocation spent waiting for and holding the GIL.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GilTiming {
+    pub wait: Duration,
+    pub held: Duration,
+}
+
+impl AddAssign for GilTiming {
+    fn add_assign(&mut self, rhs: Self) {
+        self.wait += rhs.wait;
+        self.held += rhs.held;
+    }
+}
+
+/// Runs `f` with the GIL held, like `Python::attach`, and adds the time spent
+/// acquiring and holding it to `timing`.
+pub(crate) fn attach_timed<F, R>(timing: &mut GilTiming, f: F) -> R
+where
+    F: for<'py> FnOnce(Python<'py>) -> R,
+{
+    let requested = Instant::now();
+    Python::attach(|py| {
+        let acquired = Instant::now();
+        let res = f(py);
+        *timing += GilTiming {
+            wait: acquired - requested,
+            held: acquired.elapsed(),
+        };
+        res
+    })
+}
+
+/// Writes `bytes` to a temporary sibling file and renames it over `path`.
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Invocation counters of the servers started by an `OaasEngine`.
+pub struct ServerMetricsSnapshot {
+    /// Invocations handled since this process started.
+    pub invocations: u64,
+    /// Failed invocations (Python exception or non-`Okay` status) since this process started.
+    pub errors: u64,
+    /// Invocations handled including counts restored from the snapshot file.
+    pub lifetime_invocations: u64,
+    /// Failed invocations including counts restored from the snapshot file.
+    pub lifetime_errors: u64,
+    /// Total time handlers waited to acquire the GIL, in microseconds.
+    pub gil_wait_us: u64,
+    /// Total time handlers held the GIL, in microseconds. For async handlers this
+    /// covers request/response conversion but not the coroutine itself.
+    pub gil_held_us: u64,
+    /// Longest GIL hold of a single invocation, in microseconds.
+    pub max_gil_held_us: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl ServerMetricsSnapshot {
+    /// Returns a string representation of the `ServerMetricsSnapshot`.
+    fn __str__(&self) -> String {
+        format!(
+            "ServerMetricsSnapshot {{ invocations: {}, errors: {}, lifetime_invocations: {}, lifetime_errors: {}, gil_held_us: {}, gil_wait_us: {} }}",
+            self.invocations,
+            self.errors,
+            self.lifetime_invocations,
+            self.lifetime_errors,
+            self.gil_held_us,
+            self.gil_wait_us
+        )
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Invocation counters of a single served function.
+pub struct FunctionStats {
+    pub cls_id: String,
+    pub fn_id: String,
+    /// Invocations handled since this process started.
+    pub invocations: u64,
+    /// Failed invocations (Python exception or non-`Okay` status).
+    pub errors: u64,
+    /// Mean latency in milliseconds, measured from admission to response.
+    pub avg_latency_ms: f64,
+    /// Highest latency in milliseconds.
+    pub max_latency_ms: f64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl FunctionStats {
+    /// Returns a string representation of the `FunctionStats`.
+    fn __str__(&self) -> String {
+        format!(
+            "FunctionStats {{ {}/{}: invocations: {}, errors: {}, avg_latency_ms: {:.3}, max_latency_ms: {:.3} }}",
+            self.cls_id,
+            self.fn_id,
+            self.invocations,
+            self.errors,
+            self.avg_latency_ms,
+            self.max_latency_ms
+        )
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Failures of a served function with a given Python exception class.
+pub struct ExceptionStats {
+    pub cls_id: String,
+    pub fn_id: String,
+    /// Class name of the exception, e.g. `ValidationError`.
+    pub exception_type: String,
+    /// Invocations that failed with it since this process started.
+    pub count: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl ExceptionStats {
+    /// Returns a string representation of the `ExceptionStats`.
+    fn __str__(&self) -> String {
+        format!(
+            "ExceptionStats {{ {}/{}: {}: {} }}",
+            self.cls_id, self.fn_id, self.exception_type, self.count
+        )
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// An object among the most invoked of its class within the tracking window.
+pub struct HotObject {
+    pub cls_id: String,
+    pub object_id: u64,
+    /// Invocations of this object within the window.
+    pub invocations: u64,
+    /// Fraction of the class's invocations within the window that hit this object.
+    pub share: f64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl HotObject {
+    /// Returns a string representation of the `HotObject`.
+    fn __str__(&self) -> String {
+        format!(
+            "HotObject {{ {}/{}: invocations: {}, share: {:.3} }}",
+            self.cls_id, self.object_id, self.invocations, self.share
+        )
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// A value of a metric with a stable name, as returned by
+/// `export_metrics()`, ready to forward to a metrics backend.
+pub struct MetricPoint {
+    /// Name of the metric, e.g. `oprc.server.inflight`.
+    pub name: String,
+    /// Labels of the value: `cls` and `fn` for per-function metrics, plus
+    /// `quantile` for latencies.
+    pub labels: HashMap<String, String>,
+    pub value: f64,
+}
+
+impl MetricPoint {
+    /// A value of the per-function metric `name` for `cls_id/fn_id`.
+    pub(crate) fn function(name: &str, cls_id: &str, fn_id: &str, value: f64) -> Self {
+        MetricPoint {
+            name: name.to_string(),
+            labels: HashMap::from([
+                (CLS_LABEL.to_string(), cls_id.to_string()),
+                (FN_LABEL.to_string(), fn_id.to_string()),
+            ]),
+            value,
+        }
+    }
+
+    /// Adds the label `key` with `value`.
+    pub(crate) fn with_label(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.labels.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl MetricPoint {
+    /// Returns a string representation of the `MetricPoint`.
+    fn __str__(&self) -> String {
+        let mut labels: Vec<_> = self.labels.iter().collect();
+        labels.sort();
+        let labels: Vec<_> = labels
+            .into_iter()
+            .map(|(key, value)| format!("{}={:?}", key, value))
+            .collect();
+        format!("{}{{{}}} {}", self.name, labels.join(","), self.value)
+    }
+}
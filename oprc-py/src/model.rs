@@ -1,4 +1,60 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use pyo3::{Bound, PyAny, PyResult, Python, exceptions::PyValueError};
+
+use crate::codec;
+use crate::envelope::{self, TYPE_URL_OPTION};
+use crate::errors::ErrorCode;
+use crate::ids;
+use crate::request_builder::ObjectInvocationRequestBuilder;
+use crate::rpc::local::PREFER_LOCAL_OPTION;
+use crate::sticky::REPLICA_ID_OPTION;
+use crate::timefmt;
+
+/// Validates a single path segment of an invocation target string.
+fn validate_segment<'a>(kind: &str, value: &'a str, target: &str) -> PyResult<&'a str> {
+    if value.is_empty() {
+        return Err(PyValueError::new_err(format!(
+            "Invalid target '{}': {} must not be empty",
+            target, kind
+        )));
+    }
+    if let Some(c) = illegal_char(value) {
+        return Err(PyValueError::new_err(format!(
+            "Invalid target '{}': {} contains illegal character {:?}",
+            target, kind, c
+        )));
+    }
+    Ok(value)
+}
+
+/// The first character of `value` that a class or function id must not
+/// contain, as it would break the key expression the id is part of.
+pub(crate) fn illegal_char(value: &str) -> Option<char> {
+    value
+        .chars()
+        .find(|c| c.is_whitespace() || matches!(c, '*' | '$' | '#' | '?' | '&' | '='))
+}
+
+/// Parses a numeric segment of an invocation target string.
+fn parse_number<T: std::str::FromStr>(kind: &str, value: &str, target: &str) -> PyResult<T> {
+    validate_segment(kind, value, target)?;
+    if !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(PyValueError::new_err(format!(
+            "Invalid target '{}': {} must be a non-negative integer, got '{}'",
+            target, kind, value
+        )));
+    }
+    value.parse::<T>().map_err(|_| {
+        PyValueError::new_err(format!(
+            "Invalid target '{}': {} '{}' is out of range",
+            target, kind, value
+        ))
+    })
+}
 
 
 #[derive(Clone)]
@@ -11,20 +67,31 @@ pub struct InvocationRequest {
     pub fn_id: String,
     pub options: HashMap<String, String>,
     pub payload: Vec<u8>,
+    /// Per-request timeout in milliseconds; overrides the manager and module defaults.
+    pub timeout_ms: Option<u64>,
+    /// Per-request retry limit; overrides the manager's retry policy.
+    pub max_retries: Option<u32>,
+    /// Request headers, such as correlation ids, passed to the handler apart
+    /// from `options`.
+    pub headers: HashMap<String, String>,
 }
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
 #[pyo3::pymethods]
 impl InvocationRequest {
     #[new]
-    #[pyo3(signature = (cls_id, fn_id, partition_id=0, options=HashMap::new(), payload=vec![]))]
+    #[pyo3(signature = (cls_id, fn_id, partition_id=0, options=HashMap::new(), payload=vec![], timeout_ms=None, max_retries=None, headers=HashMap::new()))]
+    #[allow(clippy::too_many_arguments)]
     /// Creates a new `InvocationRequest`.
     pub fn new(
         cls_id: String,
         fn_id: String,
-        partition_id: u32,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
         options: HashMap<String, String>,
         payload: Vec<u8>,
+        timeout_ms: Option<u64>,
+        max_retries: Option<u32>,
+        headers: HashMap<String, String>,
     ) -> Self {
         InvocationRequest {
             partition_id,
@@ -32,26 +99,208 @@ impl InvocationRequest {
             fn_id,
             options,
             payload,
+            timeout_ms,
+            max_retries,
+            headers,
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (target, payload=vec![]))]
+    /// Parses an `InvocationRequest` from a target string of the form
+    /// `cls_id/fn_id` or `cls_id/fn_id?partition=<id>`.
+    ///
+    /// Raises `ValueError` if the target is malformed.
+    pub fn parse(target: &str, payload: Vec<u8>) -> PyResult<Self> {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (target, None),
+        };
+        let segments: Vec<&str> = path.split('/').collect();
+        if segments.len() != 2 {
+            return Err(PyValueError::new_err(format!(
+                "Invalid target '{}': expected 'cls_id/fn_id[?partition=<id>]'",
+                target
+            )));
+        }
+        let cls_id = validate_segment("cls_id", segments[0], target)?;
+        let fn_id = validate_segment("fn_id", segments[1], target)?;
+        let mut partition_id = 0;
+        if let Some(query) = query {
+            let mut seen_partition = false;
+            for pair in query.split('&') {
+                let (key, value) = pair.split_once('=').ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "Invalid target '{}': malformed query parameter '{}'",
+                        target, pair
+                    ))
+                })?;
+                match key {
+                    "partition" if !seen_partition => {
+                        partition_id = parse_number("partition", value, target)?;
+                        seen_partition = true;
+                    }
+                    "partition" => {
+                        return Err(PyValueError::new_err(format!(
+                            "Invalid target '{}': duplicate query parameter 'partition'",
+                            target
+                        )));
+                    }
+                    _ => {
+                        return Err(PyValueError::new_err(format!(
+                            "Invalid target '{}': unknown query parameter '{}'",
+                            target, key
+                        )));
+                    }
+                }
+            }
         }
+        Ok(InvocationRequest::new(
+            cls_id.to_string(),
+            fn_id.to_string(),
+            partition_id,
+            HashMap::new(),
+            payload,
+            None,
+            None,
+            HashMap::new(),
+        ))
+    }
+
+    /// Wraps `value` in a `google.protobuf.Any` as the payload and records its
+    /// type URL in the `oprc-type-url` option.
+    pub fn pack_payload(&mut self, type_url: &str, value: Vec<u8>) {
+        self.payload = envelope::pack(type_url, value);
+        self.options
+            .insert(TYPE_URL_OPTION.to_string(), type_url.to_string());
+    }
+
+    /// Decodes an `Any`-wrapped payload with the decoder registered for its type URL.
+    pub fn unpack_payload<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        envelope::decode(
+            py,
+            &self.payload,
+            self.options.get(TYPE_URL_OPTION).map(String::as_str),
+        )
+    }
+
+    /// The type URL of an `Any`-wrapped payload, if set.
+    #[getter]
+    pub fn payload_type_url(&self) -> Option<String> {
+        self.options.get(TYPE_URL_OPTION).cloned()
+    }
+
+    /// The claims of the caller's verified ID token, if the server runs the
+    /// `jwt` interceptor.
+    #[getter]
+    pub fn claims<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.options
+            .get(CLAIMS_OPTION)
+            .map(|raw| codec::decode_json(py, raw.as_bytes()))
+            .transpose()
+    }
+
+    /// Key expression to send response chunks to with
+    /// `RpcManager.send_chunk`, if the caller streams the response.
+    #[getter]
+    pub fn response_stream(&self) -> Option<String> {
+        self.options.get(RESPONSE_STREAM_OPTION).cloned()
+    }
+
+    /// Key expression to report progress to with
+    /// `RpcManager.report_progress`, if the caller listens for it.
+    #[getter]
+    pub fn progress(&self) -> Option<String> {
+        self.options.get(PROGRESS_OPTION).cloned()
+    }
+
+    /// Absolute deadline in Unix epoch milliseconds, kept in the
+    /// `oprc-deadline` option. `RpcManager` fails the invocation with
+    /// `TimeoutError` once it has passed and sends the time left, which
+    /// handlers rebase on their own clock and reject with `DeadlineExceeded`
+    /// once spent.
+    #[getter]
+    pub fn get_deadline_ms(&self) -> Option<u64> {
+        self.options.get(DEADLINE_OPTION).and_then(|v| v.parse().ok())
+    }
+
+    #[setter]
+    pub fn set_deadline_ms(&mut self, deadline_ms: Option<u64>) {
+        crate::deadline::set(&mut self.options, deadline_ms);
+    }
+
+    /// Key identifying the invocation across retries, sent in the
+    /// `oprc-idempotency-key` option. Handlers run an invocation once per
+    /// key and replay its response to duplicate deliveries.
+    #[getter]
+    pub fn get_idempotency_key(&self) -> Option<String> {
+        self.options.get(IDEMPOTENCY_KEY_OPTION).cloned()
+    }
+
+    #[setter]
+    pub fn set_idempotency_key(&mut self, key: Option<String>) {
+        match key {
+            Some(key) => {
+                self.options.insert(IDEMPOTENCY_KEY_OPTION.to_string(), key);
+            }
+            None => {
+                self.options.remove(IDEMPOTENCY_KEY_OPTION);
+            }
+        }
+    }
+
+    /// Stores `value` under `key` in the options as an ISO 8601 duration.
+    pub fn set_duration_option(&mut self, key: String, value: Duration) {
+        self.options.insert(key, timefmt::format_duration(value));
+    }
+
+    /// Reads the ISO 8601 duration stored under `key` as a `timedelta`.
+    /// Raises `ValueError` if the value is not a duration.
+    pub fn get_duration_option(&self, key: &str) -> PyResult<Option<Duration>> {
+        timefmt::get_duration(&self.options, key)
+    }
+
+    /// Stores `value` under `key` in the options as an RFC 3339 timestamp.
+    pub fn set_timestamp_option(&mut self, key: String, value: SystemTime) {
+        self.options.insert(key, timefmt::format_timestamp(value));
+    }
+
+    /// Reads the RFC 3339 timestamp stored under `key` as a UTC `datetime`.
+    /// Raises `ValueError` if the value is not a timestamp.
+    pub fn get_timestamp_option(&self, key: &str) -> PyResult<Option<SystemTime>> {
+        timefmt::get_timestamp(&self.options, key)
     }
 }
 
 impl InvocationRequest {
     /// Converts this `InvocationRequest` into its protobuf representation.
     pub fn into_proto(&self) -> oprc_pb::InvocationRequest {
+        let mut options = self.options.clone();
+        embed_headers(&mut options, &self.headers);
         oprc_pb::InvocationRequest {
             partition_id: self.partition_id,
             cls_id: self.cls_id.clone(),
             fn_id: self.fn_id.clone(),
-            options: self.options.clone(),
+            options,
             payload: self.payload.clone(),
         }
     }
+
+    /// Returns the per-request timeout in milliseconds, if set.
+    pub fn timeout_ms(&self) -> Option<u64> {
+        self.timeout_ms
+    }
+
+    /// Returns the per-request retry limit, if set.
+    pub fn max_retries(&self) -> Option<u32> {
+        self.max_retries
+    }
 }
 
 impl Into<oprc_pb::InvocationRequest> for InvocationRequest {
     /// Converts this `InvocationRequest` into its protobuf representation.
-    fn into(self) -> oprc_pb::InvocationRequest {
+    fn into(mut self) -> oprc_pb::InvocationRequest {
+        embed_headers(&mut self.options, &self.headers);
         oprc_pb::InvocationRequest {
             partition_id: self.partition_id,
             cls_id: self.cls_id,
@@ -64,13 +313,17 @@ impl Into<oprc_pb::InvocationRequest> for InvocationRequest {
 
 impl From<oprc_pb::InvocationRequest> for InvocationRequest {
     /// Creates an `InvocationRequest` from its protobuf representation.
-    fn from(value: oprc_pb::InvocationRequest) -> Self {
+    fn from(mut value: oprc_pb::InvocationRequest) -> Self {
+        let headers = take_headers(&mut value.options);
         InvocationRequest {
             partition_id: value.partition_id,
             cls_id: value.cls_id,
             fn_id: value.fn_id,
             options: value.options,
             payload: value.payload,
+            timeout_ms: None,
+            max_retries: None,
+            headers,
         }
     }
 }
@@ -84,6 +337,16 @@ pub enum InvocationResponseCode {
     InvalidRequest = 1,
     AppError = 2,
     SystemError = 3,
+    /// The object's state changed since the version the caller expected.
+    Conflict = 4,
+    /// The caller's tenant exceeded its invocation quota.
+    QuotaExceeded = 5,
+    /// The caller did not present valid credentials.
+    Unauthorized = 6,
+    /// The caller's deadline passed before the invocation was handled.
+    DeadlineExceeded = 7,
+    /// The server's memory budget could not cover the request payload.
+    ResourceExhausted = 8,
 }
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
@@ -155,6 +418,327 @@ impl InvocationResponse {
             self.payload, self.status, self.header
         )
     }
+
+    /// The `ErrorCode` of the status, `None` for `Okay`.
+    #[getter]
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        ErrorCode::of_response(self.status, &self.header)
+    }
+
+    /// Stores `value` under `key` in the headers as an ISO 8601 duration.
+    pub fn set_duration_header(&mut self, key: String, value: Duration) {
+        self.header.insert(key, timefmt::format_duration(value));
+    }
+
+    /// Reads the ISO 8601 duration stored under `key` as a `timedelta`.
+    /// Raises `ValueError` if the value is not a duration.
+    pub fn get_duration_header(&self, key: &str) -> PyResult<Option<Duration>> {
+        timefmt::get_duration(&self.header, key)
+    }
+
+    /// Stores `value` under `key` in the headers as an RFC 3339 timestamp.
+    pub fn set_timestamp_header(&mut self, key: String, value: SystemTime) {
+        self.header.insert(key, timefmt::format_timestamp(value));
+    }
+
+    /// Reads the RFC 3339 timestamp stored under `key` as a UTC `datetime`.
+    /// Raises `ValueError` if the value is not a timestamp.
+    pub fn get_timestamp_header(&self, key: &str) -> PyResult<Option<SystemTime>> {
+        timefmt::get_timestamp(&self.header, key)
+    }
+
+    /// Wraps `value` in a `google.protobuf.Any` as the payload and records its
+    /// type URL in the `oprc-type-url` header.
+    pub fn pack_payload(&mut self, type_url: &str, value: Vec<u8>) {
+        self.payload = envelope::pack(type_url, value);
+        self.header
+            .insert(TYPE_URL_OPTION.to_string(), type_url.to_string());
+    }
+
+    /// Decodes an `Any`-wrapped payload with the decoder registered for its type URL.
+    pub fn unpack_payload<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        envelope::decode(
+            py,
+            &self.payload,
+            self.header.get(TYPE_URL_OPTION).map(String::as_str),
+        )
+    }
+
+    /// The type URL of an `Any`-wrapped payload, if set.
+    #[getter]
+    pub fn payload_type_url(&self) -> Option<String> {
+        self.header.get(TYPE_URL_OPTION).cloned()
+    }
+}
+
+/// Request option carrying the comma-separated object ids of a multi-object invocation.
+pub const OBJECT_IDS_OPTION: &str = "oprc-object-ids";
+
+#[derive(Clone)]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all, set_all)]
+/// Represents a request to invoke a function once on a set of objects of the
+/// same class and partition. The handler receives all object ids in one call.
+pub struct MultiObjectInvocationRequest {
+    pub partition_id: u32,
+    pub cls_id: String,
+    pub fn_id: String,
+    pub object_ids: Vec<u64>,
+    pub options: HashMap<String, String>,
+    pub payload: Vec<u8>,
+    /// Per-request timeout in milliseconds; overrides the manager and module defaults.
+    pub timeout_ms: Option<u64>,
+    /// Per-request retry limit; overrides the manager's retry policy.
+    pub max_retries: Option<u32>,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl MultiObjectInvocationRequest {
+    #[new]
+    #[pyo3(signature = (cls_id, fn_id, object_ids, partition_id=0, options=HashMap::new(), payload=vec![], timeout_ms=None, max_retries=None))]
+    #[allow(clippy::too_many_arguments)]
+    /// Creates a new `MultiObjectInvocationRequest`.
+    pub fn new(
+        cls_id: String,
+        fn_id: String,
+        #[pyo3(from_py_with = ids::object_ids)] object_ids: Vec<u64>,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        options: HashMap<String, String>,
+        payload: Vec<u8>,
+        timeout_ms: Option<u64>,
+        max_retries: Option<u32>,
+    ) -> PyResult<Self> {
+        if object_ids.is_empty() {
+            return Err(PyValueError::new_err("object_ids must not be empty"));
+        }
+        Ok(MultiObjectInvocationRequest {
+            partition_id,
+            cls_id,
+            fn_id,
+            object_ids,
+            options,
+            payload,
+            timeout_ms,
+            max_retries,
+        })
+    }
+}
+
+impl MultiObjectInvocationRequest {
+    /// Converts this request into the stateless protobuf request that carries it,
+    /// with the object ids stored under `OBJECT_IDS_OPTION`.
+    pub fn into_proto(&self) -> oprc_pb::InvocationRequest {
+        let mut options = self.options.clone();
+        let ids = self
+            .object_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        options.insert(OBJECT_IDS_OPTION.to_string(), ids);
+        oprc_pb::InvocationRequest {
+            partition_id: self.partition_id,
+            cls_id: self.cls_id.clone(),
+            fn_id: self.fn_id.clone(),
+            options,
+            payload: self.payload.clone(),
+        }
+    }
+
+    /// Returns the per-request timeout in milliseconds, if set.
+    pub fn timeout_ms(&self) -> Option<u64> {
+        self.timeout_ms
+    }
+
+    /// Returns the per-request retry limit, if set.
+    pub fn max_retries(&self) -> Option<u32> {
+        self.max_retries
+    }
+
+    /// Recovers a multi-object request from a stateless protobuf request, or
+    /// `None` if `req` is a plain function invocation.
+    pub fn from_fn_request(req: &oprc_pb::InvocationRequest) -> Option<PyResult<Self>> {
+        let raw = req.options.get(OBJECT_IDS_OPTION)?;
+        let ids = raw
+            .split(',')
+            .map(|id| id.trim().parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                PyValueError::new_err(format!("invalid {} option: '{}'", OBJECT_IDS_OPTION, raw))
+            });
+        Some(ids.map(|object_ids| {
+            let mut options = req.options.clone();
+            options.remove(OBJECT_IDS_OPTION);
+            MultiObjectInvocationRequest {
+                partition_id: req.partition_id,
+                cls_id: req.cls_id.clone(),
+                fn_id: req.fn_id.clone(),
+                object_ids,
+                options,
+                payload: req.payload.clone(),
+                timeout_ms: None,
+                max_retries: None,
+            }
+        }))
+    }
+}
+
+/// Request option carrying the object version a conditional invocation expects.
+pub const EXPECTED_VERSION_OPTION: &str = "oprc-expected-version";
+
+/// Request option carrying the verified ID token claims as a JSON object, set
+/// by the `jwt` interceptor.
+pub const CLAIMS_OPTION: &str = "oprc-claims";
+
+/// Request option carrying the caller's deadline in Unix epoch milliseconds,
+/// on the clock of the host that set it. It is not sent as is; see
+/// [`TIMEOUT_OPTION`].
+pub const DEADLINE_OPTION: &str = "oprc-deadline";
+
+/// Request option carrying the milliseconds left of the caller's deadline or
+/// timeout when the request was sent. Handlers turn it back into an
+/// `oprc-deadline` on their own clock, so clock skew between hosts does not
+/// move the deadline.
+pub const TIMEOUT_OPTION: &str = "oprc-timeout-ms";
+
+/// Prefix of the request options that carry the request headers on the wire,
+/// as the protobuf request has no field for them.
+pub const HEADER_OPTION_PREFIX: &str = "oprc-header-";
+
+/// Writes `headers` into `options`, each under [`HEADER_OPTION_PREFIX`].
+fn embed_headers(options: &mut HashMap<String, String>, headers: &HashMap<String, String>) {
+    for (name, value) in headers {
+        options.insert(format!("{}{}", HEADER_OPTION_PREFIX, name), value.clone());
+    }
+}
+
+/// Moves the headers carried in `options` out of them.
+fn take_headers(options: &mut HashMap<String, String>) -> HashMap<String, String> {
+    let names: Vec<String> = options
+        .keys()
+        .filter(|k| k.starts_with(HEADER_OPTION_PREFIX))
+        .cloned()
+        .collect();
+    names
+        .into_iter()
+        .filter_map(|key| {
+            let value = options.remove(&key)?;
+            Some((key[HEADER_OPTION_PREFIX.len()..].to_string(), value))
+        })
+        .collect()
+}
+
+/// Request option with the key expression a streaming caller
+/// (`RpcManager.invoke_fn_stream`) receives response chunks on.
+pub const RESPONSE_STREAM_OPTION: &str = "oprc-response-stream";
+
+/// Request option with the key expression a caller awaiting progress events
+/// (`on_progress` of `RpcManager.invoke_fn_async`) receives them on.
+pub const PROGRESS_OPTION: &str = "oprc-progress";
+
+/// Request option carrying a caller-chosen key identifying the invocation
+/// across retries, so handlers run it at most once.
+pub const IDEMPOTENCY_KEY_OPTION: &str = "oprc-idempotency-key";
+
+/// Response header a serving runtime may set to report which replica handled the call.
+pub const REPLICA_HEADER: &str = "oprc-replica";
+
+/// Response header naming the class of the Python exception a handler failed
+/// with, set by the SDK on the error responses it builds from caught exceptions.
+pub const EXCEPTION_TYPE_HEADER: &str = "oprc-exception-type";
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[derive(Clone, Debug)]
+#[pyo3::pyclass(get_all)]
+/// One attempt of an invocation, first or retried.
+pub struct InvocationAttempt {
+    /// Position of the attempt, starting at 1.
+    pub number: u32,
+    /// Where the attempt was sent: `"local"`, the address of a direct gRPC
+    /// invocation, or the Zenoh key expression invoked.
+    pub endpoint: String,
+    /// The replica that answered, if it reported one.
+    pub replica: Option<String>,
+    /// Status of the response, `None` if no response was received.
+    pub status: Option<i32>,
+    /// Why the attempt failed, `None` if it succeeded.
+    pub error_code: Option<ErrorCode>,
+    /// Message of the failure, if the attempt got no response.
+    pub error: Option<String>,
+    /// Time from sending the attempt until its outcome, in milliseconds.
+    pub latency_ms: f64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl InvocationAttempt {
+    /// `"ok"`, or the name of the error code, e.g. `"TIMEOUT"`.
+    #[getter]
+    pub fn outcome(&self) -> &'static str {
+        self.error_code.map_or("ok", ErrorCode::name)
+    }
+
+    fn __str__(&self) -> String {
+        format!(
+            "InvocationAttempt {{ number: {}, endpoint: {}, outcome: {}, latency_ms: {:.3} }}",
+            self.number,
+            self.endpoint,
+            self.outcome(),
+            self.latency_ms
+        )
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[derive(Clone)]
+#[pyo3::pyclass(get_all)]
+/// An `InvocationResponse` together with delivery metadata.
+pub struct DetailedInvocationResponse {
+    /// The response returned by the target.
+    pub response: InvocationResponse,
+    /// Number of attempts made before the response was obtained.
+    pub attempts: u32,
+    /// Every attempt made, in order; the last one produced `response`.
+    pub attempt_log: Vec<InvocationAttempt>,
+    /// The replica that served the call, if the target reported one.
+    pub replica: Option<String>,
+    /// Total time from the call until the response, in milliseconds.
+    pub latency_ms: f64,
+    /// Time spent waiting inside the client before the first attempt was sent, in milliseconds.
+    pub queue_time_ms: f64,
+}
+
+impl DetailedInvocationResponse {
+    /// Builds a detailed response, reading the replica from the response headers.
+    pub fn new(
+        response: InvocationResponse,
+        attempt_log: Vec<InvocationAttempt>,
+        latency: std::time::Duration,
+        queue_time: std::time::Duration,
+    ) -> Self {
+        let replica = response.header.get(REPLICA_HEADER).cloned();
+        DetailedInvocationResponse {
+            response,
+            attempts: attempt_log.len() as u32,
+            attempt_log,
+            replica,
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            queue_time_ms: queue_time.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl DetailedInvocationResponse {
+    /// Returns a string representation of the `DetailedInvocationResponse`.
+    fn __str__(&self) -> String {
+        format!(
+            "DetailedInvocationResponse {{ status: {}, attempts: {}, replica: {:?}, latency_ms: {:.3}, queue_time_ms: {:.3} }}",
+            self.response.status, self.attempts, self.replica, self.latency_ms, self.queue_time_ms
+        )
+    }
 }
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
@@ -168,21 +752,36 @@ pub struct ObjectInvocationRequest {
     object_id: u64,
     options: HashMap<String, String>,
     payload: Vec<u8>,
+    timeout_ms: Option<u64>,
+    /// If set, the server rejects the call with `Conflict` unless the object's
+    /// current `ObjectData.version` equals this value. Checked before the
+    /// handler runs, not on its writes.
+    expected_version: Option<u64>,
+    /// Per-request retry limit; overrides the manager's retry policy.
+    max_retries: Option<u32>,
+    /// Request headers, such as correlation ids, passed to the handler apart
+    /// from `options`.
+    headers: HashMap<String, String>,
 }
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
 #[pyo3::pymethods]
 impl ObjectInvocationRequest {
     #[new]
-    #[pyo3(signature = (cls_id, fn_id, object_id, partition_id=0,  options=HashMap::new(), payload=vec![]))]
+    #[pyo3(signature = (cls_id, fn_id, object_id, partition_id=0,  options=HashMap::new(), payload=vec![], timeout_ms=None, expected_version=None, max_retries=None, headers=HashMap::new()))]
+    #[allow(clippy::too_many_arguments)]
     /// Creates a new `ObjectInvocationRequest`.
     pub fn new(
         cls_id: String,
         fn_id: String,
-        object_id: u64,
-        partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] object_id: u64,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
         options: HashMap<String, String>,
         payload: Vec<u8>,
+        timeout_ms: Option<u64>,
+        expected_version: Option<u64>,
+        max_retries: Option<u32>,
+        headers: HashMap<String, String>,
     ) -> Self {
         ObjectInvocationRequest {
             partition_id,
@@ -191,13 +790,211 @@ impl ObjectInvocationRequest {
             object_id,
             options,
             payload,
+            timeout_ms,
+            expected_version,
+            max_retries,
+            headers,
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (target, payload=vec![]))]
+    /// Parses an `ObjectInvocationRequest` from a target string of the form
+    /// `cls_id/partition_id/object_id/fn_id`.
+    ///
+    /// Raises `ValueError` if the target is malformed.
+    pub fn parse(target: &str, payload: Vec<u8>) -> PyResult<Self> {
+        let segments: Vec<&str> = target.split('/').collect();
+        if segments.len() != 4 {
+            return Err(PyValueError::new_err(format!(
+                "Invalid target '{}': expected 'cls_id/partition_id/object_id/fn_id'",
+                target
+            )));
         }
+        let cls_id = validate_segment("cls_id", segments[0], target)?;
+        let partition_id = parse_number("partition_id", segments[1], target)?;
+        let object_id = parse_number("object_id", segments[2], target)?;
+        let fn_id = validate_segment("fn_id", segments[3], target)?;
+        Ok(ObjectInvocationRequest::new(
+            cls_id.to_string(),
+            fn_id.to_string(),
+            object_id,
+            partition_id,
+            HashMap::new(),
+            payload,
+            None,
+            None,
+            None,
+            HashMap::new(),
+        ))
+    }
+
+    #[staticmethod]
+    /// Returns an empty `ObjectInvocationRequestBuilder`, to set the fields
+    /// of a request one by one and check them when building it.
+    pub fn builder() -> ObjectInvocationRequestBuilder {
+        ObjectInvocationRequestBuilder::new()
+    }
+
+    /// Wraps `value` in a `google.protobuf.Any` as the payload and records its
+    /// type URL in the `oprc-type-url` option.
+    pub fn pack_payload(&mut self, type_url: &str, value: Vec<u8>) {
+        self.payload = envelope::pack(type_url, value);
+        self.options
+            .insert(TYPE_URL_OPTION.to_string(), type_url.to_string());
+    }
+
+    /// Decodes an `Any`-wrapped payload with the decoder registered for its type URL.
+    pub fn unpack_payload<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        envelope::decode(
+            py,
+            &self.payload,
+            self.options.get(TYPE_URL_OPTION).map(String::as_str),
+        )
+    }
+
+    /// The type URL of an `Any`-wrapped payload, if set.
+    #[getter]
+    pub fn payload_type_url(&self) -> Option<String> {
+        self.options.get(TYPE_URL_OPTION).cloned()
+    }
+
+    /// The claims of the caller's verified ID token, if the server runs the
+    /// `jwt` interceptor.
+    #[getter]
+    pub fn claims<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.options
+            .get(CLAIMS_OPTION)
+            .map(|raw| codec::decode_json(py, raw.as_bytes()))
+            .transpose()
+    }
+
+    /// Key expression to send response chunks to with
+    /// `RpcManager.send_chunk`, if the caller streams the response.
+    #[getter]
+    pub fn response_stream(&self) -> Option<String> {
+        self.options.get(RESPONSE_STREAM_OPTION).cloned()
+    }
+
+    /// Key expression to report progress to with
+    /// `RpcManager.report_progress`, if the caller listens for it.
+    #[getter]
+    pub fn progress(&self) -> Option<String> {
+        self.options.get(PROGRESS_OPTION).cloned()
+    }
+
+    /// Absolute deadline in Unix epoch milliseconds, kept in the
+    /// `oprc-deadline` option. `RpcManager` fails the invocation with
+    /// `TimeoutError` once it has passed and sends the time left, which
+    /// handlers rebase on their own clock and reject with `DeadlineExceeded`
+    /// once spent.
+    #[getter]
+    pub fn get_deadline_ms(&self) -> Option<u64> {
+        self.options.get(DEADLINE_OPTION).and_then(|v| v.parse().ok())
+    }
+
+    #[setter]
+    pub fn set_deadline_ms(&mut self, deadline_ms: Option<u64>) {
+        crate::deadline::set(&mut self.options, deadline_ms);
+    }
+
+    /// Key identifying the invocation across retries, sent in the
+    /// `oprc-idempotency-key` option. Handlers run an invocation once per
+    /// key and replay its response to duplicate deliveries.
+    #[getter]
+    pub fn get_idempotency_key(&self) -> Option<String> {
+        self.options.get(IDEMPOTENCY_KEY_OPTION).cloned()
+    }
+
+    #[setter]
+    pub fn set_idempotency_key(&mut self, key: Option<String>) {
+        match key {
+            Some(key) => {
+                self.options.insert(IDEMPOTENCY_KEY_OPTION.to_string(), key);
+            }
+            None => {
+                self.options.remove(IDEMPOTENCY_KEY_OPTION);
+            }
+        }
+    }
+
+    /// Whether a handler of this process serving the object runs the
+    /// invocation first, sent in the `oprc-prefer-local` option. `None`
+    /// leaves it to `RpcManager.prefer_local`.
+    #[getter]
+    pub fn get_prefer_local(&self) -> Option<bool> {
+        match self.options.get(PREFER_LOCAL_OPTION).map(String::as_str) {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        }
+    }
+
+    #[setter]
+    pub fn set_prefer_local(&mut self, prefer_local: Option<bool>) {
+        match prefer_local {
+            Some(prefer_local) => {
+                self.options
+                    .insert(PREFER_LOCAL_OPTION.to_string(), prefer_local.to_string());
+            }
+            None => {
+                self.options.remove(PREFER_LOCAL_OPTION);
+            }
+        }
+    }
+
+    /// Replica the invocation is sent to, sent in the `oprc-replica-id`
+    /// option. Set it to the `oprc-replica` header of an earlier response so
+    /// repeated calls to the object hit the same replica. If that replica no
+    /// longer answers, any replica serves the call.
+    #[getter]
+    pub fn get_replica_id(&self) -> Option<String> {
+        self.options.get(REPLICA_ID_OPTION).cloned()
+    }
+
+    #[setter]
+    pub fn set_replica_id(&mut self, replica_id: Option<String>) {
+        match replica_id {
+            Some(replica_id) => {
+                self.options.insert(REPLICA_ID_OPTION.to_string(), replica_id);
+            }
+            None => {
+                self.options.remove(REPLICA_ID_OPTION);
+            }
+        }
+    }
+
+    /// Stores `value` under `key` in the options as an ISO 8601 duration.
+    pub fn set_duration_option(&mut self, key: String, value: Duration) {
+        self.options.insert(key, timefmt::format_duration(value));
+    }
+
+    /// Reads the ISO 8601 duration stored under `key` as a `timedelta`.
+    /// Raises `ValueError` if the value is not a duration.
+    pub fn get_duration_option(&self, key: &str) -> PyResult<Option<Duration>> {
+        timefmt::get_duration(&self.options, key)
+    }
+
+    /// Stores `value` under `key` in the options as an RFC 3339 timestamp.
+    pub fn set_timestamp_option(&mut self, key: String, value: SystemTime) {
+        self.options.insert(key, timefmt::format_timestamp(value));
+    }
+
+    /// Reads the RFC 3339 timestamp stored under `key` as a UTC `datetime`.
+    /// Raises `ValueError` if the value is not a timestamp.
+    pub fn get_timestamp_option(&self, key: &str) -> PyResult<Option<SystemTime>> {
+        timefmt::get_timestamp(&self.options, key)
     }
 }
 
 impl From<oprc_pb::ObjectInvocationRequest> for ObjectInvocationRequest {
     /// Creates an `ObjectInvocationRequest` from its protobuf representation.
-    fn from(value: oprc_pb::ObjectInvocationRequest) -> Self {
+    fn from(mut value: oprc_pb::ObjectInvocationRequest) -> Self {
+        let expected_version = value
+            .options
+            .remove(EXPECTED_VERSION_OPTION)
+            .and_then(|v| v.parse().ok());
+        let headers = take_headers(&mut value.options);
         ObjectInvocationRequest {
             partition_id: value.partition_id,
             cls_id: value.cls_id,
@@ -205,6 +1002,10 @@ impl From<oprc_pb::ObjectInvocationRequest> for ObjectInvocationRequest {
             object_id: value.object_id,
             options: value.options,
             payload: value.payload,
+            timeout_ms: None,
+            expected_version,
+            max_retries: None,
+            headers,
         }
     }
 }
@@ -212,13 +1013,147 @@ impl From<oprc_pb::ObjectInvocationRequest> for ObjectInvocationRequest {
 impl ObjectInvocationRequest {
     /// Converts this `ObjectInvocationRequest` into its protobuf representation.
     pub fn into_proto(&self) -> oprc_pb::ObjectInvocationRequest {
+        let mut options = self.options.clone();
+        if let Some(version) = self.expected_version {
+            options.insert(EXPECTED_VERSION_OPTION.to_string(), version.to_string());
+        }
+        embed_headers(&mut options, &self.headers);
         oprc_pb::ObjectInvocationRequest {
             partition_id: self.partition_id,
             cls_id: self.cls_id.clone(),
             fn_id: self.fn_id.clone(),
             object_id: self.object_id,
-            options: self.options.clone(),
+            options,
             payload: self.payload.clone(),
         }
     }
+
+    /// Returns the per-request timeout in milliseconds, if set.
+    pub fn timeout_ms(&self) -> Option<u64> {
+        self.timeout_ms
+    }
+
+    /// Returns the per-request retry limit, if set.
+    pub fn max_retries(&self) -> Option<u32> {
+        self.max_retries
+    }
+
+    /// Returns the class ID of the target object.
+    pub fn cls_id(&self) -> &str {
+        &self.cls_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_function_targets() {
+        let req = InvocationRequest::parse("example.Cls/fn", b"x".to_vec()).unwrap();
+        assert_eq!(
+            (req.cls_id.as_str(), req.fn_id.as_str()),
+            ("example.Cls", "fn")
+        );
+        assert_eq!(req.partition_id, 0);
+        assert_eq!(req.payload, b"x");
+        let req = InvocationRequest::parse("cls/fn?partition=3", vec![]).unwrap();
+        assert_eq!(req.partition_id, 3);
+    }
+
+    #[test]
+    fn rejects_malformed_function_targets() {
+        for target in [
+            "",
+            "cls",
+            "cls/",
+            "/fn",
+            "cls/fn/extra",
+            "c s/fn",
+            "cls/f*",
+            "cls/fn?partition",
+            "cls/fn?partition=x",
+            "cls/fn?partition=-1",
+            "cls/fn?partition=1&partition=2",
+            "cls/fn?shard=1",
+        ] {
+            assert!(
+                InvocationRequest::parse(target, vec![]).is_err(),
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn parses_object_targets() {
+        let req = ObjectInvocationRequest::parse("cls/2/42/fn", vec![]).unwrap();
+        assert_eq!(req.cls_id, "cls");
+        assert_eq!(req.partition_id, 2);
+        assert_eq!(req.object_id, 42);
+        assert_eq!(req.fn_id, "fn");
+        for target in [
+            "cls/2/fn",
+            "cls/x/42/fn",
+            "cls/2/99999999999999999999/fn",
+            "cls/2/42/",
+        ] {
+            assert!(
+                ObjectInvocationRequest::parse(target, vec![]).is_err(),
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn headers_travel_in_options() {
+        let mut req = InvocationRequest::parse("cls/fn", vec![]).unwrap();
+        req.options = headers(&[("tenant", "a")]);
+        req.headers = headers(&[("x-correlation-id", "c1")]);
+        let proto = req.into_proto();
+        assert_eq!(
+            proto.options,
+            headers(&[("tenant", "a"), ("oprc-header-x-correlation-id", "c1")])
+        );
+        let back = InvocationRequest::from(proto);
+        assert_eq!(back.options, headers(&[("tenant", "a")]));
+        assert_eq!(back.headers, headers(&[("x-correlation-id", "c1")]));
+    }
+
+    #[test]
+    fn object_requests_carry_headers_and_expected_version() {
+        let mut req = ObjectInvocationRequest::parse("cls/0/1/fn", vec![]).unwrap();
+        req.expected_version = Some(7);
+        req.headers = headers(&[("x-id", "1")]);
+        let proto = req.into_proto();
+        assert_eq!(proto.options[EXPECTED_VERSION_OPTION], "7");
+        let back = ObjectInvocationRequest::from(proto);
+        assert_eq!(back.expected_version, Some(7));
+        assert_eq!(back.headers, headers(&[("x-id", "1")]));
+        assert!(back.options.is_empty());
+    }
+
+    #[test]
+    fn timeouts_stay_local_and_deadlines_are_options() {
+        let mut req = InvocationRequest::parse("cls/fn", vec![]).unwrap();
+        req.timeout_ms = Some(500);
+        req.set_deadline_ms(Some(1_000));
+        assert_eq!(req.get_deadline_ms(), Some(1_000));
+        let back = InvocationRequest::from(req.into_proto());
+        assert_eq!(back.timeout_ms(), None);
+        assert_eq!(back.get_deadline_ms(), Some(1_000));
+        req.set_deadline_ms(None);
+        assert!(!req.options.contains_key(DEADLINE_OPTION));
+        req.options
+            .insert(DEADLINE_OPTION.to_string(), "soon".to_string());
+        assert_eq!(req.get_deadline_ms(), None);
+    }
 }
@@ -1,7 +1,61 @@
 use std::collections::HashMap;
 
 use oprc_pb::{ObjMeta, ValType};
-use pyo3::Bound;
+use pyo3::{
+    Bound, PyAny, PyResult,
+    exceptions::{PyKeyError, PyTypeError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+use crate::{codec, ids, keys::{self, EntryKey}};
+
+/// Class attribute holding the field name to entry key mapping used by
+/// `ObjectData.to_model` / `ObjectData.from_model`.
+const FIELDS_ATTR: &str = "__oprc_fields__";
+
+/// Computes a stable fingerprint of an object's entries, used as its version
+/// for conditional invocations. Independent of entry iteration order.
+///
+/// This is an FNV-1a hash of the content, not a counter: state that changes
+/// and changes back has its old version again.
+pub(crate) fn state_version<'a>(entries: impl Iterator<Item = (u32, &'a [u8])>) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut sorted: Vec<(u32, &[u8])> = entries.collect();
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted.sort_unstable_by_key(|(k, _)| *k);
+    let mut hash = FNV_OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    for (key, data) in sorted {
+        feed(&key.to_le_bytes());
+        feed(&(data.len() as u64).to_le_bytes());
+        feed(data);
+    }
+    hash
+}
+
+/// Resolves the field name to entry key mapping for a model class, falling
+/// back to the entry keys registered for `cls_id`.
+fn model_fields(cls: &Bound<'_, PyAny>, cls_id: &str) -> PyResult<HashMap<String, u32>> {
+    if let Ok(attr) = cls.getattr(FIELDS_ATTR) {
+        return attr.extract();
+    }
+    keys::names_of(cls_id).ok_or_else(|| {
+        PyTypeError::new_err(format!(
+            "{} has no entry field mapping; decorate it with @oprc_py.entry_fields(...), register entry keys for '{}' or pass fields=",
+            cls.str().map(|s| s.to_string()).unwrap_or_default(),
+            cls_id
+        ))
+    })
+}
 
 
 #[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
@@ -52,7 +106,11 @@ impl ObjectMetadata {
 impl ObjectMetadata {
     #[new]
     /// Creates a new `ObjectMetadata`.
-    pub fn new(cls_id: String, partition_id: u32, object_id: u64) -> Self {
+    pub fn new(
+        cls_id: String,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        #[pyo3(from_py_with = ids::object_id)] object_id: u64,
+    ) -> Self {
         ObjectMetadata {
             object_id,
             cls_id,
@@ -137,6 +195,129 @@ impl ObjectData {
     pub fn copy(&self) -> Self {
         Self { meta: self.meta.clone(), entries: self.entries.clone(), event: self.event.clone() }
     }
+
+    #[getter]
+    /// Returns the version of this object's state, as checked by conditional
+    /// invocations (`ObjectInvocationRequest.expected_version`). `0` for no entries.
+    pub fn version(&self) -> u64 {
+        state_version(self.entries.iter().map(|(k, v)| (*k, v.as_slice())))
+    }
+
+    /// Returns the value of an entry by index or registered name.
+    ///
+    /// Raises `KeyError` if the name is not registered or the entry is absent.
+    fn __getitem__(&self, key: EntryKey) -> PyResult<Vec<u8>> {
+        let index = key.resolve(&self.meta.cls_id)?;
+        self.entries
+            .get(&index)
+            .cloned()
+            .ok_or_else(|| PyKeyError::new_err(index))
+    }
+
+    /// Sets the value of an entry by index or registered name.
+    fn __setitem__(&mut self, key: EntryKey, value: Vec<u8>) -> PyResult<()> {
+        let index = key.resolve(&self.meta.cls_id)?;
+        self.entries.insert(index, value);
+        Ok(())
+    }
+
+    /// Removes an entry by index or registered name.
+    fn __delitem__(&mut self, key: EntryKey) -> PyResult<()> {
+        let index = key.resolve(&self.meta.cls_id)?;
+        self.entries
+            .remove(&index)
+            .map(|_| ())
+            .ok_or_else(|| PyKeyError::new_err(index))
+    }
+
+    /// Returns whether an entry exists, by index or registered name.
+    fn __contains__(&self, key: EntryKey) -> bool {
+        key.resolve(&self.meta.cls_id)
+            .map(|index| self.entries.contains_key(&index))
+            .unwrap_or(false)
+    }
+
+    #[pyo3(signature = (key, default=None))]
+    /// Returns the value of an entry by index or registered name, or `default` if absent.
+    pub fn get(&self, key: EntryKey, default: Option<Vec<u8>>) -> PyResult<Option<Vec<u8>>> {
+        let index = key.resolve(&self.meta.cls_id)?;
+        Ok(self.entries.get(&index).cloned().or(default))
+    }
+
+    #[pyo3(signature = (cls, fields=None))]
+    /// Builds an instance of `cls` (e.g. a dataclass or pydantic model) from the
+    /// JSON-encoded entries of this object.
+    ///
+    /// # Arguments
+    /// * `cls` - The model class; called with one keyword argument per present field.
+    /// * `fields` - Field name to entry key mapping; defaults to `cls.__oprc_fields__`.
+    pub fn to_model<'py>(
+        &self,
+        cls: &Bound<'py, PyAny>,
+        fields: Option<HashMap<String, u32>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let py = cls.py();
+        let fields = match fields {
+            Some(f) => f,
+            None => model_fields(cls, &self.meta.cls_id)?,
+        };
+        let kwargs = PyDict::new(py);
+        for (name, key) in fields {
+            if let Some(raw) = self.entries.get(&key) {
+                let value = codec::decode_json(py, raw).map_err(|e| {
+                    PyValueError::new_err(format!(
+                        "Failed to decode entry {} for field '{}': {}",
+                        key, name, e
+                    ))
+                })?;
+                kwargs.set_item(name, value)?;
+            }
+        }
+        cls.call((), Some(&kwargs))
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (meta, instance, fields=None))]
+    /// Creates an `ObjectData` whose entries are the JSON-encoded fields of `instance`.
+    ///
+    /// # Arguments
+    /// * `meta` - The metadata of the new object.
+    /// * `instance` - The model instance to read fields from.
+    /// * `fields` - Field name to entry key mapping; defaults to `type(instance).__oprc_fields__`.
+    pub fn from_model(
+        meta: ObjectMetadata,
+        instance: &Bound<'_, PyAny>,
+        fields: Option<HashMap<String, u32>>,
+    ) -> PyResult<Self> {
+        let mut data = ObjectData::new(meta, HashMap::new(), None);
+        data.update_from_model(instance, fields)?;
+        Ok(data)
+    }
+
+    #[pyo3(signature = (instance, fields=None))]
+    /// Overwrites the entries mapped by `fields` with the JSON-encoded fields of `instance`,
+    /// leaving other entries and the event untouched.
+    pub fn update_from_model(
+        &mut self,
+        instance: &Bound<'_, PyAny>,
+        fields: Option<HashMap<String, u32>>,
+    ) -> PyResult<()> {
+        let fields = match fields {
+            Some(f) => f,
+            None => model_fields(&instance.get_type().into_any(), &self.meta.cls_id)?,
+        };
+        for (name, key) in fields {
+            let value = instance.getattr(name.as_str())?;
+            let raw = codec::encode_json(&value).map_err(|e| {
+                PyValueError::new_err(format!(
+                    "Failed to encode field '{}' into entry {}: {}",
+                    name, key, e
+                ))
+            })?;
+            self.entries.insert(key, raw);
+        }
+        Ok(())
+    }
 }
 
 impl Into<oprc_pb::ObjData> for &ObjectData {
@@ -497,9 +678,9 @@ impl PyTriggerTarget {
     /// Creates a new `PyTriggerTarget`.
     pub fn new(
         cls_id: String,
-        partition_id: u32,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
         fn_id: String,
-        object_id: Option<u64>,
+        #[pyo3(from_py_with = ids::optional_object_id)] object_id: Option<u64>,
         req_options: HashMap<String, String>,
     ) -> Self {
         Self {
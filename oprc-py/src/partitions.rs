@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+use pyo3::{prelude::*, types::PyTuple};
+use pyo3_async_runtimes::tokio::get_runtime;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::session_events::notify;
+
+/// Key expression the platform publishes the partition assignment of a class on.
+pub(crate) fn assignment_key(cls_id: &str) -> String {
+    format!("oprc/{}/assignment", cls_id)
+}
+
+/// A partition assignment published by the platform.
+#[derive(Deserialize)]
+struct Assignment {
+    /// Increases with every rebalance; older assignments are ignored.
+    epoch: u64,
+    /// Partitions this process should serve.
+    partitions: BTreeSet<u32>,
+}
+
+/// Python callback notified of partition assignment changes.
+pub(crate) struct PartitionHooks {
+    /// Called with `(assigned, revoked, epoch)` whenever the assignment changes.
+    pub(crate) on_change: Py<PyAny>,
+    /// If set, the callback is scheduled with `call_soon_threadsafe` on this loop.
+    pub(crate) event_loop: Option<Py<PyAny>>,
+}
+
+/// Subscribes to the assignment of `cls_id` and reports the partitions gained
+/// and lost with each new epoch to `hooks` until the returned task is aborted.
+pub(crate) async fn watch(
+    session: zenoh::Session,
+    cls_id: String,
+    hooks: PartitionHooks,
+) -> zenoh::Result<JoinHandle<()>> {
+    let key = assignment_key(&cls_id);
+    let subscriber = session.declare_subscriber(key.clone()).await?;
+    Ok(get_runtime().spawn(async move {
+        let mut epoch = None;
+        let mut current = BTreeSet::new();
+        while let Ok(sample) = subscriber.recv_async().await {
+            let assignment: Assignment = match serde_json::from_slice(&sample.payload().to_bytes())
+            {
+                Ok(a) => a,
+                Err(e) => {
+                    warn!("ignoring malformed partition assignment on {}: {}", key, e);
+                    continue;
+                }
+            };
+            if epoch.is_some_and(|e| assignment.epoch <= e) {
+                debug!(
+                    "ignoring stale partition assignment epoch {} on {}",
+                    assignment.epoch, key
+                );
+                continue;
+            }
+            epoch = Some(assignment.epoch);
+            let assigned: Vec<u32> = assignment
+                .partitions
+                .difference(&current)
+                .copied()
+                .collect();
+            let revoked: Vec<u32> = current
+                .difference(&assignment.partitions)
+                .copied()
+                .collect();
+            current = assignment.partitions;
+            if assigned.is_empty() && revoked.is_empty() {
+                continue;
+            }
+            notify(&hooks.on_change, hooks.event_loop.as_ref(), |py| {
+                PyTuple::new(
+                    py,
+                    [
+                        assigned.into_pyobject(py)?.into_any(),
+                        revoked.into_pyobject(py)?.into_any(),
+                        assignment.epoch.into_pyobject(py)?.into_any(),
+                    ],
+                )
+            });
+        }
+    }))
+}
@@ -0,0 +1,48 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pyo3::create_exception!(
+    oprc_py,
+    PayloadTooLarge,
+    pyo3::exceptions::PyValueError,
+    "Raised when a request payload exceeds `RpcManager.max_payload_bytes`, without sending, or the limit of the handler."
+);
+
+/// Response header of a request rejected for its payload size, holding the
+/// limit in bytes. Its status stays `ResourceExhausted`; the header tells it
+/// apart from an exhausted memory budget.
+pub const PAYLOAD_LIMIT_HEADER: &str = "oprc-payload-limit";
+
+/// Returns the limit a handler rejected a request payload over, from the
+/// headers of its response.
+pub(crate) fn rejected(headers: &HashMap<String, String>) -> Option<&str> {
+    headers.get(PAYLOAD_LIMIT_HEADER).map(String::as_str)
+}
+
+/// Largest payload accepted, shared by the invocations of a manager or the
+/// servers of an engine so changes apply right away. Unlimited by default.
+#[derive(Default)]
+pub(crate) struct PayloadLimit {
+    /// The limit in bytes; `0` for none.
+    max: AtomicUsize,
+}
+
+impl PayloadLimit {
+    pub(crate) fn get(&self) -> Option<usize> {
+        match self.max.load(Ordering::Relaxed) {
+            0 => None,
+            max => Some(max),
+        }
+    }
+
+    pub(crate) fn set(&self, max: Option<usize>) {
+        self.max.store(max.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Returns the limit if a payload of `len` bytes is over it.
+    pub(crate) fn exceeded(&self, len: usize) -> Option<usize> {
+        self.get().filter(|max| len > *max)
+    }
+}
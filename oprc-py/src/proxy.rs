@@ -0,0 +1,335 @@
+use std::sync::{Arc, RwLock};
+
+use hyper_util::rt::TokioIo;
+use pyo3::{exceptions::PyValueError, prelude::*};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Environment variable with the proxy used for every outbound HTTP and gRPC
+/// connection, taking precedence over `HTTPS_PROXY`, `HTTP_PROXY` and
+/// `ALL_PROXY`.
+const PROXY_ENV: &str = "OPRC_PROXY";
+/// Environment variable with the comma separated hosts reached without a
+/// proxy, taking precedence over `NO_PROXY`.
+const NO_PROXY_ENV: &str = "OPRC_NO_PROXY";
+/// Largest proxy response head accepted when opening a tunnel.
+const MAX_HEAD: usize = 8 * 1024;
+
+/// Configuration set with `set_proxy`, replacing the environment.
+struct Explicit {
+    url: String,
+    no_proxy: Vec<String>,
+}
+
+static EXPLICIT: RwLock<Option<Explicit>> = RwLock::new(None);
+
+/// An HTTP proxy outbound connections go through.
+pub(crate) struct Proxy {
+    /// The proxy URL, including credentials.
+    pub(crate) url: String,
+    host: String,
+    port: u16,
+    /// Base64 encoded `user:password` for `Proxy-Authorization`.
+    auth: Option<String>,
+}
+
+impl Proxy {
+    fn parse(url: &str) -> Result<Self, String> {
+        let rest = match url.split_once("://") {
+            Some(("http", rest)) => rest,
+            Some((scheme, _)) => {
+                return Err(format!(
+                    "unsupported proxy scheme '{}'; only http proxies are supported",
+                    scheme
+                ));
+            }
+            None => url,
+        };
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (userinfo, hostport) = match authority.rsplit_once('@') {
+            Some((userinfo, hostport)) => (Some(userinfo), hostport),
+            None => (None, authority),
+        };
+        let (host, port) = split_host(hostport);
+        let port = match port {
+            Some(p) => p
+                .parse::<u16>()
+                .map_err(|_| format!("invalid proxy port '{}'", p))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err(format!("proxy URL '{}' has no host", url));
+        }
+        Ok(Proxy {
+            url: if url.contains("://") {
+                url.to_string()
+            } else {
+                format!("http://{}", url)
+            },
+            host: host.to_string(),
+            port,
+            auth: userinfo.map(|u| base64(&percent_decode(u))),
+        })
+    }
+
+    /// Returns the `Proxy-Authorization` header line, if the proxy has
+    /// credentials.
+    pub(crate) fn auth_header(&self) -> String {
+        self.auth
+            .as_ref()
+            .map(|auth| format!("Proxy-Authorization: Basic {}\r\n", auth))
+            .unwrap_or_default()
+    }
+
+    /// Opens a TCP connection to the proxy itself.
+    pub(crate) async fn connect_proxy(&self) -> Result<TcpStream, String> {
+        TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| format!("proxy {}:{} is unreachable: {}", self.host, self.port, e))
+    }
+}
+
+/// Splits `host[:port]`, where IPv6 hosts are bracketed: `[::1]:4317`.
+fn split_host(authority: &str) -> (&str, Option<&str>) {
+    match authority.strip_prefix('[') {
+        Some(v6) => match v6.split_once(']') {
+            Some((host, rest)) => (host, rest.strip_prefix(':')),
+            None => (v6, None),
+        },
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    }
+}
+
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (u32::from(*b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn env(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+}
+
+/// Whether `host` matches an entry of a `NO_PROXY` list: `*`, the host itself
+/// or a parent domain (`example.com` or `.example.com`).
+fn bypassed(host: &str, no_proxy: &[String]) -> bool {
+    no_proxy.iter().any(|entry| {
+        let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+        !entry.is_empty()
+            && (entry == "*"
+                || host.eq_ignore_ascii_case(entry)
+                || host.len().checked_sub(entry.len() + 1).is_some_and(|at| {
+                    host.as_bytes()[at] == b'.' && host[at + 1..].eq_ignore_ascii_case(entry)
+                }))
+    })
+}
+
+/// Returns the proxy connections to `url` should go through: the one set with
+/// `set_proxy`, otherwise `OPRC_PROXY`, `HTTPS_PROXY` (for https URLs),
+/// `HTTP_PROXY` (for others) or `ALL_PROXY`, unless the host is excluded by
+/// the matching no-proxy list. Invalid proxy URLs are logged and ignored.
+pub(crate) fn for_url(url: &str) -> Option<Proxy> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split('/').next().unwrap_or_default();
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+    let (host, _) = split_host(authority);
+    let (proxy, no_proxy) = match &*EXPLICIT.read().unwrap() {
+        Some(explicit) => (explicit.url.clone(), explicit.no_proxy.clone()),
+        None => {
+            let proxy = env(&[PROXY_ENV])
+                .or_else(|| match scheme {
+                    "https" => env(&["HTTPS_PROXY", "https_proxy"]),
+                    _ => env(&["HTTP_PROXY", "http_proxy"]),
+                })
+                .or_else(|| env(&["ALL_PROXY", "all_proxy"]))?;
+            let no_proxy = env(&[NO_PROXY_ENV, "NO_PROXY", "no_proxy"])
+                .map(|v| v.split(',').map(|h| h.trim().to_string()).collect())
+                .unwrap_or_default();
+            (proxy, no_proxy)
+        }
+    };
+    if bypassed(host, &no_proxy) {
+        return None;
+    }
+    match Proxy::parse(&proxy) {
+        Ok(proxy) => Some(proxy),
+        Err(e) => {
+            tracing::warn!("ignoring proxy for {}: {}", url, e);
+            None
+        }
+    }
+}
+
+/// Opens a TCP connection to `host:port`, through an HTTP `CONNECT` tunnel
+/// when `proxy` is set.
+pub(crate) async fn connect(
+    proxy: Option<&Proxy>,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, String> {
+    let Some(proxy) = proxy else {
+        return TcpStream::connect((host, port))
+            .await
+            .map_err(|e| e.to_string());
+    };
+    let mut stream = proxy.connect_proxy().await?;
+    let target = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let req = format!(
+        "CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n{1}\r\n",
+        target,
+        proxy.auth_header()
+    );
+    stream
+        .write_all(req.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    // Read byte by byte, so nothing sent through the tunnel is consumed.
+    let mut head = Vec::with_capacity(128);
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HEAD {
+            return Err("proxy response head is too large".to_string());
+        }
+        let byte = stream
+            .read_u8()
+            .await
+            .map_err(|e| format!("proxy closed the connection: {}", e))?;
+        head.push(byte);
+    }
+    let status = String::from_utf8_lossy(&head)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok());
+    match status {
+        Some(200..=299) => Ok(stream),
+        Some(status) => Err(format!(
+            "proxy refused the tunnel to {} with HTTP {}",
+            target, status
+        )),
+        None => Err("proxy sent an invalid response".to_string()),
+    }
+}
+
+/// Builds a client for the reqwest based transports that resolves its proxy
+/// per request with [`for_url`].
+pub(crate) fn reqwest_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().proxy(reqwest::Proxy::custom(|url| {
+        for_url(url.as_str()).map(|p| p.url)
+    }))
+}
+
+/// Opens the connection of a tonic channel to `uri` through `proxy`.
+async fn tunnel(proxy: Arc<Proxy>, uri: http::Uri) -> std::io::Result<TokioIo<TcpStream>> {
+    let host = uri
+        .host()
+        .unwrap_or_default()
+        .trim_matches(['[', ']'])
+        .to_string();
+    let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+    connect(Some(&proxy), &host, port)
+        .await
+        .map(TokioIo::new)
+        .map_err(std::io::Error::other)
+}
+
+/// Builds a lazily connecting gRPC channel to `endpoint` that tunnels through
+/// `proxy`, for the OTLP exporter.
+#[cfg(feature = "telemetry")]
+pub(crate) fn otlp_channel(
+    endpoint: &str,
+    proxy: Proxy,
+) -> Result<tonic_otlp::transport::Channel, String> {
+    let endpoint = tonic_otlp::transport::Endpoint::from_shared(endpoint.to_string())
+        .map_err(|e| e.to_string())?;
+    let proxy = Arc::new(proxy);
+    Ok(endpoint
+        .connect_with_connector_lazy(tower::service_fn(move |uri| tunnel(proxy.clone(), uri))))
+}
+
+/// Builds a lazily connecting gRPC channel to `endpoint`, through the proxy
+/// [`for_url`] picks for it, if any.
+pub(crate) fn grpc_channel(endpoint: &str) -> Result<tonic::transport::Channel, String> {
+    let builder =
+        tonic::transport::Endpoint::from_shared(endpoint.to_string()).map_err(|e| e.to_string())?;
+    Ok(match for_url(endpoint) {
+        Some(proxy) => {
+            let proxy = Arc::new(proxy);
+            builder.connect_with_connector_lazy(tower::service_fn(move |uri| {
+                tunnel(proxy.clone(), uri)
+            }))
+        }
+        None => builder.connect_lazy(),
+    })
+}
+
+/// Routes outbound HTTP and gRPC connections (JWKS fetches, the OTLP exporter,
+/// direct invocations and `doctor` probes) through the HTTP proxy at `url`,
+/// instead of the one from `OPRC_PROXY`, `HTTPS_PROXY`, `HTTP_PROXY` or
+/// `ALL_PROXY`. Hosts in `no_proxy` (`*`, a host or a parent domain) are
+/// reached directly.
+///
+/// `set_proxy(None)` goes back to the environment. Call it before
+/// telemetry is enabled, as the exporter picks its proxy when it is built.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+#[pyo3(signature = (url, no_proxy=Vec::new()))]
+pub fn set_proxy(url: Option<String>, no_proxy: Vec<String>) -> PyResult<()> {
+    let explicit = match url {
+        Some(url) => {
+            Proxy::parse(&url).map_err(PyValueError::new_err)?;
+            Some(Explicit { url, no_proxy })
+        }
+        None => None,
+    };
+    *EXPLICIT.write().unwrap() = explicit;
+    Ok(())
+}
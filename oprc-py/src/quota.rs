@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Request option naming the tenant an invocation is accounted to.
+pub const TENANT_OPTION: &str = "oprc-tenant";
+/// Tenant used when a request does not carry `TENANT_OPTION`.
+pub const DEFAULT_TENANT: &str = "default";
+
+#[derive(Clone, Copy)]
+struct Quota {
+    max_invocations: Option<u64>,
+    max_payload_bytes: Option<u64>,
+    window: Duration,
+}
+
+struct TenantState {
+    quota: Option<Quota>,
+    window_start: Instant,
+    invocations: u64,
+    payload_bytes: u64,
+    total_invocations: u64,
+    total_payload_bytes: u64,
+    rejected: u64,
+}
+
+impl TenantState {
+    fn new() -> Self {
+        TenantState {
+            quota: None,
+            window_start: Instant::now(),
+            invocations: 0,
+            payload_bytes: 0,
+            total_invocations: 0,
+            total_payload_bytes: 0,
+            rejected: 0,
+        }
+    }
+
+    fn roll_window(&mut self) {
+        if self
+            .quota
+            .is_some_and(|q| self.window_start.elapsed() >= q.window)
+        {
+            self.window_start = Instant::now();
+            self.invocations = 0;
+            self.payload_bytes = 0;
+        }
+    }
+
+    fn usage(&self, tenant: &str) -> TenantUsage {
+        TenantUsage {
+            tenant: tenant.to_string(),
+            invocations: self.invocations,
+            payload_bytes: self.payload_bytes,
+            total_invocations: self.total_invocations,
+            total_payload_bytes: self.total_payload_bytes,
+            rejected: self.rejected,
+            max_invocations: self.quota.and_then(|q| q.max_invocations),
+            max_payload_bytes: self.quota.and_then(|q| q.max_payload_bytes),
+            window_ms: self.quota.map(|q| q.window.as_millis() as u64),
+        }
+    }
+}
+
+/// Tracks invocation counts and payload bytes per tenant and enforces
+/// optional per-tenant quotas over a fixed window.
+#[derive(Default)]
+pub struct QuotaTracker {
+    tenants: Mutex<HashMap<String, TenantState>>,
+}
+
+impl QuotaTracker {
+    /// Sets (or clears, when both limits are `None`) the quota of `tenant`.
+    pub fn set_quota(
+        &self,
+        tenant: &str,
+        max_invocations: Option<u64>,
+        max_payload_bytes: Option<u64>,
+        window: Duration,
+    ) {
+        let mut tenants = self.tenants.lock().unwrap();
+        let state = tenants
+            .entry(tenant.to_string())
+            .or_insert_with(TenantState::new);
+        state.quota = if max_invocations.is_none() && max_payload_bytes.is_none() {
+            None
+        } else {
+            Some(Quota {
+                max_invocations,
+                max_payload_bytes,
+                window,
+            })
+        };
+        state.window_start = Instant::now();
+        state.invocations = 0;
+        state.payload_bytes = 0;
+    }
+
+    /// Accounts one invocation of `payload_len` bytes to `tenant`.
+    ///
+    /// Returns `Err` with a description if the invocation would exceed the quota;
+    /// rejected invocations are not added to the window usage.
+    pub fn charge(&self, tenant: &str, payload_len: usize) -> Result<(), String> {
+        let mut tenants = self.tenants.lock().unwrap();
+        let state = tenants
+            .entry(tenant.to_string())
+            .or_insert_with(TenantState::new);
+        state.roll_window();
+        let bytes = payload_len as u64;
+        if let Some(q) = state.quota {
+            if let Some(max) = q.max_invocations.filter(|max| state.invocations + 1 > *max) {
+                state.rejected += 1;
+                return Err(format!(
+                    "tenant '{}' exceeded its quota of {} invocations per {} ms",
+                    tenant,
+                    max,
+                    q.window.as_millis()
+                ));
+            }
+            if let Some(max) = q
+                .max_payload_bytes
+                .filter(|max| state.payload_bytes + bytes > *max)
+            {
+                state.rejected += 1;
+                return Err(format!(
+                    "tenant '{}' exceeded its quota of {} payload bytes per {} ms",
+                    tenant,
+                    max,
+                    q.window.as_millis()
+                ));
+            }
+        }
+        state.invocations += 1;
+        state.payload_bytes += bytes;
+        state.total_invocations += 1;
+        state.total_payload_bytes += bytes;
+        Ok(())
+    }
+
+    /// Returns the usage of `tenant`, or of every known tenant when `None`.
+    pub fn usage(&self, tenant: Option<&str>) -> Vec<TenantUsage> {
+        let mut tenants = self.tenants.lock().unwrap();
+        match tenant {
+            Some(t) => tenants
+                .get_mut(t)
+                .map(|s| {
+                    s.roll_window();
+                    vec![s.usage(t)]
+                })
+                .unwrap_or_default(),
+            None => tenants
+                .iter_mut()
+                .map(|(t, s)| {
+                    s.roll_window();
+                    s.usage(t)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Resolves the tenant of a request from its options.
+pub fn tenant_of(options: &HashMap<String, String>) -> &str {
+    options
+        .get(TENANT_OPTION)
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_TENANT)
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Invocation consumption of a single tenant.
+pub struct TenantUsage {
+    /// The tenant name.
+    pub tenant: String,
+    /// Invocations accounted in the current quota window.
+    pub invocations: u64,
+    /// Payload bytes accounted in the current quota window.
+    pub payload_bytes: u64,
+    /// Invocations accounted since the server started.
+    pub total_invocations: u64,
+    /// Payload bytes accounted since the server started.
+    pub total_payload_bytes: u64,
+    /// Invocations rejected with `QuotaExceeded`.
+    pub rejected: u64,
+    /// Configured invocation limit per window, if any.
+    pub max_invocations: Option<u64>,
+    /// Configured payload byte limit per window, if any.
+    pub max_payload_bytes: Option<u64>,
+    /// Length of the quota window in milliseconds, if a quota is configured.
+    pub window_ms: Option<u64>,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl TenantUsage {
+    /// Returns a string representation of the `TenantUsage`.
+    fn __str__(&self) -> String {
+        format!(
+            "TenantUsage {{ tenant: {}, invocations: {}, payload_bytes: {}, rejected: {} }}",
+            self.tenant, self.invocations, self.payload_bytes, self.rejected
+        )
+    }
+}
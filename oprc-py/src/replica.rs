@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+use oprc_invoke::proxy::ObjectProxy;
+use oprc_pb::{ObjData, ObjMeta};
+use prost::Message;
+use pyo3::prelude::*;
+use tracing::debug;
+use zenoh::query::{ConsolidationMode, QueryTarget};
+
+use crate::data_stats::DataCounters;
+use crate::obj::ObjectData;
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass_enum)]
+#[pyclass(eq, eq_int)]
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+/// Where `DataManager` reads object state from.
+///
+/// Replicas may serve state older than the primary's. The data plane does not
+/// report how far a replica lags behind, so there is no staleness bound.
+pub enum ReadPreference {
+    /// Always read from the primary (freshest, default).
+    #[default]
+    Primary,
+    /// Ask every replica and use the first answer (lowest latency).
+    Nearest,
+    /// Ask a single replica chosen by Zenoh (least load on the data plane).
+    AnyReplica,
+}
+
+/// Read routing settings shared by a `DataManager` and its in-flight reads.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ReadSettings {
+    pub(crate) preference: ReadPreference,
+}
+
+/// An object read, with where it came from.
+pub(crate) struct Routed {
+    pub(crate) obj: Option<ObjData>,
+    /// Zenoh id of the replica that answered; `None` for the primary.
+    pub(crate) replica: Option<String>,
+}
+
+/// Reads objects according to the current `ReadSettings`.
+#[derive(Clone)]
+pub(crate) struct Reader {
+    pub(crate) proxy: ObjectProxy,
+    pub(crate) session: zenoh::Session,
+    pub(crate) settings: Arc<Mutex<ReadSettings>>,
+    /// Traffic of the reads, per class.
+    pub(crate) counters: Arc<DataCounters>,
+}
+
+impl Reader {
+    /// Reads `meta` with the configured preference, or with `preference` if given.
+    ///
+    /// Replica reads that fail or find nothing are retried on the primary.
+    /// Successful reads are counted.
+    pub(crate) async fn get(
+        &self,
+        meta: &ObjMeta,
+        preference: Option<ReadPreference>,
+    ) -> Result<Routed, String> {
+        let routed = self.route(meta, preference).await?;
+        self.counters.record_read(&meta.cls_id, routed.obj.as_ref());
+        Ok(routed)
+    }
+
+    /// Reads `meta` like `get`, without counting the read.
+    async fn route(
+        &self,
+        meta: &ObjMeta,
+        preference: Option<ReadPreference>,
+    ) -> Result<Routed, String> {
+        let preference = preference.unwrap_or(self.settings.lock().unwrap().preference);
+        if preference != ReadPreference::Primary {
+            match self.get_replica(meta, preference).await {
+                Ok(routed) => return Ok(routed),
+                Err(e) => debug!(
+                    "replica read of {}/{}/{} failed ({}); reading primary",
+                    meta.cls_id, meta.partition_id, meta.object_id, e
+                ),
+            }
+        }
+        let obj = self.proxy.get_obj(meta).await.map_err(|e| e.to_string())?;
+        Ok(Routed { obj, replica: None })
+    }
+
+    async fn get_replica(
+        &self,
+        meta: &ObjMeta,
+        preference: ReadPreference,
+    ) -> Result<Routed, String> {
+        let key = object_key(meta);
+        let target = match preference {
+            ReadPreference::Nearest => QueryTarget::All,
+            _ => QueryTarget::BestMatching,
+        };
+        let replies = self
+            .session
+            .get(key.as_str())
+            .target(target)
+            .consolidation(ConsolidationMode::None)
+            .await
+            .map_err(|e| e.to_string())?;
+        let reply = replies
+            .recv_async()
+            .await
+            .map_err(|_| "no replica answered".to_string())?;
+        let replica = reply.replier_id().map(|id| id.zid().to_string());
+        let sample = reply.result().map_err(|e| {
+            e.payload()
+                .try_to_string()
+                .map(|s| s.into_owned())
+                .unwrap_or_else(|_| "replica returned an error".to_string())
+        })?;
+        let obj = ObjData::decode(sample.payload().to_bytes().as_ref())
+            .map_err(|e| format!("invalid object from replica: {}", e))?;
+        Ok(Routed {
+            obj: Some(obj),
+            replica,
+        })
+    }
+}
+
+/// Key expression the data plane serves the object described by `meta` on.
+pub(crate) fn object_key(meta: &ObjMeta) -> String {
+    format!(
+        "oprc/{}/{}/objects/{}",
+        meta.cls_id, meta.partition_id, meta.object_id
+    )
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+/// An object read together with where it was served from.
+pub struct ReadResult {
+    /// The object, or `None` if it does not exist.
+    pub obj: Option<Py<ObjectData>>,
+    /// Zenoh id of the replica that served the read; `None` if the primary did.
+    pub replica: Option<String>,
+}
+
+impl ReadResult {
+    /// Wraps a routed read whose object was already converted outside the GIL.
+    pub(crate) fn new(py: Python<'_>, obj: Option<ObjectData>, routed: Routed) -> PyResult<Self> {
+        Ok(ReadResult {
+            obj: obj.map(|o| Py::new(py, o)).transpose()?,
+            replica: routed.replica,
+        })
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ReadResult {
+    /// `True` if the read was served by the primary.
+    #[getter]
+    fn from_primary(&self) -> bool {
+        self.replica.is_none()
+    }
+}
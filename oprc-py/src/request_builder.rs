@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    codec::Codec,
+    deadline, envelope,
+    envelope::TYPE_URL_OPTION,
+    ids,
+    model::{self, HEADER_OPTION_PREFIX, IDEMPOTENCY_KEY_OPTION, ObjectInvocationRequest},
+    rpc::{
+        cache::CACHE_TTL_OPTION,
+        lanes::{LANE_OPTION, Lane},
+        local::PREFER_LOCAL_OPTION,
+        partitioning::AUTO_PARTITION_OPTION,
+    },
+    sticky::{self, REPLICA_ID_OPTION},
+    timefmt,
+};
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+#[derive(Clone, Default)]
+/// Builds an `ObjectInvocationRequest` field by field, as made by
+/// `ObjectInvocationRequest.builder()`. Every setter returns the builder, so
+/// calls chain. Nothing is checked until `validate` or `build`.
+pub struct ObjectInvocationRequestBuilder {
+    cls_id: Option<String>,
+    fn_id: Option<String>,
+    object_id: Option<u64>,
+    partition_id: u32,
+    options: HashMap<String, String>,
+    payload: Vec<u8>,
+    timeout_ms: Option<u64>,
+    expected_version: Option<u64>,
+    max_retries: Option<u32>,
+    headers: HashMap<String, String>,
+}
+
+impl ObjectInvocationRequestBuilder {
+    /// Appends a problem with an id named `kind` to `problems`.
+    fn check_id(problems: &mut Vec<String>, kind: &str, value: Option<&str>) {
+        match value {
+            None => problems.push(format!("{} is not set", kind)),
+            Some("") => problems.push(format!("{} must not be empty", kind)),
+            Some(value) => {
+                if let Some(c) = model::illegal_char(value) {
+                    problems.push(format!(
+                        "{} '{}' contains illegal character {:?}",
+                        kind, value, c
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ObjectInvocationRequestBuilder {
+    #[new]
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the class of the object.
+    pub fn cls_id(mut slf: PyRefMut<'_, Self>, cls_id: String) -> PyRefMut<'_, Self> {
+        slf.cls_id = Some(cls_id);
+        slf
+    }
+
+    /// Sets the function to invoke.
+    pub fn fn_id(mut slf: PyRefMut<'_, Self>, fn_id: String) -> PyRefMut<'_, Self> {
+        slf.fn_id = Some(fn_id);
+        slf
+    }
+
+    /// Sets the object to invoke the function on.
+    pub fn object_id(
+        mut slf: PyRefMut<'_, Self>,
+        #[pyo3(from_py_with = ids::object_id)] object_id: u64,
+    ) -> PyRefMut<'_, Self> {
+        slf.object_id = Some(object_id);
+        slf
+    }
+
+    /// Sets the partition of the object; `0` unless set.
+    pub fn partition_id(
+        mut slf: PyRefMut<'_, Self>,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+    ) -> PyRefMut<'_, Self> {
+        slf.partition_id = partition_id;
+        slf
+    }
+
+    /// Sets the option `key` to `value`.
+    pub fn option(mut slf: PyRefMut<'_, Self>, key: String, value: String) -> PyRefMut<'_, Self> {
+        slf.options.insert(key, value);
+        slf
+    }
+
+    /// Sets every option in `options`, keeping the others.
+    pub fn options(
+        mut slf: PyRefMut<'_, Self>,
+        options: HashMap<String, String>,
+    ) -> PyRefMut<'_, Self> {
+        slf.options.extend(options);
+        slf
+    }
+
+    /// Sets the header `name` to `value`.
+    pub fn header(mut slf: PyRefMut<'_, Self>, name: String, value: String) -> PyRefMut<'_, Self> {
+        slf.headers.insert(name, value);
+        slf
+    }
+
+    /// Sets the raw payload.
+    pub fn payload(mut slf: PyRefMut<'_, Self>, payload: Vec<u8>) -> PyRefMut<'_, Self> {
+        slf.payload = payload;
+        slf
+    }
+
+    /// Sets the payload to `value` encoded with `codec`: `json` (the
+    /// default), `msgpack` or `pickle`. Raises if `value` cannot be encoded.
+    #[pyo3(signature = (value, codec="json"))]
+    pub fn value<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        value: &Bound<'py, PyAny>,
+        codec: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let codec: Codec = codec.parse().map_err(PyValueError::new_err)?;
+        slf.payload = codec.encode(value)?;
+        Ok(slf)
+    }
+
+    /// Sets the payload to `value` wrapped in a `google.protobuf.Any`; see
+    /// `ObjectInvocationRequest.pack_payload`.
+    pub fn packed(
+        mut slf: PyRefMut<'_, Self>,
+        type_url: String,
+        value: Vec<u8>,
+    ) -> PyRefMut<'_, Self> {
+        slf.payload = envelope::pack(&type_url, value);
+        slf.options.insert(TYPE_URL_OPTION.to_string(), type_url);
+        slf
+    }
+
+    /// Sets the timeout of the invocation in milliseconds.
+    pub fn timeout_ms(mut slf: PyRefMut<'_, Self>, timeout_ms: u64) -> PyRefMut<'_, Self> {
+        slf.timeout_ms = Some(timeout_ms);
+        slf
+    }
+
+    /// Sets the retry limit of the invocation.
+    pub fn max_retries(mut slf: PyRefMut<'_, Self>, max_retries: u32) -> PyRefMut<'_, Self> {
+        slf.max_retries = Some(max_retries);
+        slf
+    }
+
+    /// Sets the object version the invocation expects.
+    pub fn expected_version(mut slf: PyRefMut<'_, Self>, version: u64) -> PyRefMut<'_, Self> {
+        slf.expected_version = Some(version);
+        slf
+    }
+
+    /// Sets the absolute deadline in Unix epoch milliseconds.
+    pub fn deadline_ms(mut slf: PyRefMut<'_, Self>, deadline_ms: u64) -> PyRefMut<'_, Self> {
+        deadline::set(&mut slf.options, Some(deadline_ms));
+        slf
+    }
+
+    /// Sets the idempotency key of the invocation.
+    pub fn idempotency_key(mut slf: PyRefMut<'_, Self>, key: String) -> PyRefMut<'_, Self> {
+        slf.options.insert(IDEMPOTENCY_KEY_OPTION.to_string(), key);
+        slf
+    }
+
+    /// Sets whether a handler of this process runs the invocation first.
+    pub fn prefer_local(mut slf: PyRefMut<'_, Self>, prefer_local: bool) -> PyRefMut<'_, Self> {
+        slf.options
+            .insert(PREFER_LOCAL_OPTION.to_string(), prefer_local.to_string());
+        slf
+    }
+
+    /// Sets whether the partition is computed from the object id; see
+    /// `ClassDefaults.partitions`.
+    pub fn auto_partition(mut slf: PyRefMut<'_, Self>, auto_partition: bool) -> PyRefMut<'_, Self> {
+        slf.options.insert(
+            AUTO_PARTITION_OPTION.to_string(),
+            auto_partition.to_string(),
+        );
+        slf
+    }
+
+    /// Caches the response for `ttl_ms` milliseconds, so identical
+    /// invocations within that time skip the round trip; see
+    /// `RpcManager.cache_capacity`.
+    pub fn cache_ttl_ms(mut slf: PyRefMut<'_, Self>, ttl_ms: u64) -> PyRefMut<'_, Self> {
+        slf.options
+            .insert(CACHE_TTL_OPTION.to_string(), ttl_ms.to_string());
+        slf
+    }
+
+    /// Sends the invocation in priority lane `lane`: `high`, `normal` or
+    /// `low`; see `RpcManager.set_lane_limit`.
+    pub fn lane(mut slf: PyRefMut<'_, Self>, lane: String) -> PyRefMut<'_, Self> {
+        slf.options.insert(LANE_OPTION.to_string(), lane);
+        slf
+    }
+
+    /// Pins the invocation to a replica; see `ObjectInvocationRequest.replica_id`.
+    pub fn replica_id(mut slf: PyRefMut<'_, Self>, replica_id: String) -> PyRefMut<'_, Self> {
+        slf.options
+            .insert(REPLICA_ID_OPTION.to_string(), replica_id);
+        slf
+    }
+
+    /// Sets the option `key` to `value` as an ISO 8601 duration.
+    pub fn duration_option(
+        mut slf: PyRefMut<'_, Self>,
+        key: String,
+        value: Duration,
+    ) -> PyRefMut<'_, Self> {
+        slf.options.insert(key, timefmt::format_duration(value));
+        slf
+    }
+
+    /// Sets the option `key` to `value` as an RFC 3339 timestamp.
+    pub fn timestamp_option(
+        mut slf: PyRefMut<'_, Self>,
+        key: String,
+        value: SystemTime,
+    ) -> PyRefMut<'_, Self> {
+        slf.options.insert(key, timefmt::format_timestamp(value));
+        slf
+    }
+
+    /// Returns every problem that keeps the request from being built, empty
+    /// if there is none.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        Self::check_id(&mut problems, "cls_id", self.cls_id.as_deref());
+        Self::check_id(&mut problems, "fn_id", self.fn_id.as_deref());
+        if self.object_id.is_none() {
+            problems.push("object_id is not set".to_string());
+        }
+        if self.timeout_ms == Some(0) {
+            problems.push("timeout_ms must be > 0".to_string());
+        }
+        let mut reserved: Vec<&String> = self
+            .options
+            .keys()
+            .filter(|key| key.starts_with(HEADER_OPTION_PREFIX))
+            .collect();
+        reserved.sort();
+        for key in reserved {
+            problems.push(format!(
+                "option '{}' is reserved for headers, set it with header()",
+                key
+            ));
+        }
+        if let Some(Err(e)) = self
+            .options
+            .get(REPLICA_ID_OPTION)
+            .map(|replica| sticky::check_replica(replica))
+        {
+            problems.push(e);
+        }
+        if let Some(value) = self
+            .options
+            .get(CACHE_TTL_OPTION)
+            .filter(|v| v.parse::<u64>().is_err())
+        {
+            problems.push(format!(
+                "{} must be a number of milliseconds, got '{}'",
+                CACHE_TTL_OPTION, value
+            ));
+        }
+        if let Some(Err(e)) = self.options.get(LANE_OPTION).map(|v| v.parse::<Lane>()) {
+            problems.push(e);
+        }
+        for option in [PREFER_LOCAL_OPTION, AUTO_PARTITION_OPTION] {
+            if let Some(value) = self
+                .options
+                .get(option)
+                .filter(|v| *v != "true" && *v != "false")
+            {
+                problems.push(format!(
+                    "{} must be 'true' or 'false', got '{}'",
+                    option, value
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Builds the request. Raises `ValueError` listing every problem
+    /// `validate` finds.
+    pub fn build(&self) -> PyResult<ObjectInvocationRequest> {
+        let problems = self.validate();
+        if !problems.is_empty() {
+            return Err(PyValueError::new_err(format!(
+                "invalid ObjectInvocationRequest: {}",
+                problems.join("; ")
+            )));
+        }
+        Ok(ObjectInvocationRequest::new(
+            self.cls_id.clone().unwrap_or_default(),
+            self.fn_id.clone().unwrap_or_default(),
+            self.object_id.unwrap_or_default(),
+            self.partition_id,
+            self.options.clone(),
+            self.payload.clone(),
+            self.timeout_ms,
+            self.expected_version,
+            self.max_retries,
+            self.headers.clone(),
+        ))
+    }
+}
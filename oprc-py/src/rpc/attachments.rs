@@ -0,0 +1,129 @@
+use std::{collections::HashMap, str::FromStr};
+
+use prost::Message;
+use zenoh::query::QueryTarget;
+
+use super::{ProtoRequest, qos::Qos};
+use crate::compat;
+use crate::errors::{CodedError, ErrorCode};
+use crate::replica::object_key;
+
+/// Where the options of an outgoing request (and the headers of its response)
+/// travel over Zenoh.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum OptionsEncoding {
+    /// Inside the protobuf request, as this SDK's servers expect.
+    #[default]
+    Embedded,
+    /// In the Zenoh attachment, as a JSON object; the protobuf request is
+    /// sent without options.
+    Attachment,
+}
+
+impl OptionsEncoding {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            OptionsEncoding::Embedded => "embedded",
+            OptionsEncoding::Attachment => "attachment",
+        }
+    }
+}
+
+impl FromStr for OptionsEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "embedded" => Ok(OptionsEncoding::Embedded),
+            "attachment" => Ok(OptionsEncoding::Attachment),
+            other => Err(format!(
+                "unknown options encoding '{}'; expected 'embedded' or 'attachment'",
+                other
+            )),
+        }
+    }
+}
+
+/// Key expression the invocation `req` is served on.
+pub(crate) fn invoke_key(req: &ProtoRequest) -> String {
+    match req {
+        ProtoRequest::Fn(r) => format!("oprc/{}/{}/invokes/{}", r.cls_id, r.partition_id, r.fn_id),
+        ProtoRequest::Obj(r) => format!(
+            "{}/invokes/{}",
+            object_key(&oprc_pb::ObjMeta {
+                cls_id: r.cls_id.clone(),
+                partition_id: r.partition_id,
+                object_id: r.object_id,
+            }),
+            r.fn_id
+        ),
+    }
+}
+
+/// Sends a single attempt of `req` directly over the session to `key` with
+/// `qos`, with its options embedded or in the Zenoh attachment per
+/// `encoding`. Headers found in the reply's attachment are added to the
+/// response, without replacing ones embedded in it.
+pub(crate) async fn send(
+    session: &zenoh::Session,
+    req: &ProtoRequest,
+    key: &str,
+    encoding: OptionsEncoding,
+    qos: Qos,
+) -> Result<oprc_pb::InvocationResponse, CodedError> {
+    let mut req = req.clone();
+    let options = match encoding {
+        OptionsEncoding::Attachment => std::mem::take(req.options_mut()),
+        OptionsEncoding::Embedded => HashMap::new(),
+    };
+    let payload = match &req {
+        ProtoRequest::Fn(r) => r.encode_to_vec(),
+        ProtoRequest::Obj(r) => r.encode_to_vec(),
+    };
+    let mut query = session
+        .get(key)
+        .payload(payload)
+        .target(QueryTarget::BestMatching);
+    if encoding == OptionsEncoding::Attachment {
+        let attachment = serde_json::to_vec(&options)
+            .map_err(|e| CodedError::new(ErrorCode::InvalidRequest, e.to_string()))?;
+        query = query.attachment(attachment);
+    }
+    if let Some(priority) = qos.priority {
+        query = query.priority(priority);
+    }
+    if let Some(congestion_control) = qos.congestion_control {
+        query = query.congestion_control(congestion_control);
+    }
+    if let Some(express) = qos.express {
+        query = query.express(express);
+    }
+    let replies = query
+        .await
+        .map_err(|e| CodedError::new(ErrorCode::TransportError, e.to_string()))?;
+    let reply = replies.recv_async().await.map_err(|_| {
+        CodedError::new(
+            ErrorCode::Unavailable,
+            format!("no handler answered {}", key),
+        )
+    })?;
+    let sample = reply.result().map_err(|e| {
+        let msg = e
+            .payload()
+            .try_to_string()
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| "invocation failed".to_string());
+        CodedError::new(ErrorCode::SystemError, msg)
+    })?;
+    let invalid = |msg| CodedError::new(ErrorCode::InvalidResponse, msg);
+    let mut response = compat::decode_response(sample.payload().to_bytes().as_ref())
+        .map_err(|e| invalid(format!("invalid response: {}", e)))?;
+    if let Some(attachment) = sample.attachment() {
+        let headers: HashMap<String, String> = serde_json::from_slice(&attachment.to_bytes())
+            .map_err(|e| invalid(format!("invalid response attachment: {}", e)))?;
+        for (k, v) in headers {
+            response.headers.entry(k).or_insert(v);
+        }
+    }
+    Ok(response)
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use zenoh::{
+    key_expr::{KeyExpr, OwnedKeyExpr},
+    sample::SampleKind,
+};
+
+use crate::sticky;
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass_enum)]
+#[pyclass(eq, eq_int)]
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+/// How `RpcManager` picks the replica a stateless function invocation goes
+/// to, among those serving it.
+pub enum LoadBalancing {
+    /// Leave the choice to Zenoh (default).
+    #[default]
+    Zenoh,
+    /// Take the replicas in turn.
+    RoundRobin,
+    /// Pick a replica at random.
+    Random,
+    /// Pick the replica with the fewest invocations of this manager in flight.
+    LeastLoaded,
+}
+
+#[derive(Default)]
+struct State {
+    /// Replicas serving each key expression, from their liveliness tokens.
+    served: HashMap<OwnedKeyExpr, BTreeSet<String>>,
+    /// Attempts of this manager in flight per replica.
+    inflight: HashMap<String, usize>,
+    /// Turns taken per invoked key expression, for round-robin.
+    turns: HashMap<String, usize>,
+}
+
+/// Replicas of the functions invoked, learned from the liveliness tokens
+/// engines declare next to the functions they serve. Watching starts with
+/// the first strategy other than `Zenoh`.
+#[derive(Default)]
+pub(crate) struct ReplicaTable {
+    state: Mutex<State>,
+    watcher: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for ReplicaTable {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// An attempt sent to a replica chosen by the load balancer, counted as in
+/// flight until dropped.
+pub(crate) struct Lease {
+    table: Arc<ReplicaTable>,
+    replica: String,
+}
+
+impl Lease {
+    pub(crate) fn replica(&self) -> &str {
+        &self.replica
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut state = self.table.state.lock().unwrap();
+        if let Some(n) = state.inflight.get_mut(&self.replica) {
+            *n -= 1;
+            if *n == 0 {
+                state.inflight.remove(&self.replica);
+            }
+        }
+    }
+}
+
+/// The replicas in `state` serving `key_expr`, sorted by id.
+fn serving(state: &State, key_expr: &KeyExpr<'_>) -> Vec<String> {
+    state
+        .served
+        .iter()
+        .filter(|(served, _)| served.intersects(key_expr))
+        .flat_map(|(_, replicas)| replicas.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+impl ReplicaTable {
+    /// Starts following the replicas that join and leave over `session`,
+    /// unless already doing so.
+    pub(crate) fn watch(self: &Arc<Self>, session: &zenoh::Session) {
+        let mut watcher = self.watcher.lock().unwrap();
+        if watcher.is_some() {
+            return;
+        }
+        let table = Arc::downgrade(self);
+        let session = session.clone();
+        *watcher = Some(get_runtime().spawn(async move {
+            let tokens = sticky::pinned_key("**", "*");
+            let subscriber = match session
+                .liveliness()
+                .declare_subscriber(&tokens)
+                .history(true)
+                .await
+            {
+                Ok(subscriber) => subscriber,
+                Err(e) => {
+                    warn!("failed to watch replicas on {}: {}", tokens, e);
+                    return;
+                }
+            };
+            while let Ok(sample) = subscriber.recv_async().await {
+                let Some(table) = table.upgrade() else {
+                    break;
+                };
+                table.update(sample.key_expr(), sample.kind() == SampleKind::Put);
+            }
+        }));
+    }
+
+    /// Stops following the replicas, if doing so.
+    pub(crate) fn stop(&self) {
+        if let Some(watcher) = self.watcher.lock().unwrap().take() {
+            watcher.abort();
+        }
+    }
+
+    /// Records the token on `key_expr` as declared or, if not `alive`,
+    /// undeclared.
+    fn update(&self, key_expr: &KeyExpr<'_>, alive: bool) {
+        let Some((served, replica)) = sticky::split_pinned(key_expr) else {
+            return;
+        };
+        debug!(
+            "replica {} {} {}",
+            replica,
+            if alive { "serves" } else { "left" },
+            served
+        );
+        let mut state = self.state.lock().unwrap();
+        if alive {
+            state.served.entry(served).or_default().insert(replica);
+        } else if let Some(replicas) = state.served.get_mut(&served) {
+            replicas.remove(&replica);
+            if replicas.is_empty() {
+                state.served.remove(&served);
+            }
+        }
+    }
+
+    /// Returns the known replicas serving `key`, sorted by id.
+    pub(crate) fn serving(&self, key: &str) -> Vec<String> {
+        let Ok(key_expr) = KeyExpr::try_from(key) else {
+            return Vec::new();
+        };
+        serving(&self.state.lock().unwrap(), &key_expr)
+    }
+
+    /// Leases the replica the next attempt on `key` goes to per `strategy`.
+    /// `None` if no replica serving `key` is known.
+    pub(crate) fn lease(self: &Arc<Self>, key: &str, strategy: LoadBalancing) -> Option<Lease> {
+        let key_expr = KeyExpr::try_from(key).ok()?;
+        let mut state = self.state.lock().unwrap();
+        let candidates = serving(&state, &key_expr);
+        if candidates.is_empty() {
+            return None;
+        }
+        let turn = state.turns.entry(key.to_string()).or_default();
+        let start = *turn % candidates.len();
+        *turn = turn.wrapping_add(1);
+        let replica = match strategy {
+            LoadBalancing::Zenoh => return None,
+            LoadBalancing::RoundRobin => candidates[start].clone(),
+            LoadBalancing::Random => candidates[fastrand::usize(..candidates.len())].clone(),
+            // Ties go to the replicas in turn, so an idle pool is still spread.
+            LoadBalancing::LeastLoaded => candidates[start..]
+                .iter()
+                .chain(&candidates[..start])
+                .min_by_key(|r| state.inflight.get(*r).copied().unwrap_or(0))?
+                .clone(),
+        };
+        *state.inflight.entry(replica.clone()).or_default() += 1;
+        Some(Lease {
+            table: self.clone(),
+            replica,
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pyo3::create_exception!(
+    oprc_py,
+    CircuitOpenError,
+    pyo3::exceptions::PyRuntimeError,
+    "Raised without sending when the circuit of the invoked function is open."
+);
+
+/// State of the circuit of one `(cls_id, fn_id)` target.
+#[derive(Clone, Copy)]
+enum Circuit {
+    /// Invocations pass; counts the failures in a row.
+    Closed(u32),
+    /// Invocations fail fast until the instant.
+    Open(Instant),
+    /// One probe invocation, started at the instant, decides whether the
+    /// circuit closes again. A probe that never reports back, e.g. because
+    /// it was cancelled, is replaced after the cool-down.
+    HalfOpen(Instant),
+}
+
+struct BreakerState {
+    /// Failures in a row that open a circuit; `0` disables the breaker.
+    failure_threshold: u32,
+    cool_down: Duration,
+    circuits: HashMap<(String, String), Circuit>,
+}
+
+/// Circuit breakers of the targets invoked through one `RpcManager`.
+///
+/// A target's circuit opens after `failure_threshold` failed invocations in
+/// a row. While open, invocations of it fail fast with `CircuitOpenError`.
+/// After the cool-down, the next invocation goes through as a probe: its
+/// success closes the circuit, its failure opens it for another cool-down.
+pub(crate) struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker {
+            state: Mutex::new(BreakerState {
+                failure_threshold: 0,
+                cool_down: Duration::from_secs(30),
+                circuits: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Updates the breaker parameters, closing all circuits.
+    pub(crate) fn configure(&self, failure_threshold: u32, cool_down: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.failure_threshold = failure_threshold;
+        state.cool_down = cool_down;
+        state.circuits.clear();
+    }
+
+    /// Checks whether an invocation of `cls_id`/`fn_id` may be sent,
+    /// returning the error to raise if its circuit is open.
+    pub(crate) fn admit(&self, cls_id: &str, fn_id: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        if state.failure_threshold == 0 {
+            return Ok(());
+        }
+        let cool_down = state.cool_down;
+        let Some(circuit) = state
+            .circuits
+            .get_mut(&(cls_id.to_string(), fn_id.to_string()))
+        else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        match *circuit {
+            Circuit::Closed(_) => Ok(()),
+            Circuit::Open(until) if until > now => Err(format!(
+                "circuit of {}/{} is open for another {} ms",
+                cls_id,
+                fn_id,
+                (until - now).as_millis()
+            )),
+            Circuit::HalfOpen(probe) if probe + cool_down > now => Err(format!(
+                "circuit of {}/{} is half-open; a probe invocation is in flight",
+                cls_id, fn_id
+            )),
+            Circuit::Open(_) | Circuit::HalfOpen(_) => {
+                *circuit = Circuit::HalfOpen(now);
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the outcome of an admitted invocation of `cls_id`/`fn_id`.
+    pub(crate) fn record(&self, cls_id: &str, fn_id: &str, failed: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.failure_threshold == 0 {
+            return;
+        }
+        let threshold = state.failure_threshold;
+        let open = Circuit::Open(Instant::now() + state.cool_down);
+        let key = (cls_id.to_string(), fn_id.to_string());
+        if !failed {
+            state.circuits.remove(&key);
+            return;
+        }
+        let circuit = state.circuits.entry(key).or_insert(Circuit::Closed(0));
+        *circuit = match *circuit {
+            Circuit::Closed(failures) if failures + 1 < threshold => Circuit::Closed(failures + 1),
+            // A failure reported while open comes from an invocation admitted
+            // before the circuit opened; it keeps the cool-down running.
+            Circuit::Open(until) => Circuit::Open(until),
+            Circuit::Closed(_) | Circuit::HalfOpen(_) => open,
+        };
+    }
+
+    /// Name of the state of the circuit of `cls_id`/`fn_id`: `closed`,
+    /// `open` or `half_open`.
+    pub(crate) fn state(&self, cls_id: &str, fn_id: &str) -> &'static str {
+        let state = self.state.lock().unwrap();
+        match state.circuits.get(&(cls_id.to_string(), fn_id.to_string())) {
+            None | Some(Circuit::Closed(_)) => "closed",
+            Some(Circuit::Open(until)) if *until > Instant::now() => "open",
+            Some(Circuit::Open(_) | Circuit::HalfOpen(_)) => "half_open",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOL_DOWN: Duration = Duration::from_millis(30);
+
+    fn breaker(failure_threshold: u32) -> CircuitBreaker {
+        let breaker = CircuitBreaker::default();
+        breaker.configure(failure_threshold, COOL_DOWN);
+        breaker
+    }
+
+    fn fail(breaker: &CircuitBreaker, times: u32) {
+        for _ in 0..times {
+            breaker.admit("cls", "fn").unwrap();
+            breaker.record("cls", "fn", true);
+        }
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..100 {
+            breaker.record("cls", "fn", true);
+        }
+        assert_eq!(breaker.state("cls", "fn"), "closed");
+        assert!(breaker.admit("cls", "fn").is_ok());
+    }
+
+    #[test]
+    fn opens_after_failures_in_a_row() {
+        let breaker = breaker(3);
+        fail(&breaker, 2);
+        breaker.record("cls", "fn", false);
+        fail(&breaker, 2);
+        assert_eq!(breaker.state("cls", "fn"), "closed");
+        fail(&breaker, 1);
+        assert_eq!(breaker.state("cls", "fn"), "open");
+        assert!(breaker.admit("cls", "fn").is_err());
+        assert!(breaker.admit("cls", "other").is_ok());
+    }
+
+    #[test]
+    fn probe_success_closes_the_circuit() {
+        let breaker = breaker(1);
+        fail(&breaker, 1);
+        std::thread::sleep(COOL_DOWN * 2);
+        assert_eq!(breaker.state("cls", "fn"), "half_open");
+        assert!(breaker.admit("cls", "fn").is_ok());
+        // Only one probe at a time.
+        assert!(breaker.admit("cls", "fn").is_err());
+        breaker.record("cls", "fn", false);
+        assert_eq!(breaker.state("cls", "fn"), "closed");
+        assert!(breaker.admit("cls", "fn").is_ok());
+    }
+
+    #[test]
+    fn probe_failure_reopens_the_circuit() {
+        let breaker = breaker(1);
+        fail(&breaker, 1);
+        std::thread::sleep(COOL_DOWN * 2);
+        assert!(breaker.admit("cls", "fn").is_ok());
+        breaker.record("cls", "fn", true);
+        assert_eq!(breaker.state("cls", "fn"), "open");
+        assert!(breaker.admit("cls", "fn").is_err());
+    }
+
+    #[test]
+    fn lost_probe_is_replaced_after_the_cool_down() {
+        let breaker = breaker(1);
+        fail(&breaker, 1);
+        std::thread::sleep(COOL_DOWN * 2);
+        assert!(breaker.admit("cls", "fn").is_ok());
+        std::thread::sleep(COOL_DOWN * 2);
+        assert!(breaker.admit("cls", "fn").is_ok());
+    }
+
+    #[test]
+    fn configure_closes_all_circuits() {
+        let breaker = breaker(1);
+        fail(&breaker, 1);
+        breaker.configure(1, COOL_DOWN);
+        assert_eq!(breaker.state("cls", "fn"), "closed");
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of buckets the sliding window is divided into.
+const BUCKETS: usize = 10;
+
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    slot: u64,
+    requests: u64,
+    retries: u64,
+}
+
+struct BudgetState {
+    max_retry_ratio: f64,
+    min_retries: u64,
+    window: Duration,
+    buckets: [Bucket; BUCKETS],
+    total_requests: u64,
+    total_retries_allowed: u64,
+    total_retries_rejected: u64,
+}
+
+impl BudgetState {
+    fn bucket_width(&self) -> Duration {
+        (self.window / BUCKETS as u32).max(Duration::from_millis(1))
+    }
+
+    fn current_slot(&self, origin: Instant) -> u64 {
+        (origin.elapsed().as_millis() / self.bucket_width().as_millis()) as u64
+    }
+
+    fn bucket_mut(&mut self, slot: u64) -> &mut Bucket {
+        let bucket = &mut self.buckets[(slot % BUCKETS as u64) as usize];
+        if bucket.slot != slot {
+            *bucket = Bucket {
+                slot,
+                ..Default::default()
+            };
+        }
+        bucket
+    }
+
+    fn window_totals(&self, slot: u64) -> (u64, u64) {
+        self.buckets
+            .iter()
+            .filter(|b| b.slot + (BUCKETS as u64) > slot && b.slot <= slot)
+            .fold((0, 0), |(req, ret), b| (req + b.requests, ret + b.retries))
+    }
+}
+
+/// A retry budget shared by every invocation issued through one `RpcManager`.
+///
+/// Retries are only allowed while the number of retries in the sliding window
+/// stays below `max_retry_ratio` of the first attempts in the same window
+/// (plus a small `min_retries` allowance), so retries cannot multiply load
+/// during an outage.
+pub(crate) struct RetryBudget {
+    origin: Instant,
+    state: Mutex<BudgetState>,
+}
+
+impl RetryBudget {
+    pub(crate) fn new(max_retry_ratio: f64, window: Duration, min_retries: u64) -> Self {
+        RetryBudget {
+            origin: Instant::now(),
+            state: Mutex::new(BudgetState {
+                max_retry_ratio,
+                min_retries,
+                window,
+                buckets: [Bucket::default(); BUCKETS],
+                total_requests: 0,
+                total_retries_allowed: 0,
+                total_retries_rejected: 0,
+            }),
+        }
+    }
+
+    /// Updates the budget parameters, discarding the current window.
+    pub(crate) fn configure(&self, max_retry_ratio: f64, window: Duration, min_retries: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.max_retry_ratio = max_retry_ratio;
+        state.window = window;
+        state.min_retries = min_retries;
+        state.buckets = [Bucket::default(); BUCKETS];
+    }
+
+    /// Records a first attempt of an invocation.
+    pub(crate) fn record_request(&self) {
+        let mut state = self.state.lock().unwrap();
+        let slot = state.current_slot(self.origin);
+        state.bucket_mut(slot).requests += 1;
+        state.total_requests += 1;
+    }
+
+    /// Tries to withdraw one retry from the budget. Returns `false` when the
+    /// budget is exhausted and the caller must not retry.
+    pub(crate) fn try_acquire_retry(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let slot = state.current_slot(self.origin);
+        let (requests, retries) = state.window_totals(slot);
+        let allowed = state.min_retries + (requests as f64 * state.max_retry_ratio) as u64;
+        if retries < allowed {
+            state.bucket_mut(slot).retries += 1;
+            state.total_retries_allowed += 1;
+            true
+        } else {
+            state.total_retries_rejected += 1;
+            false
+        }
+    }
+
+    pub(crate) fn stats(&self) -> RetryBudgetStats {
+        let state = self.state.lock().unwrap();
+        let slot = state.current_slot(self.origin);
+        let (requests, retries) = state.window_totals(slot);
+        let allowed = state.min_retries + (requests as f64 * state.max_retry_ratio) as u64;
+        RetryBudgetStats {
+            max_retry_ratio: state.max_retry_ratio,
+            window_ms: state.window.as_millis() as u64,
+            min_retries: state.min_retries,
+            window_requests: requests,
+            window_retries: retries,
+            window_retries_available: allowed.saturating_sub(retries),
+            total_requests: state.total_requests,
+            total_retries_allowed: state.total_retries_allowed,
+            total_retries_rejected: state.total_retries_rejected,
+        }
+    }
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        RetryBudget::new(0.2, Duration::from_secs(10), 10)
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Snapshot of the retry budget consumption of an `RpcManager`.
+pub struct RetryBudgetStats {
+    /// Maximum ratio of retries to first attempts within the window.
+    pub max_retry_ratio: f64,
+    /// Length of the sliding window in milliseconds.
+    pub window_ms: u64,
+    /// Retries always allowed per window regardless of traffic.
+    pub min_retries: u64,
+    /// First attempts recorded in the current window.
+    pub window_requests: u64,
+    /// Retries withdrawn in the current window.
+    pub window_retries: u64,
+    /// Retries still available in the current window.
+    pub window_retries_available: u64,
+    /// First attempts recorded since the manager was created.
+    pub total_requests: u64,
+    /// Retries granted since the manager was created.
+    pub total_retries_allowed: u64,
+    /// Retries denied because the budget was exhausted.
+    pub total_retries_rejected: u64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl RetryBudgetStats {
+    /// Returns a string representation of the `RetryBudgetStats`.
+    fn __str__(&self) -> String {
+        format!(
+            "RetryBudgetStats {{ window_requests: {}, window_retries: {}, available: {}, rejected: {} }}",
+            self.window_requests,
+            self.window_retries,
+            self.window_retries_available,
+            self.total_retries_rejected
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_min_retries_without_traffic() {
+        let budget = RetryBudget::new(0.0, Duration::from_secs(60), 2);
+        assert!(budget.try_acquire_retry());
+        assert!(budget.try_acquire_retry());
+        assert!(!budget.try_acquire_retry());
+        let stats = budget.stats();
+        assert_eq!(stats.total_retries_allowed, 2);
+        assert_eq!(stats.total_retries_rejected, 1);
+        assert_eq!(stats.window_retries_available, 0);
+    }
+
+    #[test]
+    fn scales_with_first_attempts() {
+        let budget = RetryBudget::new(0.5, Duration::from_secs(60), 0);
+        assert!(!budget.try_acquire_retry());
+        for _ in 0..4 {
+            budget.record_request();
+        }
+        assert!(budget.try_acquire_retry());
+        assert!(budget.try_acquire_retry());
+        assert!(!budget.try_acquire_retry());
+        let stats = budget.stats();
+        assert_eq!(stats.window_requests, 4);
+        assert_eq!(stats.window_retries, 2);
+        assert_eq!(stats.total_requests, 4);
+    }
+
+    #[test]
+    fn configure_discards_the_window() {
+        let budget = RetryBudget::new(1.0, Duration::from_secs(60), 0);
+        budget.record_request();
+        assert!(budget.try_acquire_retry());
+        budget.configure(1.0, Duration::from_secs(60), 1);
+        let stats = budget.stats();
+        assert_eq!(stats.window_requests, 0);
+        assert_eq!(stats.window_retries, 0);
+        assert_eq!(stats.window_retries_available, 1);
+        assert_eq!(stats.total_requests, 1);
+    }
+
+    #[test]
+    fn old_buckets_leave_the_window() {
+        let budget = RetryBudget::new(1.0, Duration::from_millis(20), 0);
+        budget.record_request();
+        assert!(budget.try_acquire_retry());
+        std::thread::sleep(Duration::from_millis(40));
+        let stats = budget.stats();
+        assert_eq!(stats.window_requests, 0);
+        assert_eq!(stats.window_retries, 0);
+    }
+}
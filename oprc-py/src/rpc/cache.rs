@@ -0,0 +1,277 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::{
+    ProtoRequest,
+    lanes::LANE_OPTION,
+    qos::{CONGESTION_CONTROL_OPTION, EXPRESS_OPTION, PRIORITY_OPTION},
+};
+use crate::{
+    model::{DEADLINE_OPTION, IDEMPOTENCY_KEY_OPTION, TIMEOUT_OPTION},
+    telemetry::CHAIN_ID_OPTION,
+};
+
+/// Request option caching the `Okay` response of an invocation for this
+/// many milliseconds. Later invocations with the option and the same
+/// function, object, payload and options get it without a round trip.
+pub const CACHE_TTL_OPTION: &str = "oprc-cache-ttl-ms";
+
+/// Responses kept unless `RpcManager.cache_capacity` says otherwise.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Options that only affect how an invocation travels, not what it answers,
+/// and so are left out of its `CacheKey`.
+const TRANSPORT_OPTIONS: &[&str] = &[
+    DEADLINE_OPTION,
+    TIMEOUT_OPTION,
+    IDEMPOTENCY_KEY_OPTION,
+    CHAIN_ID_OPTION,
+    "traceparent",
+    "tracestate",
+    PRIORITY_OPTION,
+    CONGESTION_CONTROL_OPTION,
+    EXPRESS_OPTION,
+    LANE_OPTION,
+];
+
+/// What a cached response answers: a function, on a partition and object
+/// for object invocations, with a payload and options. The options include
+/// the tenant, credentials and object ids of the caller, so one caller never
+/// gets the response cached for another. Transport options are left out.
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub(crate) struct CacheKey {
+    cls_id: String,
+    fn_id: String,
+    partition_id: u32,
+    object_id: Option<u64>,
+    payload_hash: u64,
+    options_hash: u64,
+}
+
+impl CacheKey {
+    pub(crate) fn of(req: &ProtoRequest) -> Self {
+        let mut options: Vec<_> = req
+            .options()
+            .iter()
+            .filter(|(k, _)| !TRANSPORT_OPTIONS.contains(&k.as_str()))
+            .collect();
+        options.sort();
+        let mut encoded = Vec::new();
+        for (k, v) in options {
+            // Lengths first, so no two option sets encode the same.
+            encoded.extend_from_slice(&(k.len() as u64).to_le_bytes());
+            encoded.extend_from_slice(k.as_bytes());
+            encoded.extend_from_slice(&(v.len() as u64).to_le_bytes());
+            encoded.extend_from_slice(v.as_bytes());
+        }
+        CacheKey {
+            cls_id: req.cls_id().to_string(),
+            fn_id: req.fn_id().to_string(),
+            partition_id: req.partition_id(),
+            object_id: req.object_id(),
+            payload_hash: xxh3_64(req.payload()),
+            options_hash: xxh3_64(&encoded),
+        }
+    }
+}
+
+struct Entry {
+    response: oprc_pb::InvocationResponse,
+    expires: Instant,
+}
+
+/// Removes the `oprc-cache-ttl-ms` option from `options` and returns the
+/// time to live it sets, if any.
+pub(crate) fn take_ttl(options: &mut HashMap<String, String>) -> Result<Option<Duration>, String> {
+    let Some(value) = options.remove(CACHE_TTL_OPTION) else {
+        return Ok(None);
+    };
+    match value.parse::<u64>() {
+        Ok(0) => Ok(None),
+        Ok(ms) => Ok(Some(Duration::from_millis(ms))),
+        Err(_) => Err(format!(
+            "invalid {} '{}', expected milliseconds",
+            CACHE_TTL_OPTION, value
+        )),
+    }
+}
+
+/// Read-through cache of the responses of an `RpcManager`, for idempotent
+/// reads invoked in hot loops. Only invocations with the
+/// `oprc-cache-ttl-ms` option read or fill it.
+pub(crate) struct ResponseCache {
+    capacity: AtomicUsize,
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        ResponseCache {
+            capacity: AtomicUsize::new(DEFAULT_CAPACITY),
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl ResponseCache {
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Keeps at most `capacity` responses, evicting those closest to expiry;
+    /// `0` disables the cache and clears it.
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        if capacity == 0 {
+            entries.clear();
+        }
+        while entries.len() > capacity {
+            evict(&mut entries);
+        }
+    }
+
+    /// The response cached for `key`, unless it expired.
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<oprc_pb::InvocationResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+        if entry.expires <= Instant::now() {
+            entries.remove(key);
+            return None;
+        }
+        Some(entry.response.clone())
+    }
+
+    /// Caches `response` for `ttl`, evicting another response if the cache
+    /// is full.
+    pub(crate) fn insert(
+        &self,
+        key: CacheKey,
+        response: oprc_pb::InvocationResponse,
+        ttl: Duration,
+    ) {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&key) && entries.len() >= capacity {
+            evict(&mut entries);
+        }
+        entries.insert(
+            key,
+            Entry {
+                response,
+                expires: Instant::now() + ttl,
+            },
+        );
+    }
+
+    /// Drops the responses cached for `fn_id` of `cls_id`, any function of
+    /// `cls_id`, or all of them. Returns how many were dropped.
+    pub(crate) fn invalidate(&self, cls_id: Option<&str>, fn_id: Option<&str>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, _| {
+            cls_id.is_some_and(|c| c != key.cls_id) || fn_id.is_some_and(|f| f != key.fn_id)
+        });
+        before - entries.len()
+    }
+}
+
+/// Drops the expired responses, or the one closest to expiry if none is.
+fn evict(entries: &mut HashMap<CacheKey, Entry>) {
+    let now = Instant::now();
+    let before = entries.len();
+    entries.retain(|_, entry| entry.expires > now);
+    if entries.len() < before {
+        return;
+    }
+    let soonest = entries
+        .iter()
+        .min_by_key(|(_, entry)| entry.expires)
+        .map(|(key, _)| key.clone());
+    if let Some(key) = soonest {
+        entries.remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(options: &[(&str, &str)]) -> ProtoRequest {
+        ProtoRequest::Fn(oprc_pb::InvocationRequest {
+            partition_id: 0,
+            cls_id: "cls".to_string(),
+            fn_id: "fn".to_string(),
+            options: options
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            payload: b"payload".to_vec(),
+        })
+    }
+
+    fn key(options: &[(&str, &str)]) -> CacheKey {
+        CacheKey::of(&request(options))
+    }
+
+    #[test]
+    fn ignores_transport_options() {
+        assert!(
+            key(&[("tenant", "a")])
+                == key(&[
+                    ("tenant", "a"),
+                    (TIMEOUT_OPTION, "500"),
+                    (IDEMPOTENCY_KEY_OPTION, "k"),
+                    ("traceparent", "00-abc-def-01"),
+                    (PRIORITY_OPTION, "1"),
+                ])
+        );
+    }
+
+    #[test]
+    fn differs_by_options() {
+        assert!(key(&[("tenant", "a")]) != key(&[("tenant", "b")]));
+        assert!(key(&[("tenant", "a")]) != key(&[]));
+        // Keys and values do not run into each other.
+        assert!(key(&[("ab", "c")]) != key(&[("a", "bc")]));
+    }
+
+    #[test]
+    fn differs_by_partition_and_payload() {
+        let mut other = request(&[]);
+        let ProtoRequest::Fn(r) = &mut other else {
+            unreachable!()
+        };
+        r.partition_id = 1;
+        assert!(key(&[]) != CacheKey::of(&other));
+        r.partition_id = 0;
+        r.payload = b"other".to_vec();
+        assert!(key(&[]) != CacheKey::of(&other));
+        r.payload = b"payload".to_vec();
+        assert!(key(&[]) == CacheKey::of(&other));
+    }
+
+    #[test]
+    fn expires_and_evicts() {
+        let cache = ResponseCache::default();
+        cache.set_capacity(1);
+        let response = oprc_pb::InvocationResponse::default();
+        cache.insert(key(&[]), response.clone(), Duration::from_secs(60));
+        assert!(cache.get(&key(&[])).is_some());
+        cache.insert(key(&[("tenant", "a")]), response, Duration::ZERO);
+        assert!(cache.get(&key(&[])).is_none());
+        assert!(cache.get(&key(&[("tenant", "a")])).is_none());
+        assert_eq!(cache.invalidate(None, None), 0);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use pyo3::prelude::*;
+
+use super::{ProtoRequest, attachments::OptionsEncoding, partitioning::PartitionHash};
+
+#[derive(Clone, Default)]
+struct Defaults {
+    options: HashMap<String, String>,
+    timeout_ms: Option<u64>,
+    /// Overrides the manager's options encoding for this class.
+    encoding: Option<OptionsEncoding>,
+    /// Number of partitions object ids of this class are spread over.
+    partitions: Option<u32>,
+    partition_hash: PartitionHash,
+}
+
+/// Per-class request defaults of an `RpcManager`, shared with its invoker.
+#[derive(Clone, Default)]
+pub(crate) struct DefaultsTable(Arc<RwLock<HashMap<String, Defaults>>>);
+
+impl DefaultsTable {
+    /// Adds the default options of the request's class that the request does
+    /// not set itself, and returns the class default timeout.
+    pub(crate) fn apply(&self, req: &mut ProtoRequest) -> Option<u64> {
+        let table = self.0.read().unwrap();
+        let defaults = table.get(req.cls_id())?;
+        let options = req.options_mut();
+        for (k, v) in &defaults.options {
+            options.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+        defaults.timeout_ms
+    }
+
+    /// Returns the default timeout of `cls_id`, if any.
+    pub(crate) fn timeout_ms(&self, cls_id: &str) -> Option<u64> {
+        self.0
+            .read()
+            .unwrap()
+            .get(cls_id)
+            .and_then(|d| d.timeout_ms)
+    }
+
+    /// Returns the options encoding set for `cls_id`, if any.
+    pub(crate) fn encoding(&self, cls_id: &str) -> Option<OptionsEncoding> {
+        self.0.read().unwrap().get(cls_id).and_then(|d| d.encoding)
+    }
+
+    /// Returns the partition count and hash set for `cls_id`, if any.
+    pub(crate) fn partitioning(&self, cls_id: &str) -> Option<(u32, PartitionHash)> {
+        self.read(cls_id, |d| d.partitions.map(|n| (n, d.partition_hash)))
+            .flatten()
+    }
+
+    fn read<R>(&self, cls_id: &str, f: impl FnOnce(&Defaults) -> R) -> Option<R> {
+        self.0.read().unwrap().get(cls_id).map(f)
+    }
+
+    fn update(&self, cls_id: &str, f: impl FnOnce(&mut Defaults)) {
+        let mut table = self.0.write().unwrap();
+        let defaults = table.entry(cls_id.to_string()).or_default();
+        f(defaults);
+        if defaults.options.is_empty()
+            && defaults.timeout_ms.is_none()
+            && defaults.encoding.is_none()
+            && defaults.partitions.is_none()
+            && defaults.partition_hash == PartitionHash::default()
+        {
+            table.remove(cls_id);
+        }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+/// Default options and timeout applied to every request an `RpcManager`
+/// sends to one class. Obtained with `RpcManager.defaults(cls_id)`; changes
+/// take effect immediately.
+///
+/// Request options take precedence over default options. The default timeout
+/// overrides the manager's `timeout_ms` and is overridden by the request's.
+pub struct ClassDefaults {
+    #[pyo3(get)]
+    cls_id: String,
+    table: DefaultsTable,
+}
+
+impl ClassDefaults {
+    pub(crate) fn new(cls_id: String, table: DefaultsTable) -> Self {
+        ClassDefaults { cls_id, table }
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ClassDefaults {
+    /// Default timeout in milliseconds for requests to this class.
+    #[getter]
+    fn get_timeout_ms(&self) -> Option<u64> {
+        self.table.timeout_ms(&self.cls_id)
+    }
+
+    #[setter]
+    fn set_timeout_ms(&self, timeout_ms: Option<u64>) {
+        self.table
+            .update(&self.cls_id, |d| d.timeout_ms = timeout_ms);
+    }
+
+    /// Where options travel over Zenoh for this class, `"embedded"` or
+    /// `"attachment"`; `None` uses the manager's `options_encoding`. Set to
+    /// `"attachment"` for runtimes that read options from Zenoh attachments.
+    #[getter]
+    fn get_options_encoding(&self) -> Option<&'static str> {
+        self.table.encoding(&self.cls_id).map(OptionsEncoding::name)
+    }
+
+    #[setter]
+    fn set_options_encoding(&self, encoding: Option<&str>) -> PyResult<()> {
+        let encoding = encoding
+            .map(str::parse)
+            .transpose()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        self.table.update(&self.cls_id, |d| d.encoding = encoding);
+        Ok(())
+    }
+
+    /// Number of partitions objects of this class are spread over. When set,
+    /// the `partition_id` of every object invocation of this class is
+    /// computed from its `object_id` with `partition_hash`, replacing the
+    /// request's. The `oprc-auto-partition` request option (`"true"` or
+    /// `"false"`) overrides this for one call.
+    #[getter]
+    fn get_partitions(&self) -> Option<u32> {
+        self.table.read(&self.cls_id, |d| d.partitions).flatten()
+    }
+
+    #[setter]
+    fn set_partitions(&self, partitions: Option<u32>) -> PyResult<()> {
+        if partitions == Some(0) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "partitions must be > 0",
+            ));
+        }
+        self.table
+            .update(&self.cls_id, |d| d.partitions = partitions);
+        Ok(())
+    }
+
+    /// How an object id maps to a partition, `"modulo"` (the default) or
+    /// `"xxh3"`. Must match the cluster.
+    #[getter]
+    fn get_partition_hash(&self) -> &'static str {
+        self.table
+            .read(&self.cls_id, |d| d.partition_hash)
+            .unwrap_or_default()
+            .name()
+    }
+
+    #[setter]
+    fn set_partition_hash(&self, hash: &str) -> PyResult<()> {
+        let hash = hash
+            .parse()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        self.table.update(&self.cls_id, |d| d.partition_hash = hash);
+        Ok(())
+    }
+
+    /// The partition `object_id` maps to, or `None` if `partitions` is not
+    /// set.
+    fn partition_of(
+        &self,
+        #[pyo3(from_py_with = crate::ids::object_id)] object_id: u64,
+    ) -> Option<u32> {
+        self.table
+            .partitioning(&self.cls_id)
+            .map(|(partitions, hash)| hash.partition_of(object_id, partitions))
+    }
+
+    /// Default request options for this class. Assigning replaces all of them.
+    #[getter]
+    fn get_options(&self) -> HashMap<String, String> {
+        self.table
+            .read(&self.cls_id, |d| d.options.clone())
+            .unwrap_or_default()
+    }
+
+    #[setter]
+    fn set_options(&self, options: HashMap<String, String>) {
+        self.table.update(&self.cls_id, |d| d.options = options);
+    }
+
+    /// Sets one default request option.
+    fn set_option(&self, key: String, value: String) {
+        self.table.update(&self.cls_id, |d| {
+            d.options.insert(key, value);
+        });
+    }
+
+    /// Removes one default request option.
+    fn remove_option(&self, key: &str) {
+        self.table.update(&self.cls_id, |d| {
+            d.options.remove(key);
+        });
+    }
+
+    /// Removes all defaults of this class.
+    fn clear(&self) {
+        self.table
+            .update(&self.cls_id, |d| *d = Defaults::default());
+    }
+
+    /// Returns a string representation of the `ClassDefaults`.
+    fn __str__(&self) -> String {
+        format!(
+            "ClassDefaults {{ cls_id: {}, timeout_ms: {:?}, options_encoding: {:?}, partitions: {:?}, partition_hash: {}, options: {:?} }}",
+            self.cls_id,
+            self.get_timeout_ms(),
+            self.get_options_encoding(),
+            self.get_partitions(),
+            self.get_partition_hash(),
+            self.get_options()
+        )
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use oprc_pb::oprc_function_client::OprcFunctionClient;
+use tonic::transport::Channel;
+
+use super::invoker::ProtoRequest;
+use crate::errors::{CodedError, ErrorCode};
+use crate::proxy;
+
+/// Request option with the gRPC address an invocation is sent to, bypassing
+/// Zenoh. Set by `RpcManager.invoke_*_direct`.
+pub(crate) const TARGET_ADDR_OPTION: &str = "oprc-target-addr";
+
+/// Channels to the addresses invoked so far. A channel connects on first use
+/// and reconnects by itself, so it is kept for the life of the process.
+static CHANNELS: OnceLock<Mutex<HashMap<String, Channel>>> = OnceLock::new();
+
+/// Turns `host:port` into a URI; addresses with a scheme are kept as is.
+fn endpoint(addr: &str) -> String {
+    if addr.contains("://") {
+        addr.to_string()
+    } else {
+        format!("http://{}", addr)
+    }
+}
+
+fn channel(addr: &str) -> Result<Channel, String> {
+    let mut channels = CHANNELS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(channel) = channels.get(addr) {
+        return Ok(channel.clone());
+    }
+    let channel = proxy::grpc_channel(&endpoint(addr))
+        .map_err(|e| format!("invalid target address '{}': {}", addr, e))?;
+    channels.insert(addr.to_string(), channel.clone());
+    Ok(channel)
+}
+
+/// Removes the target address from `options`, checking it can be connected
+/// to. It only steers the invocation, so handlers never see it.
+pub(crate) fn take_target(options: &mut HashMap<String, String>) -> Result<Option<String>, String> {
+    let Some(addr) = options.remove(TARGET_ADDR_OPTION) else {
+        return Ok(None);
+    };
+    channel(&addr)?;
+    Ok(Some(addr))
+}
+
+/// Sends `req` to the `OprcFunction` gRPC service at `addr`.
+pub(crate) async fn send(
+    addr: &str,
+    req: &ProtoRequest,
+) -> Result<oprc_pb::InvocationResponse, CodedError> {
+    let channel = channel(addr).map_err(|msg| CodedError::new(ErrorCode::InvalidRequest, msg))?;
+    let mut client = OprcFunctionClient::new(channel).max_decoding_message_size(usize::MAX);
+    let response = match req {
+        ProtoRequest::Fn(r) => client.invoke_fn(r.clone()).await,
+        ProtoRequest::Obj(r) => client.invoke_obj(r.clone()).await,
+    };
+    response.map(tonic::Response::into_inner).map_err(|status| {
+        CodedError::new(
+            ErrorCode::of_grpc(status.code()).unwrap_or(ErrorCode::Unknown),
+            format!("gRPC invocation at {} failed: {}", addr, status.message()),
+        )
+    })
+}
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+
+use super::{
+    Invoker, ProtoRequest,
+    attachments::invoke_key,
+    balancer::LoadBalancing,
+    cache::{self, CacheKey},
+    direct,
+    invoker::LOCAL_ENDPOINT,
+    lanes, local, partitioning,
+    qos::{CONGESTION_CONTROL_OPTION, EXPRESS_OPTION, PRIORITY_OPTION, Qos},
+};
+use crate::{compression::CONTENT_ENCODING, deadline, sticky};
+
+/// Works out where `req` would go and how, as `Invoker::invoke` would with
+/// `timeout` and `max_retries`, without sending it. Interceptors are not
+/// run. Returns `Err` for options the invocation would be rejected for.
+pub(crate) fn plan(
+    invoker: &Invoker,
+    mut req: ProtoRequest,
+    timeout: Option<Duration>,
+    max_retries: Option<u32>,
+) -> Result<InvocationPlan, String> {
+    let cache_ttl = cache::take_ttl(req.options_mut())?;
+    let cached = cache_ttl.is_some() && invoker.cache.get(&CacheKey::of(&req)).is_some();
+    partitioning::resolve(&invoker.defaults, &mut req)?;
+    // A passed deadline leaves no time at all; the invocation would fail
+    // without an attempt.
+    let timeout = match deadline::remaining(req.options()) {
+        Some(Ok(left)) => Some(timeout.map_or(left, |t| t.min(left))),
+        Some(Err(_)) => Some(Duration::ZERO),
+        None => timeout,
+    };
+    let qos_option = |name: &str| req.options().get(name).cloned();
+    let priority = qos_option(PRIORITY_OPTION);
+    let congestion_control = qos_option(CONGESTION_CONTROL_OPTION);
+    let express = qos_option(EXPRESS_OPTION).map(|v| v == "true");
+    Qos::take(req.options_mut())?;
+    let lane = lanes::take_lane(req.options_mut())?;
+    let prefer_local = local::take_preference(req.options_mut())?.unwrap_or(invoker.prefer_local);
+    let target = direct::take_target(req.options_mut())?;
+    let replica = sticky::take_replica(req.options_mut())?;
+    let fn_id = req.fn_id().to_string();
+    let variant = invoker
+        .routes
+        .route(&mut req)
+        .map(|_| req.fn_id().to_string());
+    let encoding = invoker
+        .defaults
+        .encoding(req.cls_id())
+        .unwrap_or(invoker.encoding);
+    let key_expr = invoke_key(&req);
+    let (transport, endpoint) = match &target {
+        None if prefer_local && local::serves(&req) => ("local", LOCAL_ENDPOINT.to_string()),
+        Some(addr) => ("grpc", addr.clone()),
+        None => (
+            "zenoh",
+            replica
+                .as_deref()
+                .map_or_else(|| key_expr.clone(), |r| sticky::pinned_key(&key_expr, r)),
+        ),
+    };
+    // Only stateless invocations over Zenoh that are not pinned are balanced.
+    let balanced = invoker.balancing != LoadBalancing::Zenoh
+        && transport == "zenoh"
+        && replica.is_none()
+        && matches!(req, ProtoRequest::Fn(_));
+    let compression = invoker
+        .compression
+        .filter(|encoding| {
+            !req.options().contains_key(CONTENT_ENCODING)
+                && invoker.peers.supports(req.cls_id(), encoding.capability())
+                && req.payload().len() >= invoker.compression_min_bytes
+        })
+        .map(|encoding| encoding.name().to_string());
+    Ok(InvocationPlan {
+        cls_id: req.cls_id().to_string(),
+        fn_id,
+        variant,
+        partition_id: req.partition_id(),
+        object_id: req.object_id(),
+        transport: transport.to_string(),
+        endpoint,
+        key_expr: key_expr.clone(),
+        replica,
+        load_balancing: balanced.then_some(invoker.balancing),
+        replicas: if balanced {
+            invoker.replicas.serving(&key_expr)
+        } else {
+            Vec::new()
+        },
+        options_encoding: encoding.name().to_string(),
+        priority,
+        congestion_control,
+        express,
+        timeout_ms: timeout.map(|t| t.as_millis() as u64),
+        max_retries: max_retries.unwrap_or(invoker.retry.max_retries),
+        lane: lane.name().to_string(),
+        compression,
+        checksum: invoker
+            .checksum
+            .map(|algorithm| algorithm.name().to_string()),
+        encrypted: invoker.keys.get(req.cls_id()).is_some(),
+        cache_ttl_ms: cache_ttl.map(|ttl| ttl.as_millis() as u64),
+        cached,
+        circuit: invoker.breaker.state(req.cls_id(), req.fn_id()).to_string(),
+    })
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// Where and how an invocation would be sent, as returned by
+/// `RpcManager.explain()`.
+pub struct InvocationPlan {
+    pub cls_id: String,
+    /// Function of the request, before routing.
+    pub fn_id: String,
+    /// Function the route of `fn_id` picked, if it has one. Weighted routes
+    /// pick anew for every invocation.
+    pub variant: Option<String>,
+    /// Partition after class partitioning was applied.
+    pub partition_id: u32,
+    pub object_id: Option<u64>,
+    /// `local` for a handler of this process, `grpc` for a target address,
+    /// or `zenoh`.
+    pub transport: String,
+    /// Key expression, address, or `local` the first attempt goes to.
+    pub endpoint: String,
+    /// Key expression the function is served on.
+    pub key_expr: String,
+    /// Replica the attempts are pinned to, if any.
+    pub replica: Option<String>,
+    /// Strategy picking the replica of each attempt, or `None` if Zenoh picks.
+    pub load_balancing: Option<LoadBalancing>,
+    /// Known replicas the strategy picks among. Empty while none is known,
+    /// in which case Zenoh picks.
+    pub replicas: Vec<String>,
+    /// Where the options travel over Zenoh: `embedded` or `attachment`.
+    pub options_encoding: String,
+    pub priority: Option<String>,
+    pub congestion_control: Option<String>,
+    pub express: Option<bool>,
+    /// Timeout in milliseconds, the deadline included; `0` if the deadline
+    /// passed.
+    pub timeout_ms: Option<u64>,
+    /// Retries after the first attempt.
+    pub max_retries: u32,
+    /// Priority lane the invocation waits for a slot in.
+    pub lane: String,
+    /// Encoding the payload is compressed with, if it is to be.
+    pub compression: Option<String>,
+    /// Checksum algorithm stamped on the payload, if any.
+    pub checksum: Option<String>,
+    /// Whether the payload is encrypted for the class.
+    pub encrypted: bool,
+    pub cache_ttl_ms: Option<u64>,
+    /// Whether the response cache would answer without sending it.
+    pub cached: bool,
+    /// State of the circuit of the function: `closed`, `open` or `half_open`.
+    pub circuit: String,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl InvocationPlan {
+    /// Returns a string representation of the `InvocationPlan`.
+    fn __str__(&self) -> String {
+        format!(
+            "InvocationPlan {{ {}/{}: transport: {}, endpoint: {}, timeout_ms: {:?}, max_retries: {}, compression: {:?}, circuit: {} }}",
+            self.cls_id,
+            self.variant.as_deref().unwrap_or(&self.fn_id),
+            self.transport,
+            self.endpoint,
+            self.timeout_ms,
+            self.max_retries,
+            self.compression,
+            self.circuit
+        )
+    }
+}
@@ -0,0 +1,103 @@
+use std::{collections::HashMap, time::Duration};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyStopAsyncIteration},
+    prelude::*,
+};
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::task::{JoinHandle, JoinSet};
+
+use super::{Delivery, Invoker, ProtoRequest};
+use crate::model::InvocationResponse;
+
+/// Results buffered before the invocations wait for Python to catch up.
+const BUFFERED_RESULTS: usize = 64;
+
+/// Runs `reqs`, the invocation of each object with its timeout, with at most
+/// `concurrency` in flight, and streams their outcomes as they complete.
+pub(crate) fn open(
+    invoker: Invoker,
+    reqs: Vec<(u64, ProtoRequest, Option<Duration>)>,
+    concurrency: usize,
+    span: &'static str,
+) -> ObjectResultStream {
+    let (tx, rx) = flume::bounded(BUFFERED_RESULTS);
+    let task = get_runtime().spawn(async move {
+        let mut pending = reqs.into_iter();
+        let mut running = JoinSet::new();
+        // Object of each running task, for tasks that fail to complete.
+        let mut objects = HashMap::new();
+        loop {
+            while running.len() < concurrency {
+                let Some((object_id, req, timeout)) = pending.next() else {
+                    break;
+                };
+                let invoker = invoker.clone();
+                let handle = running.spawn(async move {
+                    (object_id, invoker.invoke(req, timeout, None, span).await)
+                });
+                objects.insert(handle.id(), object_id);
+            }
+            let result = match running.join_next_with_id().await {
+                None => return,
+                Some(Ok((id, result))) => {
+                    objects.remove(&id);
+                    result
+                }
+                Some(Err(e)) => {
+                    let object_id = objects.remove(&e.id()).unwrap_or_default();
+                    let msg = format!("invocation task failed: {}", e);
+                    (object_id, Err(PyRuntimeError::new_err(msg)))
+                }
+            };
+            if tx.send_async(result).await.is_err() {
+                return;
+            }
+        }
+    });
+    ObjectResultStream { rx, task }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+/// Async iterator over the results of a bulk object invocation, as returned
+/// by `RpcManager.invoke_obj_multi`.
+///
+/// Yields an `(object_id, result)` pair per object in the order the
+/// invocations complete, where `result` is the `InvocationResponse` or the
+/// exception the invocation raised. Dropping the iterator cancels the
+/// invocations not yet completed.
+pub struct ObjectResultStream {
+    rx: flume::Receiver<(u64, PyResult<Delivery>)>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ObjectResultStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ObjectResultStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let rx = self.rx.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let Ok((object_id, result)) = rx.recv_async().await else {
+                return Err(PyStopAsyncIteration::new_err(()));
+            };
+            Python::attach(|py| {
+                let result = match result {
+                    Ok(delivery) => Py::new(py, InvocationResponse::from(delivery))?.into_any(),
+                    Err(e) => e.into_value(py).into_any(),
+                };
+                Ok((object_id, result))
+            })
+        })
+    }
+}
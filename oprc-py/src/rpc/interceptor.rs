@@ -0,0 +1,225 @@
+use std::{collections::HashMap, sync::Arc};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use tracing::{info, warn};
+
+use super::invoker::ProtoRequest;
+use crate::{handler::AUTH_TOKEN_OPTION, model};
+
+/// A client-side interceptor of an `RpcManager`. Interceptors see every
+/// invocation once: its request before the first attempt, in the order they
+/// were added, and its final response in reverse order.
+#[derive(Clone)]
+pub(crate) enum ClientInterceptor {
+    /// Logs every invocation and its outcome.
+    Logging,
+    /// Sets the `oprc-auth-token` option on requests without one, so a token
+    /// forwarded from the caller of a handler wins.
+    Auth(Arc<str>),
+    /// Python `on_request(req)` and `on_response(req, resp)` callables, which
+    /// may modify their arguments in place. `on_request` rejects the
+    /// invocation by raising.
+    Python {
+        on_request: Option<Arc<Py<PyAny>>>,
+        on_response: Option<Arc<Py<PyAny>>>,
+    },
+}
+
+impl ClientInterceptor {
+    /// Parses a spec of the form `name` or `name?key=value&key=value`.
+    ///
+    /// Supported: `logging` and `auth?token=T`.
+    pub(crate) fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let (name, query) = spec.split_once('?').unwrap_or((spec, ""));
+        let mut params = HashMap::new();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (k, v) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("invalid parameter '{}' in interceptor '{}'", pair, spec))?;
+            params.insert(k, v);
+        }
+        let interceptor = match name {
+            "logging" => ClientInterceptor::Logging,
+            "auth" => match params.remove("token") {
+                Some(token) if !token.is_empty() => ClientInterceptor::Auth(token.into()),
+                _ => return Err("interceptor 'auth' needs a 'token' parameter".to_string()),
+            },
+            other => return Err(format!("unknown interceptor '{}'", other)),
+        };
+        if let Some(key) = params.keys().next() {
+            return Err(format!(
+                "unknown parameter '{}' for interceptor '{}'",
+                key, name
+            ));
+        }
+        Ok(interceptor)
+    }
+
+    /// Wraps `interceptor`, a spec or an object with `on_request` and/or
+    /// `on_response` methods.
+    pub(crate) fn extract(interceptor: &Bound<'_, PyAny>) -> PyResult<Self> {
+        if let Ok(spec) = interceptor.extract::<String>() {
+            return Self::parse(&spec).map_err(PyValueError::new_err);
+        }
+        let method = |name: &str| -> PyResult<Option<Arc<Py<PyAny>>>> {
+            if !interceptor.hasattr(name)? {
+                return Ok(None);
+            }
+            Ok(Some(Arc::new(interceptor.getattr(name)?.unbind())))
+        };
+        let on_request = method("on_request")?;
+        let on_response = method("on_response")?;
+        if on_request.is_none() && on_response.is_none() {
+            return Err(PyValueError::new_err(
+                "an interceptor needs an on_request or on_response method",
+            ));
+        }
+        Ok(ClientInterceptor::Python {
+            on_request,
+            on_response,
+        })
+    }
+
+    /// The name of the interceptor, without parameters, which may be secret.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ClientInterceptor::Logging => "logging",
+            ClientInterceptor::Auth(_) => "auth",
+            ClientInterceptor::Python { .. } => "python",
+        }
+    }
+
+    fn on_request(&self, req: &mut ProtoRequest) -> PyResult<()> {
+        match self {
+            ClientInterceptor::Logging => {
+                info!(
+                    cls_id = req.cls_id(),
+                    fn_id = req.fn_id(),
+                    object_id = ?req.object_id(),
+                    payload_bytes = req.payload().len(),
+                    "invocation sent"
+                );
+                Ok(())
+            }
+            ClientInterceptor::Auth(token) => {
+                req.options_mut()
+                    .entry(AUTH_TOKEN_OPTION.to_string())
+                    .or_insert_with(|| token.to_string());
+                Ok(())
+            }
+            ClientInterceptor::Python {
+                on_request: Some(hook),
+                ..
+            } => Python::attach(|py| match req {
+                ProtoRequest::Fn(r) => {
+                    let py_req = Py::new(py, model::InvocationRequest::from(r.clone()))?;
+                    hook.call1(py, (py_req.clone_ref(py),))?;
+                    *r = py_req.borrow(py).into_proto();
+                    Ok(())
+                }
+                ProtoRequest::Obj(r) => {
+                    let py_req = Py::new(py, model::ObjectInvocationRequest::from(r.clone()))?;
+                    hook.call1(py, (py_req.clone_ref(py),))?;
+                    *r = py_req.borrow(py).into_proto();
+                    Ok(())
+                }
+            }),
+            ClientInterceptor::Python { .. } => Ok(()),
+        }
+    }
+
+    fn on_response(
+        &self,
+        req: &ProtoRequest,
+        resp: &mut oprc_pb::InvocationResponse,
+    ) -> PyResult<()> {
+        match self {
+            ClientInterceptor::Logging => {
+                info!(
+                    cls_id = req.cls_id(),
+                    fn_id = req.fn_id(),
+                    status = resp.status,
+                    "invocation completed"
+                );
+                Ok(())
+            }
+            ClientInterceptor::Auth(_) => Ok(()),
+            ClientInterceptor::Python {
+                on_response: Some(hook),
+                ..
+            } => Python::attach(|py| {
+                let py_req = match req {
+                    ProtoRequest::Fn(r) => {
+                        Py::new(py, model::InvocationRequest::from(r.clone()))?.into_any()
+                    }
+                    ProtoRequest::Obj(r) => {
+                        Py::new(py, model::ObjectInvocationRequest::from(r.clone()))?.into_any()
+                    }
+                };
+                let py_resp = Py::new(py, model::InvocationResponse::from(resp.clone()))?;
+                hook.call1(py, (py_req, py_resp.clone_ref(py)))?;
+                *resp = oprc_pb::InvocationResponse::from(&*py_resp.borrow(py));
+                Ok(())
+            }),
+            ClientInterceptor::Python { .. } => Ok(()),
+        }
+    }
+
+    fn on_failure(&self, req: &ProtoRequest, err: &PyErr) {
+        if let ClientInterceptor::Logging = self {
+            warn!(
+                cls_id = req.cls_id(),
+                fn_id = req.fn_id(),
+                error = %err,
+                "invocation failed"
+            );
+        }
+    }
+
+    /// Whether `on_response` needs the request as the interceptors left it,
+    /// before it was compressed, encrypted or checksummed.
+    fn needs_request(&self) -> bool {
+        matches!(
+            self,
+            ClientInterceptor::Python {
+                on_response: Some(_),
+                ..
+            }
+        )
+    }
+}
+
+/// Runs the request side of `chain` on `req`. Returns a copy of the request
+/// for the response side if it needs one.
+pub(crate) fn before(
+    chain: &[ClientInterceptor],
+    req: &mut ProtoRequest,
+) -> PyResult<Option<ProtoRequest>> {
+    for interceptor in chain {
+        interceptor.on_request(req)?;
+    }
+    Ok(chain
+        .iter()
+        .any(ClientInterceptor::needs_request)
+        .then(|| req.clone()))
+}
+
+/// Runs the response side of `chain`, in reverse, on the outcome of `req`.
+/// A failing interceptor fails the invocation.
+pub(crate) fn after(
+    chain: &[ClientInterceptor],
+    req: &ProtoRequest,
+    outcome: &mut PyResult<oprc_pb::InvocationResponse>,
+) {
+    for interceptor in chain.iter().rev() {
+        match outcome {
+            Ok(resp) => {
+                if let Err(e) = interceptor.on_response(req, resp) {
+                    *outcome = Err(e);
+                }
+            }
+            Err(e) => interceptor.on_failure(req, e),
+        }
+    }
+}
@@ -0,0 +1,802 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use oprc_invoke::proxy::ObjectProxy;
+use pyo3::{
+    PyResult,
+    exceptions::{PyRuntimeError, PyTimeoutError, PyValueError},
+};
+use tracing::{Instrument, debug, warn};
+
+use super::{
+    attachments::{self, OptionsEncoding},
+    balancer::{Lease, LoadBalancing, ReplicaTable},
+    breaker::{CircuitBreaker, CircuitOpenError},
+    budget::RetryBudget,
+    cache::{self, CacheKey, ResponseCache},
+    defaults::DefaultsTable,
+    direct,
+    interceptor::{self, ClientInterceptor},
+    lanes::{self, Lanes},
+    lifecycle::Lifecycle,
+    limiter::InflightLimiter,
+    local,
+    mirror::MirrorTable,
+    partitioning,
+    qos::Qos,
+    retry::{Failure, RetryDecision, RetryPolicy},
+    routing::RoutingTable,
+    stats::{InvocationCounters, PerFunctionCounters},
+    timeout,
+};
+use crate::{
+    call_log::CallLog,
+    capabilities::{CAPABILITIES_HEADER, Capabilities, Capability, PeerTable},
+    checksum::{self, ChecksumAlgorithm},
+    chunks::{
+        self, CHUNKED_HEADER, ChunkServer, ChunkSource, DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_THRESHOLD,
+    },
+    compression::{self, CONTENT_ENCODING, DEFAULT_COMPRESSION_MIN_BYTES, Encoding},
+    deadline,
+    e2e::{self, KeyTable, PayloadKey},
+    errors::{CodedError, ErrorCode},
+    flags, memory,
+    model::{InvocationAttempt, InvocationResponseCode, REPLICA_HEADER},
+    payload_limit::{self, PayloadLimit, PayloadTooLarge},
+    sticky, telemetry,
+};
+
+/// Endpoint of the attempts run by a handler of this process.
+pub(crate) const LOCAL_ENDPOINT: &str = "local";
+
+/// A protobuf request accepted by the invocation pipeline.
+#[derive(Clone)]
+pub(crate) enum ProtoRequest {
+    Fn(oprc_pb::InvocationRequest),
+    Obj(oprc_pb::ObjectInvocationRequest),
+}
+
+impl ProtoRequest {
+    pub(crate) fn cls_id(&self) -> &str {
+        match self {
+            ProtoRequest::Fn(r) => &r.cls_id,
+            ProtoRequest::Obj(r) => &r.cls_id,
+        }
+    }
+
+    pub(crate) fn fn_id(&self) -> &str {
+        match self {
+            ProtoRequest::Fn(r) => &r.fn_id,
+            ProtoRequest::Obj(r) => &r.fn_id,
+        }
+    }
+
+    pub(crate) fn partition_id(&self) -> u32 {
+        match self {
+            ProtoRequest::Fn(r) => r.partition_id,
+            ProtoRequest::Obj(r) => r.partition_id,
+        }
+    }
+
+    pub(crate) fn object_id(&self) -> Option<u64> {
+        match self {
+            ProtoRequest::Fn(_) => None,
+            ProtoRequest::Obj(r) => Some(r.object_id),
+        }
+    }
+
+    pub(crate) fn options(&self) -> &HashMap<String, String> {
+        match self {
+            ProtoRequest::Fn(r) => &r.options,
+            ProtoRequest::Obj(r) => &r.options,
+        }
+    }
+
+    pub(crate) fn options_mut(&mut self) -> &mut HashMap<String, String> {
+        match self {
+            ProtoRequest::Fn(r) => &mut r.options,
+            ProtoRequest::Obj(r) => &mut r.options,
+        }
+    }
+
+    /// Adds the checksum of the payload to the request options.
+    pub(crate) fn stamp_checksum(&mut self, algorithm: ChecksumAlgorithm) {
+        match self {
+            ProtoRequest::Fn(r) => algorithm.stamp(&mut r.options, &r.payload),
+            ProtoRequest::Obj(r) => algorithm.stamp(&mut r.options, &r.payload),
+        }
+    }
+
+    pub(crate) fn payload(&self) -> &[u8] {
+        match self {
+            ProtoRequest::Fn(r) => &r.payload,
+            ProtoRequest::Obj(r) => &r.payload,
+        }
+    }
+
+    pub(crate) fn payload_mut(&mut self) -> &mut Vec<u8> {
+        match self {
+            ProtoRequest::Fn(r) => &mut r.payload,
+            ProtoRequest::Obj(r) => &mut r.payload,
+        }
+    }
+}
+
+/// Where the attempts of an invocation go, per the routing options of its
+/// request.
+#[derive(Clone, Copy, Default)]
+struct Route<'a> {
+    /// Whether a handler of this process serving the target runs them first.
+    prefer_local: bool,
+    /// Address they are sent to over gRPC.
+    target: Option<&'a str>,
+    /// Replica they are pinned to over Zenoh.
+    replica: Option<&'a str>,
+}
+
+/// The outcome of a successful pass through the pipeline.
+pub(crate) struct Delivery {
+    pub(crate) response: oprc_pb::InvocationResponse,
+    pub(crate) attempts: Vec<InvocationAttempt>,
+    pub(crate) latency: Duration,
+    pub(crate) queue_time: Duration,
+}
+
+impl From<Delivery> for crate::model::InvocationResponse {
+    fn from(value: Delivery) -> Self {
+        value.response.into()
+    }
+}
+
+impl From<Delivery> for crate::model::DetailedInvocationResponse {
+    fn from(value: Delivery) -> Self {
+        crate::model::DetailedInvocationResponse::new(
+            value.response.into(),
+            value.attempts,
+            value.latency,
+            value.queue_time,
+        )
+    }
+}
+
+/// Shared invocation pipeline used by both the sync and async entry points
+/// of `RpcManager`. Cloning is cheap; all shared state lives behind `Arc`s.
+#[derive(Clone)]
+pub(crate) struct Invoker {
+    pub(crate) proxy: ObjectProxy,
+    /// Session requests with options in attachments are sent over.
+    session: zenoh::Session,
+    /// Where options travel unless the class defaults say otherwise.
+    pub(crate) encoding: OptionsEncoding,
+    pub(crate) budget: Arc<RetryBudget>,
+    /// Checksum attached to outgoing payloads, if enabled.
+    pub(crate) checksum: Option<ChecksumAlgorithm>,
+    /// Encoding outgoing payloads are compressed with, if enabled, once the
+    /// target class advertised it.
+    pub(crate) compression: Option<Encoding>,
+    /// Payloads smaller than this are sent uncompressed.
+    pub(crate) compression_min_bytes: usize,
+    /// Largest request payload sent, set by `RpcManager.max_payload_bytes`.
+    pub(crate) max_payload: Arc<PayloadLimit>,
+    /// Request payloads larger than this are sent in chunks, once the target
+    /// class accepts them; `None` sends every payload inline.
+    pub(crate) chunk_threshold: Option<usize>,
+    /// Public keys of the classes whose invocations are encrypted.
+    pub(crate) keys: KeyTable,
+    /// Per-class default options and timeouts.
+    pub(crate) defaults: DefaultsTable,
+    /// Which failed attempts are retried, and how often.
+    pub(crate) retry: RetryPolicy,
+    /// What the servers of each class support, learned from their responses.
+    pub(crate) peers: PeerTable,
+    /// Counters reported by `RpcManager.stats()`.
+    pub(crate) stats: Arc<InvocationCounters>,
+    /// Counters of each function, exported by `RpcManager.export_metrics()`.
+    pub(crate) functions: Arc<PerFunctionCounters>,
+    /// Whether invocations run on a handler of this process first, if it
+    /// serves the target.
+    pub(crate) prefer_local: bool,
+    /// Cap on the invocations in flight, set by `RpcManager.max_inflight`.
+    pub(crate) inflight: Arc<InflightLimiter>,
+    /// Caps on the invocations in flight per priority lane, set by
+    /// `RpcManager.set_lane_limit`.
+    pub(crate) lanes: Arc<Lanes>,
+    /// Circuits of the targets invoked, configured by
+    /// `RpcManager.configure_circuit_breaker`.
+    pub(crate) breaker: Arc<CircuitBreaker>,
+    /// Targets a share of the invocations is copied to.
+    pub(crate) mirrors: MirrorTable,
+    /// How the invocations of a function are split between its variants.
+    pub(crate) routes: RoutingTable,
+    /// How stateless function invocations pick among the replicas serving
+    /// them.
+    pub(crate) balancing: LoadBalancing,
+    /// Replicas known to the load balancer.
+    pub(crate) replicas: Arc<ReplicaTable>,
+    /// Whether invocations are still accepted, and those in flight.
+    pub(crate) lifecycle: Arc<Lifecycle>,
+    /// Interceptors every invocation passes, in order.
+    pub(crate) interceptors: Arc<Vec<ClientInterceptor>>,
+    /// The last invocations, reported by `RpcManager.recent_calls()`.
+    pub(crate) calls: Arc<CallLog>,
+    /// Responses of invocations with a cache TTL.
+    pub(crate) cache: Arc<ResponseCache>,
+}
+
+impl Invoker {
+    pub(crate) fn new(session: zenoh::Session) -> Self {
+        Invoker {
+            proxy: ObjectProxy::new(session.clone()),
+            session,
+            encoding: OptionsEncoding::default(),
+            budget: Arc::new(RetryBudget::default()),
+            checksum: None,
+            compression: None,
+            compression_min_bytes: DEFAULT_COMPRESSION_MIN_BYTES,
+            max_payload: Arc::default(),
+            chunk_threshold: Some(DEFAULT_CHUNK_THRESHOLD),
+            keys: KeyTable::default(),
+            defaults: DefaultsTable::default(),
+            retry: RetryPolicy::default(),
+            peers: PeerTable::default(),
+            stats: Arc::default(),
+            functions: Arc::default(),
+            prefer_local: false,
+            inflight: Arc::default(),
+            lanes: Arc::default(),
+            breaker: Arc::default(),
+            mirrors: MirrorTable::default(),
+            routes: RoutingTable::default(),
+            balancing: LoadBalancing::default(),
+            replicas: Arc::default(),
+            lifecycle: Arc::default(),
+            interceptors: Arc::default(),
+            calls: Arc::default(),
+            cache: Arc::default(),
+        }
+    }
+
+    /// Sets the load balancing strategy, following the replicas that serve
+    /// functions once one other than `Zenoh` is chosen.
+    pub(crate) fn set_balancing(&mut self, balancing: LoadBalancing) {
+        if balancing != LoadBalancing::Zenoh {
+            self.replicas.watch(&self.session);
+        }
+        self.balancing = balancing;
+    }
+
+    /// Stops accepting invocations and waits up to `timeout` for those in
+    /// flight. Returns how many were still in flight. The session is the
+    /// engine's and stays open, so its other users keep working.
+    pub(crate) async fn close(&self, timeout: Option<Duration>) -> usize {
+        let left = self.lifecycle.drain(timeout).await;
+        self.replicas.stop();
+        left
+    }
+
+    /// Leases the replica the next attempt of `req` goes to per the load
+    /// balancing strategy. `None` leaves the choice to Zenoh: for object
+    /// invocations, attempts already pinned or sent over gRPC, or while no
+    /// replica serving `req` is known.
+    fn balance(&self, req: &ProtoRequest, route: Route<'_>) -> Option<Lease> {
+        if self.balancing == LoadBalancing::Zenoh
+            || !matches!(req, ProtoRequest::Fn(_))
+            || route.target.is_some()
+            || route.replica.is_some()
+        {
+            return None;
+        }
+        self.replicas
+            .lease(&attachments::invoke_key(req), self.balancing)
+    }
+
+    /// Sends a single attempt of `req` along `route` and checks the
+    /// integrity of the response, decrypting it with `sealed` if `req` was
+    /// encrypted. With a target address, it goes there over gRPC. With
+    /// `prefer_local`, a handler of this process serving the target runs it
+    /// first. Otherwise requests pinned to a replica, with their options in
+    /// the attachment or with a QoS go directly over the session, others
+    /// through the proxy. Returns the endpoint the attempt was sent to along
+    /// with its outcome.
+    async fn send(
+        &self,
+        req: &ProtoRequest,
+        qos: Qos,
+        route: Route<'_>,
+        sealed: Option<&PayloadKey>,
+    ) -> (String, Result<oprc_pb::InvocationResponse, CodedError>) {
+        let encoding = self
+            .defaults
+            .encoding(req.cls_id())
+            .unwrap_or(self.encoding);
+        self.stats.record_attempt(req.payload().len());
+        let via_session =
+            encoding == OptionsEncoding::Attachment || !qos.is_default() || route.replica.is_some();
+        let local_response = if route.prefer_local && route.target.is_none() {
+            local::invoke(req).await
+        } else {
+            None
+        };
+        let transport = |msg: String| CodedError::new(ErrorCode::TransportError, msg);
+        let (endpoint, response) = match (local_response, route.target, req) {
+            (Some(response), _, _) => (LOCAL_ENDPOINT.to_string(), Ok(response)),
+            (None, Some(addr), _) => (addr.to_string(), direct::send(addr, req).await),
+            (None, None, _) if via_session => {
+                self.send_over_session(req, route.replica, encoding, qos)
+                    .await
+            }
+            (None, None, ProtoRequest::Fn(r)) => (
+                attachments::invoke_key(req),
+                self.proxy
+                    .invoke_fn_with_req(r)
+                    .await
+                    .map_err(|e| transport(e.to_string())),
+            ),
+            (None, None, ProtoRequest::Obj(r)) => (
+                attachments::invoke_key(req),
+                self.proxy
+                    .invoke_obj_with_req(r)
+                    .await
+                    .map_err(|e| transport(e.to_string())),
+            ),
+        };
+        let response = response.and_then(|response| self.accept(req, response, sealed));
+        (endpoint, response)
+    }
+
+    /// Sends `req` over the session, to the key `replica` serves it on if
+    /// pinned. A pinned attempt no handler answers, e.g. because the replica
+    /// is gone, goes to any replica instead.
+    async fn send_over_session(
+        &self,
+        req: &ProtoRequest,
+        replica: Option<&str>,
+        encoding: OptionsEncoding,
+        qos: Qos,
+    ) -> (String, Result<oprc_pb::InvocationResponse, CodedError>) {
+        let key = attachments::invoke_key(req);
+        if let Some(replica) = replica {
+            let pinned = sticky::pinned_key(&key, replica);
+            match attachments::send(&self.session, req, &pinned, encoding, qos).await {
+                Err(e) if e.code == ErrorCode::Unavailable => {
+                    debug!(
+                        "replica {} of {} did not answer; invoking any",
+                        replica, key
+                    );
+                }
+                response => return (pinned, response),
+            }
+        }
+        let response = attachments::send(&self.session, req, &key, encoding, qos).await;
+        (key, response)
+    }
+
+    /// Checks the integrity of the `response` to an attempt of `req`,
+    /// decrypting it with `sealed` if `req` was encrypted.
+    fn accept(
+        &self,
+        req: &ProtoRequest,
+        mut response: oprc_pb::InvocationResponse,
+        sealed: Option<&PayloadKey>,
+    ) -> Result<oprc_pb::InvocationResponse, CodedError> {
+        self.peers.observe(req.cls_id(), &response);
+        self.stats
+            .record_response(response.payload.as_ref().map_or(0, Vec::len));
+        let invalid = |msg| CodedError::new(ErrorCode::InvalidResponse, msg);
+        checksum::verify(
+            &response.headers,
+            response.payload.as_deref().unwrap_or_default(),
+        )
+        .map_err(|msg| invalid(format!("response {}", msg)))?;
+        if let Some(key) = sealed {
+            e2e::open_response(key, &mut response).map_err(invalid)?;
+        }
+        decode_payload(&mut response).map_err(invalid)?;
+        Ok(response)
+    }
+
+    /// Sends `req` until an attempt succeeds or the retry policy gives up.
+    /// Returns the final response and the attempts made. Each attempt runs in
+    /// an `rpc.attempt` span recording its number, endpoint and outcome.
+    async fn send_with_retries(
+        &self,
+        req: &ProtoRequest,
+        qos: Qos,
+        route: Route<'_>,
+        sealed: Option<&PayloadKey>,
+        max_retries: u32,
+    ) -> PyResult<(oprc_pb::InvocationResponse, Vec<InvocationAttempt>)> {
+        let mut attempts = Vec::new();
+        loop {
+            let number = attempts.len() as u32 + 1;
+            // Each attempt is balanced anew, so a retry can go to another
+            // replica.
+            let lease = self.balance(req, route);
+            let attempt_route = Route {
+                replica: lease.as_ref().map(Lease::replica).or(route.replica),
+                ..route
+            };
+            let span = tracing::info_span!(
+                "attempt",
+                otel.name = "rpc.attempt",
+                oprc.attempt = number,
+                oprc.endpoint = tracing::field::Empty,
+                oprc.outcome = tracing::field::Empty,
+            );
+            let sent = Instant::now();
+            let (endpoint, result) = self
+                .send(req, qos, attempt_route, sealed)
+                .instrument(span.clone())
+                .await;
+            drop(lease);
+            let attempt = describe_attempt(number, endpoint, &result, sent.elapsed());
+            span.record("oprc.endpoint", attempt.endpoint.as_str());
+            span.record("oprc.outcome", attempt.outcome());
+            attempts.push(attempt);
+            let failure = match result {
+                Ok(resp) if resp.status == InvocationResponseCode::Okay as i32 => {
+                    return Ok((resp, attempts));
+                }
+                Ok(resp) => Failure::Status(resp),
+                Err(e) => Failure::Error(e),
+            };
+            let decision = self.retry.classify(&failure);
+            if decision == RetryDecision::Retryable
+                && number <= max_retries
+                && self.budget.try_acquire_retry()
+            {
+                self.stats.record_retry();
+                tokio::time::sleep(self.retry.backoff(number)).await;
+                continue;
+            }
+            return match (failure, decision) {
+                (Failure::Error(e), _) => Err(e.into()),
+                (Failure::Status(resp), RetryDecision::Escalate) => {
+                    let code = ErrorCode::of_response(resp.status, &resp.headers)
+                        .unwrap_or(ErrorCode::Unknown);
+                    Err(code.tag(PyRuntimeError::new_err(format!(
+                        "invocation of {}/{} failed with status {} after {} attempt(s)",
+                        req.cls_id(),
+                        req.fn_id(),
+                        resp.status,
+                        number
+                    ))))
+                }
+                (Failure::Status(resp), _) => Ok((resp, attempts)),
+            };
+        }
+    }
+
+    /// Compresses the payload of `req` with the configured encoding if the
+    /// servers of its class advertised it. The checksum, stamped afterwards,
+    /// covers the compressed payload.
+    fn compress(&self, req: &mut ProtoRequest) {
+        let Some(encoding) = self.compression else {
+            return;
+        };
+        if req.options().contains_key(CONTENT_ENCODING)
+            || !self.peers.supports(req.cls_id(), encoding.capability())
+        {
+            return;
+        }
+        match compression::compress(req.payload_mut(), encoding, self.compression_min_bytes) {
+            Ok(true) => {
+                req.options_mut()
+                    .insert(CONTENT_ENCODING.to_string(), encoding.name().to_string());
+            }
+            Ok(false) => {}
+            Err(e) => warn!("failed to compress request payload: {}", e),
+        }
+    }
+
+    /// Serves the payload of `req` in chunks the target fetches as it admits
+    /// the call, if it is over `chunk_threshold` and the servers of its class
+    /// accept chunked payloads. The chunks are served until the returned
+    /// server is dropped.
+    async fn chunk(&self, req: &mut ProtoRequest) -> PyResult<Option<ChunkServer>> {
+        let over = self
+            .chunk_threshold
+            .is_some_and(|threshold| req.payload().len() > threshold);
+        if !over
+            || !flags::enabled(flags::CHUNKED_PAYLOADS)
+            || !self.peers.supports(req.cls_id(), Capability::Chunked)
+        {
+            return Ok(None);
+        }
+        let source = ChunkSource::bytes(std::mem::take(req.payload_mut()), DEFAULT_CHUNK_SIZE);
+        let server = chunks::serve(&self.session, source)
+            .await
+            .map_err(|msg| ErrorCode::TransportError.tag(PyRuntimeError::new_err(msg)))?;
+        req.options_mut()
+            .insert(CHUNKED_HEADER.to_string(), server.chunks().to_header());
+        Ok(Some(server))
+    }
+
+    /// Sends the mirrored copy `req` of an invocation in the background,
+    /// once, without waiting for it. It goes through the pipeline and its
+    /// limits like any invocation, except for the interceptors, which already
+    /// ran on the original. Its response is dropped and a failure only logged.
+    fn mirror(&self, req: ProtoRequest, timeout: Option<Duration>) {
+        let invoker = self.clone();
+        tokio::spawn(async move {
+            let target = format!("{}/{}", req.cls_id(), req.fn_id());
+            let sent = invoker
+                .run(req, timeout, Some(0), "rpc.mirror", false)
+                .await;
+            if let Err(e) = sent {
+                debug!("mirrored invocation of {} failed: {}", target, e);
+            }
+        });
+    }
+
+    /// Runs `req` through the pipeline, bounded by `timeout`. Failed attempts
+    /// are retried per the retry policy, up to `max_retries` (or the policy's
+    /// limit) and while the retry budget allows; the timeout covers all of them.
+    pub(crate) async fn invoke(
+        &self,
+        req: ProtoRequest,
+        timeout: Option<Duration>,
+        max_retries: Option<u32>,
+        span: &'static str,
+    ) -> PyResult<Delivery> {
+        self.run(req, timeout, max_retries, span, true).await
+    }
+
+    /// Runs `req` through the pipeline as `invoke` does; the interceptors
+    /// only with `intercept`.
+    async fn run(
+        &self,
+        mut req: ProtoRequest,
+        timeout: Option<Duration>,
+        max_retries: Option<u32>,
+        span: &'static str,
+        intercept: bool,
+    ) -> PyResult<Delivery> {
+        let Some(_active) = self.lifecycle.enter() else {
+            return Err(PyRuntimeError::new_err("RpcManager is closed"));
+        };
+        let started = Instant::now();
+        // Cached by the request as the caller built it.
+        let cache_ttl = cache::take_ttl(req.options_mut())
+            .map_err(|msg| ErrorCode::InvalidRequest.tag(PyValueError::new_err(msg)))?;
+        let cache_key = cache_ttl.map(|_| CacheKey::of(&req));
+        if let Some(response) = cache_key.as_ref().and_then(|key| self.cache.get(key)) {
+            self.stats.record_cache_hit();
+            return Ok(Delivery {
+                response,
+                attempts: Vec::new(),
+                latency: started.elapsed(),
+                queue_time: Duration::ZERO,
+            });
+        }
+        // Interceptors see the request as the caller built it, before it is
+        // compressed, encrypted or checksummed.
+        let intercepted = if intercept {
+            interceptor::before(&self.interceptors, &mut req)?
+        } else {
+            None
+        };
+        // Copied as intercepted, before anything is resolved or encoded for
+        // the invoked class, as the copy may go to another one.
+        if let Some(copy) = self.mirrors.copy(&req) {
+            self.mirror(copy, timeout);
+        }
+        partitioning::resolve(&self.defaults, &mut req)
+            .map_err(|msg| ErrorCode::InvalidRequest.tag(PyValueError::new_err(msg)))?;
+        self.budget.record_request();
+        // A trace context forwarded by the caller (e.g. from the request a
+        // handler is serving) becomes the parent of this invocation's span.
+        let span = telemetry::span(span);
+        telemetry::adopt_remote(&span, req.options(), false);
+        telemetry::inject(&span, req.options_mut());
+        req.options_mut().insert(
+            CAPABILITIES_HEADER.to_string(),
+            Capabilities::local_header().to_string(),
+        );
+        self.compress(&mut req);
+        // Encrypted after compression, which would gain nothing on the
+        // ciphertext, and before the checksum, which covers what is sent.
+        let sealed = self.keys.get(req.cls_id()).map(|key| match &mut req {
+            ProtoRequest::Fn(r) => e2e::seal_request(&key, &mut r.options, &mut r.payload),
+            ProtoRequest::Obj(r) => e2e::seal_request(&key, &mut r.options, &mut r.payload),
+        });
+        // Checked on the payload as sent, so a payload compression brings
+        // under the limit passes.
+        let payload_bytes = req.payload().len();
+        if let Some(max) = self.max_payload.exceeded(payload_bytes) {
+            return Err(
+                ErrorCode::PayloadTooLarge.tag(PayloadTooLarge::new_err(format!(
+                    "request payload of {}/{} is {} bytes, over the limit of {} bytes set by max_payload_bytes",
+                    req.cls_id(),
+                    req.fn_id(),
+                    payload_bytes,
+                    max
+                ))),
+            );
+        }
+        // Chunks carry the payload compressed and encrypted. The checksum
+        // then covers the empty inline payload, as for `invoke_from_file`.
+        let _chunks = self.chunk(&mut req).await?;
+        if let Some(algorithm) = self.checksum {
+            req.stamp_checksum(algorithm);
+        }
+        // A caller deadline bounds the whole invocation, like a timeout. The
+        // resulting budget is sent on, so handlers forwarding it cannot make
+        // nested calls that outlive this one. Without either, none is sent.
+        // Retries carry the budget of the first attempt.
+        let timeout = match deadline::remaining(req.options()) {
+            Some(Ok(left)) => Some(timeout.map_or(left, |t| t.min(left))),
+            Some(Err(ago)) => {
+                return Err(
+                    ErrorCode::DeadlineExceeded.tag(PyTimeoutError::new_err(format!(
+                        "deadline of {}/{} passed {} ms ago",
+                        req.cls_id(),
+                        req.fn_id(),
+                        ago.as_millis()
+                    ))),
+                );
+            }
+            None => timeout,
+        };
+        if let Some(timeout) = timeout {
+            deadline::send_budget(req.options_mut(), timeout);
+        }
+        let invalid = |msg| ErrorCode::InvalidRequest.tag(PyValueError::new_err(msg));
+        let qos = Qos::take(req.options_mut()).map_err(invalid)?;
+        let lane = lanes::take_lane(req.options_mut()).map_err(invalid)?;
+        let prefer_local = local::take_preference(req.options_mut())
+            .map_err(invalid)?
+            .unwrap_or(self.prefer_local);
+        let target = direct::take_target(req.options_mut()).map_err(invalid)?;
+        let replica = sticky::take_replica(req.options_mut()).map_err(invalid)?;
+        let variant = self.routes.route(&mut req);
+        self.breaker
+            .admit(req.cls_id(), req.fn_id())
+            .map_err(|msg| ErrorCode::CircuitOpen.tag(CircuitOpenError::new_err(msg)))?;
+        let max_retries = max_retries.unwrap_or(self.retry.max_retries);
+        // Waiting for a slot of the lane, then for an in-flight slot, or for
+        // the memory budget to cover the payload, counts as queue time and
+        // against the timeout. All are held across retries.
+        let mut queue_time = started.elapsed();
+        let result = timeout::bound(
+            timeout,
+            async {
+                let _lane_slot = self.lanes.get(lane).acquire().await;
+                let _slot = self.inflight.acquire().await;
+                let _memory = memory::reserve(payload_bytes as u64, "request payload")
+                    .await
+                    .map_err(|msg| CodedError::new(ErrorCode::ResourceExhausted, msg))?;
+                queue_time = started.elapsed();
+                self.send_with_retries(
+                    &req,
+                    qos,
+                    Route {
+                        prefer_local,
+                        target: target.as_deref(),
+                        replica: replica.as_deref(),
+                    },
+                    sealed.as_ref(),
+                    max_retries,
+                )
+                .await
+            }
+            .instrument(span),
+        )
+        .await;
+        let latency = started.elapsed();
+        let ok = result
+            .as_ref()
+            .is_ok_and(|(resp, _)| resp.status == InvocationResponseCode::Okay as i32);
+        self.stats.record(!ok, latency);
+        self.functions
+            .record(req.cls_id(), req.fn_id(), !ok, latency);
+        if let Some(counters) = &variant {
+            counters.record(!ok, latency);
+        }
+        // Only failures that suggest the target is down count towards its
+        // circuit; application errors do not.
+        let down = !result
+            .as_ref()
+            .is_ok_and(|(resp, _)| resp.status != InvocationResponseCode::SystemError as i32);
+        self.breaker.record(req.cls_id(), req.fn_id(), down);
+        // A handler rejecting the payload as too large raises like the local
+        // limit does.
+        let result = result.and_then(|(resp, attempts)| {
+            if payload_limit::rejected(&resp.headers).is_none() {
+                return Ok((resp, attempts));
+            }
+            Err(
+                ErrorCode::PayloadTooLarge.tag(PayloadTooLarge::new_err(format!(
+                    "handler of {}/{} rejected the request: {}",
+                    req.cls_id(),
+                    req.fn_id(),
+                    String::from_utf8_lossy(resp.payload.as_deref().unwrap_or_default())
+                ))),
+            )
+        });
+        let (mut outcome, attempts) = match result {
+            Ok((response, attempts)) => (Ok(response), attempts),
+            Err(e) => (Err(e), Vec::new()),
+        };
+        if intercept {
+            interceptor::after(
+                &self.interceptors,
+                intercepted.as_ref().unwrap_or(&req),
+                &mut outcome,
+            );
+        }
+        if self.calls.enabled() {
+            self.calls.record(
+                req.cls_id(),
+                req.fn_id(),
+                req.object_id(),
+                outcome
+                    .as_ref()
+                    .map(|resp| resp.status)
+                    .map_err(|e| e.to_string()),
+                latency,
+            );
+        }
+        let response = outcome?;
+        let cacheable = response.status == InvocationResponseCode::Okay as i32;
+        if let Some((key, ttl)) = cache_key.zip(cache_ttl).filter(|_| cacheable) {
+            self.cache.insert(key, response.clone(), ttl);
+        }
+        Ok(Delivery {
+            response,
+            attempts,
+            latency,
+            queue_time,
+        })
+    }
+}
+
+/// Describes the attempt `number`, sent to `endpoint`, from its `result`.
+fn describe_attempt(
+    number: u32,
+    endpoint: String,
+    result: &Result<oprc_pb::InvocationResponse, CodedError>,
+    latency: Duration,
+) -> InvocationAttempt {
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    match result {
+        Ok(resp) => InvocationAttempt {
+            number,
+            endpoint,
+            replica: resp.headers.get(REPLICA_HEADER).cloned(),
+            status: Some(resp.status),
+            error_code: ErrorCode::of_response(resp.status, &resp.headers),
+            error: None,
+            latency_ms,
+        },
+        Err(e) => InvocationAttempt {
+            number,
+            endpoint,
+            replica: None,
+            status: None,
+            error_code: Some(e.code),
+            error: Some(e.message.clone()),
+            latency_ms,
+        },
+    }
+}
+
+/// Inflates a response payload compressed by the server's `compression` interceptor.
+fn decode_payload(response: &mut oprc_pb::InvocationResponse) -> Result<(), String> {
+    let Some(name) = response.headers.get(CONTENT_ENCODING) else {
+        return Ok(());
+    };
+    let encoding = Encoding::parse(name)
+        .ok_or_else(|| format!("unsupported response content encoding '{}'", name))?;
+    if let Some(payload) = &response.payload {
+        let inflated = encoding
+            .decode(payload)
+            .map_err(|e| format!("failed to decompress response: {}", e))?;
+        response.payload = Some(inflated);
+    }
+    response.headers.remove(CONTENT_ENCODING);
+    Ok(())
+}
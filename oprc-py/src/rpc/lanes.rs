@@ -0,0 +1,73 @@
+use std::{collections::HashMap, str::FromStr};
+
+use super::limiter::InflightLimiter;
+
+/// Request option with the lane an invocation waits for a slot in: `high`,
+/// `normal` (the default) or `low`.
+pub const LANE_OPTION: &str = "oprc-lane";
+
+/// Priority lane of an invocation. Each lane has its own cap on the
+/// invocations in flight, so batch jobs in `low` cannot take the slots of
+/// interactive invocations in `high`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Lane {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Lane {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Lane::High => "high",
+            Lane::Normal => "normal",
+            Lane::Low => "low",
+        }
+    }
+}
+
+impl FromStr for Lane {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(Lane::High),
+            "normal" => Ok(Lane::Normal),
+            "low" => Ok(Lane::Low),
+            other => Err(format!(
+                "unknown lane '{}'; expected 'high', 'normal' or 'low'",
+                other
+            )),
+        }
+    }
+}
+
+/// Removes the `oprc-lane` option from `options` and parses it.
+pub(crate) fn take_lane(options: &mut HashMap<String, String>) -> Result<Lane, String> {
+    options
+        .remove(LANE_OPTION)
+        .map_or(Ok(Lane::default()), |v| {
+            v.parse()
+                .map_err(|e| format!("invalid {}: {}", LANE_OPTION, e))
+        })
+}
+
+/// The in-flight caps of the lanes of an `RpcManager`, all unlimited until
+/// set with `RpcManager.set_lane_limit`.
+#[derive(Default)]
+pub(crate) struct Lanes {
+    high: InflightLimiter,
+    normal: InflightLimiter,
+    low: InflightLimiter,
+}
+
+impl Lanes {
+    pub(crate) fn get(&self, lane: Lane) -> &InflightLimiter {
+        match lane {
+            Lane::High => &self.high,
+            Lane::Normal => &self.normal,
+            Lane::Low => &self.low,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use tokio::sync::watch;
+
+/// Whether an `RpcManager` still accepts invocations, and how many it has in
+/// flight, so closing it can wait for them to complete.
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    /// Invocations in flight. Admission and closing both update it, so no
+    /// invocation is admitted after closing started.
+    active: watch::Sender<usize>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle {
+            closed: AtomicBool::new(false),
+            active: watch::Sender::new(0),
+        }
+    }
+}
+
+/// An admitted invocation, counted as in flight until dropped.
+pub(crate) struct Active<'a>(&'a Lifecycle);
+
+impl Drop for Active<'_> {
+    fn drop(&mut self) {
+        self.0.active.send_modify(|n| *n -= 1);
+    }
+}
+
+impl Lifecycle {
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Admits an invocation; `None` once closing started.
+    pub(crate) fn enter(&self) -> Option<Active<'_>> {
+        let mut admitted = false;
+        self.active.send_if_modified(|n| {
+            admitted = !self.is_closed();
+            if admitted {
+                *n += 1;
+            }
+            admitted
+        });
+        admitted.then_some(Active(self))
+    }
+
+    /// The invocations in flight.
+    pub(crate) fn active(&self) -> usize {
+        *self.active.borrow()
+    }
+
+    /// Stops admitting invocations.
+    pub(crate) fn close(&self) {
+        self.active
+            .send_modify(|_| self.closed.store(true, Ordering::Release));
+    }
+
+    /// Stops admitting invocations and waits up to `timeout` (or for as long
+    /// as it takes) for those in flight. Returns how many were still in
+    /// flight when it stopped waiting.
+    pub(crate) async fn drain(&self, timeout: Option<Duration>) -> usize {
+        self.close();
+        let mut active = self.active.subscribe();
+        let idle = active.wait_for(|n| *n == 0);
+        match timeout {
+            // The sender lives as long as `self`, so waiting cannot fail.
+            Some(timeout) => {
+                let _ = tokio::time::timeout(timeout, idle).await;
+            }
+            None => {
+                let _ = idle.await;
+            }
+        }
+        self.active()
+    }
+}
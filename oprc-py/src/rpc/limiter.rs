@@ -0,0 +1,43 @@
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bounds the invocations of an `RpcManager` in flight at once. Invocations
+/// over the limit wait for a slot in FIFO order, so a burst queues here
+/// instead of flooding the session and the target's runtime.
+#[derive(Default)]
+pub(crate) struct InflightLimiter {
+    /// The limit and the semaphore enforcing it; `None` when unlimited.
+    limit: RwLock<Option<(usize, Arc<Semaphore>)>>,
+}
+
+impl InflightLimiter {
+    pub(crate) fn max_inflight(&self) -> Option<usize> {
+        self.limit
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|(max, _)| *max)
+    }
+
+    /// Replaces the limit. Invocations already holding a slot keep it and
+    /// release it to the old limit; those still waiting are admitted by the
+    /// old one.
+    pub(crate) fn set_max_inflight(&self, max: Option<usize>) {
+        *self.limit.write().unwrap_or_else(|e| e.into_inner()) =
+            max.map(|max| (max, Arc::new(Semaphore::new(max))));
+    }
+
+    /// Waits for a slot, held until the returned permit is dropped. Returns
+    /// `None` right away when unlimited.
+    pub(crate) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self
+            .limit
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|(_, semaphore)| semaphore.clone())?;
+        // The semaphore is never closed.
+        semaphore.acquire_owned().await.ok()
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use pyo3::prelude::*;
+use tokio::task::JoinSet;
+use tracing::{Instrument, Span, field::Empty};
+
+use super::{Invoker, ProtoRequest};
+use crate::model::InvocationResponseCode;
+
+/// How the send times of a load test are spaced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Arrival {
+    /// Exponentially distributed gaps, as from independent clients.
+    Poisson,
+    /// Evenly spaced sends.
+    Uniform,
+}
+
+impl FromStr for Arrival {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "poisson" => Ok(Arrival::Poisson),
+            "uniform" | "constant" => Ok(Arrival::Uniform),
+            other => Err(format!(
+                "unknown arrival distribution '{}'; expected 'poisson' or 'uniform'",
+                other
+            )),
+        }
+    }
+}
+
+/// Parameters of an open-loop load test.
+pub(crate) struct LoadSpec {
+    pub(crate) rps: f64,
+    pub(crate) duration: Duration,
+    /// Sends before this point are made but not measured.
+    pub(crate) warmup: Duration,
+    pub(crate) arrival: Arrival,
+    /// Sends scheduled while this many invocations are outstanding are
+    /// dropped instead of delayed, so the schedule never slows down.
+    pub(crate) max_in_flight: usize,
+    /// Reports the run as a `loadgen.run` span, exported over OTLP when
+    /// telemetry is initialized.
+    pub(crate) export: bool,
+}
+
+/// Gaps between sends; SplitMix64 is plenty for spacing requests.
+struct Arrivals {
+    arrival: Arrival,
+    mean: f64,
+    state: u64,
+}
+
+impl Arrivals {
+    fn new(arrival: Arrival, rps: f64) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Arrivals {
+            arrival,
+            mean: 1.0 / rps,
+            state: seed,
+        }
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn uniform(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_gap(&mut self) -> Duration {
+        let gap = match self.arrival {
+            Arrival::Poisson => -(1.0 - self.uniform()).ln() * self.mean,
+            Arrival::Uniform => self.mean,
+        };
+        Duration::from_secs_f64(gap)
+    }
+}
+
+/// Outcome of a single send.
+struct Sample {
+    measured: bool,
+    ok: bool,
+    latency: Duration,
+}
+
+/// Sends `req` at `spec.rps` for the warmup and then `spec.duration`, without
+/// waiting for responses, and reports the latencies of the measured sends.
+///
+/// Latency is taken from the scheduled send time, so delays in the generator
+/// count against the target rather than hiding behind a slower schedule.
+pub(crate) async fn run(
+    invoker: &Invoker,
+    req: ProtoRequest,
+    timeout: Option<Duration>,
+    max_retries: Option<u32>,
+    spec: LoadSpec,
+) -> PyResult<LoadReport> {
+    let span = if spec.export {
+        tracing::info_span!(
+            "load_test",
+            otel.name = "loadgen.run",
+            cls_id = %req.cls_id(),
+            fn_id = %req.fn_id(),
+            loadgen.target_rps = spec.rps,
+            loadgen.arrival = ?spec.arrival,
+            loadgen.sent = Empty,
+            loadgen.errors = Empty,
+            loadgen.dropped = Empty,
+            loadgen.achieved_rps = Empty,
+            loadgen.p50_ms = Empty,
+            loadgen.p90_ms = Empty,
+            loadgen.p99_ms = Empty,
+            loadgen.max_ms = Empty,
+        )
+    } else {
+        Span::none()
+    };
+    let mut arrivals = Arrivals::new(spec.arrival, spec.rps);
+    let start = tokio::time::Instant::now();
+    let measure_from = start + spec.warmup;
+    let end = measure_from + spec.duration;
+    let mut tasks = JoinSet::new();
+    let mut samples = Vec::new();
+    let mut dropped = 0u64;
+    let mut at = start;
+    while at < end {
+        tokio::time::sleep_until(at).await;
+        while let Some(done) = tasks.try_join_next() {
+            samples.extend(done.ok());
+        }
+        let measured = at >= measure_from;
+        if tasks.len() >= spec.max_in_flight {
+            if measured {
+                dropped += 1;
+            }
+        } else {
+            let invoker = invoker.clone();
+            let req = req.clone();
+            let scheduled = at.into_std();
+            tasks.spawn(
+                async move {
+                    let result = invoker
+                        .invoke(req, timeout, max_retries, "rpc.load_test")
+                        .await;
+                    Sample {
+                        measured,
+                        ok: result.is_ok_and(|d| {
+                            d.response.status == InvocationResponseCode::Okay as i32
+                        }),
+                        latency: scheduled.elapsed(),
+                    }
+                }
+                .instrument(span.clone()),
+            );
+        }
+        at += arrivals.next_gap();
+    }
+    while let Some(done) = tasks.join_next().await {
+        samples.extend(done.ok());
+    }
+    let report = LoadReport::new(spec.rps, spec.duration, samples, dropped);
+    span.record("loadgen.sent", report.sent);
+    span.record("loadgen.errors", report.errors);
+    span.record("loadgen.dropped", report.dropped);
+    span.record("loadgen.achieved_rps", report.achieved_rps);
+    span.record("loadgen.p50_ms", report.p50_ms);
+    span.record("loadgen.p90_ms", report.p90_ms);
+    span.record("loadgen.p99_ms", report.p99_ms);
+    span.record("loadgen.max_ms", report.max_ms);
+    Ok(report)
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// Result of a `load_test` run. Counts and latencies cover the measured
+/// period only, not the warmup.
+pub struct LoadReport {
+    /// Requested sends per second.
+    pub target_rps: f64,
+    /// Successful responses per second over the measured period.
+    pub achieved_rps: f64,
+    /// Length of the measured period in seconds.
+    pub duration_s: f64,
+    /// Invocations sent.
+    pub sent: u64,
+    /// Invocations that failed or returned a non-`Okay` status.
+    pub errors: u64,
+    /// Sends skipped because `max_in_flight` invocations were outstanding.
+    pub dropped: u64,
+    /// Latency percentiles of the successful invocations, in milliseconds.
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Sorted latencies of the successful invocations, in milliseconds.
+    pub latencies_ms: Vec<f64>,
+}
+
+impl LoadReport {
+    fn new(target_rps: f64, duration: Duration, samples: Vec<Sample>, dropped: u64) -> Self {
+        let measured: Vec<&Sample> = samples.iter().filter(|s| s.measured).collect();
+        let mut latencies_ms: Vec<f64> = measured
+            .iter()
+            .filter(|s| s.ok)
+            .map(|s| s.latency.as_secs_f64() * 1000.0)
+            .collect();
+        latencies_ms.sort_by(f64::total_cmp);
+        let duration_s = duration.as_secs_f64();
+        let mean_ms = if latencies_ms.is_empty() {
+            0.0
+        } else {
+            latencies_ms.iter().sum::<f64>() / latencies_ms.len() as f64
+        };
+        let mut report = LoadReport {
+            target_rps,
+            achieved_rps: if duration_s > 0.0 {
+                latencies_ms.len() as f64 / duration_s
+            } else {
+                0.0
+            },
+            duration_s,
+            sent: measured.len() as u64,
+            errors: measured.iter().filter(|s| !s.ok).count() as u64,
+            dropped,
+            p50_ms: 0.0,
+            p90_ms: 0.0,
+            p99_ms: 0.0,
+            p999_ms: 0.0,
+            mean_ms,
+            max_ms: latencies_ms.last().copied().unwrap_or_default(),
+            latencies_ms,
+        };
+        report.p50_ms = report.quantile(0.5);
+        report.p90_ms = report.quantile(0.9);
+        report.p99_ms = report.quantile(0.99);
+        report.p999_ms = report.quantile(0.999);
+        report
+    }
+
+    /// Nearest-rank quantile of the successful latencies, `0` without any.
+    fn quantile(&self, q: f64) -> f64 {
+        if self.latencies_ms.is_empty() {
+            return 0.0;
+        }
+        let rank = (q * self.latencies_ms.len() as f64).ceil() as usize;
+        self.latencies_ms[rank.clamp(1, self.latencies_ms.len()) - 1]
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl LoadReport {
+    /// Returns the latency in milliseconds below which `percent` percent of
+    /// the successful invocations completed.
+    fn percentile(&self, percent: f64) -> PyResult<f64> {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "percent must be between 0 and 100",
+            ));
+        }
+        Ok(self.quantile(percent / 100.0))
+    }
+
+    /// Returns a multi-line, human readable report.
+    fn __str__(&self) -> String {
+        format!(
+            "target: {:.1} rps, achieved: {:.1} rps over {:.1}s\n\
+             sent: {}, errors: {}, dropped: {}\n\
+             latency (ms): p50 {:.3}, p90 {:.3}, p99 {:.3}, p99.9 {:.3}, mean {:.3}, max {:.3}",
+            self.target_rps,
+            self.achieved_rps,
+            self.duration_s,
+            self.sent,
+            self.errors,
+            self.dropped,
+            self.p50_ms,
+            self.p90_ms,
+            self.p99_ms,
+            self.p999_ms,
+            self.mean_ms,
+            self.max_ms
+        )
+    }
+}
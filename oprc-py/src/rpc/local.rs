@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use oprc_pb::oprc_function_server::OprcFunction;
+use tonic::Request;
+use tracing::debug;
+use zenoh::key_expr::KeyExpr;
+
+use super::{ProtoRequest, attachments::invoke_key};
+use crate::{firewall::ZenohFirewall, handler::LOOP_CLOSED, model::InvocationResponseCode};
+
+/// Request option that, set to `true` or `false`, overrides
+/// `RpcManager.prefer_local` for one invocation.
+pub(crate) const PREFER_LOCAL_OPTION: &str = "oprc-prefer-local";
+
+/// A handler this process serves over Zenoh.
+struct LocalHandler {
+    key_expr: KeyExpr<'static>,
+    firewall: Arc<ZenohFirewall>,
+    handler: Arc<dyn OprcFunction>,
+}
+
+/// Handlers served by any engine of this process.
+static HANDLERS: RwLock<Vec<LocalHandler>> = RwLock::new(Vec::new());
+
+/// Makes `handler`, served on `key_expr`, available to local invocations.
+pub(crate) fn register(
+    key_expr: &str,
+    firewall: Arc<ZenohFirewall>,
+    handler: Arc<dyn OprcFunction>,
+) {
+    let Ok(key_expr) = KeyExpr::try_from(key_expr.to_string()) else {
+        return;
+    };
+    let mut handlers = HANDLERS.write().unwrap();
+    handlers.retain(|h| h.key_expr != key_expr);
+    handlers.push(LocalHandler {
+        key_expr,
+        firewall,
+        handler,
+    });
+}
+
+/// Removes the handler served on `key_expr`, once it is no longer served.
+pub(crate) fn unregister(key_expr: &str) {
+    HANDLERS
+        .write()
+        .unwrap()
+        .retain(|h| h.key_expr.as_str() != key_expr);
+}
+
+/// Parses the `oprc-prefer-local` option, removing it from `options`.
+pub(crate) fn take_preference(
+    options: &mut HashMap<String, String>,
+) -> Result<Option<bool>, String> {
+    options
+        .remove(PREFER_LOCAL_OPTION)
+        .map(|v| match v.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            other => Err(format!(
+                "invalid {} '{}'; expected 'true' or 'false'",
+                PREFER_LOCAL_OPTION, other
+            )),
+        })
+        .transpose()
+}
+
+/// Whether a handler of this process serves `req`.
+pub(crate) fn serves(req: &ProtoRequest) -> bool {
+    KeyExpr::try_from(invoke_key(req))
+        .ok()
+        .and_then(|key| handler_for(&key))
+        .is_some()
+}
+
+/// The handler serving `key` that its firewall lets through, if any.
+fn handler_for(key: &KeyExpr<'_>) -> Option<Arc<dyn OprcFunction>> {
+    HANDLERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|h| h.key_expr.intersects(key) && h.firewall.check(key).is_ok())
+        .map(|h| h.handler.clone())
+}
+
+/// Runs `req` on a handler this process serves for it, without going through
+/// Zenoh. Returns `None` when no handler matches or it cannot take the
+/// invocation (its event loop is closed), so the caller sends it remotely.
+pub(crate) async fn invoke(req: &ProtoRequest) -> Option<oprc_pb::InvocationResponse> {
+    let key = KeyExpr::try_from(invoke_key(req)).ok()?;
+    let handler = handler_for(&key)?;
+    let result = match req.clone() {
+        ProtoRequest::Fn(r) => handler.invoke_fn(Request::new(r)).await,
+        ProtoRequest::Obj(r) => handler.invoke_obj(Request::new(r)).await,
+    };
+    match result {
+        Ok(response) => {
+            let response = response.into_inner();
+            let closed = response.status == InvocationResponseCode::SystemError as i32
+                && response
+                    .payload
+                    .as_deref()
+                    .is_some_and(|p| p.starts_with(LOOP_CLOSED.as_bytes()));
+            if closed {
+                debug!("local handler for {} is closed, sending remotely", key);
+                return None;
+            }
+            Some(response)
+        }
+        Err(status) => {
+            debug!(
+                "local invocation of {} failed, sending remotely: {}",
+                key, status
+            );
+            None
+        }
+    }
+}
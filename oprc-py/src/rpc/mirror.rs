@@ -0,0 +1,103 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use super::{ProtoRequest, direct::TARGET_ADDR_OPTION};
+use crate::sticky::REPLICA_ID_OPTION;
+
+/// Request option marking a mirrored copy of an invocation, so its handler
+/// can skip side effects. Mirrored requests are never mirrored again.
+pub(crate) const MIRROR_OPTION: &str = "oprc-mirror";
+
+/// Where a share of the invocations of a class, or of one of its functions,
+/// is copied to.
+#[derive(Clone)]
+struct Mirror {
+    /// Share of the invocations copied, between `0` and `1`.
+    fraction: f64,
+    /// Class the copies go to; `None` keeps the invoked class.
+    cls_id: Option<String>,
+    /// Function the copies go to; `None` keeps the invoked function.
+    fn_id: Option<String>,
+}
+
+/// Mirrors of an `RpcManager`, by class and optional function.
+#[derive(Clone, Default)]
+pub(crate) struct MirrorTable(Arc<RwLock<HashMap<(String, Option<String>), Mirror>>>);
+
+impl MirrorTable {
+    /// Mirrors `fraction` of the invocations of `cls_id` (only of `fn_id`,
+    /// if given) to `target_cls_id`/`target_fn_id`.
+    pub(crate) fn set(
+        &self,
+        cls_id: String,
+        fn_id: Option<String>,
+        fraction: f64,
+        target_cls_id: Option<String>,
+        target_fn_id: Option<String>,
+    ) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err("fraction must be between 0 and 1".to_string());
+        }
+        if target_cls_id.is_none() && target_fn_id.is_none() {
+            return Err("a mirror needs a target_cls_id or a target_fn_id".to_string());
+        }
+        self.0.write().unwrap().insert(
+            (cls_id, fn_id),
+            Mirror {
+                fraction,
+                cls_id: target_cls_id,
+                fn_id: target_fn_id,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes the mirror of `cls_id` (of `fn_id`, if given). Returns whether
+    /// there was one.
+    pub(crate) fn clear(&self, cls_id: String, fn_id: Option<String>) -> bool {
+        self.0.write().unwrap().remove(&(cls_id, fn_id)).is_some()
+    }
+
+    /// Returns the copy of `req` to send to its mirror, if it has one and
+    /// `req` falls into the mirrored share. A function's own mirror takes
+    /// precedence over its class's.
+    pub(crate) fn copy(&self, req: &ProtoRequest) -> Option<ProtoRequest> {
+        if req.options().contains_key(MIRROR_OPTION) {
+            return None;
+        }
+        let mirror = {
+            let table = self.0.read().unwrap();
+            if table.is_empty() {
+                return None;
+            }
+            let cls_id = req.cls_id().to_string();
+            table
+                .get(&(cls_id.clone(), Some(req.fn_id().to_string())))
+                .or_else(|| table.get(&(cls_id, None)))
+                .cloned()?
+        };
+        if fastrand::f64() >= mirror.fraction {
+            return None;
+        }
+        let mut copy = req.clone();
+        let (cls_id, fn_id) = match &mut copy {
+            ProtoRequest::Fn(r) => (&mut r.cls_id, &mut r.fn_id),
+            ProtoRequest::Obj(r) => (&mut r.cls_id, &mut r.fn_id),
+        };
+        if let Some(target) = mirror.cls_id {
+            *cls_id = target;
+        }
+        if let Some(target) = mirror.fn_id {
+            *fn_id = target;
+        }
+        // A target address or replica of the original does not serve the
+        // copy's class.
+        copy.options_mut().remove(TARGET_ADDR_OPTION);
+        copy.options_mut().remove(REPLICA_ID_OPTION);
+        copy.options_mut()
+            .insert(MIRROR_OPTION.to_string(), "true".to_string());
+        Some(copy)
+    }
+}
@@ -0,0 +1,2163 @@
+mod attachments;
+mod balancer;
+mod breaker;
+mod budget;
+pub(crate) mod cache;
+mod defaults;
+mod direct;
+mod explain;
+mod fan_out;
+mod interceptor;
+mod invoker;
+pub(crate) mod lanes;
+mod lifecycle;
+mod limiter;
+mod loadgen;
+pub(crate) mod local;
+mod mirror;
+pub(crate) mod partitioning;
+mod progress;
+mod qos;
+mod response_stream;
+mod retry;
+mod routing;
+mod stats;
+mod stream;
+mod timeout;
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pyo3::{exceptions::{PyRuntimeError, PyTypeError, PyValueError}, Bound, Py, PyAny, PyRef, PyResult, Python};
+use pyo3::types::{PyAnyMethods, PyCFunction, PyDict, PyTuple, PyTupleMethods};
+use tokio::task::JoinHandle;
+
+use crate::call_log::CallRecord;
+use crate::catalog;
+use crate::checksum::ChecksumAlgorithm;
+use crate::chunks::{ChunkSource, DEFAULT_CHUNK_SIZE};
+use crate::codec::{self, Codec};
+use crate::compression::Encoding;
+use crate::e2e;
+use crate::ids;
+use crate::metrics::MetricPoint;
+use crate::model::{
+    DetailedInvocationResponse, InvocationRequest, InvocationResponse, InvocationResponseCode,
+    MultiObjectInvocationRequest, ObjectInvocationRequest,
+};
+
+pub use balancer::LoadBalancing;
+pub use breaker::CircuitOpenError;
+pub use budget::RetryBudgetStats;
+pub use defaults::ClassDefaults;
+pub use explain::InvocationPlan;
+pub use fan_out::ObjectResultStream;
+use interceptor::ClientInterceptor;
+pub(crate) use invoker::{Delivery, Invoker, ProtoRequest};
+use lanes::Lane;
+pub(crate) use lifecycle::Lifecycle;
+pub use loadgen::LoadReport;
+use loadgen::LoadSpec;
+use progress::Progress;
+pub use response_stream::ResponseStream;
+pub use retry::RetryDecision;
+use retry::RetryPolicy;
+pub use stats::InvocationStats;
+pub use timeout::{get_default_timeout, set_default_timeout};
+
+/// Manages RPC invocations using an ObjectProxy.
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass]
+pub struct RpcManager {
+    invoker: Invoker,
+    /// Session chunked payloads are transferred over.
+    session: zenoh::Session,
+    /// Manager-level timeout in milliseconds; overrides the module default
+    /// and is overridden by a class default or a per-request `timeout_ms`.
+    #[pyo3(get, set)]
+    timeout_ms: Option<u64>,
+}
+
+/// Per-request settings that override the manager's.
+struct Overrides {
+    timeout_ms: Option<u64>,
+    max_retries: Option<u32>,
+}
+
+/// An invocation running on the shared runtime. Dropping it aborts the task,
+/// so a cancelled Python caller does not leave the query or call running.
+struct InFlight(JoinHandle<PyResult<Delivery>>);
+
+impl Future for InFlight {
+    type Output = PyResult<Delivery>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|res| {
+            res.unwrap_or_else(|e| {
+                Err(PyRuntimeError::new_err(format!("invocation task failed: {}", e)))
+            })
+        })
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl RpcManager {
+    /// Creates a new RpcManager with a Zenoh session.
+    pub fn new(z_session: zenoh::Session) -> Self {
+        let invoker = Invoker::new(z_session.clone());
+        crate::shutdown::track_manager(&invoker.lifecycle);
+        RpcManager {
+            invoker,
+            session: z_session,
+            timeout_ms: None,
+        }
+    }
+
+    /// Extracts the protobuf request and its per-request settings from a Python request.
+    fn extract_fn(py: Python<'_>, req: Py<InvocationRequest>) -> (ProtoRequest, Overrides) {
+        let req = req.into_bound(py);
+        let req = req.borrow();
+        let overrides = Overrides {
+            timeout_ms: req.timeout_ms(),
+            max_retries: req.max_retries(),
+        };
+        (ProtoRequest::Fn(req.into_proto()), overrides)
+    }
+
+    /// Extracts the protobuf request and its per-request settings from a Python request.
+    fn extract_obj(py: Python<'_>, req: Py<ObjectInvocationRequest>) -> (ProtoRequest, Overrides) {
+        let req = req.into_bound(py);
+        let req = req.borrow();
+        let overrides = Overrides {
+            timeout_ms: req.timeout_ms(),
+            max_retries: req.max_retries(),
+        };
+        (ProtoRequest::Obj(req.into_proto()), overrides)
+    }
+
+    /// Extracts the carrying protobuf request and its per-request settings from a Python request.
+    fn extract_multi(
+        py: Python<'_>,
+        req: Py<MultiObjectInvocationRequest>,
+    ) -> (ProtoRequest, Overrides) {
+        let req = req.into_bound(py);
+        let req = req.borrow();
+        let overrides = Overrides {
+            timeout_ms: req.timeout_ms(),
+            max_retries: req.max_retries(),
+        };
+        (ProtoRequest::Fn(req.into_proto()), overrides)
+    }
+
+    /// Extracts the protobuf request and its per-request settings from any
+    /// kind of Python request.
+    fn extract_any(req: &Bound<'_, PyAny>) -> PyResult<(ProtoRequest, Overrides)> {
+        let py = req.py();
+        if let Ok(r) = req.downcast::<InvocationRequest>() {
+            Ok(Self::extract_fn(py, r.clone().unbind()))
+        } else if let Ok(r) = req.downcast::<ObjectInvocationRequest>() {
+            Ok(Self::extract_obj(py, r.clone().unbind()))
+        } else if let Ok(r) = req.downcast::<MultiObjectInvocationRequest>() {
+            Ok(Self::extract_multi(py, r.clone().unbind()))
+        } else {
+            Err(PyTypeError::new_err(
+                "expected InvocationRequest, ObjectInvocationRequest or MultiObjectInvocationRequest",
+            ))
+        }
+    }
+
+    /// Writes the QoS arguments of an `invoke_*` call into the options of `req`.
+    fn with_qos(
+        mut req: ProtoRequest,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+    ) -> PyResult<ProtoRequest> {
+        qos::stamp(req.options_mut(), priority, congestion_control, express)
+            .map_err(PyValueError::new_err)?;
+        Ok(req)
+    }
+
+    /// Replaces the payload of `req` with `value` encoded with `codec`, if
+    /// given.
+    fn with_value(
+        mut req: ProtoRequest,
+        value: Option<&Bound<'_, PyAny>>,
+        codec: Codec,
+    ) -> PyResult<ProtoRequest> {
+        if let Some(value) = value {
+            *req.payload_mut() = codec.encode(value)?;
+        }
+        Ok(req)
+    }
+
+    /// Decodes the response payload of a typed invocation with `codec`;
+    /// `None` if it is empty. Raises if the response is not `Okay`.
+    fn typed_result(py: Python<'_>, delivery: Delivery, codec: Codec) -> PyResult<Py<PyAny>> {
+        let resp = delivery.response;
+        let payload = resp.payload.unwrap_or_default();
+        if resp.status != InvocationResponseCode::Okay as i32 {
+            return Err(PyRuntimeError::new_err(format!(
+                "invocation returned status {}: {}",
+                resp.status,
+                String::from_utf8_lossy(&payload)
+            )));
+        }
+        if payload.is_empty() {
+            return Ok(py.None());
+        }
+        codec.decode(py, &payload).map(Bound::unbind)
+    }
+
+    /// Points `req` at the `OprcFunction` gRPC server at `addr`.
+    fn with_target(mut req: ProtoRequest, addr: String) -> ProtoRequest {
+        req.options_mut()
+            .insert(direct::TARGET_ADDR_OPTION.to_string(), addr);
+        req
+    }
+
+    /// Applies the class defaults to `req` and resolves its effective timeout.
+    fn prepare(
+        &self,
+        mut req: ProtoRequest,
+        req_timeout: Option<u64>,
+    ) -> (ProtoRequest, Option<Duration>) {
+        let class_timeout = self.invoker.defaults.apply(&mut req);
+        let timeout = timeout::resolve(class_timeout.or(self.timeout_ms), req_timeout);
+        (req, timeout)
+    }
+
+    /// Starts `req` on the shared runtime with the manager and per-request
+    /// settings.
+    fn spawn(&self, req: ProtoRequest, overrides: Overrides, span: &'static str) -> InFlight {
+        let (req, timeout) = self.prepare(req, overrides.timeout_ms);
+        let invoker = self.invoker.clone();
+        InFlight(pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            invoker.invoke(req, timeout, overrides.max_retries, span).await
+        }))
+    }
+
+    /// Runs `req` through the invoker with the manager and per-request settings.
+    /// Cancelling the awaiting Python task cancels the invocation.
+    async fn invoke(
+        &self,
+        req: ProtoRequest,
+        overrides: Overrides,
+        span: &'static str,
+    ) -> PyResult<Delivery> {
+        self.spawn(req, overrides, span).await
+    }
+
+    /// Like `invoke`, passing the progress events the handler reports to
+    /// `on_progress`, if given.
+    async fn invoke_with_progress(
+        &self,
+        mut req: ProtoRequest,
+        overrides: Overrides,
+        on_progress: Option<Py<PyAny>>,
+        span: &'static str,
+    ) -> PyResult<Delivery> {
+        let Some(callback) = on_progress else {
+            return self.invoke(req, overrides, span).await;
+        };
+        let progress = Progress::watch(&self.session, &mut req, callback).await?;
+        progress.forward(self.invoke(req, overrides, span)).await
+    }
+
+    /// Runs all `reqs` concurrently on the shared runtime and returns their
+    /// outcomes in order, each an `InvocationResponse` or the exception its
+    /// invocation raised. Cancelling the batch cancels the invocations still
+    /// running.
+    async fn invoke_batch(
+        &self,
+        reqs: Vec<(ProtoRequest, Overrides)>,
+        span: &'static str,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let tasks: Vec<_> = reqs
+            .into_iter()
+            .map(|(req, overrides)| self.spawn(req, overrides, span))
+            .collect();
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(task.await);
+        }
+        Python::attach(|py| {
+            results
+                .into_iter()
+                .map(|res| match res {
+                    Ok(delivery) => {
+                        Py::new(py, InvocationResponse::from(delivery)).map(Py::into_any)
+                    }
+                    Err(e) => Ok(e.into_value(py).into_any()),
+                })
+                .collect()
+        })
+    }
+
+    /// Builds the requests of a scatter-gather invocation, one per partition.
+    /// Raises `ValueError` if a partition is listed twice, as the results are
+    /// keyed by partition.
+    fn scatter(
+        cls_id: String,
+        fn_id: String,
+        partition_ids: &[u32],
+        payload: Vec<u8>,
+        options: HashMap<String, String>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Vec<(ProtoRequest, Overrides)>> {
+        let mut seen = HashSet::with_capacity(partition_ids.len());
+        if let Some(dup) = partition_ids.iter().find(|id| !seen.insert(**id)) {
+            return Err(PyValueError::new_err(format!(
+                "partition {} is listed more than once",
+                dup
+            )));
+        }
+        Ok(partition_ids
+            .iter()
+            .map(|&partition_id| {
+                let req = oprc_pb::InvocationRequest {
+                    partition_id,
+                    cls_id: cls_id.clone(),
+                    fn_id: fn_id.clone(),
+                    options: options.clone(),
+                    payload: payload.clone(),
+                };
+                let overrides = Overrides {
+                    timeout_ms,
+                    max_retries: None,
+                };
+                (ProtoRequest::Fn(req), overrides)
+            })
+            .collect())
+    }
+
+    /// Collects the outcomes of a scatter-gather invocation into a dict keyed
+    /// by partition.
+    fn gather(partition_ids: &[u32], results: Vec<Py<PyAny>>) -> PyResult<Py<PyDict>> {
+        Python::attach(|py| {
+            let dict = PyDict::new(py);
+            for (partition_id, result) in partition_ids.iter().zip(results) {
+                dict.set_item(partition_id, result)?;
+            }
+            Ok(dict.unbind())
+        })
+    }
+
+    /// Runs `req` on the shared runtime, blocking the current thread without holding the GIL.
+    fn invoke_blocking(
+        &self,
+        py: Python<'_>,
+        req: ProtoRequest,
+        overrides: Overrides,
+        span: &'static str,
+    ) -> PyResult<Delivery> {
+        let (req, timeout) = self.prepare(req, overrides.timeout_ms);
+        let invoker = self.invoker.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(move || {
+            runtime.block_on(async move {
+                invoker.invoke(req, timeout, overrides.max_retries, span).await
+            })
+        })
+    }
+
+    /// Starts `req` on the shared runtime and returns a
+    /// `concurrent.futures.Future` resolved with its `InvocationResponse` or
+    /// exception. Cancelling the future before it resolves aborts the
+    /// invocation.
+    fn submit(
+        &self,
+        py: Python<'_>,
+        req: ProtoRequest,
+        overrides: Overrides,
+        span: &'static str,
+    ) -> PyResult<Py<PyAny>> {
+        let future = py
+            .import("concurrent.futures")?
+            .getattr("Future")?
+            .call0()?
+            .unbind();
+        let resolved = future.clone_ref(py);
+        let task = self.spawn(req, overrides, span);
+        let abort = pyo3_async_runtimes::tokio::get_runtime()
+            .spawn(async move {
+                let res = task.await;
+                Python::attach(|py| {
+                    let future = resolved.bind(py);
+                    // Fails only if the future was cancelled meanwhile.
+                    let _ = match res {
+                        Ok(delivery) => {
+                            future.call_method1("set_result", (InvocationResponse::from(delivery),))
+                        }
+                        Err(e) => future.call_method1("set_exception", (e.into_value(py),)),
+                    };
+                });
+            })
+            .abort_handle();
+        let on_done = PyCFunction::new_closure(
+            py,
+            None,
+            None,
+            move |args: &Bound<'_, PyTuple>, _: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+                if args.get_item(0)?.call_method0("cancelled")?.is_truthy()? {
+                    abort.abort();
+                }
+                Ok(())
+            },
+        )?;
+        future.call_method1(py, "add_done_callback", (on_done,))?;
+        Ok(future)
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl RpcManager {
+    /// Invokes a function based on the provided InvocationRequest. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `InvocationRequest` instance.
+    /// * `priority`: Zenoh priority of the query (`real_time`, `interactive_high`,
+    ///   `interactive_low`, `data_high`, `data`, `data_low` or `background`).
+    /// * `congestion_control`: `drop` or `block` when the link is congested.
+    /// * `express`: Send the query without batching it with other messages.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    #[pyo3(signature = (req, priority=None, congestion_control=None, express=None))]
+    pub fn invoke_fn(
+        &self,
+        py: Python<'_>,
+        req: Py<InvocationRequest>,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Self::extract_fn(py, req);
+        let proto_req = Self::with_qos(proto_req, priority, congestion_control, express)?;
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_fn")
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes a function based on the provided InvocationRequest. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `InvocationRequest` instance.
+    /// * `priority`: Zenoh priority of the query (`real_time`, `interactive_high`,
+    ///   `interactive_low`, `data_high`, `data`, `data_low` or `background`).
+    /// * `congestion_control`: `drop` or `block` when the link is congested.
+    /// * `express`: Send the query without batching it with other messages.
+    /// * `on_progress`: Called with every progress event the handler reports
+    ///   with `report_progress` while the invocation runs, decoded from JSON.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    #[pyo3(signature = (req, priority=None, congestion_control=None, express=None, on_progress=None))]
+    pub async fn invoke_fn_async(
+        &self,
+        req: Py<InvocationRequest>,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+        on_progress: Option<Py<PyAny>>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_fn(py, req));
+        let proto_req = Self::with_qos(proto_req, priority, congestion_control, express)?;
+        self.invoke_with_progress(proto_req, overrides, on_progress, "rpc.invoke_fn_async")
+            .await
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes an object method based on the provided ObjectInvocationRequest. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    /// * `priority`: Zenoh priority of the query (`real_time`, `interactive_high`,
+    ///   `interactive_low`, `data_high`, `data`, `data_low` or `background`).
+    /// * `congestion_control`: `drop` or `block` when the link is congested.
+    /// * `express`: Send the query without batching it with other messages.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    #[pyo3(signature = (req, priority=None, congestion_control=None, express=None))]
+    pub fn invoke_obj(
+        &self,
+        py: Python<'_>,
+        req: Py<ObjectInvocationRequest>,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Self::extract_obj(py, req);
+        let proto_req = Self::with_qos(proto_req, priority, congestion_control, express)?;
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_obj")
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes an object method based on the provided ObjectInvocationRequest. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    /// * `priority`: Zenoh priority of the query (`real_time`, `interactive_high`,
+    ///   `interactive_low`, `data_high`, `data`, `data_low` or `background`).
+    /// * `congestion_control`: `drop` or `block` when the link is congested.
+    /// * `express`: Send the query without batching it with other messages.
+    /// * `on_progress`: Called with every progress event the handler reports
+    ///   with `report_progress` while the invocation runs, decoded from JSON.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    #[pyo3(signature = (req, priority=None, congestion_control=None, express=None, on_progress=None))]
+    pub async fn invoke_obj_async(
+        &self,
+        req: Py<ObjectInvocationRequest>,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+        on_progress: Option<Py<PyAny>>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_obj(py, req));
+        let proto_req = Self::with_qos(proto_req, priority, congestion_control, express)?;
+        self.invoke_with_progress(proto_req, overrides, on_progress, "rpc.invoke_obj_async")
+            .await
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes a function without blocking and returns a
+    /// `concurrent.futures.Future` for its `InvocationResponse`.
+    ///
+    /// The invocation runs on the Rust runtime, so threaded applications
+    /// can fan out invocations without an event loop or a thread per call.
+    /// `future.result()` raises what `invoke_fn` would have raised.
+    /// Cancelling the future before it resolves aborts the invocation.
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `InvocationRequest` instance.
+    /// * `priority`: Zenoh priority of the query; see `invoke_fn`.
+    /// * `congestion_control`: `drop` or `block` when the link is congested.
+    /// * `express`: Send the query without batching it with other messages.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing a `concurrent.futures.Future`.
+    #[pyo3(signature = (req, priority=None, congestion_control=None, express=None))]
+    pub fn invoke_fn_future(
+        &self,
+        py: Python<'_>,
+        req: Py<InvocationRequest>,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+    ) -> PyResult<Py<PyAny>> {
+        let (proto_req, overrides) = Self::extract_fn(py, req);
+        let proto_req = Self::with_qos(proto_req, priority, congestion_control, express)?;
+        self.submit(py, proto_req, overrides, "rpc.invoke_fn_future")
+    }
+
+    /// Invokes an object method without blocking and returns a
+    /// `concurrent.futures.Future` for its `InvocationResponse`; see
+    /// `invoke_fn_future`.
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    /// * `priority`: Zenoh priority of the query; see `invoke_fn`.
+    /// * `congestion_control`: `drop` or `block` when the link is congested.
+    /// * `express`: Send the query without batching it with other messages.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing a `concurrent.futures.Future`.
+    #[pyo3(signature = (req, priority=None, congestion_control=None, express=None))]
+    pub fn invoke_obj_future(
+        &self,
+        py: Python<'_>,
+        req: Py<ObjectInvocationRequest>,
+        priority: Option<String>,
+        congestion_control: Option<String>,
+        express: Option<bool>,
+    ) -> PyResult<Py<PyAny>> {
+        let (proto_req, overrides) = Self::extract_obj(py, req);
+        let proto_req = Self::with_qos(proto_req, priority, congestion_control, express)?;
+        self.submit(py, proto_req, overrides, "rpc.invoke_obj_future")
+    }
+
+    /// Invokes a function with `value` as the payload and returns the
+    /// decoded response payload. (Synchronous)
+    ///
+    /// Both are encoded with `codec`: `json` (the default) and `msgpack`
+    /// handle what JSON can represent, pydantic models and dataclasses
+    /// included; `pickle` handles any picklable value, but must only be used
+    /// between trusted peers. An empty response payload decodes to `None`.
+    /// A response with a status other than `Okay` raises `RuntimeError`.
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `InvocationRequest` instance.
+    /// * `value`: The value to send; `None` keeps the payload of `req`.
+    /// * `codec`: `json`, `msgpack` or `pickle`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing the decoded response payload.
+    #[pyo3(signature = (req, value=None, codec="json"))]
+    pub fn invoke_fn_typed(
+        &self,
+        py: Python<'_>,
+        req: Py<InvocationRequest>,
+        value: Option<&Bound<'_, PyAny>>,
+        codec: &str,
+    ) -> PyResult<Py<PyAny>> {
+        let codec: Codec = codec.parse().map_err(PyValueError::new_err)?;
+        let (proto_req, overrides) = Self::extract_fn(py, req);
+        let proto_req = Self::with_value(proto_req, value, codec)?;
+        let delivery = self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_fn_typed")?;
+        Self::typed_result(py, delivery, codec)
+    }
+
+    /// Invokes a function with `value` as the payload and returns the
+    /// decoded response payload; see `invoke_fn_typed`. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `InvocationRequest` instance.
+    /// * `value`: The value to send; `None` keeps the payload of `req`.
+    /// * `codec`: `json`, `msgpack` or `pickle`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing the decoded response payload.
+    #[pyo3(signature = (req, value=None, codec="json"))]
+    pub async fn invoke_fn_typed_async(
+        &self,
+        req: Py<InvocationRequest>,
+        value: Option<Py<PyAny>>,
+        codec: String,
+    ) -> PyResult<Py<PyAny>> {
+        let codec: Codec = codec.parse().map_err(PyValueError::new_err)?;
+        let (proto_req, overrides) = Python::attach(|py| {
+            let (proto_req, overrides) = Self::extract_fn(py, req);
+            Self::with_value(proto_req, value.as_ref().map(|v| v.bind(py)), codec)
+                .map(|req| (req, overrides))
+        })?;
+        let delivery = self
+            .invoke(proto_req, overrides, "rpc.invoke_fn_typed_async")
+            .await?;
+        Python::attach(|py| Self::typed_result(py, delivery, codec))
+    }
+
+    /// Invokes an object method with `value` as the payload and returns the
+    /// decoded response payload; see `invoke_fn_typed`. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    /// * `value`: The value to send; `None` keeps the payload of `req`.
+    /// * `codec`: `json`, `msgpack` or `pickle`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing the decoded response payload.
+    #[pyo3(signature = (req, value=None, codec="json"))]
+    pub fn invoke_obj_typed(
+        &self,
+        py: Python<'_>,
+        req: Py<ObjectInvocationRequest>,
+        value: Option<&Bound<'_, PyAny>>,
+        codec: &str,
+    ) -> PyResult<Py<PyAny>> {
+        let codec: Codec = codec.parse().map_err(PyValueError::new_err)?;
+        let (proto_req, overrides) = Self::extract_obj(py, req);
+        let proto_req = Self::with_value(proto_req, value, codec)?;
+        let delivery = self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_obj_typed")?;
+        Self::typed_result(py, delivery, codec)
+    }
+
+    /// Invokes an object method with `value` as the payload and returns the
+    /// decoded response payload; see `invoke_fn_typed`. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    /// * `value`: The value to send; `None` keeps the payload of `req`.
+    /// * `codec`: `json`, `msgpack` or `pickle`.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing the decoded response payload.
+    #[pyo3(signature = (req, value=None, codec="json"))]
+    pub async fn invoke_obj_typed_async(
+        &self,
+        req: Py<ObjectInvocationRequest>,
+        value: Option<Py<PyAny>>,
+        codec: String,
+    ) -> PyResult<Py<PyAny>> {
+        let codec: Codec = codec.parse().map_err(PyValueError::new_err)?;
+        let (proto_req, overrides) = Python::attach(|py| {
+            let (proto_req, overrides) = Self::extract_obj(py, req);
+            Self::with_value(proto_req, value.as_ref().map(|v| v.bind(py)), codec)
+                .map(|req| (req, overrides))
+        })?;
+        let delivery = self
+            .invoke(proto_req, overrides, "rpc.invoke_obj_typed_async")
+            .await?;
+        Python::attach(|py| Self::typed_result(py, delivery, codec))
+    }
+
+    /// Invokes a function on the gRPC server at `addr` (`host:port` or a URI),
+    /// bypassing Zenoh. (Synchronous)
+    ///
+    /// Useful to debug a single worker or where the Zenoh mesh is unavailable.
+    /// Timeouts, retries, deadlines and checksums apply as usual.
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `addr`: Address of a server started with `serve_grpc_server`.
+    /// * `req`: A Python `InvocationRequest` instance.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    pub fn invoke_fn_direct(
+        &self,
+        py: Python<'_>,
+        addr: String,
+        req: Py<InvocationRequest>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Self::extract_fn(py, req);
+        let proto_req = Self::with_target(proto_req, addr);
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_fn_direct")
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes a function on the gRPC server at `addr` (`host:port` or a URI),
+    /// bypassing Zenoh. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `addr`: Address of a server started with `serve_grpc_server`.
+    /// * `req`: A Python `InvocationRequest` instance.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    pub async fn invoke_fn_direct_async(
+        &self,
+        addr: String,
+        req: Py<InvocationRequest>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_fn(py, req));
+        let proto_req = Self::with_target(proto_req, addr);
+        self.invoke(proto_req, overrides, "rpc.invoke_fn_direct_async")
+            .await
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes an object method on the gRPC server at `addr` (`host:port` or a
+    /// URI), bypassing Zenoh. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `addr`: Address of a server started with `serve_grpc_server`.
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    pub fn invoke_obj_direct(
+        &self,
+        py: Python<'_>,
+        addr: String,
+        req: Py<ObjectInvocationRequest>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Self::extract_obj(py, req);
+        let proto_req = Self::with_target(proto_req, addr);
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_obj_direct")
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes an object method on the gRPC server at `addr` (`host:port` or a
+    /// URI), bypassing Zenoh. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `addr`: Address of a server started with `serve_grpc_server`.
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    pub async fn invoke_obj_direct_async(
+        &self,
+        addr: String,
+        req: Py<ObjectInvocationRequest>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_obj(py, req));
+        let proto_req = Self::with_target(proto_req, addr);
+        self.invoke(proto_req, overrides, "rpc.invoke_obj_direct_async")
+            .await
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes all `reqs` concurrently from the Rust layer, saving the
+    /// per-call overhead of awaiting each from Python. (Asynchronous)
+    ///
+    /// A failed invocation does not affect the others: the result list holds,
+    /// in the order of `reqs`, an `InvocationResponse` or the exception that
+    /// invocation would have raised.
+    ///
+    /// # Arguments
+    ///
+    /// * `reqs`: The `InvocationRequest`s to send.
+    pub async fn invoke_fn_batch_async(
+        &self,
+        reqs: Vec<Py<InvocationRequest>>,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let reqs = Python::attach(|py| {
+            reqs.into_iter()
+                .map(|req| Self::extract_fn(py, req))
+                .collect()
+        });
+        self.invoke_batch(reqs, "rpc.invoke_fn_batch_async").await
+    }
+
+    /// Invokes all `reqs` concurrently from the Rust layer; see
+    /// `invoke_fn_batch_async`. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `reqs`: The `ObjectInvocationRequest`s to send.
+    pub async fn invoke_obj_batch_async(
+        &self,
+        reqs: Vec<Py<ObjectInvocationRequest>>,
+    ) -> PyResult<Vec<Py<PyAny>>> {
+        let reqs = Python::attach(|py| {
+            reqs.into_iter()
+                .map(|req| Self::extract_obj(py, req))
+                .collect()
+        });
+        self.invoke_batch(reqs, "rpc.invoke_obj_batch_async").await
+    }
+
+    /// Invokes the stateless function `fn_id` of `cls_id` with the same
+    /// payload on every partition in `partition_ids` concurrently, for
+    /// map-reduce style workloads. (Synchronous)
+    ///
+    /// A failed invocation does not affect the others: the result dict maps
+    /// each partition to its `InvocationResponse` or the exception its
+    /// invocation raised.
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `cls_id`: The class of the function.
+    /// * `fn_id`: The function to invoke.
+    /// * `partition_ids`: The partitions to invoke it on, each listed once.
+    /// * `payload`: The payload sent to every partition.
+    /// * `options`: The options sent to every partition.
+    /// * `timeout_ms`: Per-invocation timeout; overrides the manager and module defaults.
+    #[pyo3(signature = (cls_id, fn_id, partition_ids, payload=None, options=None, timeout_ms=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn invoke_scatter(
+        &self,
+        py: Python<'_>,
+        cls_id: String,
+        fn_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partition_ids: Vec<u32>,
+        payload: Option<Vec<u8>>,
+        options: Option<HashMap<String, String>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Py<PyDict>> {
+        let reqs = Self::scatter(
+            cls_id,
+            fn_id,
+            &partition_ids,
+            payload.unwrap_or_default(),
+            options.unwrap_or_default(),
+            timeout_ms,
+        )?;
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let results =
+            py.detach(|| runtime.block_on(self.invoke_batch(reqs, "rpc.invoke_scatter")))?;
+        Self::gather(&partition_ids, results)
+    }
+
+    /// Invokes a function on every partition in `partition_ids`
+    /// concurrently; see `invoke_scatter`. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class of the function.
+    /// * `fn_id`: The function to invoke.
+    /// * `partition_ids`: The partitions to invoke it on, each listed once.
+    /// * `payload`: The payload sent to every partition.
+    /// * `options`: The options sent to every partition.
+    /// * `timeout_ms`: Per-invocation timeout; overrides the manager and module defaults.
+    #[pyo3(signature = (cls_id, fn_id, partition_ids, payload=None, options=None, timeout_ms=None))]
+    pub async fn invoke_scatter_async(
+        &self,
+        cls_id: String,
+        fn_id: String,
+        #[pyo3(from_py_with = ids::partition_ids)] partition_ids: Vec<u32>,
+        payload: Option<Vec<u8>>,
+        options: Option<HashMap<String, String>>,
+        timeout_ms: Option<u64>,
+    ) -> PyResult<Py<PyDict>> {
+        let reqs = Self::scatter(
+            cls_id,
+            fn_id,
+            &partition_ids,
+            payload.unwrap_or_default(),
+            options.unwrap_or_default(),
+            timeout_ms,
+        )?;
+        let results = self.invoke_batch(reqs, "rpc.invoke_scatter_async").await?;
+        Self::gather(&partition_ids, results)
+    }
+
+    /// Invokes the function `fn_id` of `cls_id` with the same payload on
+    /// every object in `object_ids` concurrently and returns an async
+    /// iterator over the outcomes as they complete.
+    ///
+    /// Each object gets its own invocation, so a failed invocation does not
+    /// affect the others: the iterator yields an `(object_id, result)` pair
+    /// per object, where `result` is its `InvocationResponse` or the
+    /// exception its invocation raised. Leaving the iteration early cancels
+    /// the invocations still running.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class of the objects.
+    /// * `fn_id`: The function to invoke.
+    /// * `object_ids`: The objects to invoke it on, each listed once.
+    /// * `payload`: The payload sent to every object.
+    /// * `partition_id`: The partition of the objects, unless resolved from the class defaults.
+    /// * `options`: The options sent to every object.
+    /// * `timeout_ms`: Per-invocation timeout; overrides the manager and module defaults.
+    /// * `concurrency`: Most invocations running at once; all of them if `None`.
+    #[pyo3(signature = (cls_id, fn_id, object_ids, payload=None, partition_id=0, options=None, timeout_ms=None, concurrency=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn invoke_obj_multi(
+        &self,
+        cls_id: String,
+        fn_id: String,
+        #[pyo3(from_py_with = ids::object_ids)] object_ids: Vec<u64>,
+        payload: Option<Vec<u8>>,
+        #[pyo3(from_py_with = ids::partition_id)] partition_id: u32,
+        options: Option<HashMap<String, String>>,
+        timeout_ms: Option<u64>,
+        concurrency: Option<usize>,
+    ) -> PyResult<ObjectResultStream> {
+        if concurrency == Some(0) {
+            return Err(PyValueError::new_err("concurrency must be at least 1"));
+        }
+        let mut seen = HashSet::with_capacity(object_ids.len());
+        if let Some(dup) = object_ids.iter().find(|id| !seen.insert(**id)) {
+            return Err(PyValueError::new_err(format!(
+                "object {} is listed more than once",
+                dup
+            )));
+        }
+        let payload = payload.unwrap_or_default();
+        let options = options.unwrap_or_default();
+        let reqs: Vec<_> = object_ids
+            .iter()
+            .map(|&object_id| {
+                let req = oprc_pb::ObjectInvocationRequest {
+                    partition_id,
+                    object_id,
+                    cls_id: cls_id.clone(),
+                    fn_id: fn_id.clone(),
+                    options: options.clone(),
+                    payload: payload.clone(),
+                };
+                let (req, timeout) = self.prepare(ProtoRequest::Obj(req), timeout_ms);
+                (object_id, req, timeout)
+            })
+            .collect();
+        Ok(fan_out::open(
+            self.invoker.clone(),
+            reqs,
+            concurrency.unwrap_or(object_ids.len()),
+            "rpc.invoke_obj_multi",
+        ))
+    }
+
+    /// Invokes a function once on a set of objects of the same class and
+    /// partition. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `MultiObjectInvocationRequest` instance.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    pub fn invoke_multi(
+        &self,
+        py: Python<'_>,
+        req: Py<MultiObjectInvocationRequest>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Self::extract_multi(py, req);
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_multi")
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes a function once on a set of objects of the same class and
+    /// partition. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `MultiObjectInvocationRequest` instance.
+    ///
+    /// # Returns
+    ///
+    /// A `PyResult` containing an `InvocationResponse`.
+    pub async fn invoke_multi_async(
+        &self,
+        req: Py<MultiObjectInvocationRequest>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_multi(py, req));
+        self.invoke(proto_req, overrides, "rpc.invoke_multi_async")
+            .await
+            .map(InvocationResponse::from)
+    }
+
+    /// Invokes a function and returns the response together with delivery
+    /// metadata (attempts, replica, latency, queue time). (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `InvocationRequest` instance.
+    pub fn invoke_fn_detailed(
+        &self,
+        py: Python<'_>,
+        req: Py<InvocationRequest>,
+    ) -> PyResult<DetailedInvocationResponse> {
+        let (proto_req, overrides) = Self::extract_fn(py, req);
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_fn_detailed")
+            .map(DetailedInvocationResponse::from)
+    }
+
+    /// Invokes a function and returns the response together with delivery
+    /// metadata (attempts, replica, latency, queue time). (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `InvocationRequest` instance.
+    pub async fn invoke_fn_detailed_async(
+        &self,
+        req: Py<InvocationRequest>,
+    ) -> PyResult<DetailedInvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_fn(py, req));
+        self.invoke(proto_req, overrides, "rpc.invoke_fn_detailed_async")
+            .await
+            .map(DetailedInvocationResponse::from)
+    }
+
+    /// Invokes an object method and returns the response together with delivery
+    /// metadata (attempts, replica, latency, queue time). (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    pub fn invoke_obj_detailed(
+        &self,
+        py: Python<'_>,
+        req: Py<ObjectInvocationRequest>,
+    ) -> PyResult<DetailedInvocationResponse> {
+        let (proto_req, overrides) = Self::extract_obj(py, req);
+        self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_obj_detailed")
+            .map(DetailedInvocationResponse::from)
+    }
+
+    /// Invokes an object method and returns the response together with delivery
+    /// metadata (attempts, replica, latency, queue time). (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: A Python `ObjectInvocationRequest` instance.
+    pub async fn invoke_obj_detailed_async(
+        &self,
+        req: Py<ObjectInvocationRequest>,
+    ) -> PyResult<DetailedInvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_obj(py, req));
+        self.invoke(proto_req, overrides, "rpc.invoke_obj_detailed_async")
+            .await
+            .map(DetailedInvocationResponse::from)
+    }
+
+    /// Invokes `req` and writes the response payload to the file at `path`
+    /// from the Rust layer, without materializing it in Python. Chunked
+    /// payloads are fetched and written one chunk at a time. (Synchronous)
+    ///
+    /// The file is only written for an `Okay` response, which is returned
+    /// without its payload; other responses are returned as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`.
+    /// * `path`: The file to write; replaced once the payload is complete.
+    pub fn invoke_to_file(
+        &self,
+        py: Python<'_>,
+        req: &Bound<'_, PyAny>,
+        path: PathBuf,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Self::extract_any(req)?;
+        let delivery = self.invoke_blocking(py, proto_req, overrides, "rpc.invoke_to_file")?;
+        let session = self.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(move || {
+            runtime.block_on(stream::save_payload(&session, delivery.response, path))
+        })
+        .map(InvocationResponse::from)
+        .map_err(PyRuntimeError::new_err)
+    }
+
+    /// Invokes `req` and writes the response payload to the file at `path`
+    /// from the Rust layer, without materializing it in Python. Chunked
+    /// payloads are fetched and written one chunk at a time. (Asynchronous)
+    ///
+    /// The file is only written for an `Okay` response, which is returned
+    /// without its payload; other responses are returned as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`.
+    /// * `path`: The file to write; replaced once the payload is complete.
+    pub async fn invoke_to_file_async(
+        &self,
+        req: Py<PyAny>,
+        path: PathBuf,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_any(req.bind(py)))?;
+        let delivery = self
+            .invoke(proto_req, overrides, "rpc.invoke_to_file_async")
+            .await?;
+        stream::save_payload(&self.session, delivery.response, path)
+            .await
+            .map(InvocationResponse::from)
+            .map_err(PyRuntimeError::new_err)
+    }
+
+    /// Invokes `req` with the contents of the file at `path` as its payload,
+    /// read and sent in chunks from the Rust layer without materializing it in
+    /// Python. A file larger than `chunk_size` is served in chunks the target
+    /// fetches as it admits the call. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`; its payload is replaced.
+    /// * `path`: The file to send.
+    /// * `chunk_size`: Size of the chunks in bytes.
+    #[pyo3(signature = (req, path, chunk_size=DEFAULT_CHUNK_SIZE))]
+    pub fn invoke_from_file(
+        &self,
+        py: Python<'_>,
+        req: &Bound<'_, PyAny>,
+        path: PathBuf,
+        chunk_size: usize,
+    ) -> PyResult<InvocationResponse> {
+        if chunk_size == 0 {
+            return Err(PyValueError::new_err("chunk_size must be > 0"));
+        }
+        let (proto_req, overrides) = Self::extract_any(req)?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        let invoker = self.invoker.clone();
+        let session = self.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(move || {
+            runtime.block_on(async move {
+                let source = ChunkSource::file(&path, chunk_size)
+                    .await
+                    .map_err(PyRuntimeError::new_err)?;
+                stream::invoke_streamed(
+                    &invoker,
+                    &session,
+                    proto_req,
+                    source,
+                    timeout,
+                    overrides.max_retries,
+                    "rpc.invoke_from_file",
+                )
+                .await
+            })
+        })
+        .map(InvocationResponse::from)
+    }
+
+    /// Invokes `req` with the contents of the file at `path` as its payload,
+    /// read and sent in chunks from the Rust layer without materializing it in
+    /// Python. A file larger than `chunk_size` is served in chunks the target
+    /// fetches as it admits the call. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`; its payload is replaced.
+    /// * `path`: The file to send.
+    /// * `chunk_size`: Size of the chunks in bytes.
+    #[pyo3(signature = (req, path, chunk_size=DEFAULT_CHUNK_SIZE))]
+    pub async fn invoke_from_file_async(
+        &self,
+        req: Py<PyAny>,
+        path: PathBuf,
+        chunk_size: usize,
+    ) -> PyResult<InvocationResponse> {
+        if chunk_size == 0 {
+            return Err(PyValueError::new_err("chunk_size must be > 0"));
+        }
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_any(req.bind(py)))?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        let source = ChunkSource::file(&path, chunk_size)
+            .await
+            .map_err(PyRuntimeError::new_err)?;
+        stream::invoke_streamed(
+            &self.invoker,
+            &self.session,
+            proto_req,
+            source,
+            timeout,
+            overrides.max_retries,
+            "rpc.invoke_from_file_async",
+        )
+        .await
+        .map(InvocationResponse::from)
+    }
+
+    /// Invokes `req` with a payload produced by `producer`, an async iterable
+    /// of `bytes` (e.g. an async generator) or a callable returning one. Each
+    /// item is sent as a chunk when the target fetches it, so the payload is
+    /// never materialized as a whole. The invocation is not retried, as the
+    /// producer cannot be replayed. (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`; its payload is replaced.
+    /// * `producer`: The source of the payload chunks.
+    pub async fn invoke_from_stream_async(
+        &self,
+        req: Py<PyAny>,
+        producer: Py<PyAny>,
+    ) -> PyResult<InvocationResponse> {
+        let (proto_req, overrides, source) = Python::attach(|py| -> PyResult<_> {
+            let (proto_req, overrides) = Self::extract_any(req.bind(py))?;
+            let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?;
+            let source = ChunkSource::python(producer.bind(py), locals)?;
+            Ok((proto_req, overrides, source))
+        })?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        stream::invoke_streamed(
+            &self.invoker,
+            &self.session,
+            proto_req,
+            source,
+            timeout,
+            overrides.max_retries,
+            "rpc.invoke_from_stream_async",
+        )
+        .await
+        .map(InvocationResponse::from)
+    }
+
+    /// Invokes `req` and returns an async iterator over its response as the
+    /// handler produces it: an `InvocationResponse` for every chunk the
+    /// handler sends with `send_chunk`, then the final response it returns.
+    /// The invocation is not retried; leaving the iteration early cancels it.
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`.
+    pub fn invoke_fn_stream(&self, req: &Bound<'_, PyAny>) -> PyResult<ResponseStream> {
+        let (proto_req, overrides) = Self::extract_any(req)?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        Ok(response_stream::open(
+            self.invoker.clone(),
+            self.session.clone(),
+            proto_req,
+            timeout,
+            "rpc.invoke_fn_stream",
+        ))
+    }
+
+    /// Sends `resp` as a chunk of a streamed response, from the handler of a
+    /// request whose `response_stream` is set. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `response_stream`: The `response_stream` of the request being handled.
+    /// * `resp`: The chunk.
+    pub fn send_chunk(
+        &self,
+        py: Python<'_>,
+        response_stream: &str,
+        resp: PyRef<'_, InvocationResponse>,
+    ) -> PyResult<()> {
+        let resp = oprc_pb::InvocationResponse::from(&*resp);
+        let session = self.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(move || {
+            runtime.block_on(response_stream::send_chunk(&session, response_stream, resp))
+        })
+    }
+
+    /// Sends `resp` as a chunk of a streamed response; see `send_chunk`.
+    /// (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `response_stream`: The `response_stream` of the request being handled.
+    /// * `resp`: The chunk.
+    pub async fn send_chunk_async(
+        &self,
+        response_stream: String,
+        resp: Py<InvocationResponse>,
+    ) -> PyResult<()> {
+        let resp = Python::attach(|py| oprc_pb::InvocationResponse::from(&*resp.borrow(py)));
+        response_stream::send_chunk(&self.session, &response_stream, resp).await
+    }
+
+    /// Reports a progress event to the caller, from the handler of a request
+    /// whose `progress` is set. Does nothing if it is `None`, so handlers can
+    /// report unconditionally. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `progress`: The `progress` of the request being handled.
+    /// * `event`: A JSON-serializable value passed to the caller's `on_progress`.
+    pub fn report_progress(
+        &self,
+        py: Python<'_>,
+        progress: Option<&str>,
+        event: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let Some(key_expr) = progress else {
+            return Ok(());
+        };
+        let event = codec::encode_json(event)?;
+        let session = self.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(move || runtime.block_on(progress::report(&session, key_expr, event)))
+    }
+
+    /// Reports a progress event to the caller; see `report_progress`.
+    /// (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `progress`: The `progress` of the request being handled.
+    /// * `event`: A JSON-serializable value passed to the caller's `on_progress`.
+    pub async fn report_progress_async(
+        &self,
+        progress: Option<String>,
+        event: Py<PyAny>,
+    ) -> PyResult<()> {
+        let Some(key_expr) = progress else {
+            return Ok(());
+        };
+        let event = Python::attach(|py| codec::encode_json(event.bind(py)))?;
+        progress::report(&self.session, &key_expr, event).await
+    }
+
+    /// Lists the functions served by every engine answering within
+    /// `timeout_ms`, as dicts with `key_expr`, `cls_id`, `fn_id`,
+    /// `description`, `schema` and the `zid` of the engine. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `timeout_ms`: How long to wait for engines to answer.
+    #[pyo3(signature = (timeout_ms=1000))]
+    pub fn list_functions<'py>(
+        &self,
+        py: Python<'py>,
+        timeout_ms: u64,
+    ) -> PyResult<Vec<Bound<'py, PyAny>>> {
+        let session = self.session.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        let entries = py
+            .detach(move || {
+                runtime.block_on(catalog::discover(&session, Duration::from_millis(timeout_ms)))
+            })
+            .map_err(|e| PyRuntimeError::new_err(format!("failed to list functions: {}", e)))?;
+        entries
+            .iter()
+            .map(|entry| codec::json_to_py(py, entry))
+            .collect()
+    }
+
+    /// Lists the functions served by every engine; see `list_functions`.
+    /// (Asynchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_ms`: How long to wait for engines to answer.
+    #[pyo3(signature = (timeout_ms=1000))]
+    pub async fn list_functions_async(&self, timeout_ms: u64) -> PyResult<Vec<Py<PyAny>>> {
+        let entries = catalog::discover(&self.session, Duration::from_millis(timeout_ms))
+            .await
+            .map_err(|e| PyRuntimeError::new_err(format!("failed to list functions: {}", e)))?;
+        Python::attach(|py| {
+            entries
+                .iter()
+                .map(|entry| codec::json_to_py(py, entry).map(Bound::unbind))
+                .collect()
+        })
+    }
+
+    /// Returns the timeout in milliseconds that would apply to `req`, or `None`
+    /// if the invocation would run without a timeout.
+    ///
+    /// Precedence is: module default < manager `timeout_ms` < class default
+    /// (see `defaults`) < request `timeout_ms`.
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: An `InvocationRequest` or `ObjectInvocationRequest`.
+    pub fn effective_timeout(&self, req: &Bound<'_, PyAny>) -> PyResult<Option<u64>> {
+        let (cls_id, req_timeout) = if let Ok(r) = req.downcast::<InvocationRequest>() {
+            let r = r.borrow();
+            (r.cls_id.clone(), r.timeout_ms())
+        } else if let Ok(r) = req.downcast::<ObjectInvocationRequest>() {
+            let r = r.borrow();
+            (r.cls_id().to_string(), r.timeout_ms())
+        } else {
+            return Err(PyTypeError::new_err(
+                "expected InvocationRequest or ObjectInvocationRequest",
+            ));
+        };
+        let class_timeout = self.invoker.defaults.timeout_ms(&cls_id);
+        Ok(timeout::resolve(class_timeout.or(self.timeout_ms), req_timeout)
+            .map(|d| d.as_millis() as u64))
+    }
+
+    /// Returns where and how `req` would be sent, without sending it: the
+    /// transport and endpoint, the key expression, the replicas, timeout,
+    /// retries, compression and the other settings that would apply.
+    ///
+    /// Class defaults, partitioning, routes and the routing options of the
+    /// request are resolved as for an invocation. Interceptors are not run,
+    /// so changes they would make to the request are not reflected. Raises
+    /// `ValueError` for an option the invocation would be rejected for.
+    ///
+    /// # Arguments
+    ///
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or
+    ///   `MultiObjectInvocationRequest`.
+    pub fn explain(&self, req: &Bound<'_, PyAny>) -> PyResult<InvocationPlan> {
+        let (proto_req, overrides) = Self::extract_any(req)?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        explain::plan(&self.invoker, proto_req, timeout, overrides.max_retries)
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Returns the request defaults of `cls_id`, e.g.
+    /// `rpc.defaults("my.cls").timeout_ms = 2000`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class the defaults apply to.
+    pub fn defaults(&self, cls_id: String) -> ClassDefaults {
+        ClassDefaults::new(cls_id, self.invoker.defaults.clone())
+    }
+
+    /// Where request options (and response headers) travel over Zenoh:
+    /// `"embedded"` in the protobuf message (the default) or `"attachment"`,
+    /// as a JSON object in the Zenoh attachment. Classes can override it with
+    /// `defaults(cls_id).options_encoding`.
+    #[getter]
+    pub fn get_options_encoding(&self) -> &'static str {
+        self.invoker.encoding.name()
+    }
+
+    #[setter]
+    pub fn set_options_encoding(&mut self, encoding: &str) -> PyResult<()> {
+        self.invoker.encoding = encoding.parse().map_err(PyValueError::new_err)?;
+        Ok(())
+    }
+
+    /// Configures the retry budget shared by all invocations of this manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retry_ratio`: Maximum ratio of retries to first attempts within the window.
+    /// * `window_ms`: Length of the sliding window in milliseconds.
+    /// * `min_retries`: Retries always allowed per window, so low traffic can still retry.
+    #[pyo3(signature = (max_retry_ratio=0.2, window_ms=10_000, min_retries=10))]
+    pub fn configure_retry_budget(
+        &self,
+        max_retry_ratio: f64,
+        window_ms: u64,
+        min_retries: u64,
+    ) -> PyResult<()> {
+        if max_retry_ratio.is_nan() || max_retry_ratio < 0.0 {
+            return Err(PyValueError::new_err("max_retry_ratio must be >= 0"));
+        }
+        if window_ms == 0 {
+            return Err(PyValueError::new_err("window_ms must be > 0"));
+        }
+        self.invoker
+            .budget
+            .configure(max_retry_ratio, Duration::from_millis(window_ms), min_retries);
+        Ok(())
+    }
+
+    /// Configures which failed attempts are retried. Retries also draw from the
+    /// retry budget (see `configure_retry_budget`) and stay within the
+    /// invocation timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries`: Retries after the first attempt; a request's `max_retries` overrides it.
+    /// * `backoff_ms`: Delay before the first retry, doubled for each further retry.
+    /// * `max_backoff_ms`: Upper bound of the delay between retries.
+    /// * `jitter`: Fraction of each delay, between `0` and `1`, randomly cut off so
+    ///   callers retrying at the same time spread out.
+    /// * `retryable_statuses`: Response status codes retried without a classifier,
+    ///   e.g. `[int(InvocationResponseCode.SystemError)]` (the default).
+    /// * `classify_error`: Optional `classify_error(failure) -> RetryDecision | str`,
+    ///   called with a `RuntimeError` if an attempt did not complete, or with the
+    ///   `InvocationResponse` if it returned a status other than `Okay`. It returns
+    ///   `Retryable`, `Fatal` (return the response or raise the error without
+    ///   retrying) or `Escalate` (raise, even for a response). Without it,
+    ///   incomplete attempts and responses with a retryable status are retried
+    ///   and any other status is fatal.
+    #[pyo3(signature = (max_retries=0, backoff_ms=50, max_backoff_ms=2_000, classify_error=None, jitter=0.0, retryable_statuses=None))]
+    pub fn configure_retry(
+        &mut self,
+        max_retries: u32,
+        backoff_ms: u64,
+        max_backoff_ms: u64,
+        classify_error: Option<Py<PyAny>>,
+        jitter: f64,
+        retryable_statuses: Option<Vec<i32>>,
+    ) -> PyResult<()> {
+        if max_backoff_ms < backoff_ms {
+            return Err(PyValueError::new_err("max_backoff_ms must be >= backoff_ms"));
+        }
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(PyValueError::new_err("jitter must be between 0 and 1"));
+        }
+        let retryable_statuses =
+            retryable_statuses.unwrap_or_else(|| RetryPolicy::default().retryable_statuses);
+        self.invoker.retry = RetryPolicy {
+            max_retries,
+            backoff: Duration::from_millis(backoff_ms),
+            max_backoff: Duration::from_millis(max_backoff_ms),
+            jitter,
+            retryable_statuses,
+            classifier: classify_error.map(Arc::new),
+        };
+        Ok(())
+    }
+
+    /// Configures the circuit breakers of the targets invoked through this
+    /// manager, one per `(cls_id, fn_id)`. A circuit opens after
+    /// `failure_threshold` invocations in a row raised or returned
+    /// `SystemError`. While it is open, invocations of the target raise
+    /// `CircuitOpenError` without being sent. After `cool_down_ms`, one
+    /// invocation is let through as a probe: success closes the circuit,
+    /// failure opens it again. Reconfiguring closes all circuits.
+    ///
+    /// # Arguments
+    ///
+    /// * `failure_threshold`: Failures in a row that open a circuit; `0` disables the breakers.
+    /// * `cool_down_ms`: How long a circuit stays open before a probe is let through.
+    #[pyo3(signature = (failure_threshold=5, cool_down_ms=30_000))]
+    pub fn configure_circuit_breaker(
+        &self,
+        failure_threshold: u32,
+        cool_down_ms: u64,
+    ) -> PyResult<()> {
+        if cool_down_ms == 0 {
+            return Err(PyValueError::new_err("cool_down_ms must be > 0"));
+        }
+        self.invoker
+            .breaker
+            .configure(failure_threshold, Duration::from_millis(cool_down_ms));
+        Ok(())
+    }
+
+    /// Copies a share of the invocations of `cls_id` to another class or
+    /// function, e.g. a new version under test. Copies are sent once in the
+    /// background, with the `oprc-mirror` option set to `true`; their
+    /// responses are dropped and failures only logged. Replaces the mirror of
+    /// the same class and function.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class whose invocations are mirrored.
+    /// * `fraction`: Share of the invocations copied, between `0` and `1`.
+    /// * `fn_id`: Only mirror this function; it takes precedence over a mirror of the whole class.
+    /// * `target_cls_id`: Class the copies go to; `None` keeps the invoked class.
+    /// * `target_fn_id`: Function the copies go to; `None` keeps the invoked function.
+    #[pyo3(signature = (cls_id, fraction, fn_id=None, target_cls_id=None, target_fn_id=None))]
+    pub fn set_mirror(
+        &self,
+        cls_id: String,
+        fraction: f64,
+        fn_id: Option<String>,
+        target_cls_id: Option<String>,
+        target_fn_id: Option<String>,
+    ) -> PyResult<()> {
+        self.invoker
+            .mirrors
+            .set(cls_id, fn_id, fraction, target_cls_id, target_fn_id)
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Stops mirroring the invocations of `cls_id` (of `fn_id`, if given).
+    /// Returns whether a mirror was set.
+    #[pyo3(signature = (cls_id, fn_id=None))]
+    pub fn clear_mirror(&self, cls_id: String, fn_id: Option<String>) -> bool {
+        self.invoker.mirrors.clear(cls_id, fn_id)
+    }
+
+    /// Splits the invocations of `cls_id`/`fn_id` between variants of the
+    /// function, e.g. to roll out a new version to a share of the traffic.
+    /// An invocation matching one of `rules` goes to its variant; any other
+    /// goes to a variant picked at random by `weights`, or stays unchanged
+    /// without weights. Replaces the route of the same function and resets
+    /// its counters.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class of the routed function.
+    /// * `fn_id`: The function invoked by callers.
+    /// * `weights`: Relative weight of each variant `fn_id`, e.g. `{"v1": 9, "v2": 1}`.
+    /// * `rules`: `(option, value, variant)` tuples, checked in order; headers match as `oprc-header-<name>`.
+    #[pyo3(signature = (cls_id, fn_id, weights=None, rules=None))]
+    pub fn set_route(
+        &self,
+        cls_id: String,
+        fn_id: String,
+        weights: Option<HashMap<String, f64>>,
+        rules: Option<Vec<(String, String, String)>>,
+    ) -> PyResult<()> {
+        self.invoker
+            .routes
+            .set(
+                cls_id,
+                fn_id,
+                weights.unwrap_or_default(),
+                rules.unwrap_or_default(),
+            )
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Stops routing the invocations of `cls_id`/`fn_id`. Returns whether a
+    /// route was set.
+    pub fn clear_route(&self, cls_id: String, fn_id: String) -> bool {
+        self.invoker.routes.clear(cls_id, fn_id)
+    }
+
+    /// Returns the invocation counters of each variant of the route of
+    /// `cls_id`/`fn_id`, empty if it has none. Only completed invocations,
+    /// failures and latencies are counted per variant.
+    pub fn route_stats(&self, cls_id: &str, fn_id: &str) -> HashMap<String, InvocationStats> {
+        self.invoker.routes.stats(cls_id, fn_id)
+    }
+
+    /// Returns the state of the circuit of `cls_id`/`fn_id`: `"closed"`,
+    /// `"open"`, or `"half_open"` once the cool-down is over and a probe is
+    /// due or in flight.
+    pub fn circuit_state(&self, cls_id: &str, fn_id: &str) -> &'static str {
+        self.invoker.breaker.state(cls_id, fn_id)
+    }
+
+    /// Adds an interceptor that every invocation of this manager passes,
+    /// after those added before it.
+    ///
+    /// `interceptor` is either a spec of a built-in interceptor, `logging` or
+    /// `auth?token=T` (sets the `oprc-auth-token` option on requests without
+    /// one), or an object with `on_request(req)` and/or
+    /// `on_response(req, resp)` methods. These run on a runtime thread, once
+    /// per invocation whatever its retries, and may modify their arguments
+    /// in place. `on_request` sees the request before it is compressed,
+    /// encrypted or checksummed and rejects the invocation by raising;
+    /// `on_response` sees the final response, and its exceptions propagate
+    /// to the caller. Responses pass the interceptors in reverse order.
+    ///
+    /// # Arguments
+    ///
+    /// * `interceptor`: A spec string or an interceptor object.
+    pub fn add_interceptor(&mut self, interceptor: &Bound<'_, PyAny>) -> PyResult<()> {
+        let interceptor = ClientInterceptor::extract(interceptor)?;
+        Arc::make_mut(&mut self.invoker.interceptors).push(interceptor);
+        Ok(())
+    }
+
+    /// Removes all interceptors.
+    pub fn clear_interceptors(&mut self) {
+        self.invoker.interceptors = Arc::default();
+    }
+
+    /// Names of the interceptors in order: the built-in name, or `python`.
+    #[getter]
+    pub fn interceptors(&self) -> Vec<&'static str> {
+        self.invoker
+            .interceptors
+            .iter()
+            .map(ClientInterceptor::name)
+            .collect()
+    }
+
+    /// Checksum algorithm attached to outgoing payloads, or `None` if disabled.
+    ///
+    /// Servers verify the checksum before dispatching and reply with a checksum
+    /// of the response payload, which is verified here.
+    #[getter]
+    pub fn get_payload_checksum(&self) -> Option<ChecksumAlgorithm> {
+        self.invoker.checksum
+    }
+
+    #[setter]
+    pub fn set_payload_checksum(&mut self, algorithm: Option<ChecksumAlgorithm>) {
+        self.invoker.checksum = algorithm;
+    }
+
+    /// Encoding request payloads are compressed with, `"gzip"`, `"zstd"` or
+    /// `None` (the default) to send them as is.
+    ///
+    /// A payload is only compressed once the servers of its class advertised
+    /// the encoding, if it has at least `compression_min_bytes` and if it
+    /// gets smaller. Servers inflate it before calling the handler. Responses
+    /// are decompressed regardless of this setting.
+    #[getter]
+    pub fn get_compression(&self) -> Option<&'static str> {
+        self.invoker.compression.map(Encoding::name)
+    }
+
+    #[setter]
+    pub fn set_compression(&mut self, encoding: Option<&str>) -> PyResult<()> {
+        self.invoker.compression = encoding
+            .map(|name| {
+                Encoding::parse(name).ok_or_else(|| {
+                    PyValueError::new_err(format!(
+                        "unknown compression '{}', expected 'gzip' or 'zstd'",
+                        name
+                    ))
+                })
+            })
+            .transpose()?;
+        Ok(())
+    }
+
+    /// Request payloads smaller than this are sent uncompressed. Defaults to
+    /// 1024 bytes.
+    #[getter]
+    pub fn get_compression_min_bytes(&self) -> usize {
+        self.invoker.compression_min_bytes
+    }
+
+    #[setter]
+    pub fn set_compression_min_bytes(&mut self, min_bytes: usize) {
+        self.invoker.compression_min_bytes = min_bytes;
+    }
+
+    /// Request payloads larger than this are served in chunks the target
+    /// fetches as it admits the call, instead of inline. Defaults to 16 MiB.
+    /// `None` sends every payload inline.
+    ///
+    /// Only applies once the servers of the class advertised chunked
+    /// payloads and while the `chunked_payloads` feature flag is on. The size
+    /// is taken after compression and encryption.
+    #[getter]
+    pub fn get_chunk_threshold_bytes(&self) -> Option<usize> {
+        self.invoker.chunk_threshold
+    }
+
+    #[setter]
+    pub fn set_chunk_threshold_bytes(&mut self, threshold: Option<usize>) -> PyResult<()> {
+        if threshold == Some(0) {
+            return Err(PyValueError::new_err("chunk_threshold_bytes must be > 0"));
+        }
+        self.invoker.chunk_threshold = threshold;
+        Ok(())
+    }
+
+    /// Encrypts the payloads of invocations of `cls_id` end to end for the
+    /// server holding the private key of `public_key`, as published by
+    /// `OaasEngine.encryption_public_key`. Responses are decrypted before
+    /// they are returned. `None` sends them in the clear again.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class whose invocations are encrypted.
+    /// * `public_key`: The 32-byte X25519 public key of its servers.
+    #[pyo3(signature = (cls_id, public_key))]
+    pub fn set_encryption_key(&self, cls_id: String, public_key: Option<&[u8]>) -> PyResult<()> {
+        let key = public_key
+            .map(e2e::public_key)
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        self.invoker.keys.set(cls_id, key);
+        Ok(())
+    }
+
+    /// Whether invocations run first on a handler this process serves for
+    /// the target (started with `OaasEngine.serve_function`), skipping Zenoh.
+    /// The invocation is sent remotely when no local handler matches or its
+    /// event loop is closed. The `oprc-prefer-local` request option (`true` or
+    /// `false`) overrides it per invocation.
+    #[getter]
+    pub fn get_prefer_local(&self) -> bool {
+        self.invoker.prefer_local
+    }
+
+    #[setter]
+    pub fn set_prefer_local(&mut self, prefer_local: bool) {
+        self.invoker.prefer_local = prefer_local;
+    }
+
+    /// How stateless function invocations pick among the replicas serving
+    /// them: `LoadBalancing.Zenoh` (the default) leaves it to Zenoh. The
+    /// other strategies pin each attempt to a replica found through the
+    /// liveliness tokens engines declare for the functions they serve.
+    /// Object invocations, pinned and direct invocations are not balanced.
+    #[getter]
+    pub fn get_load_balancing(&self) -> LoadBalancing {
+        self.invoker.balancing
+    }
+
+    #[setter]
+    pub fn set_load_balancing(&mut self, balancing: LoadBalancing) {
+        self.invoker.set_balancing(balancing);
+    }
+
+    /// Maximum number of invocations of this manager in flight at once, or
+    /// `None` (the default) for no limit. Further invocations wait in FIFO
+    /// order for one to complete; the wait counts against their timeout and
+    /// is reported as `queue_time_ms`. Retries keep the slot of their
+    /// invocation.
+    #[getter]
+    pub fn get_max_inflight(&self) -> Option<usize> {
+        self.invoker.inflight.max_inflight()
+    }
+
+    #[setter]
+    pub fn set_max_inflight(&self, max_inflight: Option<usize>) -> PyResult<()> {
+        if max_inflight == Some(0) {
+            return Err(PyValueError::new_err("max_inflight must be > 0"));
+        }
+        self.invoker.inflight.set_max_inflight(max_inflight);
+        Ok(())
+    }
+
+    /// Caps the invocations of priority lane `lane` (`high`, `normal` or
+    /// `low`) in flight at once; `None` (the default) lifts the cap.
+    /// Invocations pick their lane with the `oprc-lane` option and default
+    /// to `normal`. Over the cap they wait in FIFO order within the lane,
+    /// then for a slot of `max_inflight`, so a cap on `low` keeps batch jobs
+    /// from taking every slot.
+    ///
+    /// # Arguments
+    ///
+    /// * `lane`: `high`, `normal` or `low`.
+    /// * `max_inflight`: The cap of the lane, or `None` for no cap.
+    #[pyo3(signature = (lane, max_inflight))]
+    pub fn set_lane_limit(&self, lane: &str, max_inflight: Option<usize>) -> PyResult<()> {
+        let lane: Lane = lane.parse().map_err(PyValueError::new_err)?;
+        if max_inflight == Some(0) {
+            return Err(PyValueError::new_err("max_inflight must be > 0"));
+        }
+        self.invoker.lanes.get(lane).set_max_inflight(max_inflight);
+        Ok(())
+    }
+
+    /// Returns the cap of priority lane `lane` set by `set_lane_limit`, or
+    /// `None` if it has none.
+    pub fn lane_limit(&self, lane: &str) -> PyResult<Option<usize>> {
+        let lane: Lane = lane.parse().map_err(PyValueError::new_err)?;
+        Ok(self.invoker.lanes.get(lane).max_inflight())
+    }
+
+    /// Largest request payload in bytes this manager sends, or `None` (the
+    /// default) for no limit. Larger invocations raise `PayloadTooLarge`
+    /// without being sent. The limit applies to the payload as sent, after
+    /// compression.
+    #[getter]
+    pub fn get_max_payload_bytes(&self) -> Option<usize> {
+        self.invoker.max_payload.get()
+    }
+
+    #[setter]
+    pub fn set_max_payload_bytes(&self, max_payload_bytes: Option<usize>) -> PyResult<()> {
+        if max_payload_bytes == Some(0) {
+            return Err(PyValueError::new_err("max_payload_bytes must be > 0"));
+        }
+        self.invoker.max_payload.set(max_payload_bytes);
+        Ok(())
+    }
+
+    /// Returns a snapshot of the retry budget consumption.
+    pub fn retry_budget_stats(&self) -> RetryBudgetStats {
+        self.invoker.budget.stats()
+    }
+
+    /// Returns the invocation counters collected since the manager was
+    /// created: completed invocations, failures, retries, latency and payload
+    /// bytes. Reading them is cheap, so monitoring code can scrape them often.
+    pub fn stats(&self) -> InvocationStats {
+        self.invoker.stats.stats()
+    }
+
+    /// Returns the `oprc.client.latency` of every function invoked through
+    /// this manager, in milliseconds, labelled with `cls`, `fn` and
+    /// `quantile` (`0.5`, `0.9`, `0.99`, and `1` for the slowest). Names and
+    /// labels are stable across SDK releases.
+    pub fn export_metrics(&self) -> Vec<MetricPoint> {
+        self.invoker.functions.export()
+    }
+
+    /// Number of responses the response cache keeps, `1024` by default.
+    /// Only invocations with the `oprc-cache-ttl-ms` option use the cache;
+    /// `0` disables it. Shrinking evicts the responses closest to expiry.
+    #[getter]
+    pub fn get_cache_capacity(&self) -> usize {
+        self.invoker.cache.capacity()
+    }
+
+    #[setter]
+    pub fn set_cache_capacity(&self, capacity: usize) {
+        self.invoker.cache.set_capacity(capacity);
+    }
+
+    /// Drops cached responses, e.g. after invoking a function that changes
+    /// what cached reads return.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: Only drop responses of this class; all classes if `None`.
+    /// * `fn_id`: Only drop responses of this function; all functions if `None`.
+    ///
+    /// # Returns
+    ///
+    /// The number of responses dropped.
+    #[pyo3(signature = (cls_id=None, fn_id=None))]
+    pub fn invalidate_cache(&self, cls_id: Option<&str>, fn_id: Option<&str>) -> usize {
+        self.invoker.cache.invalidate(cls_id, fn_id)
+    }
+
+    /// Number of recent invocations kept for `recent_calls`; `0` (the
+    /// default) keeps none. Shrinking drops the oldest ones.
+    #[getter]
+    pub fn get_call_log_size(&self) -> usize {
+        self.invoker.calls.capacity()
+    }
+
+    #[setter]
+    pub fn set_call_log_size(&self, size: usize) {
+        self.invoker.calls.set_capacity(size);
+    }
+
+    /// Returns the last invocations sent through this manager, newest first:
+    /// their target, status or error, and duration. Invocations rejected
+    /// before being sent, e.g. by a closed manager, are not recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit`: At most this many invocations; all kept if `None`.
+    #[pyo3(signature = (limit=None))]
+    pub fn recent_calls(&self, limit: Option<usize>) -> Vec<CallRecord> {
+        self.invoker.calls.recent(limit)
+    }
+
+    /// Returns the protocol version and features (e.g. `gzip`, `chunked`)
+    /// last advertised by the servers of `cls_id`, or `None` before any
+    /// response from them. Version `0` means an SDK without the handshake.
+    ///
+    /// Features the servers do not advertise are not used towards them; e.g.
+    /// `invoke_from_file` then sends the payload inline.
+    ///
+    /// # Arguments
+    ///
+    /// * `cls_id`: The class whose servers to look up.
+    pub fn peer_capabilities(&self, cls_id: &str) -> Option<(u32, Vec<String>)> {
+        self.invoker
+            .peers
+            .get(cls_id)
+            .map(|caps| (caps.version, caps.features))
+    }
+
+    /// Runs an open-loop load test: sends `req` at `rps` invocations per
+    /// second for `warmup_s` and then `duration_s` seconds, on a schedule that
+    /// does not wait for responses, and reports the latency percentiles of the
+    /// sends after the warmup. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `req`: An `InvocationRequest`, `ObjectInvocationRequest` or `MultiObjectInvocationRequest`.
+    /// * `rps`: Target invocations per second.
+    /// * `duration_s`: Length of the measured period in seconds.
+    /// * `warmup_s`: Unmeasured period before it, in seconds.
+    /// * `arrival`: `poisson` for exponentially spaced sends, or `uniform`.
+    /// * `max_in_flight`: Outstanding invocations above which sends are dropped.
+    /// * `export`: Records the run and its results as a `loadgen.run` span,
+    ///   exported over OTLP when telemetry is initialized.
+    #[pyo3(signature = (req, rps, duration_s, warmup_s=0.0, arrival="poisson", max_in_flight=10_000, export=false))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn load_test(
+        &self,
+        py: Python<'_>,
+        req: &Bound<'_, PyAny>,
+        rps: f64,
+        duration_s: f64,
+        warmup_s: f64,
+        arrival: &str,
+        max_in_flight: usize,
+        export: bool,
+    ) -> PyResult<LoadReport> {
+        let (proto_req, overrides) = Self::extract_any(req)?;
+        let spec = load_spec(rps, duration_s, warmup_s, arrival, max_in_flight, export)?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        let invoker = self.invoker.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        py.detach(move || {
+            runtime.block_on(loadgen::run(
+                &invoker,
+                proto_req,
+                timeout,
+                overrides.max_retries,
+                spec,
+            ))
+        })
+    }
+
+    /// Runs an open-loop load test; see `load_test`. (Asynchronous)
+    #[pyo3(signature = (req, rps, duration_s, warmup_s=0.0, arrival="poisson", max_in_flight=10_000, export=false))]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn load_test_async(
+        &self,
+        req: Py<PyAny>,
+        rps: f64,
+        duration_s: f64,
+        warmup_s: f64,
+        arrival: String,
+        max_in_flight: usize,
+        export: bool,
+    ) -> PyResult<LoadReport> {
+        let (proto_req, overrides) = Python::attach(|py| Self::extract_any(req.bind(py)))?;
+        let spec = load_spec(rps, duration_s, warmup_s, &arrival, max_in_flight, export)?;
+        let (proto_req, timeout) = self.prepare(proto_req, overrides.timeout_ms);
+        loadgen::run(&self.invoker, proto_req, timeout, overrides.max_retries, spec).await
+    }
+
+    /// Whether `close` was called. Invocations on a closed manager raise
+    /// `RuntimeError`.
+    #[getter]
+    pub fn closed(&self) -> bool {
+        self.invoker.lifecycle.is_closed()
+    }
+
+    /// Closes the manager: stops accepting invocations and waits for those in
+    /// flight to complete. The Zenoh session is the engine's and stays open,
+    /// so its `DataManager` and served functions keep working. Calling it
+    /// again is harmless. (Synchronous)
+    ///
+    /// # Arguments
+    ///
+    /// * `py`: The Python GIL token.
+    /// * `timeout_ms`: How long to wait for the invocations in flight, or
+    ///   `None` to wait for all of them. Those still running then complete
+    ///   in the background.
+    ///
+    /// # Returns
+    ///
+    /// The number of invocations still in flight when it stopped waiting.
+    #[pyo3(signature = (timeout_ms=None))]
+    pub fn close(&self, py: Python<'_>, timeout_ms: Option<u64>) -> PyResult<usize> {
+        let invoker = self.invoker.clone();
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        Ok(py.detach(move || runtime.block_on(invoker.close(timeout_ms.map(Duration::from_millis)))))
+    }
+
+    /// Closes the manager; see `close`. (Asynchronous)
+    #[pyo3(signature = (timeout_ms=None))]
+    pub async fn close_async(&self, timeout_ms: Option<u64>) -> PyResult<usize> {
+        let invoker = self.invoker.clone();
+        pyo3_async_runtimes::tokio::get_runtime()
+            .spawn(async move { invoker.close(timeout_ms.map(Duration::from_millis)).await })
+            .await
+            .map_err(|e| PyRuntimeError::new_err(format!("close task failed: {}", e)))
+    }
+
+    /// Returns the manager itself, for `async with`.
+    pub async fn __aenter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    /// Closes the manager at the end of an `async with` block, waiting for
+    /// all invocations in flight. Exceptions raised in the block propagate.
+    pub async fn __aexit__(
+        &self,
+        _exc_type: Py<PyAny>,
+        _exc_value: Py<PyAny>,
+        _traceback: Py<PyAny>,
+    ) -> PyResult<()> {
+        self.close_async(None).await.map(|_| ())
+    }
+}
+
+/// Validates the parameters of `load_test`.
+fn load_spec(
+    rps: f64,
+    duration_s: f64,
+    warmup_s: f64,
+    arrival: &str,
+    max_in_flight: usize,
+    export: bool,
+) -> PyResult<LoadSpec> {
+    if !(rps.is_finite() && rps > 0.0) {
+        return Err(PyValueError::new_err("rps must be positive"));
+    }
+    let seconds = |name: &str, s: f64| {
+        Duration::try_from_secs_f64(s)
+            .map_err(|_| PyValueError::new_err(format!("{} must be a non-negative number", name)))
+    };
+    Ok(LoadSpec {
+        rps,
+        duration: seconds("duration_s", duration_s)?,
+        warmup: seconds("warmup_s", warmup_s)?,
+        arrival: arrival.parse().map_err(PyValueError::new_err)?,
+        max_in_flight: max_in_flight.max(1),
+        export,
+    })
+}
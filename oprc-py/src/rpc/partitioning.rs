@@ -0,0 +1,95 @@
+use std::str::FromStr;
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::{ProtoRequest, defaults::DefaultsTable};
+
+/// Request option overriding, for one object invocation, whether its
+/// `partition_id` is computed from its `object_id`: `"true"` or `"false"`.
+pub const AUTO_PARTITION_OPTION: &str = "oprc-auto-partition";
+
+/// How an object id maps to one of the partitions of its class. It must
+/// match how the cluster places objects.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub(crate) enum PartitionHash {
+    /// `object_id % partitions` (default).
+    #[default]
+    Modulo,
+    /// The XXH3 hash of the object id's 8 little-endian bytes, modulo the
+    /// number of partitions.
+    Xxh3,
+}
+
+impl PartitionHash {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            PartitionHash::Modulo => "modulo",
+            PartitionHash::Xxh3 => "xxh3",
+        }
+    }
+
+    /// The partition of `object_id` among `partitions`.
+    pub(crate) fn partition_of(self, object_id: u64, partitions: u32) -> u32 {
+        let hash = match self {
+            PartitionHash::Modulo => object_id,
+            PartitionHash::Xxh3 => xxh3_64(&object_id.to_le_bytes()),
+        };
+        (hash % partitions as u64) as u32
+    }
+}
+
+impl FromStr for PartitionHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "modulo" => Ok(PartitionHash::Modulo),
+            "xxh3" => Ok(PartitionHash::Xxh3),
+            other => Err(format!(
+                "unknown partition hash '{}'; expected 'modulo' or 'xxh3'",
+                other
+            )),
+        }
+    }
+}
+
+/// Sets the partition of an object invocation from its object id if its
+/// class has a partition count, unless the request's `oprc-auto-partition`
+/// option says otherwise. The option is removed.
+pub(crate) fn resolve(defaults: &DefaultsTable, req: &mut ProtoRequest) -> Result<(), String> {
+    let forced = match req.options_mut().remove(AUTO_PARTITION_OPTION).as_deref() {
+        None => None,
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        Some(other) => {
+            return Err(format!(
+                "invalid {} '{}', expected 'true' or 'false'",
+                AUTO_PARTITION_OPTION, other
+            ));
+        }
+    };
+    if forced == Some(false) {
+        return Ok(());
+    }
+    let cls_id = req.cls_id().to_string();
+    let ProtoRequest::Obj(r) = req else {
+        return match forced {
+            Some(true) => Err(format!(
+                "{} only applies to object invocations",
+                AUTO_PARTITION_OPTION
+            )),
+            _ => Ok(()),
+        };
+    };
+    match defaults.partitioning(&cls_id) {
+        Some((partitions, hash)) => {
+            r.partition_id = hash.partition_of(r.object_id, partitions);
+            Ok(())
+        }
+        None if forced == Some(true) => Err(format!(
+            "{} is set, but no partition count is configured for class '{}'",
+            AUTO_PARTITION_OPTION, cls_id
+        )),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,92 @@
+use std::future::Future;
+
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use tracing::warn;
+use zenoh::{handlers::FifoChannelHandler, pubsub::Subscriber, sample::Sample};
+
+use super::{ProtoRequest, response_stream::reply_key};
+use crate::{codec, model::PROGRESS_OPTION};
+
+/// Key expression prefix progress events are received under.
+const PROGRESS_PREFIX: &str = "oprc/_progress";
+
+/// Progress events of one invocation, passed to a Python callback.
+pub(crate) struct Progress {
+    subscriber: Subscriber<FifoChannelHandler<Sample>>,
+    callback: Py<PyAny>,
+}
+
+impl Progress {
+    /// Subscribes to a key expression unique to `req` and stores it in its
+    /// `oprc-progress` option, so its handler can report progress there.
+    pub(crate) async fn watch(
+        session: &zenoh::Session,
+        req: &mut ProtoRequest,
+        callback: Py<PyAny>,
+    ) -> PyResult<Self> {
+        let key_expr = reply_key(session, PROGRESS_PREFIX);
+        let subscriber = session
+            .declare_subscriber(key_expr.as_str())
+            .await
+            .map_err(|e| {
+                PyRuntimeError::new_err(format!("failed to subscribe to {}: {}", key_expr, e))
+            })?;
+        req.options_mut()
+            .insert(PROGRESS_OPTION.to_string(), key_expr);
+        Ok(Progress {
+            subscriber,
+            callback,
+        })
+    }
+
+    /// Calls the callback with the decoded event. A malformed event or a
+    /// failing callback is logged and does not affect the invocation.
+    fn deliver(&self, sample: Sample) {
+        Python::attach(|py| {
+            let res = codec::decode_json(py, &sample.payload().to_bytes())
+                .and_then(|event| self.callback.call1(py, (event,)));
+            if let Err(e) = res {
+                warn!("progress callback failed: {}", e);
+            }
+        });
+    }
+
+    /// Awaits `invocation`, passing the events reported meanwhile to the
+    /// callback. The callback runs on the thread awaiting, which for an
+    /// `async` pymethod is the thread of the event loop.
+    pub(crate) async fn forward<T>(self, invocation: impl Future<Output = T>) -> T {
+        tokio::pin!(invocation);
+        loop {
+            tokio::select! {
+                sample = self.subscriber.recv_async() => {
+                    let Ok(sample) = sample else {
+                        warn!("progress subscriber closed");
+                        return invocation.await;
+                    };
+                    self.deliver(sample);
+                }
+                result = &mut invocation => {
+                    // Events reported just before the handler returned may
+                    // still be queued behind its reply.
+                    while let Ok(Some(sample)) = self.subscriber.try_recv() {
+                        self.deliver(sample);
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+/// Publishes `event`, encoded as JSON, to the progress key expression
+/// `key_expr`.
+pub(crate) async fn report(
+    session: &zenoh::Session,
+    key_expr: &str,
+    event: Vec<u8>,
+) -> PyResult<()> {
+    session
+        .put(key_expr, event)
+        .await
+        .map_err(|e| PyRuntimeError::new_err(format!("failed to report progress: {}", e)))
+}
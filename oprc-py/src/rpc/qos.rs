@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use zenoh::qos::{CongestionControl, Priority};
+
+/// Request option with the Zenoh priority of the invocation query.
+pub(crate) const PRIORITY_OPTION: &str = "oprc-priority";
+/// Request option with the Zenoh congestion control of the invocation query:
+/// `drop` or `block`.
+pub(crate) const CONGESTION_CONTROL_OPTION: &str = "oprc-congestion-control";
+/// Request option that, when `true`, sends the invocation query without
+/// batching it with other messages.
+pub(crate) const EXPRESS_OPTION: &str = "oprc-express";
+
+/// Zenoh QoS of an invocation query. Unset fields keep Zenoh's defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Qos {
+    pub(crate) priority: Option<Priority>,
+    pub(crate) congestion_control: Option<CongestionControl>,
+    pub(crate) express: Option<bool>,
+}
+
+impl Qos {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Qos::default()
+    }
+
+    /// Removes the QoS options from `options` and parses them. They only
+    /// steer the query, so handlers never see them.
+    pub(crate) fn take(options: &mut HashMap<String, String>) -> Result<Qos, String> {
+        Ok(Qos {
+            priority: options
+                .remove(PRIORITY_OPTION)
+                .map(|v| parse_priority(&v))
+                .transpose()?,
+            congestion_control: options
+                .remove(CONGESTION_CONTROL_OPTION)
+                .map(|v| parse_congestion_control(&v))
+                .transpose()?,
+            express: options
+                .remove(EXPRESS_OPTION)
+                .map(|v| match v.as_str() {
+                    "true" => Ok(true),
+                    "false" => Ok(false),
+                    other => Err(format!(
+                        "invalid {} '{}'; expected 'true' or 'false'",
+                        EXPRESS_OPTION, other
+                    )),
+                })
+                .transpose()?,
+        })
+    }
+}
+
+fn parse_priority(s: &str) -> Result<Priority, String> {
+    match s {
+        "real_time" => Ok(Priority::RealTime),
+        "interactive_high" => Ok(Priority::InteractiveHigh),
+        "interactive_low" => Ok(Priority::InteractiveLow),
+        "data_high" => Ok(Priority::DataHigh),
+        "data" => Ok(Priority::Data),
+        "data_low" => Ok(Priority::DataLow),
+        "background" => Ok(Priority::Background),
+        other => Err(format!(
+            "invalid {} '{}'; expected one of real_time, interactive_high, \
+             interactive_low, data_high, data, data_low or background",
+            PRIORITY_OPTION, other
+        )),
+    }
+}
+
+fn parse_congestion_control(s: &str) -> Result<CongestionControl, String> {
+    match s {
+        "drop" => Ok(CongestionControl::Drop),
+        "block" => Ok(CongestionControl::Block),
+        other => Err(format!(
+            "invalid {} '{}'; expected 'drop' or 'block'",
+            CONGESTION_CONTROL_OPTION, other
+        )),
+    }
+}
+
+/// Writes the QoS passed to an `RpcManager.invoke_*` call into the request
+/// options, replacing the ones set there. Values are checked right away, so
+/// a typo fails the call instead of the invocation.
+pub(crate) fn stamp(
+    options: &mut HashMap<String, String>,
+    priority: Option<String>,
+    congestion_control: Option<String>,
+    express: Option<bool>,
+) -> Result<(), String> {
+    if let Some(priority) = priority {
+        parse_priority(&priority)?;
+        options.insert(PRIORITY_OPTION.to_string(), priority);
+    }
+    if let Some(congestion_control) = congestion_control {
+        parse_congestion_control(&congestion_control)?;
+        options.insert(CONGESTION_CONTROL_OPTION.to_string(), congestion_control);
+    }
+    if let Some(express) = express {
+        options.insert(EXPRESS_OPTION.to_string(), express.to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use prost::Message;
+use pyo3::{
+    exceptions::{PyRuntimeError, PyStopAsyncIteration},
+    prelude::*,
+};
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use super::{Invoker, ProtoRequest};
+use crate::compat;
+use crate::model::{InvocationResponse, RESPONSE_STREAM_OPTION};
+
+/// Key expression prefix response streams are received under.
+const STREAMS_PREFIX: &str = "oprc/_streams";
+/// Chunks buffered before the receiving task waits for Python to catch up.
+const BUFFERED_CHUNKS: usize = 64;
+
+/// Returns a key expression under `prefix` unique to this invocation, for
+/// the handler to publish to and the caller to subscribe to.
+pub(super) fn reply_key(session: &zenoh::Session, prefix: &str) -> String {
+    static NEXT_KEY: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!(
+        "{}/{}/{:x}-{}",
+        prefix,
+        session.zid(),
+        nanos,
+        NEXT_KEY.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Invokes `req` and forwards the response chunks the handler sends to the
+/// key expression in its `oprc-response-stream` option, followed by the final
+/// response. The invocation is not retried, as chunks cannot be taken back.
+pub(crate) fn open(
+    invoker: Invoker,
+    session: zenoh::Session,
+    mut req: ProtoRequest,
+    timeout: Option<Duration>,
+    span: &'static str,
+) -> ResponseStream {
+    let key_expr = reply_key(&session, STREAMS_PREFIX);
+    let (tx, rx) = flume::bounded(BUFFERED_CHUNKS);
+    let task = get_runtime().spawn(async move {
+        let subscriber = match session.declare_subscriber(key_expr.as_str()).await {
+            Ok(subscriber) => subscriber,
+            Err(e) => {
+                let msg = format!("failed to subscribe to {}: {}", key_expr, e);
+                let _ = tx.send_async(Err(PyRuntimeError::new_err(msg))).await;
+                return;
+            }
+        };
+        req.options_mut()
+            .insert(RESPONSE_STREAM_OPTION.to_string(), key_expr);
+        let decode = |sample: zenoh::sample::Sample| {
+            compat::decode_response(sample.payload().to_bytes().as_ref())
+                .map_err(|e| PyRuntimeError::new_err(format!("invalid response chunk: {}", e)))
+        };
+        let invocation = invoker.invoke(req, timeout, Some(0), span);
+        tokio::pin!(invocation);
+        loop {
+            tokio::select! {
+                sample = subscriber.recv_async() => {
+                    let Ok(sample) = sample else {
+                        warn!("response stream subscriber closed");
+                        break;
+                    };
+                    if tx.send_async(decode(sample)).await.is_err() {
+                        return;
+                    }
+                }
+                result = &mut invocation => {
+                    // Chunks published just before the handler returned may
+                    // still be queued behind its reply.
+                    while let Ok(Some(sample)) = subscriber.try_recv() {
+                        if tx.send_async(decode(sample)).await.is_err() {
+                            return;
+                        }
+                    }
+                    let _ = tx.send_async(result.map(|d| d.response)).await;
+                    return;
+                }
+            }
+        }
+        let _ = tx.send_async(invocation.await.map(|d| d.response)).await;
+    });
+    ResponseStream { rx, task }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass]
+/// Async iterator over the chunks of a streamed invocation response, as
+/// returned by `RpcManager.invoke_fn_stream`.
+///
+/// Yields an `InvocationResponse` for every chunk the handler sends and then
+/// the final response the handler returns. Dropping the iterator cancels the
+/// invocation.
+pub struct ResponseStream {
+    rx: flume::Receiver<PyResult<oprc_pb::InvocationResponse>>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ResponseStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ResponseStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let rx = self.rx.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match rx.recv_async().await {
+                Ok(chunk) => chunk.map(InvocationResponse::from),
+                Err(_) => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+}
+
+/// Publishes `resp` as a chunk of the response stream at `key_expr`.
+pub(crate) async fn send_chunk(
+    session: &zenoh::Session,
+    key_expr: &str,
+    resp: oprc_pb::InvocationResponse,
+) -> PyResult<()> {
+    session
+        .put(key_expr, resp.encode_to_vec())
+        .await
+        .map_err(|e| PyRuntimeError::new_err(format!("failed to send chunk: {}", e)))
+}
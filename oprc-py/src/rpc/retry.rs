@@ -0,0 +1,122 @@
+use std::{sync::Arc, time::Duration};
+
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use tracing::warn;
+
+use crate::errors::CodedError;
+use crate::model::{InvocationResponse, InvocationResponseCode};
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass_enum)]
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// How a failed invocation attempt is handled.
+pub enum RetryDecision {
+    /// Retry while attempts and the retry budget allow.
+    Retryable = 0,
+    /// Stop retrying; a failed response is returned to the caller as is.
+    Fatal = 1,
+    /// Stop retrying and raise, even for a response with a status code.
+    Escalate = 2,
+}
+
+/// A failed invocation attempt.
+pub(crate) enum Failure {
+    /// The invocation did not complete (transport or integrity error).
+    Error(CodedError),
+    /// The invocation completed with a status other than `Okay`.
+    Status(oprc_pb::InvocationResponse),
+}
+
+/// Retry settings of an `RpcManager`.
+#[derive(Clone)]
+pub(crate) struct RetryPolicy {
+    /// Retries after the first attempt, unless the request sets its own limit.
+    pub(crate) max_retries: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    pub(crate) backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    /// Fraction of each delay, between `0` and `1`, that is randomly cut off
+    /// so clients retrying together spread out.
+    pub(crate) jitter: f64,
+    /// Response statuses retried by the default classification.
+    pub(crate) retryable_statuses: Vec<i32>,
+    /// Python `classify_error(failure) -> RetryDecision | str`.
+    pub(crate) classifier: Option<Arc<Py<PyAny>>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            jitter: 0.0,
+            retryable_statuses: vec![InvocationResponseCode::SystemError as i32],
+            classifier: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1).
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - self.jitter * fastrand::f64())
+    }
+
+    /// Default classification: incomplete invocations and responses with a
+    /// retryable status (`SystemError` unless configured) are retryable, any
+    /// other status is fatal.
+    fn default_decision(&self, failure: &Failure) -> RetryDecision {
+        match failure {
+            Failure::Error(_) => RetryDecision::Retryable,
+            Failure::Status(resp) if self.retryable_statuses.contains(&resp.status) => {
+                RetryDecision::Retryable
+            }
+            Failure::Status(_) => RetryDecision::Fatal,
+        }
+    }
+
+    /// Classifies `failure`, with the Python classifier if one is registered.
+    ///
+    /// The classifier receives a `RuntimeError` with the `ErrorCode` in its
+    /// `code` attribute for incomplete invocations and the
+    /// `InvocationResponse` otherwise. If it raises or returns something
+    /// unrecognized, the default classification applies.
+    pub(crate) fn classify(&self, failure: &Failure) -> RetryDecision {
+        let Some(classifier) = &self.classifier else {
+            return self.default_decision(failure);
+        };
+        let decision = Python::attach(|py| -> PyResult<RetryDecision> {
+            let arg = match failure {
+                Failure::Error(e) => PyErr::from(e.clone()).into_value(py).into_any(),
+                Failure::Status(resp) => {
+                    Py::new(py, InvocationResponse::from(resp.clone()))?.into_any()
+                }
+            };
+            let result = classifier.call1(py, (arg,))?.into_bound(py);
+            if let Ok(decision) = result.extract::<RetryDecision>() {
+                return Ok(decision);
+            }
+            match result.extract::<String>()?.to_ascii_lowercase().as_str() {
+                "retryable" => Ok(RetryDecision::Retryable),
+                "fatal" => Ok(RetryDecision::Fatal),
+                "escalate" => Ok(RetryDecision::Escalate),
+                other => Err(PyRuntimeError::new_err(format!(
+                    "unknown retry decision '{}'",
+                    other
+                ))),
+            }
+        });
+        decision.unwrap_or_else(|e| {
+            warn!("classify_error failed, using default classification: {}", e);
+            self.default_decision(failure)
+        })
+    }
+}
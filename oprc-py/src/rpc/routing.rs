@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use super::{
+    ProtoRequest,
+    stats::{InvocationCounters, InvocationStats},
+};
+
+/// Sends invocations whose option `option` equals `value` to `variant`.
+struct Rule {
+    option: String,
+    value: String,
+    variant: String,
+}
+
+/// How the invocations of one function are split between its variants.
+struct Route {
+    /// Checked in order before the weights; the first match wins.
+    rules: Vec<Rule>,
+    /// Variants with the upper end of their share of `[0, 1)`, in ascending
+    /// order. Empty if only the rules route.
+    weights: Vec<(String, f64)>,
+    /// Counters of the invocations routed to each variant.
+    counters: HashMap<String, Arc<InvocationCounters>>,
+}
+
+/// Routes of an `RpcManager`, by class and function.
+#[derive(Clone, Default)]
+pub(crate) struct RoutingTable(Arc<RwLock<HashMap<(String, String), Route>>>);
+
+impl RoutingTable {
+    /// Splits the invocations of `cls_id`/`fn_id` between the function
+    /// variants of `weights`, after sending those matching one of `rules`,
+    /// given as `(option, value, variant)`, to its variant. Replaces the
+    /// route of the same function, counters included.
+    pub(crate) fn set(
+        &self,
+        cls_id: String,
+        fn_id: String,
+        weights: HashMap<String, f64>,
+        rules: Vec<(String, String, String)>,
+    ) -> Result<(), String> {
+        if weights.values().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("weights must be finite and not negative".to_string());
+        }
+        let total: f64 = weights.values().sum();
+        if !weights.is_empty() && total == 0.0 {
+            return Err("weights must not all be zero".to_string());
+        }
+        if weights.is_empty() && rules.is_empty() {
+            return Err("a route needs weights or rules".to_string());
+        }
+        let mut variants: Vec<(String, f64)> = weights.into_iter().collect();
+        variants.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut upto = 0.0;
+        let weights: Vec<(String, f64)> = variants
+            .into_iter()
+            .map(|(variant, weight)| {
+                upto += weight / total;
+                (variant, upto)
+            })
+            .collect();
+        let rules: Vec<Rule> = rules
+            .into_iter()
+            .map(|(option, value, variant)| Rule {
+                option,
+                value,
+                variant,
+            })
+            .collect();
+        let counters = weights
+            .iter()
+            .map(|(variant, _)| variant)
+            .chain(rules.iter().map(|rule| &rule.variant))
+            .map(|variant| (variant.clone(), Arc::default()))
+            .collect();
+        self.0.write().unwrap().insert(
+            (cls_id, fn_id),
+            Route {
+                rules,
+                weights,
+                counters,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes the route of `cls_id`/`fn_id`. Returns whether there was one.
+    pub(crate) fn clear(&self, cls_id: String, fn_id: String) -> bool {
+        self.0.write().unwrap().remove(&(cls_id, fn_id)).is_some()
+    }
+
+    /// Points `req` at the variant its route picks, if its function has a
+    /// route, and returns the counters of that variant.
+    pub(crate) fn route(&self, req: &mut ProtoRequest) -> Option<Arc<InvocationCounters>> {
+        let (variant, counters) = {
+            let table = self.0.read().unwrap();
+            if table.is_empty() {
+                return None;
+            }
+            let route = table.get(&(req.cls_id().to_string(), req.fn_id().to_string()))?;
+            let variant = route
+                .rules
+                .iter()
+                .find(|rule| req.options().get(&rule.option) == Some(&rule.value))
+                .map(|rule| &rule.variant)
+                .or_else(|| {
+                    let sample = fastrand::f64();
+                    route
+                        .weights
+                        .iter()
+                        .find(|(_, upto)| sample < *upto)
+                        .or(route.weights.last())
+                        .map(|(variant, _)| variant)
+                })?;
+            (variant.clone(), route.counters[variant].clone())
+        };
+        match req {
+            ProtoRequest::Fn(r) => r.fn_id = variant,
+            ProtoRequest::Obj(r) => r.fn_id = variant,
+        }
+        Some(counters)
+    }
+
+    /// Returns the counters of each variant of the route of `cls_id`/`fn_id`,
+    /// empty if it has none.
+    pub(crate) fn stats(&self, cls_id: &str, fn_id: &str) -> HashMap<String, InvocationStats> {
+        self.0
+            .read()
+            .unwrap()
+            .get(&(cls_id.to_string(), fn_id.to_string()))
+            .map(|route| {
+                route
+                    .counters
+                    .iter()
+                    .map(|(variant, counters)| (variant.clone(), counters.stats()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
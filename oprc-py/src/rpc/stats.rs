@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::intern::intern;
+use crate::metrics::{CLIENT_LATENCY, MetricPoint, QUANTILE_LABEL};
+
+/// Latency buckets per doubling, so percentiles are within about 9%.
+const BUCKETS_PER_OCTAVE: f64 = 8.0;
+/// Buckets cover 1 µs to about 2 minutes; slower invocations land in the last.
+const BUCKETS: usize = 8 * 27;
+
+/// Invocation counters of an `RpcManager`, updated with relaxed atomics so
+/// recording never blocks an invocation.
+pub(crate) struct InvocationCounters {
+    requests: AtomicU64,
+    failures: AtomicU64,
+    retries: AtomicU64,
+    cache_hits: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    total_latency_ns: AtomicU64,
+    max_latency_ns: AtomicU64,
+    /// Completed invocations per logarithmic latency bucket.
+    latency: [AtomicU64; BUCKETS],
+}
+
+impl Default for InvocationCounters {
+    fn default() -> Self {
+        InvocationCounters {
+            requests: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            total_latency_ns: AtomicU64::new(0),
+            max_latency_ns: AtomicU64::new(0),
+            latency: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+fn bucket(latency: Duration) -> usize {
+    let micros = latency.as_micros().max(1) as f64;
+    ((micros.log2() * BUCKETS_PER_OCTAVE) as usize).min(BUCKETS - 1)
+}
+
+/// Upper bound of bucket `i`, in milliseconds.
+fn bucket_upper_ms(i: usize) -> f64 {
+    ((i + 1) as f64 / BUCKETS_PER_OCTAVE).exp2() / 1000.0
+}
+
+impl InvocationCounters {
+    /// Records one attempt sending `sent` payload bytes.
+    pub(crate) fn record_attempt(&self, sent: usize) {
+        self.bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
+    }
+
+    /// Records a response carrying `received` payload bytes.
+    pub(crate) fn record_response(&self, received: usize) {
+        self.bytes_received
+            .fetch_add(received as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an invocation answered from the response cache, which is not
+    /// counted as a request.
+    pub(crate) fn record_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a completed invocation: one that returned `Okay` or not.
+    pub(crate) fn record(&self, failed: bool, latency: Duration) {
+        let ns = latency.as_nanos() as u64;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_latency_ns.fetch_add(ns, Ordering::Relaxed);
+        self.max_latency_ns.fetch_max(ns, Ordering::Relaxed);
+        self.latency[bucket(latency)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> InvocationStats {
+        let counts: Vec<u64> = self
+            .latency
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect();
+        let total: u64 = counts.iter().sum();
+        let max_ms = self.max_latency_ns.load(Ordering::Relaxed) as f64 / 1e6;
+        let percentile = |q: f64| {
+            if total == 0 {
+                return 0.0;
+            }
+            let rank = ((total as f64 * q).ceil() as u64).max(1);
+            let mut seen = 0;
+            for (i, count) in counts.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    return bucket_upper_ms(i).min(max_ms);
+                }
+            }
+            max_ms
+        };
+        let requests = self.requests.load(Ordering::Relaxed);
+        InvocationStats {
+            requests,
+            failures: self.failures.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            mean_latency_ms: if requests == 0 {
+                0.0
+            } else {
+                self.total_latency_ns.load(Ordering::Relaxed) as f64 / 1e6 / requests as f64
+            },
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_latency_ms: max_ms,
+        }
+    }
+}
+
+/// Invocation counters of each function invoked through an `RpcManager`,
+/// exported as `oprc.client.latency`. Keys are interned so recording does
+/// not allocate.
+#[derive(Default)]
+pub(crate) struct PerFunctionCounters {
+    functions: Mutex<HashMap<(Arc<str>, Arc<str>), Arc<InvocationCounters>>>,
+}
+
+impl PerFunctionCounters {
+    /// Records a completed invocation of `cls_id/fn_id`.
+    pub(crate) fn record(&self, cls_id: &str, fn_id: &str, failed: bool, latency: Duration) {
+        let counters = self
+            .functions
+            .lock()
+            .unwrap()
+            .entry((intern(cls_id), intern(fn_id)))
+            .or_default()
+            .clone();
+        counters.record(failed, latency);
+    }
+
+    /// Returns the p50, p90, p99 and maximum latency of every function, in
+    /// milliseconds, with `quantile` labels `0.5`, `0.9`, `0.99` and `1`,
+    /// sorted by class and function.
+    pub(crate) fn export(&self) -> Vec<MetricPoint> {
+        let mut functions: Vec<_> = self
+            .functions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, counters)| (key.clone(), counters.clone()))
+            .collect();
+        functions.sort_by(|a, b| a.0.cmp(&b.0));
+        functions
+            .into_iter()
+            .flat_map(|((cls_id, fn_id), counters)| {
+                let stats = counters.stats();
+                [
+                    ("0.5", stats.p50_ms),
+                    ("0.9", stats.p90_ms),
+                    ("0.99", stats.p99_ms),
+                    ("1", stats.max_latency_ms),
+                ]
+                .map(|(quantile, ms)| {
+                    MetricPoint::function(CLIENT_LATENCY, &cls_id, &fn_id, ms)
+                        .with_label(QUANTILE_LABEL, quantile)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+/// Counters of the invocations sent through an `RpcManager` since it was
+/// created, as returned by `RpcManager.stats()`.
+pub struct InvocationStats {
+    /// Invocations sent that completed, failed ones included.
+    pub requests: u64,
+    /// Invocations that raised or returned a status other than `Okay`.
+    pub failures: u64,
+    /// Attempts retried after a failed one.
+    pub retries: u64,
+    /// Invocations answered from the response cache without being sent.
+    pub cache_hits: u64,
+    /// Request payload bytes sent, counting every attempt.
+    pub bytes_sent: u64,
+    /// Response payload bytes received.
+    pub bytes_received: u64,
+    /// Mean latency of the completed invocations, retries included.
+    pub mean_latency_ms: f64,
+    /// Latency percentiles, from logarithmic buckets and so within about 9%.
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    /// Latency of the slowest completed invocation.
+    pub max_latency_ms: f64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl InvocationStats {
+    /// Returns a string representation of the `InvocationStats`.
+    fn __str__(&self) -> String {
+        format!(
+            "InvocationStats {{ requests: {}, failures: {}, retries: {}, cache_hits: {}, p50_ms: {:.3}, p99_ms: {:.3} }}",
+            self.requests, self.failures, self.retries, self.cache_hits, self.p50_ms, self.p99_ms
+        )
+    }
+}
@@ -0,0 +1,126 @@
+use std::{ffi::OsString, path::PathBuf, time::Duration};
+
+use tokio::{
+    fs::{self, File},
+    io::{AsyncWriteExt, BufWriter},
+};
+
+use pyo3::{PyResult, exceptions::PyRuntimeError};
+use tracing::debug;
+
+use super::{Delivery, Invoker, ProtoRequest};
+use crate::{
+    capabilities::Capability,
+    chunks::{self, CHUNKED_HEADER, ChunkRef, ChunkSource},
+    flags,
+    model::InvocationResponseCode,
+};
+
+/// Invokes `req` with the payload of `source`.
+///
+/// A source that fits into one chunk is sent inline. Otherwise its chunks are
+/// served until the invocation completes and the target fetches them as it
+/// admits the call. A Python source cannot be replayed, so its invocation is
+/// not retried.
+///
+/// Until the target's servers have advertised support for chunked payloads
+/// (see `oprc-capabilities`), or while the `chunked_payloads` feature flag is
+/// off, the source is read whole and sent inline.
+pub(crate) async fn invoke_streamed(
+    invoker: &Invoker,
+    session: &zenoh::Session,
+    mut req: ProtoRequest,
+    mut source: ChunkSource,
+    timeout: Option<Duration>,
+    max_retries: Option<u32>,
+    span: &'static str,
+) -> PyResult<Delivery> {
+    if let Some(payload) = source.read_small().await.map_err(PyRuntimeError::new_err)? {
+        *req.payload_mut() = payload;
+        return invoker.invoke(req, timeout, max_retries, span).await;
+    }
+    let replayable = matches!(source, ChunkSource::File { .. });
+    let needed: &[Capability] = if replayable {
+        &[Capability::Chunked]
+    } else {
+        &[Capability::Chunked, Capability::Streaming]
+    };
+    let cls_id = req.cls_id().to_string();
+    if !flags::enabled(flags::CHUNKED_PAYLOADS) {
+        debug!("chunked payloads are disabled; sending inline");
+        *req.payload_mut() = source.read_all().await.map_err(PyRuntimeError::new_err)?;
+        return invoker.invoke(req, timeout, max_retries, span).await;
+    }
+    if !needed.iter().all(|c| invoker.peers.supports(&cls_id, *c)) {
+        debug!(
+            "servers of {} are not known to accept chunked payloads; sending inline",
+            cls_id
+        );
+        *req.payload_mut() = source.read_all().await.map_err(PyRuntimeError::new_err)?;
+        return invoker.invoke(req, timeout, max_retries, span).await;
+    }
+    let server = chunks::serve(session, source)
+        .await
+        .map_err(PyRuntimeError::new_err)?;
+    req.payload_mut().clear();
+    req.options_mut()
+        .insert(CHUNKED_HEADER.to_string(), server.chunks().to_header());
+    let max_retries = if replayable { max_retries } else { Some(0) };
+    let delivery = invoker.invoke(req, timeout, max_retries, span).await;
+    drop(server);
+    delivery
+}
+
+/// Writes the payload of a successful `response` to `path` and returns the
+/// response without it. A chunked payload is fetched chunk by chunk, so it
+/// is never held in memory as a whole.
+///
+/// The file is written under a `.part` name and renamed once complete. A
+/// response with another status is returned untouched and nothing is written.
+pub(crate) async fn save_payload(
+    session: &zenoh::Session,
+    mut response: oprc_pb::InvocationResponse,
+    path: PathBuf,
+) -> Result<oprc_pb::InvocationResponse, String> {
+    if response.status != InvocationResponseCode::Okay as i32 {
+        return Ok(response);
+    }
+    let chunked = response
+        .headers
+        .remove(CHUNKED_HEADER)
+        .map(|v| ChunkRef::parse(&v))
+        .transpose()?;
+    let mut part: OsString = path.clone().into_os_string();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    let written = async {
+        let file = File::create(&part)
+            .await
+            .map_err(|e| format!("failed to create {}: {}", part.display(), e))?;
+        let mut out = BufWriter::new(file);
+        match &chunked {
+            Some(chunks) => {
+                chunks::fetch_into(session, chunks, &mut out, None).await?;
+            }
+            None => {
+                let payload = response.payload.take().unwrap_or_default();
+                out.write_all(&payload)
+                    .await
+                    .map_err(|e| format!("failed to write {}: {}", part.display(), e))?;
+            }
+        }
+        out.flush()
+            .await
+            .map_err(|e| format!("failed to write {}: {}", part.display(), e))
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = fs::remove_file(&part).await;
+        return Err(e);
+    }
+    fs::rename(&part, &path)
+        .await
+        .map_err(|e| format!("failed to move {} into place: {}", part.display(), e))?;
+    response.payload = None;
+    Ok(response)
+}
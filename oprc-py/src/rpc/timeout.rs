@@ -0,0 +1,98 @@
+use std::{
+    future::Future,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use pyo3::{
+    PyResult,
+    exceptions::{PyRuntimeError, PyTimeoutError},
+    pyfunction,
+};
+
+use crate::errors::ErrorCode;
+
+/// Environment variable used to seed the module-level default timeout.
+const DEFAULT_TIMEOUT_ENV: &str = "OPRC_RPC_TIMEOUT_MS";
+/// Module-level timeout when nothing else is configured: none, so
+/// long-running functions are not cut short unless the caller asks.
+const FALLBACK_TIMEOUT_MS: u64 = 0;
+
+static DEFAULT_TIMEOUT_MS: OnceLock<AtomicU64> = OnceLock::new();
+
+fn default_cell() -> &'static AtomicU64 {
+    DEFAULT_TIMEOUT_MS.get_or_init(|| {
+        let ms = std::env::var(DEFAULT_TIMEOUT_ENV)
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(FALLBACK_TIMEOUT_MS);
+        AtomicU64::new(ms)
+    })
+}
+
+/// Sets the module-level default invocation timeout in milliseconds.
+///
+/// This is the lowest-precedence timeout; it applies when neither the
+/// `RpcManager` nor the request specifies one. A value of `0` disables it.
+/// It is `0` unless seeded from `OPRC_RPC_TIMEOUT_MS`.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn set_default_timeout(timeout_ms: u64) {
+    default_cell().store(timeout_ms, Ordering::Relaxed);
+}
+
+/// Returns the module-level default invocation timeout in milliseconds.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn get_default_timeout() -> u64 {
+    default_cell().load(Ordering::Relaxed)
+}
+
+/// Resolves the effective timeout following the precedence chain
+/// module default < manager config < per-request.
+///
+/// Returns `None` when the winning value is `0` (no timeout).
+pub(crate) fn resolve(manager_ms: Option<u64>, request_ms: Option<u64>) -> Option<Duration> {
+    let ms = request_ms
+        .or(manager_ms)
+        .unwrap_or_else(get_default_timeout);
+    if ms == 0 {
+        None
+    } else {
+        Some(Duration::from_millis(ms))
+    }
+}
+
+/// Awaits a fallible non-Python future like `bound`, turning its error into
+/// a Python `RuntimeError`.
+pub(crate) async fn run<F, T, E>(timeout: Option<Duration>, fut: F) -> PyResult<T>
+where
+    F: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    bound(timeout, async {
+        fut.await
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    })
+    .await
+}
+
+/// Awaits `fut`, failing with a Python `TimeoutError` tagged `Timeout` if
+/// `timeout` elapses first. `None` waits for as long as it takes.
+pub(crate) async fn bound<F, T>(timeout: Option<Duration>, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>>,
+{
+    match timeout {
+        Some(d) => tokio::time::timeout(d, fut).await.map_err(|_| {
+            ErrorCode::Timeout.tag(PyTimeoutError::new_err(format!(
+                "Invocation timed out after {} ms",
+                d.as_millis()
+            )))
+        })?,
+        None => fut.await,
+    }
+}
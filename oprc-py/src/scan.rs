@@ -0,0 +1,65 @@
+use std::{collections::HashSet, time::SystemTime};
+
+use oprc_pb::ObjData;
+use prost::Message;
+use tracing::warn;
+use zenoh::query::ConsolidationMode;
+
+/// An object found in a partition scan.
+pub(crate) struct Scanned {
+    pub(crate) object_id: u64,
+    /// The object state, if the reply decoded as `ObjData`.
+    pub(crate) data: Option<ObjData>,
+    /// The Zenoh timestamp of the reply, if it has one. This is the last
+    /// write only if the data plane answers with the timestamp of the stored
+    /// sample, as Zenoh storages do; a queryable stamping replies when it
+    /// answers, or a replica re-stamping what it copied, breaks that.
+    pub(crate) written: Option<SystemTime>,
+}
+
+/// Streams the objects stored in one partition to `on_object`, each object
+/// once, as the replies arrive.
+pub(crate) async fn partition(
+    session: &zenoh::Session,
+    cls_id: &str,
+    partition_id: u32,
+    mut on_object: impl FnMut(Scanned),
+) -> Result<(), String> {
+    let selector = format!("oprc/{}/{}/objects/*", cls_id, partition_id);
+    let replies = session
+        .get(selector.as_str())
+        .consolidation(ConsolidationMode::Latest)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut seen = HashSet::new();
+    while let Ok(reply) = replies.recv_async().await {
+        let Ok(sample) = reply.result() else {
+            continue;
+        };
+        let data = ObjData::decode(sample.payload().to_bytes().as_ref()).ok();
+        let object_id = data
+            .as_ref()
+            .and_then(|obj| obj.metadata.as_ref().map(|m| m.object_id))
+            .or_else(|| {
+                sample
+                    .key_expr()
+                    .as_str()
+                    .rsplit('/')
+                    .next()
+                    .and_then(|id| id.parse().ok())
+            });
+        let Some(object_id) = object_id else {
+            warn!("skipping unrecognized object at {}", sample.key_expr());
+            continue;
+        };
+        if !seen.insert(object_id) {
+            continue;
+        }
+        on_object(Scanned {
+            object_id,
+            data,
+            written: sample.timestamp().map(|ts| ts.get_time().to_system_time()),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use pyo3::{intern, prelude::*, types::PyTuple};
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Python callbacks notified of changes in the Zenoh session's connectivity.
+pub(crate) struct SessionHooks {
+    /// Called with the list of connected zids when the first router/peer connects.
+    pub(crate) on_connect: Option<Py<PyAny>>,
+    /// Called with no arguments when the last router/peer disconnects.
+    pub(crate) on_disconnect: Option<Py<PyAny>>,
+    /// Called with `(added, removed)` zid lists whenever the set changes.
+    pub(crate) on_peer_change: Option<Py<PyAny>>,
+    /// If set, callbacks are scheduled with `call_soon_threadsafe` on this loop
+    /// instead of being called from a runtime thread.
+    pub(crate) event_loop: Option<Py<PyAny>>,
+}
+
+impl SessionHooks {
+    fn fire(
+        &self,
+        callback: &Option<Py<PyAny>>,
+        make_args: impl FnOnce(Python<'_>) -> PyResult<Bound<'_, PyTuple>>,
+    ) {
+        if let Some(callback) = callback {
+            notify(callback, self.event_loop.as_ref(), make_args);
+        }
+    }
+}
+
+/// Calls `callback` with the arguments built by `make_args`, on `event_loop`
+/// via `call_soon_threadsafe` if given. Errors are logged, not raised.
+pub(crate) fn notify(
+    callback: &Py<PyAny>,
+    event_loop: Option<&Py<PyAny>>,
+    make_args: impl FnOnce(Python<'_>) -> PyResult<Bound<'_, PyTuple>>,
+) {
+    Python::attach(|py| {
+        let res = make_args(py).and_then(|args| match event_loop {
+            Some(event_loop) => {
+                let mut call = vec![callback.bind(py).clone()];
+                call.extend(args.iter());
+                event_loop.call_method1(
+                    py,
+                    intern!(py, "call_soon_threadsafe"),
+                    PyTuple::new(py, call)?,
+                )
+            }
+            None => callback.call1(py, args),
+        });
+        if let Err(e) = res {
+            warn!("event callback failed: {}", e);
+        }
+    });
+}
+
+/// Polls the routers and peers connected to `session` every `interval` and
+/// reports changes to `hooks` until the returned task is aborted.
+///
+/// The first poll reports the initial connections, so `on_connect` fires right
+/// away if the session is already connected.
+pub(crate) fn watch(
+    session: zenoh::Session,
+    hooks: SessionHooks,
+    interval: Duration,
+) -> JoinHandle<()> {
+    get_runtime().spawn(async move {
+        let mut known = BTreeSet::new();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let info = session.info();
+            let mut current: BTreeSet<String> =
+                info.routers_zid().await.map(|z| z.to_string()).collect();
+            current.extend(info.peers_zid().await.map(|z| z.to_string()));
+            if current == known {
+                continue;
+            }
+            let added: Vec<String> = current.difference(&known).cloned().collect();
+            let removed: Vec<String> = known.difference(&current).cloned().collect();
+            if known.is_empty() {
+                let connected: Vec<String> = current.iter().cloned().collect();
+                hooks.fire(&hooks.on_connect, |py| PyTuple::new(py, [connected]));
+            }
+            hooks.fire(&hooks.on_peer_change, |py| {
+                PyTuple::new(py, [added, removed])
+            });
+            if current.is_empty() {
+                hooks.fire(&hooks.on_disconnect, |py| Ok(PyTuple::empty(py)));
+            }
+            known = current;
+        }
+    })
+}
@@ -0,0 +1,163 @@
+use std::{
+    sync::{Arc, Mutex, OnceLock, Weak},
+    time::{Duration, Instant},
+};
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::sync::watch;
+
+use crate::{rpc::Lifecycle, telemetry};
+
+/// How often `shutdown` checks whether the runtime's tasks completed.
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What a process-wide shutdown has to stop: the gRPC servers waiting on
+/// `stop`, the Zenoh sessions engines opened and the invocations of every
+/// `RpcManager`.
+struct Registry {
+    /// Set once shutdown starts. Each running server holds a receiver.
+    stop: watch::Sender<bool>,
+    sessions: Mutex<Vec<zenoh::Session>>,
+    managers: Mutex<Vec<Weak<Lifecycle>>>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| Registry {
+        stop: watch::Sender::new(false),
+        sessions: Mutex::default(),
+        managers: Mutex::default(),
+    })
+}
+
+/// Records a session opened by an engine, to close on shutdown.
+pub(crate) fn track_session(session: &zenoh::Session) {
+    registry().sessions.lock().unwrap().push(session.clone());
+}
+
+/// Records the invocations of an `RpcManager`, to drain on shutdown.
+pub(crate) fn track_manager(lifecycle: &Arc<Lifecycle>) {
+    let mut managers = registry().managers.lock().unwrap();
+    managers.retain(|m| m.strong_count() > 0);
+    managers.push(Arc::downgrade(lifecycle));
+}
+
+/// Watches for shutdown to start; it has once the value is `true`. A server
+/// counts as running while it holds the receiver.
+pub(crate) fn subscribe() -> watch::Receiver<bool> {
+    registry().stop.subscribe()
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyclass(get_all)]
+#[derive(Clone, Default)]
+/// Outcome of `shutdown`: what it stopped, and what was still in flight when
+/// its timeout ran out.
+pub struct ShutdownReport {
+    /// gRPC servers told to stop.
+    pub servers_stopped: usize,
+    /// Zenoh sessions closed.
+    pub sessions_closed: usize,
+    /// Invocations of `RpcManager`s still in flight; they failed as the
+    /// sessions closed.
+    pub invocations: usize,
+    /// Tasks still running on the runtime.
+    pub tasks: usize,
+    /// Time the shutdown took, in milliseconds.
+    pub elapsed_ms: f64,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pymethods]
+impl ShutdownReport {
+    /// `True` if nothing was left in flight.
+    #[getter]
+    fn clean(&self) -> bool {
+        self.invocations == 0 && self.tasks == 0
+    }
+
+    /// Returns a string representation of the `ShutdownReport`.
+    fn __str__(&self) -> String {
+        format!(
+            "ShutdownReport {{ servers_stopped: {}, sessions_closed: {}, invocations: {}, tasks: {}, elapsed_ms: {:.1} }}",
+            self.servers_stopped,
+            self.sessions_closed,
+            self.invocations,
+            self.tasks,
+            self.elapsed_ms
+        )
+    }
+}
+
+/// Shuts the SDK down: stops the gRPC servers, drains the invocations of
+/// every `RpcManager`, closes the Zenoh sessions, flushes telemetry and
+/// waits for the tasks left on the runtime. Blocks the calling thread; call
+/// it after the event loop stopped or from another thread, as handlers
+/// running on the loop cannot complete meanwhile.
+///
+/// # Arguments
+///
+/// * `timeout_ms`: How long to wait in total for the invocations and tasks
+///   in flight.
+///
+/// # Returns
+///
+/// A `ShutdownReport` with what was still in flight.
+#[pyfunction]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+#[pyo3(signature = (timeout_ms=10_000))]
+pub fn shutdown(py: Python<'_>, timeout_ms: u64) -> ShutdownReport {
+    py.detach(|| {
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(timeout_ms);
+        let registry = registry();
+        let runtime = get_runtime();
+        let servers_stopped = registry.stop.receiver_count();
+        registry.stop.send_replace(true);
+        let managers: Vec<_> = registry
+            .managers
+            .lock()
+            .unwrap()
+            .drain(..)
+            .filter_map(|m| m.upgrade())
+            .collect();
+        // Closed all at once, so none admits invocations while another drains.
+        for manager in &managers {
+            manager.close();
+        }
+        let sessions: Vec<_> = registry.sessions.lock().unwrap().drain(..).collect();
+        let (invocations, sessions_closed) = runtime.block_on(async {
+            let mut invocations = 0;
+            for manager in &managers {
+                let left = deadline.saturating_duration_since(Instant::now());
+                invocations += manager.drain(Some(left)).await;
+            }
+            let mut closed = 0;
+            for session in sessions.iter().filter(|s| !s.is_closed()) {
+                match session.close().await {
+                    Ok(()) => closed += 1,
+                    Err(e) => tracing::warn!("failed to close session {}: {}", session.zid(), e),
+                }
+            }
+            (invocations, closed)
+        });
+        // After the invocations, so their spans are exported too. The batch
+        // exporter's task ends with it.
+        telemetry::shutdown();
+        let tasks = runtime.block_on(async {
+            let metrics = runtime.metrics();
+            while metrics.num_alive_tasks() > 0 && Instant::now() < deadline {
+                tokio::time::sleep(TASK_POLL_INTERVAL).await;
+            }
+            metrics.num_alive_tasks()
+        });
+        ShutdownReport {
+            servers_stopped,
+            sessions_closed,
+            invocations,
+            tasks,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        }
+    })
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use flume::Receiver;
+use oprc_invoke::handler::InvocationZenohHandler;
+use pyo3_async_runtimes::tokio::get_runtime;
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{info, warn};
+use zenoh::{
+    liveliness::LivelinessToken,
+    query::{Query, Queryable},
+};
+
+use crate::{
+    compat::CompatHandler,
+    firewall::{FirewalledHandler, ZenohFirewall},
+    handler::AsyncInvocationHandler,
+    rpc, sticky,
+};
+
+/// Key expression prefix a promoted engine announces the functions it took
+/// over under, followed by its session id.
+const TAKEOVER_PREFIX: &str = "oprc/_takeover";
+
+#[derive(Default)]
+struct Table {
+    /// Handlers of every function served, declared or not.
+    handlers: HashMap<String, Arc<AsyncInvocationHandler>>,
+    /// Queryables of the functions currently declared.
+    queryables: HashMap<String, Declared>,
+}
+
+/// Queryables of a declared function: on its key expression, and on the key
+/// pinned to this session that invocations with a replica hint are sent to.
+/// A liveliness token on the pinned key lets load-balancing callers find it.
+struct Declared {
+    shared: Queryable<Receiver<Query>>,
+    pinned: Queryable<Receiver<Query>>,
+    token: LivelinessToken,
+}
+
+impl Declared {
+    async fn undeclare(self) -> zenoh::Result<()> {
+        self.token.undeclare().await?;
+        self.shared.undeclare().await?;
+        self.pinned.undeclare().await
+    }
+}
+
+/// Functions an engine serves over Zenoh.
+///
+/// An active engine declares a function as soon as it is served. In standby,
+/// the handler is built but its queryable is only declared on `promote`, so
+/// a new instance can get ready next to the one it replaces. `demote`
+/// undeclares the queryables again and keeps the handlers, so an engine can
+/// go back and forth. Invocations already running complete either way.
+#[derive(Default)]
+pub(crate) struct Functions {
+    standby: AtomicBool,
+    table: Mutex<Table>,
+}
+
+async fn declare_queryable(
+    session: &zenoh::Session,
+    firewall: &Arc<ZenohFirewall>,
+    key_expr: String,
+    handler: &Arc<AsyncInvocationHandler>,
+) -> Result<Queryable<Receiver<Query>>, String> {
+    let z_handler = FirewalledHandler::new(
+        firewall.clone(),
+        CompatHandler::new(
+            handler.clone(),
+            InvocationZenohHandler::new("".to_string(), handler.clone()),
+        ),
+    );
+    let conf = oprc_zenoh::util::ManagedConfig::new(key_expr, 1, 65536);
+    let session = session.clone();
+    get_runtime()
+        .spawn(async move {
+            oprc_zenoh::util::declare_managed_queryable(&session, conf, z_handler).await
+        })
+        .await
+        .map_err(|e| format!("Failed to spawn queryable: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+async fn declare(
+    session: &zenoh::Session,
+    firewall: &Arc<ZenohFirewall>,
+    key_expr: &str,
+    handler: &Arc<AsyncInvocationHandler>,
+) -> Result<Declared, String> {
+    let shared = declare_queryable(session, firewall, key_expr.to_string(), handler).await?;
+    let pinned_key = sticky::pinned_key(key_expr, &session.zid().to_string());
+    let pinned = match declare_queryable(session, firewall, pinned_key.clone(), handler).await {
+        Ok(q) => q,
+        Err(e) => {
+            let _ = shared.undeclare().await;
+            return Err(e);
+        }
+    };
+    let token = match session.liveliness().declare_token(pinned_key).await {
+        Ok(token) => token,
+        Err(e) => {
+            let _ = shared.undeclare().await;
+            let _ = pinned.undeclare().await;
+            return Err(format!("Failed to declare liveliness token: {}", e));
+        }
+    };
+    rpc::local::register(key_expr, firewall.clone(), handler.clone());
+    Ok(Declared {
+        shared,
+        pinned,
+        token,
+    })
+}
+
+impl Functions {
+    pub(crate) fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::Relaxed)
+    }
+
+    /// Makes functions served from now on wait for `promote`. Fails if a
+    /// function is already declared; `demote` those instead.
+    pub(crate) async fn enter_standby(&self) -> Result<(), String> {
+        let table = self.table.lock().await;
+        if !table.queryables.is_empty() {
+            return Err("functions are already declared; call demote() instead".to_string());
+        }
+        self.standby.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Serves `handler` on `key_expr`, declaring it unless in standby.
+    pub(crate) async fn serve(
+        &self,
+        session: &zenoh::Session,
+        firewall: &Arc<ZenohFirewall>,
+        key_expr: String,
+        handler: Arc<AsyncInvocationHandler>,
+    ) -> Result<(), String> {
+        let mut table = self.table.lock().await;
+        if !self.is_standby() {
+            let q = declare(session, firewall, &key_expr, &handler).await?;
+            if let Some(prev) = table.queryables.insert(key_expr.clone(), q) {
+                let _ = prev.undeclare().await;
+            }
+        }
+        table.handlers.insert(key_expr, handler);
+        Ok(())
+    }
+
+    /// Stops serving `key_expr`. Returns `false` if it was not served.
+    pub(crate) async fn stop(&self, key_expr: &str) -> Result<bool, String> {
+        let mut table = self.table.lock().await;
+        let served = table.handlers.remove(key_expr).is_some();
+        match table.queryables.remove(key_expr) {
+            Some(q) => {
+                rpc::local::unregister(key_expr);
+                q.undeclare()
+                    .await
+                    .map_err(|e| format!("Failed to undeclare queryable: {}", e))?;
+                Ok(true)
+            }
+            None => Ok(served),
+        }
+    }
+
+    /// Declares every served function that is not declared yet and leaves
+    /// standby. Returns the key expressions declared.
+    pub(crate) async fn promote(
+        &self,
+        session: &zenoh::Session,
+        firewall: &Arc<ZenohFirewall>,
+    ) -> Result<Vec<String>, String> {
+        let mut table = self.table.lock().await;
+        let pending: Vec<(String, Arc<AsyncInvocationHandler>)> = table
+            .handlers
+            .iter()
+            .filter(|(k, _)| !table.queryables.contains_key(*k))
+            .map(|(k, h)| (k.clone(), h.clone()))
+            .collect();
+        let mut declared = Vec::with_capacity(pending.len());
+        for (key_expr, handler) in pending {
+            let q = declare(session, firewall, &key_expr, &handler).await?;
+            table.queryables.insert(key_expr.clone(), q);
+            declared.push(key_expr);
+        }
+        self.standby.store(false, Ordering::Relaxed);
+        Ok(declared)
+    }
+
+    /// Undeclares the given functions, or all of them and enters standby.
+    /// Returns the key expressions undeclared.
+    pub(crate) async fn demote(&self, key_exprs: Option<&[String]>) -> Vec<String> {
+        let mut table = self.table.lock().await;
+        let keys: Vec<String> = match key_exprs {
+            Some(keys) => keys
+                .iter()
+                .filter(|k| table.queryables.contains_key(*k))
+                .cloned()
+                .collect(),
+            None => {
+                self.standby.store(true, Ordering::Relaxed);
+                table.queryables.keys().cloned().collect()
+            }
+        };
+        for key_expr in &keys {
+            rpc::local::unregister(key_expr);
+            if let Some(q) = table.queryables.remove(key_expr) {
+                if let Err(e) = q.undeclare().await {
+                    warn!("failed to undeclare queryable {}: {}", key_expr, e);
+                }
+            }
+        }
+        keys
+    }
+}
+
+/// Tells engines watching for takeovers that this session now serves
+/// `key_exprs`.
+pub(crate) async fn announce(session: &zenoh::Session, key_exprs: &[String]) -> zenoh::Result<()> {
+    let payload = serde_json::to_vec(key_exprs).unwrap_or_default();
+    session
+        .put(format!("{}/{}", TAKEOVER_PREFIX, session.zid()), payload)
+        .await
+}
+
+/// Demotes the functions of `functions` that another session announces it
+/// took over, `grace` after the announcement, until the returned task is
+/// aborted. The grace period lets callers learn the new route first.
+pub(crate) async fn watch(
+    session: zenoh::Session,
+    functions: Arc<Functions>,
+    grace: Duration,
+) -> zenoh::Result<JoinHandle<()>> {
+    let subscriber = session
+        .declare_subscriber(format!("{}/*", TAKEOVER_PREFIX))
+        .await?;
+    let own = format!("{}/{}", TAKEOVER_PREFIX, session.zid());
+    Ok(get_runtime().spawn(async move {
+        while let Ok(sample) = subscriber.recv_async().await {
+            if sample.key_expr().as_str() == own {
+                continue;
+            }
+            let keys: Vec<String> = match serde_json::from_slice(&sample.payload().to_bytes()) {
+                Ok(keys) => keys,
+                Err(e) => {
+                    warn!(
+                        "ignoring malformed takeover on {}: {}",
+                        sample.key_expr(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let functions = functions.clone();
+            get_runtime().spawn(async move {
+                tokio::time::sleep(grace).await;
+                let demoted = functions.demote(Some(&keys)).await;
+                if !demoted.is_empty() {
+                    info!("demoted {:?} after takeover", demoted);
+                }
+            });
+        }
+    }))
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use zenoh::key_expr::{KeyExpr, OwnedKeyExpr};
+
+/// Request option with the id of the replica an invocation is pinned to, as
+/// reported in the `oprc-replica` header of an earlier response.
+pub const REPLICA_ID_OPTION: &str = "oprc-replica-id";
+
+/// Chunk between a function's key expression and a replica id in the key
+/// the replica serves the function on for pinned invocations. It is a
+/// verbatim chunk, so wildcard queries and firewall patterns never match it.
+const PINNED_CHUNK: &str = "@replica";
+
+/// Key expression the replica `replica` serves `key_expr` on, besides
+/// `key_expr` itself.
+pub(crate) fn pinned_key(key_expr: &str, replica: &str) -> String {
+    format!("{}/{}/{}", key_expr, PINNED_CHUNK, replica)
+}
+
+/// The key expression of the function a pinned key belongs to, and the
+/// replica it is pinned to; `None` if `key_expr` is not pinned.
+pub(crate) fn split_pinned(key_expr: &KeyExpr<'_>) -> Option<(OwnedKeyExpr, String)> {
+    let (base, replica) = key_expr
+        .as_str()
+        .rsplit_once(&format!("/{}/", PINNED_CHUNK))?;
+    let base = OwnedKeyExpr::try_from(base.to_string()).ok()?;
+    Some((base, replica.to_string()))
+}
+
+/// The key expression of the function a pinned key belongs to; `None` if
+/// `key_expr` is not pinned.
+pub(crate) fn unpinned(key_expr: &KeyExpr<'_>) -> Option<KeyExpr<'static>> {
+    split_pinned(key_expr).map(|(base, _)| base.into())
+}
+
+/// Removes the replica id from `options`, checking it can be used as a key
+/// expression chunk. It only steers the invocation, so handlers never see it.
+pub(crate) fn take_replica(
+    options: &mut HashMap<String, String>,
+) -> Result<Option<String>, String> {
+    let Some(replica) = options.remove(REPLICA_ID_OPTION) else {
+        return Ok(None);
+    };
+    check_replica(&replica)?;
+    Ok(Some(replica))
+}
+
+/// Checks `replica` can be used as a key expression chunk.
+pub(crate) fn check_replica(replica: &str) -> Result<(), String> {
+    if replica.is_empty() || !replica.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("invalid {} '{}'", REPLICA_ID_OPTION, replica));
+    }
+    Ok(())
+}
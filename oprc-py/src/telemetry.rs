@@ -1,44 +1,224 @@
 #![allow(unused)]
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex;
 
 static ENABLED: AtomicBool = AtomicBool::new(false);
 
+/// Spans the exporter delivered since the process started.
+static EXPORTED_SPANS: AtomicU64 = AtomicU64::new(0);
+/// Spans dropped because the collector could not be reached.
+static DROPPED_SPANS: AtomicU64 = AtomicU64::new(0);
+/// Export attempts that failed, retries included.
+static FAILED_EXPORTS: AtomicU64 = AtomicU64::new(0);
+/// Whether export is paused after the collector kept failing.
+static EXPORT_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Request option and response header carrying the id shared by every
+/// invocation of a causal chain (an invocation and the triggers it fires).
+pub const CHAIN_ID_OPTION: &str = "oprc-chain-id";
+/// Request option set by the data plane on invocations fired by a trigger,
+/// naming the source (e.g. `cls_id/fn_id`). Such invocations link to the
+/// originating span instead of becoming its child.
+pub const TRIGGER_SOURCE_OPTION: &str = "oprc-trigger-source";
+
 #[cfg(feature = "telemetry")]
 mod impls {
-    use super::ENABLED;
+    use super::{DROPPED_SPANS, ENABLED, EXPORT_PAUSED, EXPORTED_SPANS, FAILED_EXPORTS};
     use opentelemetry::KeyValue;
     use opentelemetry::trace::TracerProvider as _;
-    use opentelemetry_otlp::{WithExportConfig, WithHttpConfig};
+    use opentelemetry_otlp::{WithExportConfig, WithHttpConfig, WithTonicConfig};
     use opentelemetry_sdk::{
         Resource,
+        error::OTelSdkResult,
         runtime::Tokio,
-        trace::{self, Sampler, SdkTracerProvider},
+        trace::{self, Sampler, SdkTracerProvider, SpanData, SpanExporter},
     };
     use opentelemetry_semantic_conventions::resource::{SERVICE_NAME, SERVICE_VERSION};
     use pyo3_async_runtimes::tokio::get_runtime;
     use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
     use tracing_opentelemetry::OpenTelemetryLayer;
     use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt};
 
-    fn build_sampler() -> Sampler {
-        if let Ok(kind) = std::env::var("OTEL_TRACES_SAMPLER") {
-            match kind.as_str() {
-                "always_off" => Sampler::AlwaysOff,
-                "always_on" => Sampler::AlwaysOn,
-                "parentbased_always_on" => Sampler::ParentBased(Box::new(Sampler::AlwaysOn)),
-                "parentbased_always_off" => Sampler::ParentBased(Box::new(Sampler::AlwaysOff)),
-                "traceidratio" => {
-                    let arg = std::env::var("OTEL_TRACES_SAMPLER_ARG")
-                        .ok()
-                        .and_then(|v| v.parse::<f64>().ok())
-                        .unwrap_or(1.0);
-                    Sampler::TraceIdRatioBased(arg)
+    /// Parses an `OTEL_TRACES_SAMPLER` value; `None` for unknown kinds.
+    fn parse_sampler(kind: &str, arg: Option<f64>) -> Option<Sampler> {
+        Some(match kind {
+            "always_off" => Sampler::AlwaysOff,
+            "always_on" => Sampler::AlwaysOn,
+            "parentbased_always_on" => Sampler::ParentBased(Box::new(Sampler::AlwaysOn)),
+            "parentbased_always_off" => Sampler::ParentBased(Box::new(Sampler::AlwaysOff)),
+            "traceidratio" => Sampler::TraceIdRatioBased(arg.unwrap_or(1.0)),
+            _ => return None,
+        })
+    }
+
+    fn build_sampler(settings: &Settings) -> Sampler {
+        settings
+            .sampler
+            .as_deref()
+            .and_then(|kind| parse_sampler(kind, settings.sampler_arg))
+            .unwrap_or_else(|| Sampler::ParentBased(Box::new(Sampler::AlwaysOn)))
+    }
+
+    /// Retries of a failed export, after the first attempt.
+    const EXPORT_RETRIES: u32 = 2;
+    /// Wait before the first retry, doubled for each further one.
+    const EXPORT_BACKOFF: Duration = Duration::from_millis(200);
+    /// Batches in a row that failed every attempt before export pauses.
+    const PAUSE_AFTER_FAILURES: u32 = 3;
+    /// How long export stays paused before a batch is tried again.
+    const PAUSE_FOR: Duration = Duration::from_secs(30);
+
+    /// Wraps the OTLP exporter so an unreachable collector costs a few
+    /// bounded retries per batch and then pauses export, instead of logging
+    /// every failure and holding up the batches behind it. Batches that
+    /// cannot be delivered are dropped and counted.
+    #[derive(Debug)]
+    struct ResilientExporter<E> {
+        inner: E,
+        /// Whether failed exports are retried. The simple exporter exports on
+        /// the thread that ends the span, which must not wait for retries.
+        retry: bool,
+        breaker: StdMutex<ExportBreaker>,
+    }
+
+    #[derive(Debug, Default)]
+    struct ExportBreaker {
+        /// Batches in a row that failed every attempt.
+        failures: u32,
+        /// Set once export is paused; kept while the next batch is tried.
+        paused_until: Option<Instant>,
+    }
+
+    impl<E> ResilientExporter<E> {
+        fn new(inner: E, retry: bool) -> Self {
+            EXPORT_PAUSED.store(false, Ordering::Relaxed);
+            ResilientExporter {
+                inner,
+                retry,
+                breaker: Default::default(),
+            }
+        }
+
+        fn paused(&self) -> bool {
+            let breaker = self.breaker.lock().unwrap();
+            breaker
+                .paused_until
+                .is_some_and(|until| Instant::now() < until)
+        }
+
+        fn delivered(&self, spans: u64) {
+            EXPORTED_SPANS.fetch_add(spans, Ordering::Relaxed);
+            let mut breaker = self.breaker.lock().unwrap();
+            breaker.failures = 0;
+            if breaker.paused_until.take().is_some() {
+                EXPORT_PAUSED.store(false, Ordering::Relaxed);
+                eprintln!("[telemetry] Collector reachable again; span export resumed.");
+            }
+        }
+
+        fn dropped(&self, spans: u64, error: &dyn std::fmt::Display) {
+            DROPPED_SPANS.fetch_add(spans, Ordering::Relaxed);
+            let mut breaker = self.breaker.lock().unwrap();
+            breaker.failures += 1;
+            // A failed batch after a pause pauses export again right away.
+            if breaker.failures < PAUSE_AFTER_FAILURES && breaker.paused_until.is_none() {
+                return;
+            }
+            let first = breaker
+                .paused_until
+                .replace(Instant::now() + PAUSE_FOR)
+                .is_none();
+            if first {
+                EXPORT_PAUSED.store(true, Ordering::Relaxed);
+                eprintln!(
+                    "[telemetry] Span export failed {} times in a row ({}); pausing export and dropping spans, retrying every {}s.",
+                    breaker.failures,
+                    error,
+                    PAUSE_FOR.as_secs()
+                );
+            }
+        }
+    }
+
+    impl<E: SpanExporter> SpanExporter for ResilientExporter<E> {
+        async fn export(&self, mut batch: Vec<SpanData>) -> OTelSdkResult {
+            let spans = batch.len() as u64;
+            if self.paused() {
+                DROPPED_SPANS.fetch_add(spans, Ordering::Relaxed);
+                return Ok(());
+            }
+            let retries = if self.retry { EXPORT_RETRIES } else { 0 };
+            let mut attempt = 0;
+            let error = loop {
+                // The last attempt can give the batch away.
+                let attempt_batch = if attempt < retries {
+                    batch.clone()
+                } else {
+                    std::mem::take(&mut batch)
+                };
+                match self.inner.export(attempt_batch).await {
+                    Ok(()) => {
+                        self.delivered(spans);
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        FAILED_EXPORTS.fetch_add(1, Ordering::Relaxed);
+                        if attempt == retries {
+                            break e;
+                        }
+                    }
                 }
-                _ => Sampler::ParentBased(Box::new(Sampler::AlwaysOn)),
+                // The batch processor exports on a thread of its own, so the
+                // wait only holds up the batches queued behind this one.
+                std::thread::sleep(EXPORT_BACKOFF * 2u32.pow(attempt));
+                attempt += 1;
+            };
+            self.dropped(spans, &error);
+            // Failures are counted and reported once export pauses; an error
+            // would have the batch processor log every dropped batch.
+            Ok(())
+        }
+
+        fn shutdown_with_timeout(&mut self, timeout: Duration) -> OTelSdkResult {
+            self.inner.shutdown_with_timeout(timeout)
+        }
+
+        fn force_flush(&mut self) -> OTelSdkResult {
+            self.inner.force_flush()
+        }
+
+        fn set_resource(&mut self, resource: &Resource) {
+            self.inner.set_resource(resource);
+        }
+    }
+
+    /// Exporter and resource settings, from the environment unless given.
+    #[derive(Clone)]
+    struct Settings {
+        endpoint: Option<String>,
+        protocol: Option<String>,
+        sampler: Option<String>,
+        sampler_arg: Option<f64>,
+        service_name: String,
+        service_version: String,
+    }
+
+    impl Settings {
+        fn from_env(service_name: Option<String>, service_version: Option<String>) -> Self {
+            Settings {
+                endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok(),
+                protocol: std::env::var("OTEL_EXPORTER_OTLP_PROTOCOL").ok(),
+                sampler: std::env::var("OTEL_TRACES_SAMPLER").ok(),
+                sampler_arg: std::env::var("OTEL_TRACES_SAMPLER_ARG")
+                    .ok()
+                    .and_then(|v| v.parse::<f64>().ok()),
+                service_name: service_name
+                    .or_else(|| std::env::var("OTEL_SERVICE_NAME").ok())
+                    .unwrap_or_else(|| "unknown_service:oaas".to_string()),
+                service_version: service_version
+                    .unwrap_or_else(|| env!("CARGO_PKG_VERSION").to_string()),
             }
-        } else {
-            Sampler::ParentBased(Box::new(Sampler::AlwaysOn))
         }
     }
 
@@ -46,10 +226,11 @@ mod impls {
         if ENABLED.swap(true, Ordering::SeqCst) {
             return;
         }
+        let settings = Settings::from_env(service_name_override, service_version);
 
         let has_runtime = tokio::runtime::Handle::try_current().is_ok();
         if has_runtime {
-            init_inner(service_name_override, service_version, false);
+            init_inner(settings, false);
         } else {
             // Create a lightweight ephemeral runtime so exporter build (reqwest client etc.) does not panic.
             match tokio::runtime::Builder::new_current_thread()
@@ -58,7 +239,7 @@ mod impls {
             {
                 Ok(rt) => {
                     rt.block_on(async {
-                        init_inner(service_name_override, service_version, false);
+                        init_inner(settings, false);
                     });
                 }
                 Err(err) => {
@@ -72,75 +253,162 @@ mod impls {
     }
 
     use std::sync::Mutex as StdMutex;
+    use tracing_subscriber::reload;
+    type OtelLayer = OpenTelemetryLayer<Registry, trace::SdkTracer>;
     static PROVIDER: StdMutex<Option<SdkTracerProvider>> = StdMutex::new(None);
+    /// Settings the current provider was built with.
+    static SETTINGS: StdMutex<Option<Settings>> = StdMutex::new(None);
+    /// Swaps the tracer spans are exported with, without replacing the global
+    /// subscriber (which can only be set once).
+    static RELOAD: StdMutex<Option<reload::Handle<OtelLayer, Registry>>> = StdMutex::new(None);
 
-    fn init_inner(
-        service_name_override: Option<String>,
-        service_version: Option<String>,
-        ephemeral: bool,
-    ) {
-        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok();
-        let svc_name = service_name_override
-            .or_else(|| std::env::var("OTEL_SERVICE_NAME").ok())
-            .unwrap_or_else(|| "unknown_service:oaas".to_string());
-        let svc_version = service_version.unwrap_or_else(|| env!("CARGO_PKG_VERSION").to_string());
-
+    fn build_provider(settings: &Settings, ephemeral: bool) -> SdkTracerProvider {
         let resource = Resource::builder()
-            .with_attribute(KeyValue::new(SERVICE_NAME, svc_name.clone()))
-            .with_attribute(KeyValue::new(SERVICE_VERSION, svc_version.clone()))
+            .with_attribute(KeyValue::new(SERVICE_NAME, settings.service_name.clone()))
+            .with_attribute(KeyValue::new(
+                SERVICE_VERSION,
+                settings.service_version.clone(),
+            ))
+            .with_attribute(KeyValue::new(
+                "oprc.feature_flags",
+                crate::flags::enabled_names().join(","),
+            ))
             .build();
 
-        let tracer_provider = {
-            let mut builder = SdkTracerProvider::builder()
-                .with_resource(resource)
-                .with_sampler(build_sampler());
-            if let Some(ep) = endpoint.clone() {
-                let use_grpc = matches!(
-                    std::env::var("OTEL_EXPORTER_OTLP_PROTOCOL").ok().as_deref(),
-                    Some("grpc")
-                );
-                let exporter_result = if use_grpc {
-                    opentelemetry_otlp::SpanExporter::builder()
-                        .with_tonic()
-                        .with_endpoint(ep.clone())
-                        .build()
-                } else {
-                    opentelemetry_otlp::SpanExporter::builder()
-                        .with_http()
-                        .with_endpoint(ep.clone())
-                        .build()
-                };
-                match exporter_result {
-                    Ok(exporter) => {
-                        if ephemeral {
-                            // Cannot safely spawn background tasks; fall back to simple exporter.
-                            builder = builder.with_simple_exporter(exporter);
-                            eprintln!(
-                                "[telemetry] Ephemeral runtime used; simple exporter configured."
-                            );
-                        } else {
-                            builder = builder.with_batch_exporter(exporter);
-                        }
+        let mut builder = SdkTracerProvider::builder()
+            .with_resource(resource)
+            .with_sampler(build_sampler(settings));
+        if let Some(ep) = settings.endpoint.clone() {
+            let use_grpc = matches!(settings.protocol.as_deref(), Some("grpc"));
+            let proxy = crate::proxy::for_url(&ep);
+            let exporter_result = if use_grpc {
+                let mut tonic = opentelemetry_otlp::SpanExporter::builder()
+                    .with_tonic()
+                    .with_endpoint(ep.clone());
+                if let Some(proxy) = proxy {
+                    match crate::proxy::otlp_channel(&ep, proxy) {
+                        Ok(channel) => tonic = tonic.with_channel(channel),
+                        Err(err) => eprintln!(
+                            "[telemetry] Failed to route OTLP exporter through proxy ({}).",
+                            err
+                        ),
+                    }
+                }
+                tonic.build()
+            } else {
+                let mut http = opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .with_endpoint(ep.clone());
+                if proxy.is_some() {
+                    match crate::proxy::reqwest_builder().build() {
+                        Ok(client) => http = http.with_http_client(client),
+                        Err(err) => eprintln!(
+                            "[telemetry] Failed to route OTLP exporter through proxy ({}).",
+                            err
+                        ),
                     }
-                    Err(err) => eprintln!(
-                        "[telemetry] Failed to build OTLP exporter ({}). Proceeding without remote export.",
-                        err
-                    ),
                 }
+                http.build()
+            };
+            match exporter_result {
+                Ok(exporter) => {
+                    if ephemeral {
+                        // Cannot safely spawn background tasks; fall back to simple exporter.
+                        builder = builder
+                            .with_simple_exporter(ResilientExporter::new(exporter, false));
+                        eprintln!(
+                            "[telemetry] Ephemeral runtime used; simple exporter configured."
+                        );
+                    } else {
+                        builder =
+                            builder.with_batch_exporter(ResilientExporter::new(exporter, true));
+                    }
+                }
+                Err(err) => eprintln!(
+                    "[telemetry] Failed to build OTLP exporter ({}). Proceeding without remote export.",
+                    err
+                ),
             }
-            builder.build()
-        };
+        }
+        builder.build()
+    }
+
+    fn init_inner(settings: Settings, ephemeral: bool) {
+        let tracer_provider = build_provider(&settings, ephemeral);
         let tracer = tracer_provider.tracer("oprc-py");
-        let otel_layer = OpenTelemetryLayer::new(tracer);
+        let (otel_layer, reload_handle) = reload::Layer::new(OpenTelemetryLayer::new(tracer));
         let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
         let fmt_layer = tracing_subscriber::fmt::layer().with_target(false);
         let subscriber = Registry::default()
+            .with(otel_layer)
             .with(filter)
-            .with(fmt_layer)
-            .with(otel_layer);
+            .with(fmt_layer);
         let _ = tracing::subscriber::set_global_default(subscriber);
         opentelemetry::global::set_tracer_provider(tracer_provider.clone());
         *PROVIDER.lock().unwrap() = Some(tracer_provider);
+        *SETTINGS.lock().unwrap() = Some(settings);
+        *RELOAD.lock().unwrap() = Some(reload_handle);
+    }
+
+    /// Rebuilds the exporter with new settings and swaps it in. `None` keeps
+    /// the current value of a setting. Spans still buffered by the previous
+    /// provider are flushed to the previous endpoint in the background.
+    pub fn reinit(
+        endpoint: Option<String>,
+        protocol: Option<String>,
+        sampler: Option<String>,
+        sampler_arg: Option<f64>,
+        service_name: Option<String>,
+        service_version: Option<String>,
+    ) -> Result<(), String> {
+        if let Some(kind) = sampler
+            .as_deref()
+            .filter(|kind| parse_sampler(kind, sampler_arg).is_none())
+        {
+            return Err(format!("unknown sampler '{}'", kind));
+        }
+        if let Some(p) = protocol
+            .as_deref()
+            .filter(|p| !matches!(*p, "grpc" | "http/protobuf" | "http/json"))
+        {
+            return Err(format!("unsupported OTLP protocol '{}'", p));
+        }
+        let _runtime = get_runtime().enter();
+        let mut current = SETTINGS.lock().unwrap();
+        let mut settings = current
+            .clone()
+            .unwrap_or_else(|| Settings::from_env(None, None));
+        settings.endpoint = endpoint.or(settings.endpoint);
+        settings.protocol = protocol.or(settings.protocol);
+        if sampler.is_some() {
+            settings.sampler = sampler;
+            settings.sampler_arg = sampler_arg;
+        }
+        settings.service_name = service_name.unwrap_or(settings.service_name);
+        settings.service_version = service_version.unwrap_or(settings.service_version);
+        if !ENABLED.swap(true, Ordering::SeqCst) {
+            drop(current);
+            init_inner(settings, false);
+            return Ok(());
+        }
+        let Some(handle) = RELOAD.lock().unwrap().clone() else {
+            return Err("telemetry was initialized without a reloadable exporter".to_string());
+        };
+        let provider = build_provider(&settings, false);
+        handle
+            .reload(OpenTelemetryLayer::new(provider.tracer("oprc-py")))
+            .map_err(|e| e.to_string())?;
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        let previous = PROVIDER.lock().unwrap().replace(provider);
+        *current = Some(settings);
+        if let Some(previous) = previous {
+            std::thread::spawn(move || {
+                if let Err(e) = previous.shutdown() {
+                    eprintln!("[telemetry] shutdown error: {:?}", e);
+                }
+            });
+        }
+        Ok(())
     }
 
     pub fn forward_log(
@@ -186,6 +454,42 @@ mod impls {
         fut.instrument(span)
     }
 
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt as _;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use std::collections::HashMap;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    pub fn span(name: &'static str) -> Span {
+        tracing::span!(tracing::Level::INFO, "dynamic", otel.name = name)
+    }
+
+    /// Connects `span` to the W3C trace context found in `carrier`: as its
+    /// parent, or only as a span link when `link_only` is set.
+    pub fn adopt_remote(span: &Span, carrier: &HashMap<String, String>, link_only: bool) {
+        if !ENABLED.load(Ordering::Relaxed) || carrier.is_empty() {
+            return;
+        }
+        let cx = TraceContextPropagator::new().extract(carrier);
+        let remote = cx.span().span_context().clone();
+        if !remote.is_valid() {
+            return;
+        }
+        if link_only {
+            span.add_link(remote);
+        } else {
+            span.set_parent(cx);
+        }
+    }
+
+    /// Writes the W3C trace context of `span` into `carrier`.
+    pub fn inject(span: &Span, carrier: &mut HashMap<String, String>) {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return;
+        }
+        TraceContextPropagator::new().inject_context(&span.context(), carrier);
+    }
+
     // upgrade function removed; always batch when endpoint + runtime available
 
     pub fn shutdown() {
@@ -216,6 +520,26 @@ mod impls {
     pub fn instrument<F>(fut: F, _name: &'static str) -> F {
         fut
     }
+    pub fn span(_name: &'static str) -> tracing::Span {
+        tracing::Span::none()
+    }
+    pub fn adopt_remote(
+        _span: &tracing::Span,
+        _carrier: &std::collections::HashMap<String, String>,
+        _link_only: bool,
+    ) {
+    }
+    pub fn inject(_span: &tracing::Span, _carrier: &mut std::collections::HashMap<String, String>) {}
+    pub fn reinit(
+        _endpoint: Option<String>,
+        _protocol: Option<String>,
+        _sampler: Option<String>,
+        _sampler_arg: Option<f64>,
+        _service_name: Option<String>,
+        _service_version: Option<String>,
+    ) -> Result<(), String> {
+        Ok(())
+    }
     pub fn upgrade_batch_if_runtime() {}
     pub fn shutdown() {}
 }
@@ -226,6 +550,52 @@ pub fn enabled() -> bool {
     ENABLED.load(std::sync::atomic::Ordering::Relaxed)
 }
 
+/// Counters of span export, as returned by `telemetry.stats()`.
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyclass)]
+#[pyo3::pyclass(get_all)]
+#[derive(Clone)]
+pub struct TelemetryStats {
+    pub enabled: bool,
+    /// Spans delivered to the collector since the process started.
+    pub exported_spans: u64,
+    /// Spans dropped because the collector could not be reached.
+    pub dropped_spans: u64,
+    /// Export attempts that failed, retries included.
+    pub failed_exports: u64,
+    /// Whether export is paused after the collector kept failing.
+    pub export_paused: bool,
+}
+
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pymethods)]
+#[pyo3::pymethods]
+impl TelemetryStats {
+    fn __str__(&self) -> String {
+        format!(
+            "TelemetryStats {{ enabled: {}, exported_spans: {}, dropped_spans: {}, failed_exports: {}, export_paused: {} }}",
+            self.enabled,
+            self.exported_spans,
+            self.dropped_spans,
+            self.failed_exports,
+            self.export_paused
+        )
+    }
+}
+
+pub fn stats() -> TelemetryStats {
+    TelemetryStats {
+        enabled: enabled(),
+        exported_spans: EXPORTED_SPANS.load(Ordering::Relaxed),
+        dropped_spans: DROPPED_SPANS.load(Ordering::Relaxed),
+        failed_exports: FAILED_EXPORTS.load(Ordering::Relaxed),
+        export_paused: EXPORT_PAUSED.load(Ordering::Relaxed),
+    }
+}
+
+/// Generates a new causal chain id (32 hex digits).
+pub fn new_chain_id() -> String {
+    format!("{:032x}", fastrand::u128(..))
+}
+
 // upgrade no-op wrapper retained for compatibility but does nothing now
 pub fn upgrade_batch_if_runtime() {}
 
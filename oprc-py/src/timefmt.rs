@@ -0,0 +1,356 @@
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+const SECS_PER_DAY: i64 = 86_400;
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Appends `.fraction` for non-zero `nanos`, without trailing zeros.
+fn push_fraction(out: &mut String, nanos: u32) {
+    if nanos > 0 {
+        let digits = format!("{:09}", nanos);
+        out.push('.');
+        out.push_str(digits.trim_end_matches('0'));
+    }
+}
+
+/// Parses up to nine fractional digits into nanoseconds.
+fn parse_fraction(digits: &str) -> u32 {
+    digits
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(9)
+        .fold(0, |n, d| n * 10 + u32::from(d - b'0'))
+}
+
+/// Formats `t` as an RFC 3339 timestamp in UTC, e.g. `2024-05-01T12:00:00.5Z`.
+pub(crate) fn format_timestamp(t: SystemTime) -> String {
+    let (secs, nanos) = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        }
+    };
+    let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+    let sod = secs.rem_euclid(SECS_PER_DAY);
+    let mut out = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        sod / 3600,
+        sod % 3600 / 60,
+        sod % 60
+    );
+    push_fraction(&mut out, nanos);
+    out.push('Z');
+    out
+}
+
+/// Parses an RFC 3339 timestamp with any UTC offset, e.g.
+/// `2024-05-01T14:00:00+02:00`. A leap second is read as the second before.
+pub(crate) fn parse_timestamp(s: &str) -> Result<SystemTime, String> {
+    let invalid = || format!("invalid RFC 3339 timestamp '{}'", s);
+    let num = |from: usize, to: usize| {
+        s.get(from..to)
+            .filter(|p| p.bytes().all(|c| c.is_ascii_digit()))
+            .and_then(|p| p.parse::<i64>().ok())
+            .ok_or_else(invalid)
+    };
+    let sep = |at: usize, allowed: &[u8]| match s.as_bytes().get(at) {
+        Some(c) if allowed.contains(c) => Ok(()),
+        _ => Err(invalid()),
+    };
+    let year = num(0, 4)?;
+    sep(4, b"-")?;
+    let month = num(5, 7)?;
+    sep(7, b"-")?;
+    let day = num(8, 10)?;
+    sep(10, b"Tt ")?;
+    let hour = num(11, 13)?;
+    sep(13, b":")?;
+    let minute = num(14, 16)?;
+    sep(16, b":")?;
+    let second = num(17, 19)?;
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return Err(invalid());
+    }
+    let mut rest = s.get(19..).ok_or_else(invalid)?;
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return Err(invalid());
+        }
+        nanos = parse_fraction(&fraction[..digits]);
+        rest = &fraction[digits..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return Err(invalid()),
+            };
+            let at = s.len() - 6;
+            let (hours, minutes) = (num(at + 1, at + 3)?, num(at + 4, at + 6)?);
+            sep(at + 3, b":")?;
+            if hours > 23 || minutes > 59 {
+                return Err(invalid());
+            }
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => return Err(invalid()),
+    };
+    let secs = days_from_civil(year, month, day) * SECS_PER_DAY
+        + hour * 3600
+        + minute * 60
+        + second.min(59)
+        - offset;
+    let nanos = Duration::from_nanos(u64::from(nanos));
+    Ok(if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64) + nanos
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + nanos
+    })
+}
+
+/// Formats `d` as an ISO 8601 duration, e.g. `PT1H30M` or `PT0.25S`.
+pub(crate) fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    let mut out = String::from("PT");
+    if hours > 0 {
+        let _ = write!(out, "{}H", hours);
+    }
+    if minutes > 0 {
+        let _ = write!(out, "{}M", minutes);
+    }
+    if seconds > 0 || d.subsec_nanos() > 0 || secs == 0 {
+        let _ = write!(out, "{}", seconds);
+        push_fraction(&mut out, d.subsec_nanos());
+        out.push('S');
+    }
+    out
+}
+
+/// Parses an ISO 8601 duration made of weeks, days, hours, minutes and
+/// seconds, e.g. `P1DT12H` or `PT0.5S`. Years and months are rejected, as
+/// their length depends on the date they apply to.
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid ISO 8601 duration '{}'", s);
+    let rest = s.strip_prefix(['P', 'p']).ok_or_else(invalid)?;
+    let (date, time) = match rest.split_once(['T', 't']) {
+        Some((date, time)) if !time.is_empty() => (date, time),
+        Some(_) => return Err(invalid()),
+        None => (rest, ""),
+    };
+    let mut total = Duration::ZERO;
+    let mut any = false;
+    let parts: [(&str, &[(char, u64)]); 2] = [
+        (date, &[('W', 604_800), ('D', 86_400)]),
+        (time, &[('H', 3600), ('M', 60), ('S', 1)]),
+    ];
+    for (mut part, units) in parts {
+        let mut next_unit = 0;
+        while !part.is_empty() {
+            let end = part
+                .find(|c: char| c.is_ascii_alphabetic())
+                .ok_or_else(invalid)?;
+            let (value, unit) = part.split_at(end);
+            let unit = unit.as_bytes()[0].to_ascii_uppercase() as char;
+            let at = units[next_unit..]
+                .iter()
+                .position(|(u, _)| *u == unit)
+                .ok_or_else(invalid)?
+                + next_unit;
+            next_unit = at + 1;
+            if value.is_empty() || !value.bytes().all(|c| c.is_ascii_digit() || c == b'.') {
+                return Err(invalid());
+            }
+            let value: f64 = value.parse().map_err(|_| invalid())?;
+            total +=
+                Duration::try_from_secs_f64(value * units[at].1 as f64).map_err(|_| invalid())?;
+            any = true;
+            part = &part[end + 1..];
+        }
+    }
+    if any { Ok(total) } else { Err(invalid()) }
+}
+
+/// Formats a `datetime` as an RFC 3339 timestamp in UTC.
+#[pyfunction(name = "format_timestamp")]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn py_format_timestamp(t: SystemTime) -> String {
+    format_timestamp(t)
+}
+
+/// Parses an RFC 3339 timestamp into a UTC `datetime`.
+#[pyfunction(name = "parse_timestamp")]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn py_parse_timestamp(s: &str) -> PyResult<SystemTime> {
+    parse_timestamp(s).map_err(PyValueError::new_err)
+}
+
+/// Formats a `timedelta` as an ISO 8601 duration.
+#[pyfunction(name = "format_duration")]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn py_format_duration(d: Duration) -> String {
+    format_duration(d)
+}
+
+/// Parses an ISO 8601 duration into a `timedelta`.
+#[pyfunction(name = "parse_duration")]
+#[cfg_attr(feature = "stub-gen", pyo3_stub_gen::derive::gen_stub_pyfunction)]
+pub fn py_parse_duration(s: &str) -> PyResult<Duration> {
+    parse_duration(s).map_err(PyValueError::new_err)
+}
+
+/// Reads the timestamp stored under `key`, if any.
+pub(crate) fn get_timestamp(
+    map: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> PyResult<Option<SystemTime>> {
+    map.get(key)
+        .map(|v| parse_timestamp(v).map_err(PyValueError::new_err))
+        .transpose()
+}
+
+/// Reads the duration stored under `key`, if any.
+pub(crate) fn get_duration(
+    map: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> PyResult<Option<Duration>> {
+    map.get(key)
+        .map(|v| parse_duration(v).map_err(PyValueError::new_err))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_round_trip() {
+        let t = UNIX_EPOCH + Duration::new(1_714_564_800, 500_000_000);
+        assert_eq!(format_timestamp(t), "2024-05-01T12:00:00.5Z");
+        assert_eq!(parse_timestamp("2024-05-01T12:00:00.5Z"), Ok(t));
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamps_before_the_epoch() {
+        let t = UNIX_EPOCH - Duration::from_millis(250);
+        assert_eq!(format_timestamp(t), "1969-12-31T23:59:59.75Z");
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59.75Z"), Ok(t));
+    }
+
+    #[test]
+    fn timestamps_apply_the_offset() {
+        assert_eq!(
+            parse_timestamp("2024-05-01T14:00:00+02:00"),
+            parse_timestamp("2024-05-01T12:00:00Z")
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01 10:30:00-01:30"),
+            parse_timestamp("2024-05-01T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn leap_second_reads_as_the_second_before() {
+        assert_eq!(
+            parse_timestamp("2016-12-31T23:59:60Z"),
+            parse_timestamp("2016-12-31T23:59:59Z")
+        );
+    }
+
+    #[test]
+    fn invalid_timestamps_are_rejected() {
+        for s in [
+            "",
+            "2024-05-01",
+            "2024-05-01T12:00:00",
+            "2024-13-01T12:00:00Z",
+            "2023-02-29T12:00:00Z",
+            "2024-05-01T24:00:00Z",
+            "2024-05-01T12:00:00.Z",
+            "2024-05-01T12:00:00+0200",
+            "2024-05-01T12:00:00+24:00",
+        ] {
+            assert!(parse_timestamp(s).is_err(), "{}", s);
+        }
+        assert!(parse_timestamp("2024-02-29T12:00:00Z").is_ok());
+    }
+
+    #[test]
+    fn durations_round_trip() {
+        for (d, s) in [
+            (Duration::ZERO, "PT0S"),
+            (Duration::from_millis(250), "PT0.25S"),
+            (Duration::from_secs(5400), "PT1H30M"),
+            (Duration::from_secs(3661), "PT1H1M1S"),
+        ] {
+            assert_eq!(format_duration(d), s);
+            assert_eq!(parse_duration(s), Ok(d));
+        }
+    }
+
+    #[test]
+    fn durations_with_days_and_weeks() {
+        assert_eq!(parse_duration("P1DT12H"), Ok(Duration::from_secs(129_600)));
+        assert_eq!(parse_duration("P1W"), Ok(Duration::from_secs(604_800)));
+        assert_eq!(parse_duration("pt0.5s"), Ok(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for s in ["", "P", "PT", "1H", "P1Y", "P1M", "PT1S1M", "PT-1S", "P1DT"] {
+            assert!(parse_duration(s).is_err(), "{}", s);
+        }
+    }
+}
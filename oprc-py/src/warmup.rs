@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use oprc_invoke::handler::InvocationExecutor;
+use tracing::{info, warn};
+
+use crate::{dispatch::Target, model::InvocationResponseCode, rpc::ProtoRequest};
+
+/// Option set on warm-up invocations, so handlers can tell them from
+/// invocations of callers.
+pub const WARMUP_OPTION: &str = "oprc-warmup";
+
+/// Time a warm-up invocation may take before the server stops waiting for
+/// it and gets ready anyway.
+const WARMUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sample invocations of a function.
+struct Samples {
+    payloads: Vec<Vec<u8>>,
+    invocations: usize,
+    partition_id: u32,
+    object_id: Option<u64>,
+}
+
+impl Samples {
+    /// The warm-up invocations, cycling through the payloads, on the
+    /// partition and object given or those of `target`.
+    fn requests(&self, cls_id: &str, fn_id: &str, target: Option<&Target>) -> Vec<ProtoRequest> {
+        let (partition_id, object_id) = match target {
+            Some(Target::Fn { partition_id, .. }) => (*partition_id, None),
+            Some(Target::Obj {
+                partition_id,
+                object_id,
+                ..
+            }) => (*partition_id, Some(*object_id)),
+            None => (self.partition_id, self.object_id),
+        };
+        let options = HashMap::from([(WARMUP_OPTION.to_string(), "true".to_string())]);
+        self.payloads
+            .iter()
+            .cycle()
+            .take(self.invocations)
+            .map(|payload| match object_id {
+                Some(object_id) => ProtoRequest::Obj(oprc_pb::ObjectInvocationRequest {
+                    partition_id,
+                    object_id,
+                    cls_id: cls_id.to_string(),
+                    fn_id: fn_id.to_string(),
+                    options: options.clone(),
+                    payload: payload.clone(),
+                }),
+                None => ProtoRequest::Fn(oprc_pb::InvocationRequest {
+                    partition_id,
+                    cls_id: cls_id.to_string(),
+                    fn_id: fn_id.to_string(),
+                    options: options.clone(),
+                    payload: payload.clone(),
+                }),
+            })
+            .collect()
+    }
+}
+
+/// Sample payloads of the functions an engine serves, invoked when a server
+/// starts and before it takes invocations of callers, so the first of those
+/// do not pay for lazily loaded code or models.
+#[derive(Default)]
+pub(crate) struct WarmUp {
+    samples: Mutex<HashMap<(String, String), Samples>>,
+}
+
+impl WarmUp {
+    /// Warms `fn_id` of `cls_id` up with `invocations` invocations cycling
+    /// through `payloads`; no payloads stops warming it up. Object functions
+    /// served by a server for all objects need `object_id`.
+    pub(crate) fn set(
+        &self,
+        cls_id: String,
+        fn_id: String,
+        payloads: Vec<Vec<u8>>,
+        invocations: usize,
+        partition_id: u32,
+        object_id: Option<u64>,
+    ) {
+        let mut samples = self.samples.lock().unwrap();
+        if payloads.is_empty() || invocations == 0 {
+            samples.remove(&(cls_id, fn_id));
+            return;
+        }
+        samples.insert(
+            (cls_id, fn_id),
+            Samples {
+                payloads,
+                invocations,
+                partition_id,
+                object_id,
+            },
+        );
+    }
+
+    /// Runs the warm-up invocations of every function on `executor`.
+    pub(crate) async fn run_all<E: InvocationExecutor>(&self, executor: &E) {
+        let reqs: Vec<_> = {
+            let samples = self.samples.lock().unwrap();
+            samples
+                .iter()
+                .flat_map(|((cls_id, fn_id), s)| s.requests(cls_id, fn_id, None))
+                .collect()
+        };
+        run(executor, reqs).await;
+    }
+
+    /// Runs the warm-up invocations of the function served on `key_expr` on
+    /// `executor`, with the partition and object of the key expression.
+    pub(crate) async fn run_key<E: InvocationExecutor>(&self, executor: &E, key_expr: &str) {
+        let Ok(target) = Target::parse(key_expr.strip_prefix("oprc/").unwrap_or(key_expr)) else {
+            return;
+        };
+        let (cls_id, fn_id) = target.function();
+        let reqs = {
+            let samples = self.samples.lock().unwrap();
+            match samples.get(&(cls_id.to_string(), fn_id.to_string())) {
+                Some(s) => s.requests(cls_id, fn_id, Some(&target)),
+                None => return,
+            }
+        };
+        run(executor, reqs).await;
+    }
+}
+
+/// Runs `reqs` one after the other; failures are logged, as the function is
+/// served either way.
+async fn run<E: InvocationExecutor>(executor: &E, reqs: Vec<ProtoRequest>) {
+    if reqs.is_empty() {
+        return;
+    }
+    let started = Instant::now();
+    let total = reqs.len();
+    let mut failed = 0;
+    for req in reqs {
+        let cls_id = req.cls_id().to_string();
+        let fn_id = req.fn_id().to_string();
+        let invocation = async {
+            match req {
+                ProtoRequest::Fn(req) => executor.invoke_fn(req).await,
+                ProtoRequest::Obj(req) => executor.invoke_obj(req).await,
+            }
+        };
+        let error = match tokio::time::timeout(WARMUP_TIMEOUT, invocation).await {
+            Err(_) => Some(format!("no response within {:?}", WARMUP_TIMEOUT)),
+            Ok(Err(e)) => Some(e.to_string()),
+            Ok(Ok(resp)) if resp.status != InvocationResponseCode::Okay as i32 => Some(format!(
+                "status {}: {}",
+                resp.status,
+                String::from_utf8_lossy(&resp.payload.unwrap_or_default())
+            )),
+            Ok(Ok(_)) => None,
+        };
+        if let Some(error) = error {
+            failed += 1;
+            warn!(
+                "warm-up invocation of {}/{} failed: {}",
+                cls_id, fn_id, error
+            );
+        }
+    }
+    info!(
+        "ran {} warm-up invocations in {:?}, {} failed",
+        total,
+        started.elapsed(),
+        failed
+    );
+}
@@ -0,0 +1,142 @@
+import pytest
+
+from oaas_sdk2_py.asgi import OaasAsgiApp
+
+
+class RecordingDispatcher:
+    """Stands in for ``oprc_py.HttpDispatcher``, recording what it dispatches."""
+
+    def __init__(self, response=(200, {"oprc-status": "Okay"}, b"done")):
+        self.response = response
+        self.calls = []
+
+    async def dispatch(self, path, body, options):
+        self.calls.append((path, body, options))
+        return self.response
+
+
+class MockEngine:
+    def __init__(self, dispatcher):
+        self.dispatcher = dispatcher
+
+    def http_dispatcher(self, loop, handler):
+        return self.dispatcher
+
+
+class FakeOparaca:
+    def __init__(self, engine):
+        self.engine = engine
+
+
+def make_app(dispatcher=None):
+    dispatcher = dispatcher or RecordingDispatcher()
+    return OaasAsgiApp(FakeOparaca(MockEngine(dispatcher))), dispatcher
+
+
+async def call(app, scope, chunks=(b"",)):
+    """Runs ``app`` on one request and returns the messages it sent."""
+    messages = [
+        {"type": "http.request", "body": chunk, "more_body": i < len(chunks) - 1}
+        for i, chunk in enumerate(chunks)
+    ]
+    sent = []
+
+    async def receive():
+        return messages.pop(0)
+
+    async def send(message):
+        sent.append(message)
+
+    await app(scope, receive, send)
+    return sent
+
+
+def http_scope(path, method="POST", query=b"", headers=(), root_path=""):
+    return {
+        "type": "http",
+        "method": method,
+        "path": path,
+        "root_path": root_path,
+        "query_string": query,
+        "headers": list(headers),
+    }
+
+
+def test_requires_an_engine():
+    with pytest.raises(RuntimeError, match="mock mode"):
+        OaasAsgiApp(FakeOparaca(None))
+
+
+async def test_rejects_methods_other_than_post():
+    app, dispatcher = make_app()
+
+    sent = await call(app, http_scope("/example.Doc/0/invokes/render", method="GET"))
+
+    assert sent[0]["status"] == 405
+    assert (b"allow", b"POST") in sent[0]["headers"]
+    assert dispatcher.calls == []
+
+
+async def test_strips_the_root_path_and_joins_the_body():
+    app, dispatcher = make_app()
+
+    await call(
+        app,
+        http_scope("/oaas/example.Doc/0/objects/7/invokes/render", root_path="/oaas"),
+        chunks=(b"hello ", b"world"),
+    )
+
+    path, body, _ = dispatcher.calls[0]
+    assert path == "/example.Doc/0/objects/7/invokes/render"
+    assert body == b"hello world"
+
+
+async def test_maps_query_parameters_and_option_headers_to_options():
+    app, dispatcher = make_app()
+
+    await call(
+        app,
+        http_scope(
+            "/example.Doc/0/invokes/render",
+            query=b"lang=en&oprc-chain-id=from-query",
+            headers=[
+                (b"Oprc-Chain-Id", b"from-header"),
+                (b"oprc-deadline", b"1700000000000"),
+                (b"content-type", b"text/plain"),
+            ],
+        ),
+    )
+
+    _, _, options = dispatcher.calls[0]
+    assert options == {
+        "lang": "en",
+        "oprc-chain-id": "from-query",
+        "oprc-deadline": "1700000000000",
+    }
+
+
+async def test_sends_the_dispatched_response():
+    dispatcher = RecordingDispatcher(
+        (404, {"oprc-status": "InvalidRequest"}, b"no such function")
+    )
+    app, _ = make_app(dispatcher)
+
+    sent = await call(app, http_scope("/example.Doc/0/invokes/missing"))
+
+    start, body = sent
+    assert start["status"] == 404
+    assert (b"oprc-status", b"InvalidRequest") in start["headers"]
+    assert body == {"type": "http.response.body", "body": b"no such function"}
+
+
+async def test_dispatch_failures_become_500():
+    class FailingDispatcher(RecordingDispatcher):
+        async def dispatch(self, path, body, options):
+            raise ValueError("malformed path")
+
+    app, _ = make_app(FailingDispatcher())
+
+    sent = await call(app, http_scope("/nowhere"))
+
+    assert sent[0]["status"] == 500
+    assert sent[1]["body"] == b"malformed path"
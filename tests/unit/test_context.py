@@ -0,0 +1,120 @@
+import contextlib
+import time
+
+import pytest
+from oprc_py import InvocationRequest, InvocationResponse, ObjectMetadata
+
+from oaas_sdk2_py import context
+
+
+class RecordingRpc:
+    """Stands in for the RpcManager, recording the requests it is given."""
+
+    def __init__(self):
+        self.requests = []
+
+    def invoke_fn(self, req):
+        self.requests.append(req)
+        return InvocationResponse(b"ok")
+
+    def invoke_obj(self, req):
+        self.requests.append(req)
+        return InvocationResponse(b"ok")
+
+    async def invoke_fn_async(self, req):
+        return self.invoke_fn(req)
+
+    async def invoke_obj_async(self, req):
+        return self.invoke_obj(req)
+
+
+class FakeOparaca:
+    def __init__(self):
+        self.rpc_manager = RecordingRpc()
+
+
+def now_ms():
+    return int(time.time() * 1000)
+
+
+@contextlib.contextmanager
+def serving(options):
+    """Serves a request of ``example.Doc/render`` carrying ``options``."""
+    oprc = FakeOparaca()
+    request = InvocationRequest(
+        cls_id="example.Doc", fn_id="render", partition_id=2, options=options
+    )
+    token = context.enter(oprc, request)
+    try:
+        yield oprc
+    finally:
+        context.leave(token)
+
+
+def test_helpers_require_a_served_invocation():
+    assert context.current() is None
+    with pytest.raises(RuntimeError, match="no invocation is being served"):
+        context.invoke("summarize")
+
+
+def test_propagates_only_the_call_chain_options():
+    with serving({
+        "traceparent": "00-abc-def-01",
+        "oprc-chain-id": "chain-1",
+        "oprc-auth-token": "secret",
+        "priority": "high",
+    }) as oprc:
+        context.invoke("summarize", b"text", options={"lang": "en"})
+
+        req = oprc.rpc_manager.requests[-1]
+        assert (req.cls_id, req.fn_id, req.partition_id) == ("example.Doc", "summarize", 2)
+        assert req.options == {
+            "traceparent": "00-abc-def-01",
+            "oprc-chain-id": "chain-1",
+            "oprc-auth-token": "secret",
+            "lang": "en",
+        }
+
+
+async def test_object_invocations_target_the_given_object():
+    with serving({"oprc-chain-id": "chain-1"}) as oprc:
+        meta = ObjectMetadata("example.Counter", 5, 9)
+
+        await context.invoke_obj_async(meta, "incr", b"1")
+
+        req = oprc.rpc_manager.requests[-1]
+        assert (req.cls_id, req.partition_id, req.object_id, req.fn_id) == (
+            "example.Counter", 5, 9, "incr",
+        )
+        assert req.options == {"oprc-chain-id": "chain-1"}
+
+
+def test_without_deadline_timeout_is_passed_through():
+    with serving({}) as oprc:
+        assert context.remaining_ms() is None
+        context.invoke("summarize", timeout_ms=1234)
+
+        assert oprc.rpc_manager.requests[-1].timeout_ms == 1234
+
+
+async def test_timeout_is_capped_to_the_remaining_budget():
+    with serving({"oprc-deadline": str(now_ms() + 5_000)}) as oprc:
+        await context.invoke_async("summarize", timeout_ms=60_000)
+        capped = oprc.rpc_manager.requests[-1].timeout_ms
+        context.invoke("summarize")
+        defaulted = oprc.rpc_manager.requests[-1].timeout_ms
+        context.invoke("summarize", timeout_ms=10)
+        shorter = oprc.rpc_manager.requests[-1].timeout_ms
+
+        assert 0 < capped <= 5_000
+        assert 0 < defaulted <= 5_000
+        assert shorter == 10
+        assert oprc.rpc_manager.requests[-1].deadline_ms is not None
+
+
+def test_passed_deadline_raises_without_sending():
+    with serving({"oprc-deadline": str(now_ms() - 1)}) as oprc:
+        assert context.remaining_ms() == 0
+        with pytest.raises(TimeoutError, match="example.Doc/render"):
+            context.invoke("summarize")
+        assert oprc.rpc_manager.requests == []
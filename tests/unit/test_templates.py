@@ -0,0 +1,133 @@
+import json
+
+import oprc_py
+import pytest
+
+from oaas_sdk2_py.templates import InvocationTemplate, TemplateRegistry
+
+
+YAML_TEMPLATES = """
+templates:
+  resize:
+    cls: example.Image
+    fn: resize
+    object_id: 42
+    options: {priority: high}
+    codec: json
+    timeout_ms: 2000
+  ping:
+    cls: example.Health
+    fn: ping
+    codec: text
+"""
+
+TOML_TEMPLATES = """
+[templates.resize]
+cls = "example.Image"
+fn = "resize_v2"
+partition = 3
+"""
+
+JSON_TEMPLATES = {
+    "templates": {
+        "upload": {"cls": "example.Blob", "fn": "put", "codec": "raw"},
+    }
+}
+
+
+@pytest.fixture
+def template_files(tmp_path):
+    yaml_path = tmp_path / "ops.yaml"
+    yaml_path.write_text(YAML_TEMPLATES)
+    toml_path = tmp_path / "ops.toml"
+    toml_path.write_text(TOML_TEMPLATES)
+    json_path = tmp_path / "ops.json"
+    json_path.write_text(json.dumps(JSON_TEMPLATES))
+    return yaml_path, toml_path, json_path
+
+
+def test_loads_yaml_toml_and_json(template_files):
+    yaml_path, _, json_path = template_files
+    registry = TemplateRegistry.load(yaml_path, json_path)
+
+    assert registry.names() == ["ping", "resize", "upload"]
+    resize = registry.get("resize")
+    assert (resize.cls, resize.fn, resize.object_id) == ("example.Image", "resize", 42)
+    assert resize.options == {"priority": "high"}
+    assert resize.timeout_ms == 2000
+    assert registry.get("upload").codec == "raw"
+
+
+def test_later_files_override_templates_of_the_same_name(template_files):
+    yaml_path, toml_path, _ = template_files
+    registry = TemplateRegistry.load(yaml_path, toml_path)
+
+    resize = registry.get("resize")
+    assert resize.fn == "resize_v2"
+    assert resize.partition == 3
+    assert resize.object_id is None
+
+
+def test_rejects_unknown_file_types_and_malformed_templates(tmp_path):
+    ini = tmp_path / "ops.ini"
+    ini.write_text("[templates]")
+    with pytest.raises(ValueError, match="Unsupported template file type"):
+        TemplateRegistry.load(ini)
+
+    bad = tmp_path / "bad.json"
+    bad.write_text(json.dumps({"templates": {"resize": "example.Image"}}))
+    with pytest.raises(ValueError, match="must be a mapping"):
+        TemplateRegistry.load(bad)
+
+
+def test_unknown_template_lists_the_available_ones(template_files):
+    registry = TemplateRegistry.load(template_files[0])
+    with pytest.raises(KeyError, match="available: \\['ping', 'resize'\\]"):
+        registry.get("missing")
+
+
+def test_builds_object_request_with_overrides(template_files):
+    registry = TemplateRegistry.load(template_files[0])
+
+    req = registry.instantiate(
+        "resize",
+        {"width": 10},
+        object_id=7,
+        options={"trace": "on"},
+        timeout_ms=500,
+    )
+
+    assert isinstance(req, oprc_py.ObjectInvocationRequest)
+    assert req.object_id == 7
+    assert req.options == {"priority": "high", "trace": "on"}
+    assert req.timeout_ms == 500
+    assert json.loads(bytes(req.payload)) == {"width": 10}
+
+
+def test_builds_stateless_request_without_object_id(template_files):
+    registry = TemplateRegistry.load(template_files[0])
+
+    req = registry.instantiate("ping", 5, partition=2)
+
+    assert isinstance(req, oprc_py.InvocationRequest)
+    assert (req.cls_id, req.fn_id, req.partition_id) == ("example.Health", "ping", 2)
+    assert bytes(req.payload) == b"5"
+
+
+def test_overridden_options_win_over_template_options(template_files):
+    registry = TemplateRegistry.load(template_files[0])
+
+    req = registry.instantiate("resize", options={"priority": "low"})
+
+    assert req.options == {"priority": "low"}
+    assert req.object_id == 42
+    assert req.timeout_ms == 2000
+
+
+def test_raw_codec_requires_bytes():
+    template = InvocationTemplate(name="upload", cls="example.Blob", fn="put", codec="raw")
+
+    assert bytes(template.build(b"\x00\x01").payload) == b"\x00\x01"
+    assert bytes(template.build().payload) == b""
+    with pytest.raises(TypeError, match="raw codec"):
+        template.build({"not": "bytes"})